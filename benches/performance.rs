@@ -0,0 +1,103 @@
+// benches/performance.rs
+
+//! Throughput of the hot paths: synthesis, offline rendering, parsing and tab layout. Run with
+//! `cargo bench` and compare against a saved baseline (`-- --save-baseline before`) when
+//! changing any of them.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use egui::pos2;
+
+use cdefgab::audio::offline_render::render_score;
+use cdefgab::guitar::guitar::GuitarConfig;
+use cdefgab::karplus_strong::karplus_strong::KarplusStrong;
+use cdefgab::music_representation::Score;
+use cdefgab::renderer::renderer::Renderer;
+
+const SAMPLE_RATE: f32 = 44_100.0;
+const SCORE_FILES: [&str; 3] = ["canonc.xml", "greensleeves.xml", "partida.xml"];
+
+fn score_path(name: &str) -> String {
+    format!("{}/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+/// The measures of `score` repeated until there are at least `measures` of them.
+fn repeated(score: &Score, measures: usize) -> Score {
+    let mut large = score.clone();
+    large.measures = score
+        .measures
+        .iter()
+        .cycle()
+        .take(measures.max(score.measures.len()))
+        .cloned()
+        .collect();
+    large
+}
+
+fn synthesis(c: &mut Criterion) {
+    let config = GuitarConfig::acoustic();
+    let mut group = c.benchmark_group("karplus_strong");
+    // One voice for two seconds, reported in samples per second
+    let seconds = 2.0;
+    group.throughput(Throughput::Elements((seconds * SAMPLE_RATE) as u64));
+    for frequency in [82.41, 196.0, 659.25] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(frequency),
+            &frequency,
+            |b, &frequency| {
+                b.iter(|| {
+                    let mut voice = KarplusStrong::new(frequency, seconds, SAMPLE_RATE, &config);
+                    black_box(voice.generate_audio_data())
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn offline_render(c: &mut Criterion) {
+    let config = GuitarConfig::acoustic();
+    let score = Score::parse_from_musicxml(score_path("canonc.xml")).unwrap();
+    let mut group = c.benchmark_group("render_score");
+    group.sample_size(10);
+    group.bench_function("canonc", |b| {
+        b.iter(|| {
+            black_box(render_score(
+                &score,
+                &config,
+                score.tempo,
+                SAMPLE_RATE,
+                Some(0),
+            ))
+        })
+    });
+    group.finish();
+}
+
+fn parsing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_musicxml");
+    for name in SCORE_FILES {
+        let xml = std::fs::read_to_string(score_path(name)).unwrap();
+        group.throughput(Throughput::Bytes(xml.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &xml, |b, xml| {
+            b.iter(|| black_box(Score::parse_from_musicxml_str(xml).unwrap()))
+        });
+    }
+    group.finish();
+}
+
+fn layout(c: &mut Criterion) {
+    let score = Score::parse_from_musicxml(score_path("canonc.xml")).unwrap();
+    let renderer = Renderer::new(4, 2);
+    let mut group = c.benchmark_group("layout");
+    for measures in [100, 1000] {
+        let large = repeated(&score, measures);
+        group.throughput(Throughput::Elements(large.measures.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(measures), &large, |b, large| {
+            b.iter(|| black_box(renderer.layout(large, pos2(20.0, 20.0))))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, synthesis, offline_render, parsing, layout);
+criterion_main!(benches);
@@ -0,0 +1,235 @@
+// annotations/bundle.rs
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::library::store::content_hash;
+use crate::music_representation::Score;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub const ANNOTATIONS_FILE: &str = "annotations.json";
+pub const BUNDLE_EXTENSION: &str = "cdefgab-notes";
+const BUNDLE_VERSION: u32 = 1;
+
+/// Identifies a score by its notes, so two copies match even if their files differ in layout or
+/// metadata.
+pub fn score_fingerprint(score: &Score) -> String {
+    let mut notes = String::new();
+    for (measure_idx, measure) in score.measures.iter().enumerate() {
+        for (division_idx, position) in measure.positions.iter().enumerate() {
+            for note in position {
+                let _ = write!(
+                    notes,
+                    "{}:{}:{:?}/{:?}/{};",
+                    measure_idx, division_idx, note.string, note.fret, note.duration
+                );
+            }
+        }
+    }
+    format!("{:016x}", content_hash(&notes))
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Comment {
+    /// One based, like the measure numbers shown to the user
+    pub measure: usize,
+    pub text: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FingeringSuggestion {
+    pub measure: usize,
+    pub division: usize,
+    pub string: u8,
+    /// 0 for the thumb, 1 to 4 for the fretting fingers
+    pub finger: u8,
+}
+
+/// Another way to finger a measure, like in fifth position rather than open.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FingeringVariant {
+    /// One based, like `Comment::measure`
+    pub measure: usize,
+    pub name: String,
+    /// String and fret of every note of the measure, division by division
+    pub frets: Vec<(Option<u8>, Option<u8>)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LoopRegion {
+    pub name: String,
+    pub start_measure: usize,
+    pub end_measure: usize,
+}
+
+/// A trouble spot marked with a key press during playback.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Flag {
+    /// One based, like `Comment::measure`
+    pub measure: usize,
+    pub division: usize,
+    /// Seconds into playback the key was pressed at
+    pub seconds: f32,
+    /// Typed in after the flag was dropped, empty if nothing was
+    #[serde(default)]
+    pub note: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Goal {
+    pub text: String,
+    pub target_tempo: Option<usize>,
+    pub done: bool,
+}
+
+/// Everything a teacher or student adds on top of a score.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Annotations {
+    #[serde(default)]
+    pub comments: Vec<Comment>,
+    #[serde(default)]
+    pub fingerings: Vec<FingeringSuggestion>,
+    #[serde(default)]
+    pub fingering_variants: Vec<FingeringVariant>,
+    #[serde(default)]
+    pub loops: Vec<LoopRegion>,
+    #[serde(default)]
+    pub goals: Vec<Goal>,
+    #[serde(default)]
+    pub flags: Vec<Flag>,
+}
+
+fn merge_into<T: PartialEq>(target: &mut Vec<T>, incoming: Vec<T>) {
+    for item in incoming {
+        if !target.contains(&item) {
+            target.push(item);
+        }
+    }
+}
+
+impl Annotations {
+    pub fn is_empty(&self) -> bool {
+        self.comments.is_empty()
+            && self.fingerings.is_empty()
+            && self.fingering_variants.is_empty()
+            && self.loops.is_empty()
+            && self.goals.is_empty()
+            && self.flags.is_empty()
+    }
+
+    /// Adds everything from `other` that isn't here yet. Fingerings from `other` replace
+    /// suggestions for the same note.
+    pub fn merge(&mut self, other: Annotations) {
+        merge_into(&mut self.comments, other.comments);
+        for fingering in other.fingerings {
+            self.fingerings.retain(|existing| {
+                (existing.measure, existing.division, existing.string)
+                    != (fingering.measure, fingering.division, fingering.string)
+            });
+            self.fingerings.push(fingering);
+        }
+        merge_into(&mut self.fingering_variants, other.fingering_variants);
+        merge_into(&mut self.loops, other.loops);
+        merge_into(&mut self.goals, other.goals);
+        merge_into(&mut self.flags, other.flags);
+    }
+}
+
+/// Annotations for one score, without any of the score itself, to send to someone who has
+/// their own copy.
+#[derive(Serialize, Deserialize)]
+pub struct AnnotationBundle {
+    pub version: u32,
+    pub score_fingerprint: String,
+    /// Only to show who the bundle is from, the score is matched by fingerprint
+    pub score_title: Option<String>,
+    pub annotations: Annotations,
+}
+
+impl AnnotationBundle {
+    pub fn new(score: &Score, annotations: &Annotations) -> Self {
+        Self {
+            version: BUNDLE_VERSION,
+            score_fingerprint: score_fingerprint(score),
+            score_title: score.title.clone(),
+            annotations: annotations.clone(),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| e.to_string())
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let bundle: Self = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        if bundle.version > BUNDLE_VERSION {
+            return Err(format!(
+                "Bundle version {} is newer than this app supports",
+                bundle.version
+            ));
+        }
+        Ok(bundle)
+    }
+}
+
+/// Annotations of every score the user has annotated, keyed by score fingerprint.
+#[derive(Default, Serialize, Deserialize)]
+pub struct AnnotationStore {
+    pub scores: BTreeMap<String, Annotations>,
+}
+
+impl AnnotationStore {
+    /// Reads the store saved at `path`, empty if nothing has been saved yet.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match std::fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json).map_err(|e| e.to_string()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Renames the file at `path` to a `.bak` next to it, so a store that couldn't be read isn't
+    /// saved over. Returns where it went.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn move_aside(path: &Path) -> Result<PathBuf, String> {
+        let mut backup = path.as_os_str().to_owned();
+        backup.push(".bak");
+        let backup = PathBuf::from(backup);
+        std::fs::rename(path, &backup).map_err(|e| e.to_string())?;
+        Ok(backup)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unreadable_store_is_an_error_and_moved_aside() {
+        let dir = std::env::temp_dir().join(format!("cdefgab-annotations-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(ANNOTATIONS_FILE);
+        assert!(AnnotationStore::load(&path).unwrap().scores.is_empty());
+
+        std::fs::write(&path, "{\"scores\": {\"abc\": ").unwrap();
+        assert!(AnnotationStore::load(&path).is_err());
+        let backup = AnnotationStore::move_aside(&path).unwrap();
+        assert!(!path.exists());
+        assert_eq!(
+            std::fs::read_to_string(&backup).unwrap(),
+            "{\"scores\": {\"abc\": "
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
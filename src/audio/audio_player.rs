@@ -1,91 +1,429 @@
 // audio/audio_player.rs
 
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::Duration;
 
+use crate::audio::callback_timer::{CallbackStats, CallbackTimer};
+use crate::audio::chorus::{ChorusControl, ChorusEffect};
+use crate::audio::convolution::{ConvolutionControl, ConvolutionEffect, ImpulseResponse};
+use crate::audio::drive::{DriveControl, DriveEffect};
+use crate::audio::effect_chain::{AudioEffect, EffectChain, EffectChainControl, EffectKind};
+use crate::audio::equalizer::{EqualizerControl, EqualizerEffect};
+use crate::audio::limiter::{LimiterControl, LimiterEffect, LimiterSettings};
+use crate::audio::offline_render::PluckedNote;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::audio::output_devices::find_output_device;
+use crate::audio::polyphony::{VoiceStealing, DEFAULT_MAX_VOICES};
+use crate::audio::signal_history::SignalHistory;
+use crate::audio::synth_stream::{SynthCommand, SynthShared, SynthSoundData};
 use crate::guitar::guitar::GuitarConfig;
-use crate::karplus_strong::karplus_strong::KarplusStrong;
-use crate::music_representation::{calculate_frequency, Note};
+use crate::music_representation::Note;
 
+#[cfg(not(target_arch = "wasm32"))]
+use kira::manager::backend::cpal::CpalBackendSettings;
 use kira::manager::{AudioManager, AudioManagerSettings};
 
-use kira::sound::static_sound::{StaticSoundData, StaticSoundSettings};
-use kira::Frame;
+use kira::sound::static_sound::{StaticSoundData, StaticSoundHandle, StaticSoundSettings};
+#[cfg(not(target_arch = "wasm32"))]
+use kira::sound::SoundData;
+use kira::track::{TrackBuilder, TrackHandle};
+use kira::tween::Tween;
+use kira::{Frame, OutputDestination, StartTime};
+
 pub struct AudioPlayer {
     manager: Option<AudioManager>,
     pub sample_rate: f32,
     configs: GuitarConfig,
-    pub output_signal: Vec<f32>,
+    pub output_signal: SignalHistory,
+    // Track the notes play on, running them through the guitar's effects
+    guitar_track: Option<TrackHandle>,
+    effect_chain: Arc<EffectChainControl>,
+    chorus: Arc<ChorusControl>,
+    drive: Arc<DriveControl>,
+    convolution: Arc<ConvolutionControl>,
+    equalizer: Arc<EqualizerControl>,
+    // Compressor and limiter on everything that plays
+    limiter: Arc<LimiterControl>,
+    /// Body and cabinet responses the guitar can be played through, bundled and loaded ones
+    pub impulse_responses: Vec<ImpulseResponse>,
+    // Response the convolution currently uses
+    impulse_response: Option<String>,
+    backing_track: Option<StaticSoundHandle>,
+    // Mixer settings as (volume, panning) of the backing track
+    backing_track_mix: (f32, f32),
+    // The notes stream from a voice pool on the audio thread, synthesized as they play
+    synth: Arc<SynthShared>,
+    max_voices: usize,
+    pub callback_stats: Arc<CallbackStats>,
+    /// Longest a block of the note stream took to synthesize lately, in milliseconds
+    pub synthesis_time: f32,
+    // Output device by name, the system default when None
+    #[cfg(not(target_arch = "wasm32"))]
+    device: Option<String>,
 }
 impl AudioPlayer {
     pub fn new(configs: GuitarConfig) -> Self {
         let sample_rate = 44_100.0; // Standard sample rate
+        let chorus = Arc::new(ChorusControl::new(&configs.chorus));
+        let synth = Arc::new(SynthShared::new(sample_rate));
 
-        Self {
+        let mut player = Self {
             manager: None,
             sample_rate,
-            configs,
-            output_signal: Vec::new(),
-        }
+            configs: configs.clone(),
+            // One second at full resolution and a minute of 10 ms buckets
+            output_signal: SignalHistory::new(44_100, 441, 6000),
+            guitar_track: None,
+            effect_chain: Arc::new(EffectChainControl::default()),
+            chorus,
+            drive: Arc::new(DriveControl::new(&configs.drive)),
+            convolution: Arc::new(ConvolutionControl::new()),
+            equalizer: Arc::new(EqualizerControl::default()),
+            limiter: Arc::new(LimiterControl::new(&LimiterSettings::default())),
+            impulse_responses: ImpulseResponse::bundled(sample_rate),
+            impulse_response: None,
+            backing_track: None,
+            backing_track_mix: (1.0, 0.5),
+            synth,
+            max_voices: DEFAULT_MAX_VOICES,
+            callback_stats: Arc::new(CallbackStats::default()),
+            synthesis_time: 0.0,
+            #[cfg(not(target_arch = "wasm32"))]
+            device: None,
+        };
+        player.update_configs(configs);
+        player
     }
     pub fn update_configs(&mut self, configs: GuitarConfig) {
+        self.effect_chain.set(&configs.effects);
+        self.chorus.set(&configs.chorus);
+        self.drive.set(&configs.drive);
+        self.equalizer.set(&configs.eq);
+        self.convolution.set_mix(configs.impulse_response_mix);
+        if configs.impulse_response != self.impulse_response {
+            self.impulse_response = configs.impulse_response.clone();
+            self.reload_impulse_response();
+        }
         self.configs = configs;
     }
+
+    fn find_impulse_response(&self, name: Option<&String>) -> Option<&ImpulseResponse> {
+        name.and_then(|name| {
+            self.impulse_responses
+                .iter()
+                .find(|impulse_response| &impulse_response.name == name)
+        })
+    }
+
+    fn reload_impulse_response(&self) {
+        let impulse_response = self.find_impulse_response(self.impulse_response.as_ref());
+        self.convolution.set_impulse_response(impulse_response);
+    }
+
+    /// The effects of `config` in the order of its chain, on controls of their own so
+    /// rendering through them offline leaves playback alone.
+    pub fn offline_effect_chain(&self, config: &GuitarConfig) -> EffectChain {
+        let drive = Arc::new(DriveControl::new(&config.drive));
+        let chorus = Arc::new(ChorusControl::new(&config.chorus));
+        let equalizer = Arc::new(EqualizerControl::default());
+        let convolution = Arc::new(ConvolutionControl::new());
+        convolution.set_mix(config.impulse_response_mix);
+        convolution
+            .set_impulse_response(self.find_impulse_response(config.impulse_response.as_ref()));
+        let effects = guitar_effects(&drive, &convolution, &chorus, &equalizer, config);
+        EffectChain::new(
+            Arc::new(EffectChainControl::default()),
+            effects,
+            &config.effects,
+        )
+    }
+
+    /// Makes a loaded response available to the profiles, replacing one with the same name.
+    pub fn add_impulse_response(&mut self, impulse_response: ImpulseResponse) {
+        let name = impulse_response.name.clone();
+        self.impulse_responses
+            .retain(|existing| existing.name != name);
+        self.impulse_responses.push(impulse_response);
+        if self.impulse_response.as_ref() == Some(&name) {
+            self.reload_impulse_response();
+        }
+    }
     pub fn start(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         if self.manager.is_none() {
-            let manager = AudioManager::new(AudioManagerSettings::default())?;
+            let timer = CallbackTimer::new(self.callback_stats.clone());
+            #[cfg(not(target_arch = "wasm32"))]
+            let backend_settings = CpalBackendSettings {
+                device: match &self.device {
+                    Some(name) => Some(find_output_device(name)?),
+                    None => None,
+                },
+                ..Default::default()
+            };
+            #[cfg(target_arch = "wasm32")]
+            let backend_settings = Default::default();
+            // The limiter sits where every track sums, the timer last to see the batch through
+            let limiter = LimiterEffect::new(self.limiter.clone());
+            let mut manager = AudioManager::new(AudioManagerSettings {
+                main_track_builder: TrackBuilder::new()
+                    .with_built_effect(Box::new(limiter))
+                    .with_built_effect(Box::new(timer)),
+                backend_settings,
+                ..Default::default()
+            })?;
+            let effects = guitar_effects(
+                &self.drive,
+                &self.convolution,
+                &self.chorus,
+                &self.equalizer,
+                &self.configs,
+            );
+            let chain = EffectChain::new(self.effect_chain.clone(), effects, &self.configs.effects);
+            let guitar_track = TrackBuilder::new().with_built_effect(Box::new(chain));
+            let guitar_track = manager.add_sub_track(guitar_track)?;
+            manager.play(SynthSoundData {
+                shared: self.synth.clone(),
+                destination: OutputDestination::from(&guitar_track),
+            })?;
+            self.guitar_track = Some(guitar_track);
+            // A new effect starts without a response, so hand it the current one again
+            self.reload_impulse_response();
             self.manager = Some(manager);
         }
 
         Ok(())
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn device_name(&self) -> Option<&str> {
+        self.device.as_deref()
+    }
+
+    /// Moves the output to the device called `name`, or the system default for `None`. Sounds
+    /// playing on the old device stop, so callers restart the ones that should go on. Stays on
+    /// the old device if the new one can't be opened.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_output_device(&mut self, name: Option<String>) -> Result<(), String> {
+        let was_started = self.manager.is_some();
+        self.manager = None;
+        self.guitar_track = None;
+        self.backing_track = None;
+        let previous = std::mem::replace(&mut self.device, name);
+        if was_started {
+            if let Err(e) = self.start() {
+                self.device = previous;
+                let _ = self.start();
+                return Err(e.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of sounds currently playing, every note and the backing track included.
+    pub fn voice_count(&self) -> usize {
+        self.manager.as_ref().map_or(0, |manager| {
+            // The note stream is a sound of its own
+            manager.num_sounds().saturating_sub(1) as usize
+                + self.synth.active_voices.load(Ordering::Relaxed)
+        })
+    }
+
+    pub fn set_max_voices(&mut self, max_voices: usize) {
+        self.max_voices = max_voices;
+        self.synth
+            .set_polyphony(max_voices, self.synth.voice_stealing());
+    }
+
+    pub fn set_voice_stealing(&mut self, stealing: VoiceStealing) {
+        self.synth.set_polyphony(self.max_voices, stealing);
+    }
+
+    /// Takes what the note stream played since the last call into the output signal for
+    /// plotting, along with how long it took to synthesize, and frees the notes it is done with.
+    pub fn collect_output(&mut self) {
+        self.synth.free_retired();
+        let samples = self.synth.take_output();
+        if !samples.is_empty() {
+            self.output_signal.push(&samples);
+            self.synthesis_time = self.synth.take_block_time();
+        }
+    }
+
+    /// Starts `notes` ringing for `duration` seconds. They are set up here and synthesized on the
+    /// audio thread as they play.
     pub fn play_notes(&mut self, notes: &[Note], duration: f32) {
+        if self.manager.is_none() {
+            eprintln!("AudioManager is not initialized");
+            return;
+        }
+        let notes = &notes[..notes.len().min(self.max_voices)];
+        self.synth.send(notes.iter().map(|note| {
+            SynthCommand::Strike(PluckedNote::new(
+                note,
+                duration,
+                self.sample_rate,
+                &self.configs,
+                None,
+            ))
+        }));
+    }
+
+    /// Plucks `note` at once for `duration` seconds, `velocity` from 0 to 1 scaling the
+    /// profile's volume, like a key pressed on a MIDI controller.
+    pub fn pluck(&mut self, note: &Note, duration: f32, velocity: f32) {
+        if self.manager.is_none() {
+            eprintln!("AudioManager is not initialized");
+            return;
+        }
+        let config = GuitarConfig {
+            volume: self.configs.volume * velocity,
+            ..self.configs.clone()
+        };
+        self.synth.send([SynthCommand::Strike(PluckedNote::new(
+            note,
+            duration,
+            self.sample_rate,
+            &config,
+            None,
+        ))]);
+    }
+
+    /// Like [`Self::play_notes`], but the notes start on the exact sample the transport reaches
+    /// `seconds` at, so they keep time however late this is called. See [`Self::set_transport`].
+    pub fn schedule_notes(&mut self, notes: &[Note], duration: f32, seconds: f32) {
+        if self.manager.is_none() {
+            eprintln!("AudioManager is not initialized");
+            return;
+        }
+        let notes = &notes[..notes.len().min(self.max_voices)];
+        self.synth
+            .send(notes.iter().map(|note| SynthCommand::Schedule {
+                note: PluckedNote::new(note, duration, self.sample_rate, &self.configs, None),
+                seconds: seconds as f64,
+            }));
+    }
+
+    /// Tells the audio thread the transport is at `seconds` now, or stopped for `None`, which
+    /// drops the notes scheduled but not started yet.
+    pub fn set_transport(&mut self, seconds: Option<f32>) {
+        self.synth
+            .send([SynthCommand::Transport(seconds.map(f64::from))]);
+    }
+
+    /// Fades out the notes still ringing.
+    pub fn release_notes(&mut self) {
+        self.synth.send([SynthCommand::Release]);
+    }
+
+    /// Plays a sound outside the guitar's voices and mix, such as a metronome click.
+    pub fn play_sound(&mut self, sound: StaticSoundData) {
+        if let Some(manager) = &mut self.manager {
+            if let Err(e) = manager.play(sound) {
+                eprintln!("Failed to play sound: {}", e);
+            }
+        }
+    }
+
+    /// Plays any kind of sound on the main output, such as the microphone monitor.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn play_sound_data<D: SoundData>(&mut self, data: D) -> Result<D::Handle, String> {
+        self.start().map_err(|e| e.to_string())?;
+        match &mut self.manager {
+            Some(manager) => manager.play(data).map_err(|e| e.to_string()),
+            None => Err("AudioManager is not initialized".to_string()),
+        }
+    }
+
+    pub fn set_limiter(&self, settings: &LimiterSettings) {
+        self.limiter.set(settings);
+    }
+
+    /// How far the compressor and limiter turn the output down right now, in dB below 0.
+    pub fn limiter_reduction_db(&self) -> f32 {
+        self.limiter.reduction_db()
+    }
+
+    /// Applies to the notes ringing and the ones played from now on.
+    pub fn set_guitar_mix(&mut self, volume: f32, panning: f32) {
+        self.synth.set_mix(volume, panning);
+    }
+
+    /// Plays the frames from `start_seconds`, or delays them if `start_seconds` is negative.
+    pub fn play_backing_track(
+        &mut self,
+        frames: Arc<[Frame]>,
+        sample_rate: u32,
+        start_seconds: f32,
+    ) {
+        self.stop_backing_track();
         if let Some(manager) = &mut self.manager {
-            let configs = &self.configs;
-
-            for note in notes {
-                let frequency = calculate_frequency(note, configs.scale_length, configs.capo_fret);
-                let mut karplus_strong =
-                    KarplusStrong::new(frequency, duration, self.sample_rate, &configs);
-                let mut audio_data = karplus_strong.generate_audio_data();
-
-                // Apply volume
-                for sample in &mut audio_data {
-                    *sample *= configs.volume;
-                }
-
-                // Collect output_signal for plotting
-                self.output_signal.extend_from_slice(&audio_data);
-
-                // Limit size
-                let n = 44100; // Keep last 1 second at 44.1kHz
-                if self.output_signal.len() > n {
-                    let remove_count = self.output_signal.len() - n;
-                    self.output_signal.drain(0..remove_count);
-                }
-
-                // Convert audio_data (Vec<f32>) to frames (Vec<Frame>)
-                let frames: Vec<Frame> = audio_data
-                    .iter()
-                    .map(|&sample| Frame::from_mono(sample))
-                    .collect();
-
-                // Convert Vec<Frame> into Arc<[Frame]>
-                let frames_arc = Arc::from(frames.into_boxed_slice());
-
-                // Create a StaticSoundData by initializing its fields
-                let sound = StaticSoundData {
-                    sample_rate: self.sample_rate as u32,
-                    frames: frames_arc,
-                    settings: StaticSoundSettings::default(),
-                    slice: None,
-                };
-
-                // Play the sound
-                manager.play(sound).expect("Failed to play sound");
+            let (volume, panning) = self.backing_track_mix;
+            let settings = StaticSoundSettings::new()
+                .volume(volume as f64)
+                .panning(panning as f64);
+            let settings = if start_seconds < 0.0 {
+                settings.start_time(StartTime::Delayed(Duration::from_secs_f32(-start_seconds)))
+            } else {
+                settings.start_position(start_seconds as f64)
+            };
+            let sound = StaticSoundData {
+                sample_rate,
+                frames,
+                settings,
+                slice: None,
+            };
+            match manager.play(sound) {
+                Ok(handle) => self.backing_track = Some(handle),
+                Err(e) => eprintln!("Failed to play backing track: {}", e),
             }
         } else {
             eprintln!("AudioManager is not initialized");
         }
     }
+
+    pub fn set_backing_track_mix(&mut self, volume: f32, panning: f32) {
+        self.backing_track_mix = (volume, panning);
+        if let Some(handle) = &mut self.backing_track {
+            handle.set_volume(volume as f64, Tween::default());
+            handle.set_panning(panning as f64, Tween::default());
+        }
+    }
+
+    /// Moves the playing backing track by `seconds`, used when nudging its alignment.
+    pub fn seek_backing_track_by(&mut self, seconds: f32) {
+        if let Some(handle) = &mut self.backing_track {
+            handle.seek_by(seconds as f64);
+        }
+    }
+
+    pub fn stop_backing_track(&mut self) {
+        if let Some(mut handle) = self.backing_track.take() {
+            handle.stop(Tween::default());
+        }
+    }
+}
+
+/// Every effect a guitar can run, following the given controls and starting from `config`.
+fn guitar_effects(
+    drive: &Arc<DriveControl>,
+    convolution: &Arc<ConvolutionControl>,
+    chorus: &Arc<ChorusControl>,
+    equalizer: &Arc<EqualizerControl>,
+    config: &GuitarConfig,
+) -> Vec<(EffectKind, Box<dyn AudioEffect>)> {
+    vec![
+        (EffectKind::Drive, Box::new(DriveEffect::new(drive.clone()))),
+        (
+            EffectKind::Body,
+            Box::new(ConvolutionEffect::new(convolution.clone())),
+        ),
+        (
+            EffectKind::Chorus,
+            Box::new(ChorusEffect::new(chorus.clone())),
+        ),
+        (
+            EffectKind::Eq,
+            Box::new(EqualizerEffect::new(equalizer.clone(), config.eq)),
+        ),
+    ]
 }
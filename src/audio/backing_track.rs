@@ -0,0 +1,273 @@
+// audio/backing_track.rs
+
+use std::io::Cursor;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use kira::sound::static_sound::StaticSoundData;
+use kira::Frame;
+
+use crate::music_representation::Score;
+
+// WSOLA parameters, in samples at the track's sample rate
+const WSOLA_WINDOW: usize = 2048;
+const WSOLA_HOP: usize = WSOLA_WINDOW / 2;
+const WSOLA_TOLERANCE: usize = 256;
+// Only every n:th sample/offset is used when searching for the best overlap
+const WSOLA_SEARCH_STEP: usize = 4;
+
+/// How the recording should be altered to follow the score playback.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrackProcessing {
+    /// Playback speed relative to the recording, 0.5 = half speed
+    pub rate: f32,
+    /// Pitch shift in semitones
+    pub semitones: i32,
+}
+
+impl TrackProcessing {
+    pub fn is_identity(&self) -> bool {
+        is_unity_rate(self.rate) && self.semitones == 0
+    }
+
+    fn matches(&self, other: &TrackProcessing) -> bool {
+        (self.rate - other.rate).abs() < 1e-3 && self.semitones == other.semitones
+    }
+}
+
+/// Processed frames and the processing they were rendered with.
+pub type ProcessedFrames = (TrackProcessing, Arc<[Frame]>);
+
+/// A re-rendering of the backing track running in the background, cancelled once dropped so a
+/// newer tempo or capo doesn't have to wait for it.
+pub struct ProcessingJob {
+    pub processing: TrackProcessing,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl ProcessingJob {
+    pub fn new(processing: TrackProcessing) -> Self {
+        Self {
+            processing,
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Flag for the rendering to stop at, set when the job is dropped.
+    pub fn cancelled(&self) -> Arc<AtomicBool> {
+        self.cancelled.clone()
+    }
+}
+
+impl Drop for ProcessingJob {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+/// A decoded recording played along with the score.
+pub struct BackingTrack {
+    pub name: String,
+    pub sample_rate: u32,
+    pub frames: Arc<[Frame]>,
+    /// Time-stretched and/or pitch-shifted copy of `frames`
+    pub processed: Option<ProcessedFrames>,
+    /// Time in the recording where the score's first beat falls, negative if the score starts
+    /// before the recording does
+    pub offset_seconds: f32,
+    /// Tempo of the recording, `None` if it is assumed to match the score
+    pub tempo: Option<f32>,
+}
+
+impl BackingTrack {
+    /// Decodes any format supported by symphonia (wav, ogg, mp3, flac).
+    pub fn from_bytes(name: String, data: Vec<u8>) -> Result<Self, String> {
+        let sound = StaticSoundData::from_cursor(Cursor::new(data)).map_err(|e| e.to_string())?;
+        Ok(Self {
+            name,
+            sample_rate: sound.sample_rate,
+            frames: sound.frames,
+            processed: None,
+            offset_seconds: 0.0,
+            tempo: None,
+        })
+    }
+
+    pub fn duration_seconds(&self) -> f32 {
+        self.frames.len() as f32 / self.sample_rate as f32
+    }
+
+    /// The measure of the score heard at `seconds` into the recording, given its alignment.
+    pub fn measure_at(&self, score: &Score, seconds: f32) -> Option<usize> {
+        let tempo = self.tempo.unwrap_or(score.tempo as f32);
+        let quarters_per_measure =
+            score.divisions_per_measure as f32 / score.divisions_per_quarter.max(1) as f32;
+        let seconds_per_measure = quarters_per_measure * 60.0 / tempo;
+        if !seconds_per_measure.is_finite() || seconds_per_measure <= 0.0 {
+            return None;
+        }
+        let measure = ((seconds - self.offset_seconds) / seconds_per_measure).round();
+        (measure >= 0.0 && (measure as usize) < score.measures.len()).then_some(measure as usize)
+    }
+
+    /// Frames to play for the given processing, if they have been prepared.
+    pub fn frames_for(&self, processing: &TrackProcessing) -> Option<Arc<[Frame]>> {
+        if processing.is_identity() {
+            return Some(self.frames.clone());
+        }
+        match &self.processed {
+            Some((processed_with, frames)) if processed_with.matches(processing) => {
+                Some(frames.clone())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Applies time-stretching and pitch-shifting. Pitch is shifted by stretching to a length
+/// compensating for the resampling that follows. `None` if `cancelled` was set meanwhile.
+pub fn process_track(
+    frames: &[Frame],
+    processing: &TrackProcessing,
+    cancelled: &AtomicBool,
+) -> Option<Vec<Frame>> {
+    if processing.semitones == 0 {
+        return time_stretch(frames, processing.rate, cancelled);
+    }
+    let pitch_ratio = 2f32.powf(processing.semitones as f32 / 12.0);
+    let stretched = time_stretch(frames, processing.rate / pitch_ratio, cancelled)?;
+    Some(resample(&stretched, pitch_ratio))
+}
+
+/// Reads `frames` at `speed` times the original speed using linear interpolation.
+fn resample(frames: &[Frame], speed: f32) -> Vec<Frame> {
+    if frames.len() < 2 {
+        return frames.to_vec();
+    }
+    let output_len = ((frames.len() - 1) as f32 / speed) as usize;
+    (0..output_len)
+        .map(|i| {
+            let position = i as f32 * speed;
+            let index = position as usize;
+            let fraction = position - index as f32;
+            let (a, b) = (frames[index], frames[(index + 1).min(frames.len() - 1)]);
+            Frame::new(
+                a.left + (b.left - a.left) * fraction,
+                a.right + (b.right - a.right) * fraction,
+            )
+        })
+        .collect()
+}
+
+pub fn is_unity_rate(rate: f32) -> bool {
+    (rate - 1.0).abs() < 1e-3
+}
+
+/// Changes the speed of `frames` by `rate` (0.5 = half speed) without changing the pitch,
+/// using waveform similarity overlap-add (WSOLA). Stops with `None` once `cancelled` is set.
+pub fn time_stretch(frames: &[Frame], rate: f32, cancelled: &AtomicBool) -> Option<Vec<Frame>> {
+    if frames.len() < WSOLA_WINDOW + 2 * WSOLA_TOLERANCE || is_unity_rate(rate) {
+        return Some(frames.to_vec());
+    }
+
+    let window: Vec<f32> = (0..WSOLA_WINDOW)
+        .map(|i| {
+            let phase = i as f32 / WSOLA_WINDOW as f32;
+            0.5 - 0.5 * (2.0 * std::f32::consts::PI * phase).cos()
+        })
+        .collect();
+    let mono: Vec<f32> = frames.iter().map(|f| (f.left + f.right) * 0.5).collect();
+
+    let output_len = (frames.len() as f32 / rate) as usize;
+    let mut output = vec![Frame::ZERO; output_len + WSOLA_WINDOW];
+    let mut weights = vec![0.0f32; output_len + WSOLA_WINDOW];
+
+    let last_start = frames.len() - WSOLA_WINDOW;
+    // Input position whose continuation the next segment should resemble
+    let mut previous_start = 0usize;
+    let mut output_pos = 0usize;
+
+    while output_pos < output_len {
+        if cancelled.load(Ordering::Relaxed) {
+            return None;
+        }
+        let nominal = ((output_pos as f32 * rate) as usize).min(last_start);
+        let start = if output_pos == 0 {
+            0
+        } else {
+            let natural = (previous_start + WSOLA_HOP).min(last_start);
+            best_overlap(&mono, natural, nominal, last_start)
+        };
+
+        for (i, &window_weight) in window.iter().enumerate() {
+            // Nothing overlaps the rising half of the very first segment
+            let w = if output_pos == 0 && i < WSOLA_HOP {
+                1.0
+            } else {
+                window_weight
+            };
+            let frame = frames[start + i];
+            output[output_pos + i].left += frame.left * w;
+            output[output_pos + i].right += frame.right * w;
+            weights[output_pos + i] += w;
+        }
+
+        previous_start = start;
+        output_pos += WSOLA_HOP;
+    }
+
+    output.truncate(output_len);
+    for (frame, weight) in output.iter_mut().zip(weights) {
+        if weight > 1e-3 {
+            frame.left /= weight;
+            frame.right /= weight;
+        }
+    }
+    Some(output)
+}
+
+/// Finds the segment start around `nominal` that best continues the segment at `natural`.
+fn best_overlap(mono: &[f32], natural: usize, nominal: usize, last_start: usize) -> usize {
+    let low = nominal.saturating_sub(WSOLA_TOLERANCE);
+    let high = (nominal + WSOLA_TOLERANCE).min(last_start);
+
+    let mut best_start = nominal;
+    let mut best_score = f32::MIN;
+    for candidate in (low..=high).step_by(WSOLA_SEARCH_STEP) {
+        let score: f32 = (0..WSOLA_HOP)
+            .step_by(WSOLA_SEARCH_STEP)
+            .map(|i| mono[natural + i] * mono[candidate + i])
+            .sum();
+        if score > best_score {
+            best_score = score;
+            best_start = candidate;
+        }
+    }
+    best_start
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dropping_the_job_stops_the_stretch() {
+        let frames: Vec<Frame> = (0..44_100)
+            .map(|i| {
+                let sample = (i as f32 * 0.05).sin();
+                Frame::new(sample, sample)
+            })
+            .collect();
+        let processing = TrackProcessing {
+            rate: 0.8,
+            semitones: 0,
+        };
+        let job = ProcessingJob::new(processing);
+        let cancelled = job.cancelled();
+        let stretched = process_track(&frames, &processing, &cancelled).unwrap();
+        assert_eq!(stretched.len(), (frames.len() as f32 / 0.8) as usize);
+
+        drop(job);
+        assert!(process_track(&frames, &processing, &cancelled).is_none());
+    }
+}
@@ -0,0 +1,168 @@
+// audio/beat_detection.rs
+
+use kira::Frame;
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+
+// Onset analysis parameters, in samples at the track's sample rate
+const ONSET_WINDOW: usize = 1024;
+const ONSET_HOP: usize = 512;
+// Tempo search range in beats per minute
+const MIN_TEMPO: f32 = 50.0;
+const MAX_TEMPO: f32 = 220.0;
+// Width of the tempo prior in octaves, centered on the score's tempo
+const TEMPO_PRIOR_WIDTH: f32 = 1.0;
+// Relative period deviations tried when fitting the beat grid
+const PERIOD_REFINE_STEPS: i32 = 200;
+const PERIOD_REFINE_RESOLUTION: f32 = 1e-4;
+
+/// Where the score's first beat falls in a recording and how fast the recording is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BeatAlignment {
+    /// Time of the first beat in the recording, in seconds
+    pub offset_seconds: f32,
+    /// Tempo of the recording in beats per minute
+    pub tempo: f32,
+}
+
+/// Estimates tempo and first beat of a recording. The score's tempo is used to pick between
+/// tempo candidates that are equally plausible, like half or double time.
+pub fn detect_beats(frames: &[Frame], sample_rate: u32, score_tempo: f32) -> Option<BeatAlignment> {
+    let envelope = onset_envelope(frames);
+    let envelope_rate = sample_rate as f32 / ONSET_HOP as f32;
+
+    let min_lag = (envelope_rate * 60.0 / MAX_TEMPO).floor() as usize;
+    let max_lag = (envelope_rate * 60.0 / MIN_TEMPO).ceil() as usize;
+    if min_lag < 2 || envelope.len() < 2 * max_lag {
+        return None;
+    }
+
+    // Autocorrelation of the onset envelope, weighted towards the score's tempo
+    let autocorrelation: Vec<f32> = (0..=max_lag + 1)
+        .map(|lag| {
+            envelope
+                .iter()
+                .zip(&envelope[lag.min(envelope.len())..])
+                .map(|(a, b)| a * b)
+                .sum()
+        })
+        .collect();
+    let prior = |lag: f32| {
+        let tempo = 60.0 * envelope_rate / lag;
+        let octaves = (tempo / score_tempo.max(1.0)).log2() / TEMPO_PRIOR_WIDTH;
+        (-0.5 * octaves * octaves).exp()
+    };
+    let best_lag = (min_lag..=max_lag).max_by(|&a, &b| {
+        let score_a = autocorrelation[a] * prior(a as f32);
+        let score_b = autocorrelation[b] * prior(b as f32);
+        score_a.total_cmp(&score_b)
+    })?;
+    if autocorrelation[best_lag] <= 0.0 {
+        return None;
+    }
+
+    // Refine the period between envelope frames with a parabola through the neighbouring lags
+    let (left, center, right) = (
+        autocorrelation[best_lag - 1],
+        autocorrelation[best_lag],
+        autocorrelation[best_lag + 1],
+    );
+    let curvature = left - 2.0 * center + right;
+    let period = if curvature < 0.0 {
+        best_lag as f32 + 0.5 * (left - right) / curvature
+    } else {
+        best_lag as f32
+    };
+
+    // Fit the beat grid collecting the most onset energy. Even a small period error drifts the
+    // grid off the beats over a long recording, so the period is refined together with the phase.
+    let mut best_fit = (f32::MIN, period, 0);
+    for step in -PERIOD_REFINE_STEPS..=PERIOD_REFINE_STEPS {
+        let candidate = period * (1.0 + step as f32 * PERIOD_REFINE_RESOLUTION);
+        for phase in 0..best_lag {
+            let energy = beat_grid_energy(&envelope, phase as f32, candidate);
+            if energy > best_fit.0 {
+                best_fit = (energy, candidate, phase);
+            }
+        }
+    }
+    let (energy, period, phase) = best_fit;
+
+    // Skip grid positions before the music starts
+    let beats = (envelope.len() as f32 - phase as f32) / period;
+    let threshold = 0.5 * energy / beats.max(1.0);
+    let mut first_beat = phase as f32;
+    let near_onset = |position: f32| {
+        (-1..=1)
+            .map(|delta| onset_at(&envelope, (position + delta as f32).max(0.0)))
+            .fold(0.0, f32::max)
+    };
+    while first_beat < envelope.len() as f32 && near_onset(first_beat) < threshold {
+        first_beat += period;
+    }
+
+    // Envelope frames are placed at the center of their analysis window
+    let first_beat_sample = first_beat * ONSET_HOP as f32 + ONSET_WINDOW as f32 / 2.0;
+    Some(BeatAlignment {
+        offset_seconds: first_beat_sample / sample_rate as f32,
+        tempo: 60.0 * envelope_rate / period,
+    })
+}
+
+/// Half-wave rectified spectral flux with its mean removed.
+fn onset_envelope(frames: &[Frame]) -> Vec<f32> {
+    if frames.len() < ONSET_WINDOW {
+        return Vec::new();
+    }
+
+    let mut planner = FftPlanner::<f32>::new();
+    let fft = planner.plan_fft_forward(ONSET_WINDOW);
+    let window: Vec<f32> = (0..ONSET_WINDOW)
+        .map(|i| {
+            let phase = i as f32 / ONSET_WINDOW as f32;
+            0.5 - 0.5 * (2.0 * std::f32::consts::PI * phase).cos()
+        })
+        .collect();
+
+    let mut previous = vec![0.0f32; ONSET_WINDOW / 2];
+    let mut buffer = vec![Complex::new(0.0, 0.0); ONSET_WINDOW];
+    let mut envelope = Vec::with_capacity(frames.len() / ONSET_HOP);
+
+    for start in (0..=frames.len() - ONSET_WINDOW).step_by(ONSET_HOP) {
+        for (i, value) in buffer.iter_mut().enumerate() {
+            let frame = frames[start + i];
+            *value = Complex::new((frame.left + frame.right) * 0.5 * window[i], 0.0);
+        }
+        fft.process(&mut buffer);
+
+        let mut flux = 0.0;
+        for (bin, magnitude) in previous.iter_mut().enumerate() {
+            // Log compression keeps quiet onsets from being drowned by loud ones
+            let current = (1.0 + buffer[bin].norm()).ln();
+            flux += (current - *magnitude).max(0.0);
+            *magnitude = current;
+        }
+        // The first window has nothing to be compared against
+        envelope.push(if start == 0 { 0.0 } else { flux });
+    }
+
+    let mean = envelope.iter().sum::<f32>() / envelope.len().max(1) as f32;
+    envelope
+        .iter_mut()
+        .for_each(|value| *value = (*value - mean).max(0.0));
+    envelope
+}
+
+fn beat_grid_energy(envelope: &[f32], phase: f32, period: f32) -> f32 {
+    let mut energy = 0.0;
+    let mut position = phase;
+    while (position as usize) < envelope.len() {
+        energy += onset_at(envelope, position);
+        position += period;
+    }
+    energy
+}
+
+fn onset_at(envelope: &[f32], position: f32) -> f32 {
+    envelope[(position.round() as usize).min(envelope.len() - 1)]
+}
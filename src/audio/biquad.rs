@@ -0,0 +1,138 @@
+// audio/biquad.rs
+
+use std::f32::consts::TAU;
+use std::ops::{Add, Mul, Sub};
+
+/// Second order IIR section with coefficients from the Audio EQ Cookbook, normalized so a0 is 1.
+#[derive(Clone, Copy, Debug)]
+pub struct Biquad {
+    b: [f32; 3],
+    a: [f32; 2],
+}
+
+impl Biquad {
+    /// Passes everything through unchanged.
+    pub const IDENTITY: Biquad = Biquad {
+        b: [1.0, 0.0, 0.0],
+        a: [0.0, 0.0],
+    };
+
+    fn new(b: [f32; 3], a: [f32; 3]) -> Self {
+        Self {
+            b: b.map(|b| b / a[0]),
+            a: [a[1] / a[0], a[2] / a[0]],
+        }
+    }
+
+    pub fn high_pass(frequency: f32, q: f32, sample_rate: f32) -> Self {
+        let (cos, alpha) = Self::omega(frequency, q, sample_rate);
+        Self::new(
+            [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    pub fn notch(frequency: f32, q: f32, sample_rate: f32) -> Self {
+        let (cos, alpha) = Self::omega(frequency, q, sample_rate);
+        Self::new(
+            [1.0, -2.0 * cos, 1.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    pub fn peak(frequency: f32, gain_db: f32, q: f32, sample_rate: f32) -> Self {
+        let amplitude = 10f32.powf(gain_db / 40.0);
+        let (cos, alpha) = Self::omega(frequency, q, sample_rate);
+        Self::new(
+            [1.0 + alpha * amplitude, -2.0 * cos, 1.0 - alpha * amplitude],
+            [1.0 + alpha / amplitude, -2.0 * cos, 1.0 - alpha / amplitude],
+        )
+    }
+
+    pub fn low_shelf(frequency: f32, gain_db: f32, q: f32, sample_rate: f32) -> Self {
+        Self::shelf(1.0, frequency, gain_db, q, sample_rate)
+    }
+
+    pub fn high_shelf(frequency: f32, gain_db: f32, q: f32, sample_rate: f32) -> Self {
+        Self::shelf(-1.0, frequency, gain_db, q, sample_rate)
+    }
+
+    // The low and high shelf only differ in the sign of a few terms
+    fn shelf(sign: f32, frequency: f32, gain_db: f32, q: f32, sample_rate: f32) -> Self {
+        let amplitude = 10f32.powf(gain_db / 40.0);
+        let (cos, alpha) = Self::omega(frequency, q, sample_rate);
+        let root = 2.0 * amplitude.sqrt() * alpha;
+        let (plus, minus) = (amplitude + 1.0, amplitude - 1.0);
+        Self::new(
+            [
+                amplitude * (plus - sign * minus * cos + root),
+                sign * 2.0 * amplitude * (minus - sign * plus * cos),
+                amplitude * (plus - sign * minus * cos - root),
+            ],
+            [
+                plus + sign * minus * cos + root,
+                -sign * 2.0 * (minus + sign * plus * cos),
+                plus + sign * minus * cos - root,
+            ],
+        )
+    }
+
+    /// cos(w0) and alpha of the cookbook formulas.
+    fn omega(frequency: f32, q: f32, sample_rate: f32) -> (f32, f32) {
+        let (sin, cos) = (TAU * frequency / sample_rate).sin_cos();
+        (cos, sin / (2.0 * q))
+    }
+
+    /// |H(e^jw)| in dB.
+    pub fn response_db(&self, frequency: f32, sample_rate: f32) -> f32 {
+        let omega = TAU * frequency / sample_rate;
+        let eval = |c: [f32; 3], w: f32| {
+            let re = c[0] + c[1] * w.cos() + c[2] * (2.0 * w).cos();
+            let im = -c[1] * w.sin() - c[2] * (2.0 * w).sin();
+            re * re + im * im
+        };
+        let numerator = eval(self.b, omega);
+        let denominator = eval([1.0, self.a[0], self.a[1]], omega);
+        10.0 * (numerator / denominator).log10()
+    }
+}
+
+/// Transposed direct form II state of one filter, for mono samples or whole frames.
+#[derive(Clone, Copy, Default)]
+pub struct BiquadState<T> {
+    z1: T,
+    z2: T,
+}
+
+impl<T> BiquadState<T>
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f32, Output = T>,
+{
+    pub fn process(&mut self, filter: &Biquad, input: T) -> T {
+        let output = input * filter.b[0] + self.z1;
+        self.z1 = input * filter.b[1] - output * filter.a[0] + self.z2;
+        self.z2 = input * filter.b[2] - output * filter.a[1];
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notch_removes_its_frequency() {
+        let sample_rate = 44_100.0;
+        let filter = Biquad::notch(60.0, 10.0, sample_rate);
+        let mut state = BiquadState::<f32>::default();
+        let output: Vec<f32> = (0..44_100)
+            .map(|i| state.process(&filter, (TAU * 60.0 * i as f32 / sample_rate).sin()))
+            .collect();
+        // Once it has settled
+        let peak = output[22_050..]
+            .iter()
+            .fold(0f32, |peak, x| peak.max(x.abs()));
+        assert!(peak < 0.01);
+        assert!(filter.response_db(1_000.0, sample_rate).abs() < 0.1);
+    }
+}
@@ -0,0 +1,110 @@
+// audio/callback_timer.rs
+
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+
+use instant::Instant;
+use kira::clock::clock_info::ClockInfoProvider;
+use kira::effect::Effect;
+use kira::modulator::value_provider::ModulatorValueProvider;
+use kira::Frame;
+
+/// Timing of the audio callbacks, written by the audio thread and read by the GUI.
+#[derive(Default)]
+pub struct CallbackStats {
+    enabled: AtomicBool,
+    last_micros: AtomicU32,
+    peak_micros: AtomicU32,
+    frames: AtomicU32,
+    sample_rate: AtomicU32,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CallbackMeasurement {
+    /// Duration of the latest callback in milliseconds
+    pub duration_ms: f32,
+    /// Longest callback since the previous measurement
+    pub peak_ms: f32,
+    /// Time the latest callback had before the output would underrun
+    pub budget_ms: f32,
+}
+
+impl CallbackStats {
+    /// Timing costs a clock read per audio frame, so it only runs while someone looks at it.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn take(&self) -> CallbackMeasurement {
+        let frames = self.frames.load(Ordering::Relaxed) as f32;
+        let sample_rate = self.sample_rate.load(Ordering::Relaxed).max(1) as f32;
+        CallbackMeasurement {
+            duration_ms: self.last_micros.load(Ordering::Relaxed) as f32 / 1000.0,
+            peak_ms: self.peak_micros.swap(0, Ordering::Relaxed) as f32 / 1000.0,
+            budget_ms: frames / sample_rate * 1000.0,
+        }
+    }
+}
+
+/// Pass-through effect on the main track measuring how long each audio callback takes, from the
+/// start of the batch until the main track has processed its last frame.
+pub struct CallbackTimer {
+    stats: Arc<CallbackStats>,
+    batch_start: Option<Instant>,
+    last_frame: Option<Instant>,
+    frames_in_batch: u32,
+}
+
+impl CallbackTimer {
+    pub fn new(stats: Arc<CallbackStats>) -> Self {
+        Self {
+            stats,
+            batch_start: None,
+            last_frame: None,
+            frames_in_batch: 0,
+        }
+    }
+}
+
+impl Effect for CallbackTimer {
+    fn init(&mut self, sample_rate: u32) {
+        self.stats.sample_rate.store(sample_rate, Ordering::Relaxed);
+    }
+
+    fn on_change_sample_rate(&mut self, sample_rate: u32) {
+        self.stats.sample_rate.store(sample_rate, Ordering::Relaxed);
+    }
+
+    fn on_start_processing(&mut self) {
+        // The previous batch is complete once the next one is requested
+        if let (Some(start), Some(end)) = (self.batch_start, self.last_frame) {
+            let micros = end.duration_since(start).as_micros().min(u32::MAX as u128) as u32;
+            self.stats.last_micros.store(micros, Ordering::Relaxed);
+            self.stats.peak_micros.fetch_max(micros, Ordering::Relaxed);
+            self.stats
+                .frames
+                .store(self.frames_in_batch, Ordering::Relaxed);
+        }
+        self.last_frame = None;
+        self.frames_in_batch = 0;
+        self.batch_start = self.stats.is_enabled().then(Instant::now);
+    }
+
+    fn process(
+        &mut self,
+        input: Frame,
+        _dt: f64,
+        _clock_info_provider: &ClockInfoProvider,
+        _modulator_value_provider: &ModulatorValueProvider,
+    ) -> Frame {
+        if self.batch_start.is_some() {
+            self.last_frame = Some(Instant::now());
+            self.frames_in_batch += 1;
+        }
+        input
+    }
+}
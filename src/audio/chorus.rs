@@ -0,0 +1,188 @@
+// audio/chorus.rs
+
+use std::f32::consts::TAU;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use kira::Frame;
+
+use crate::audio::effect_chain::AudioEffect;
+
+// Delay the modulation swings around, short enough to sound like a second string
+const CHORUS_DELAY_MS: f32 = 7.0;
+pub const MIN_DELAY_MS: f32 = 0.5;
+pub const MAX_DELAY_MS: f32 = 15.0;
+pub const MAX_DEPTH_MS: f32 = 10.0;
+// Any closer to 1 and the delay rings on into a howl
+pub const MAX_FEEDBACK: f32 = 0.9;
+
+/// A copy of the signal with a slowly swinging delay mixed back in, like a second guitar
+/// playing along slightly out of tune. With a delay of a millisecond or two and some feedback
+/// it becomes a flanger's sweeping comb filter instead.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChorusSettings {
+    /// Speed of the delay swing in Hz
+    pub rate: f32,
+    /// How far the delay swings in milliseconds
+    pub depth: f32,
+    /// 0 for the dry signal only, 1 for the delayed copy only
+    pub mix: f32,
+    /// Shortest delay of the swing in milliseconds
+    pub delay: f32,
+    /// Share of the delayed copy fed back into the delay
+    pub feedback: f32,
+}
+
+impl ChorusSettings {
+    pub fn off() -> Self {
+        Self {
+            mix: 0.0,
+            ..Self::chorus(0.8, 3.0, 0.0)
+        }
+    }
+
+    /// A chorus swinging `depth` milliseconds at `rate` Hz, mixed in at `mix`.
+    pub fn chorus(rate: f32, depth: f32, mix: f32) -> Self {
+        Self {
+            rate,
+            depth,
+            mix,
+            delay: CHORUS_DELAY_MS,
+            feedback: 0.0,
+        }
+    }
+}
+
+/// Chorus settings shared between the GUI and the audio thread.
+pub struct ChorusControl {
+    rate: AtomicU32,
+    depth: AtomicU32,
+    mix: AtomicU32,
+    delay: AtomicU32,
+    feedback: AtomicU32,
+}
+
+impl ChorusControl {
+    pub fn new(settings: &ChorusSettings) -> Self {
+        let control = Self {
+            rate: AtomicU32::new(0),
+            depth: AtomicU32::new(0),
+            mix: AtomicU32::new(0),
+            delay: AtomicU32::new(0),
+            feedback: AtomicU32::new(0),
+        };
+        control.set(settings);
+        control
+    }
+
+    pub fn set(&self, settings: &ChorusSettings) {
+        self.rate.store(settings.rate.to_bits(), Ordering::Relaxed);
+        self.depth
+            .store(settings.depth.to_bits(), Ordering::Relaxed);
+        self.mix.store(settings.mix.to_bits(), Ordering::Relaxed);
+        self.delay
+            .store(settings.delay.to_bits(), Ordering::Relaxed);
+        self.feedback
+            .store(settings.feedback.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> ChorusSettings {
+        ChorusSettings {
+            rate: f32::from_bits(self.rate.load(Ordering::Relaxed)),
+            depth: f32::from_bits(self.depth.load(Ordering::Relaxed)),
+            mix: f32::from_bits(self.mix.load(Ordering::Relaxed)),
+            delay: f32::from_bits(self.delay.load(Ordering::Relaxed)),
+            feedback: f32::from_bits(self.feedback.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// Stereo chorus on the guitar track. The left and right delays swing a quarter period apart,
+/// which spreads the sound over the stereo field.
+pub struct ChorusEffect {
+    control: Arc<ChorusControl>,
+    settings: ChorusSettings,
+    sample_rate: f32,
+    buffer: Vec<Frame>,
+    write: usize,
+    phase: f32,
+}
+
+impl ChorusEffect {
+    pub fn new(control: Arc<ChorusControl>) -> Self {
+        Self {
+            settings: control.get(),
+            control,
+            sample_rate: 0.0,
+            buffer: Vec::new(),
+            write: 0,
+            phase: 0.0,
+        }
+    }
+
+    fn allocate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        let length = ((MAX_DELAY_MS + MAX_DEPTH_MS) / 1000.0 * self.sample_rate) as usize + 2;
+        self.buffer = vec![Frame::ZERO; length];
+        self.write = 0;
+    }
+
+    /// The buffered frame `delay` samples back, interpolated between the two nearest ones.
+    fn delayed(&self, delay: f32) -> Frame {
+        let len = self.buffer.len();
+        let whole = delay.floor() as usize;
+        let fraction = delay - whole as f32;
+        let newer = self.buffer[(self.write + len - whole) % len];
+        let older = self.buffer[(self.write + len - whole - 1) % len];
+        newer + (older - newer) * fraction
+    }
+
+    fn process_frame(&mut self, input: Frame) -> Frame {
+        let len = self.buffer.len();
+        self.write = (self.write + 1) % len;
+        let ChorusSettings {
+            rate,
+            depth,
+            mix,
+            delay,
+            feedback,
+        } = self.settings;
+        if mix <= 0.0 {
+            self.buffer[self.write] = input;
+            return input;
+        }
+
+        self.phase = (self.phase + rate / self.sample_rate).fract();
+        let samples_per_ms = self.sample_rate / 1000.0;
+        let base = delay.clamp(MIN_DELAY_MS, MAX_DELAY_MS);
+        let depth = depth.clamp(0.0, MAX_DEPTH_MS);
+        let delay =
+            |phase: f32| (base + depth * 0.5 * (1.0 + (phase * TAU).sin())) * samples_per_ms;
+        // Read before the input is written, the shortest delay is still whole samples back
+        let wet = Frame {
+            left: self.delayed(delay(self.phase)).left,
+            right: self.delayed(delay(self.phase + 0.25)).right,
+        };
+        self.buffer[self.write] = input + wet * feedback.clamp(0.0, MAX_FEEDBACK);
+        input + (wet - input) * mix
+    }
+}
+
+impl AudioEffect for ChorusEffect {
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.allocate(sample_rate);
+    }
+
+    fn update(&mut self) {
+        self.settings = self.control.get();
+    }
+
+    fn process(&mut self, frames: &mut [Frame]) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        for frame in frames {
+            *frame = self.process_frame(*frame);
+        }
+    }
+}
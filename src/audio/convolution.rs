@@ -0,0 +1,334 @@
+// audio/convolution.rs
+
+use std::io::Cursor;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use kira::sound::static_sound::StaticSoundData;
+use kira::Frame;
+use rustfft::num_complex::Complex32;
+use rustfft::{Fft, FftPlanner};
+
+use crate::audio::effect_chain::AudioEffect;
+
+pub const ACOUSTIC_BODY: &str = "Acoustic body";
+pub const JUMBO_BODY: &str = "Jumbo body";
+pub const CABINET_1X12: &str = "1x12 cabinet";
+
+// Samples per partition of the impulse response, which is also the latency of the convolution
+const BLOCK_SIZE: usize = 256;
+// Samples over which a new response fades in and the old one out
+const CROSSFADE_SAMPLES: usize = 2048;
+// Longer impulse responses are cut, a body or cabinet rings much shorter than this
+const MAX_SECONDS: f32 = 1.0;
+
+/// The sound of a guitar body or speaker cabinet to a single click, convolved with the synth
+/// output to make it sound as if played through it.
+pub struct ImpulseResponse {
+    pub name: String,
+    samples: Vec<f32>,
+}
+
+impl ImpulseResponse {
+    /// Decodes any format supported by symphonia, mixed down to mono and resampled to
+    /// `sample_rate`. The response is scaled to unit energy so it keeps the overall loudness.
+    pub fn from_bytes(name: String, data: Vec<u8>, sample_rate: f32) -> Result<Self, String> {
+        let sound = StaticSoundData::from_cursor(Cursor::new(data)).map_err(|e| e.to_string())?;
+        let mono: Vec<f32> = sound
+            .frames
+            .iter()
+            .map(|frame| (frame.left + frame.right) / 2.0)
+            .collect();
+        if mono.is_empty() {
+            return Err("The impulse response is empty".to_string());
+        }
+
+        // Linear interpolation is plenty for a response this short
+        let step = sound.sample_rate as f32 / sample_rate;
+        let length =
+            ((mono.len() as f32 / step) as usize).min((MAX_SECONDS * sample_rate) as usize);
+        let mut samples: Vec<f32> = (0..length)
+            .map(|idx| {
+                let position = idx as f32 * step;
+                let whole = position as usize;
+                let next = mono.get(whole + 1).copied().unwrap_or(0.0);
+                mono[whole] + (next - mono[whole]) * position.fract()
+            })
+            .collect();
+
+        let energy = samples.iter().map(|s| s * s).sum::<f32>().sqrt();
+        if energy <= f32::EPSILON {
+            return Err("The impulse response is silent".to_string());
+        }
+        for sample in &mut samples {
+            *sample /= energy;
+        }
+        Ok(Self { name, samples })
+    }
+
+    /// The responses that come with the app.
+    pub fn bundled(sample_rate: f32) -> Vec<Self> {
+        let files: [(&str, &[u8]); 3] = [
+            (
+                ACOUSTIC_BODY,
+                include_bytes!("../../assets/impulse_responses/acoustic_body.wav"),
+            ),
+            (
+                JUMBO_BODY,
+                include_bytes!("../../assets/impulse_responses/jumbo_body.wav"),
+            ),
+            (
+                CABINET_1X12,
+                include_bytes!("../../assets/impulse_responses/cabinet_1x12.wav"),
+            ),
+        ];
+        files
+            .into_iter()
+            .filter_map(|(name, data)| {
+                Self::from_bytes(name.to_string(), data.to_vec(), sample_rate)
+                    .map_err(|e| eprintln!("Failed to load impulse response {}: {}", name, e))
+                    .ok()
+            })
+            .collect()
+    }
+}
+
+/// Input and output of one channel of the convolution.
+struct Channel {
+    // The previous and the current block of input
+    input: Vec<f32>,
+    // Spectra of the latest input blocks, one per partition of the response
+    history: Vec<Vec<Complex32>>,
+    output: Vec<f32>,
+}
+
+/// Uniformly partitioned overlap-save convolution of a stereo signal with an impulse response.
+/// Frames come out `BLOCK_SIZE` samples late.
+pub struct Convolver {
+    // Spectrum of every `BLOCK_SIZE` long part of the response
+    partitions: Vec<Vec<Complex32>>,
+    forward: Arc<dyn Fft<f32>>,
+    inverse: Arc<dyn Fft<f32>>,
+    channels: [Channel; 2],
+    // Samples of the current block received so far
+    filled: usize,
+    // Slot of `history` holding the newest block
+    newest: usize,
+    spectrum: Vec<Complex32>,
+    accumulator: Vec<Complex32>,
+    scratch: Vec<Complex32>,
+}
+
+impl Convolver {
+    pub fn new(impulse_response: &ImpulseResponse) -> Self {
+        let fft_size = 2 * BLOCK_SIZE;
+        let mut planner = FftPlanner::<f32>::new();
+        let forward = planner.plan_fft_forward(fft_size);
+        let inverse = planner.plan_fft_inverse(fft_size);
+
+        let partitions: Vec<Vec<Complex32>> = impulse_response
+            .samples
+            .chunks(BLOCK_SIZE)
+            .map(|part| {
+                let mut spectrum = vec![Complex32::new(0.0, 0.0); fft_size];
+                for (bin, &sample) in spectrum.iter_mut().zip(part) {
+                    bin.re = sample;
+                }
+                forward.process(&mut spectrum);
+                spectrum
+            })
+            .collect();
+        let channel = || Channel {
+            input: vec![0.0; fft_size],
+            history: vec![vec![Complex32::new(0.0, 0.0); fft_size]; partitions.len()],
+            output: vec![0.0; BLOCK_SIZE],
+        };
+        let scratch_len = forward
+            .get_inplace_scratch_len()
+            .max(inverse.get_inplace_scratch_len());
+        Self {
+            channels: [channel(), channel()],
+            partitions,
+            forward,
+            inverse,
+            filled: 0,
+            newest: 0,
+            spectrum: vec![Complex32::new(0.0, 0.0); fft_size],
+            accumulator: vec![Complex32::new(0.0, 0.0); fft_size],
+            scratch: vec![Complex32::new(0.0, 0.0); scratch_len],
+        }
+    }
+
+    /// Takes the next input frame and returns the dry and the convolved frame from
+    /// `BLOCK_SIZE` samples ago.
+    pub fn process(&mut self, input: Frame) -> (Frame, Frame) {
+        let [left, right] = &mut self.channels;
+        let dry = Frame::new(left.input[self.filled], right.input[self.filled]);
+        let wet = Frame::new(left.output[self.filled], right.output[self.filled]);
+        left.input[BLOCK_SIZE + self.filled] = input.left;
+        right.input[BLOCK_SIZE + self.filled] = input.right;
+        self.filled += 1;
+        if self.filled == BLOCK_SIZE {
+            self.filled = 0;
+            self.newest = (self.newest + 1) % self.partitions.len();
+            for channel in 0..2 {
+                self.convolve_block(channel);
+            }
+        }
+        (dry, wet)
+    }
+
+    fn convolve_block(&mut self, channel: usize) {
+        let parts = self.partitions.len();
+        let state = &mut self.channels[channel];
+        for (bin, &sample) in self.spectrum.iter_mut().zip(&state.input) {
+            *bin = Complex32::new(sample, 0.0);
+        }
+        self.forward
+            .process_with_scratch(&mut self.spectrum, &mut self.scratch);
+        state.history[self.newest].copy_from_slice(&self.spectrum);
+
+        // The newest block meets the start of the response, older blocks later parts of it
+        self.accumulator.fill(Complex32::new(0.0, 0.0));
+        for (age, partition) in self.partitions.iter().enumerate() {
+            let block = &state.history[(self.newest + parts - age) % parts];
+            for ((sum, x), h) in self.accumulator.iter_mut().zip(block).zip(partition) {
+                *sum += x * h;
+            }
+        }
+        self.inverse
+            .process_with_scratch(&mut self.accumulator, &mut self.scratch);
+
+        // The first half wrapped around from the previous block and is discarded
+        let scale = 1.0 / self.accumulator.len() as f32;
+        for (out, sum) in state.output.iter_mut().zip(&self.accumulator[BLOCK_SIZE..]) {
+            *out = sum.re * scale;
+        }
+        state.input.copy_within(BLOCK_SIZE.., 0);
+    }
+}
+
+/// Impulse response and mix shared between the GUI and the audio thread. New responses are
+/// prepared on the GUI thread and picked up by the effect at its next batch.
+pub struct ConvolutionControl {
+    pending: Mutex<Option<Option<Box<Convolver>>>>,
+    mix: AtomicU32,
+}
+
+impl ConvolutionControl {
+    pub fn new() -> Self {
+        Self {
+            pending: Mutex::new(None),
+            mix: AtomicU32::new(0),
+        }
+    }
+
+    /// Starts convolving with `impulse_response`, or stops convolving for `None`.
+    pub fn set_impulse_response(&self, impulse_response: Option<&ImpulseResponse>) {
+        let convolver = impulse_response.map(|ir| Box::new(Convolver::new(ir)));
+        if let Ok(mut pending) = self.pending.lock() {
+            *pending = Some(convolver);
+        }
+    }
+
+    pub fn set_mix(&self, mix: f32) {
+        self.mix.store(mix.to_bits(), Ordering::Relaxed);
+    }
+}
+
+impl Default for ConvolutionControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Convolution with a body or cabinet impulse response on the guitar track.
+pub struct ConvolutionEffect {
+    control: Arc<ConvolutionControl>,
+    convolver: Option<Box<Convolver>>,
+    // The response being replaced and how many samples it has left to fade out
+    fading_out: Option<Box<Convolver>>,
+    fade_remaining: usize,
+    mix: f32,
+}
+
+impl ConvolutionEffect {
+    pub fn new(control: Arc<ConvolutionControl>) -> Self {
+        Self {
+            control,
+            convolver: None,
+            fading_out: None,
+            fade_remaining: 0,
+            mix: 0.0,
+        }
+    }
+}
+
+fn convolve(convolver: &mut Option<Box<Convolver>>, input: Frame, mix: f32) -> Frame {
+    match convolver {
+        Some(convolver) => {
+            let (dry, wet) = convolver.process(input);
+            dry + (wet - dry) * mix
+        }
+        None => input,
+    }
+}
+
+impl ConvolutionEffect {
+    fn process_frame(&mut self, input: Frame) -> Frame {
+        let output = convolve(&mut self.convolver, input, self.mix);
+        if self.fade_remaining == 0 {
+            return output;
+        }
+        let old = convolve(&mut self.fading_out, input, self.mix);
+        let fade = self.fade_remaining as f32 / CROSSFADE_SAMPLES as f32;
+        self.fade_remaining -= 1;
+        if self.fade_remaining == 0 {
+            self.fading_out = None;
+        }
+        output + (old - output) * fade
+    }
+}
+
+impl AudioEffect for ConvolutionEffect {
+    fn update(&mut self) {
+        // See `AudioEffect::update`
+        if let Ok(mut pending) = self.control.pending.try_lock() {
+            if let Some(convolver) = pending.take() {
+                self.fading_out = std::mem::replace(&mut self.convolver, convolver);
+                self.fade_remaining = CROSSFADE_SAMPLES;
+            }
+        }
+        self.mix = f32::from_bits(self.control.mix.load(Ordering::Relaxed));
+    }
+
+    fn process(&mut self, frames: &mut [Frame]) {
+        for frame in frames {
+            *frame = self.process_frame(*frame);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convolving_with_a_click_delays_the_signal() {
+        let click = ImpulseResponse {
+            name: "Click".to_string(),
+            samples: vec![1.0],
+        };
+        let mut convolver = Convolver::new(&click);
+        let input: Vec<f32> = (0..3 * BLOCK_SIZE)
+            .map(|i| (i as f32 * 0.1).sin())
+            .collect();
+        let output: Vec<f32> = input
+            .iter()
+            .map(|&sample| convolver.process(Frame::from_mono(sample)).1.left)
+            .collect();
+        for (out, expected) in output[BLOCK_SIZE..].iter().zip(&input) {
+            assert!((out - expected).abs() < 1e-4);
+        }
+    }
+}
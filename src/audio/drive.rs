@@ -0,0 +1,118 @@
+// audio/drive.rs
+
+use std::f32::consts::TAU;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use kira::Frame;
+
+use crate::audio::effect_chain::AudioEffect;
+
+// Gain in front of the clipper at full drive, enough to square off a plucked note
+const MAX_DRIVE_DB: f32 = 36.0;
+// Corner of the tone control from fully dark to fully bright
+const DARK_CUTOFF_HZ: f32 = 800.0;
+const BRIGHT_CUTOFF_HZ: f32 = 8000.0;
+
+/// An overdriven amplifier, clipping the strings softly and rounding off the fizz that leaves.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DriveSettings {
+    /// 0 leaves the signal clean, 1 drives the clipper the hardest
+    pub gain: f32,
+    /// 0 for a dark tone, 1 for a bright one
+    pub tone: f32,
+    /// Output level after the clipper
+    pub level: f32,
+}
+
+impl DriveSettings {
+    pub fn off() -> Self {
+        Self {
+            gain: 0.0,
+            tone: 0.5,
+            level: 0.5,
+        }
+    }
+}
+
+/// Drive settings shared between the GUI and the audio thread.
+pub struct DriveControl {
+    gain: AtomicU32,
+    tone: AtomicU32,
+    level: AtomicU32,
+}
+
+impl DriveControl {
+    pub fn new(settings: &DriveSettings) -> Self {
+        let control = Self {
+            gain: AtomicU32::new(0),
+            tone: AtomicU32::new(0),
+            level: AtomicU32::new(0),
+        };
+        control.set(settings);
+        control
+    }
+
+    pub fn set(&self, settings: &DriveSettings) {
+        self.gain.store(settings.gain.to_bits(), Ordering::Relaxed);
+        self.tone.store(settings.tone.to_bits(), Ordering::Relaxed);
+        self.level
+            .store(settings.level.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> DriveSettings {
+        DriveSettings {
+            gain: f32::from_bits(self.gain.load(Ordering::Relaxed)),
+            tone: f32::from_bits(self.tone.load(Ordering::Relaxed)),
+            level: f32::from_bits(self.level.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// Waveshaping drive on the guitar track, like an amplifier in front of its cabinet.
+pub struct DriveEffect {
+    control: Arc<DriveControl>,
+    settings: DriveSettings,
+    sample_rate: f32,
+    // State of the one pole low-pass of the tone control
+    filtered: Frame,
+}
+
+impl DriveEffect {
+    pub fn new(control: Arc<DriveControl>) -> Self {
+        Self {
+            settings: control.get(),
+            control,
+            sample_rate: 0.0,
+            filtered: Frame::ZERO,
+        }
+    }
+}
+
+impl AudioEffect for DriveEffect {
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+    }
+
+    fn update(&mut self) {
+        self.settings = self.control.get();
+    }
+
+    fn process(&mut self, frames: &mut [Frame]) {
+        let DriveSettings { gain, tone, level } = self.settings;
+        if gain <= 0.0 || self.sample_rate <= 0.0 {
+            return;
+        }
+        let drive = 10f32.powf(gain.clamp(0.0, 1.0) * MAX_DRIVE_DB / 20.0);
+        // Cutoff sweeps evenly in pitch from dark to bright
+        let cutoff =
+            DARK_CUTOFF_HZ * (BRIGHT_CUTOFF_HZ / DARK_CUTOFF_HZ).powf(tone.clamp(0.0, 1.0));
+        let coefficient = 1.0 - (-TAU * cutoff / self.sample_rate).exp();
+        for frame in frames {
+            // Soft clipping, rounding off the peaks instead of cutting them flat
+            let shaped = Frame::new((frame.left * drive).tanh(), (frame.right * drive).tanh());
+            self.filtered += (shaped - self.filtered) * coefficient;
+            *frame = self.filtered * level;
+        }
+    }
+}
@@ -0,0 +1,169 @@
+// audio/effect_chain.rs
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use kira::clock::clock_info::ClockInfoProvider;
+use kira::effect::Effect;
+use kira::modulator::value_provider::ModulatorValueProvider;
+use kira::Frame;
+
+// Frames processed between picking up settings when rendering offline, about a playback batch
+const OFFLINE_BLOCK: usize = 512;
+
+/// A stage the guitar's sound goes through after the strings, run on the audio thread.
+pub trait AudioEffect: Send + Sync {
+    /// Called before the first frames and whenever the output's sample rate changes.
+    fn set_sample_rate(&mut self, _sample_rate: f32) {}
+
+    /// Picks up settings changed from the GUI, once per batch of frames. Never waits for the GUI
+    /// thread: settings that are locked right now can as well start next batch.
+    fn update(&mut self) {}
+
+    /// Processes `frames` in place, oldest first.
+    fn process(&mut self, frames: &mut [Frame]);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EffectKind {
+    Drive,
+    Body,
+    Chorus,
+    Eq,
+}
+
+impl EffectKind {
+    pub const ALL: [EffectKind; 4] = [
+        EffectKind::Drive,
+        EffectKind::Body,
+        EffectKind::Chorus,
+        EffectKind::Eq,
+    ];
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            EffectKind::Drive => "Soft clipping like an overdriven amplifier",
+            EffectKind::Body => "Convolution with the body or cabinet impulse response",
+            EffectKind::Chorus => "Chorus or flanger",
+            EffectKind::Eq => "Four band parametric EQ",
+        }
+    }
+}
+
+impl fmt::Display for EffectKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EffectKind::Drive => write!(f, "Drive"),
+            EffectKind::Body => write!(f, "Body / cabinet"),
+            EffectKind::Chorus => write!(f, "Chorus"),
+            EffectKind::Eq => write!(f, "EQ"),
+        }
+    }
+}
+
+/// The drive comes first like the amp, then its body or cabinet, the chorus after it like a
+/// pedal in the amp's effects loop, and the EQ shapes the result.
+pub const DEFAULT_CHAIN: [EffectKind; 4] = EffectKind::ALL;
+
+/// Order of the effects waiting to be picked up by the audio thread.
+#[derive(Default)]
+pub struct EffectChainControl {
+    pending: Mutex<Option<Vec<EffectKind>>>,
+}
+
+impl EffectChainControl {
+    pub fn set(&self, chain: &[EffectKind]) {
+        if let Ok(mut pending) = self.pending.lock() {
+            *pending = Some(chain.to_vec());
+        }
+    }
+}
+
+/// The guitar's effects on its track, run in the order of its chain. Effects left out of the
+/// chain keep up with their settings but don't run.
+pub struct EffectChain {
+    control: Arc<EffectChainControl>,
+    effects: Vec<(EffectKind, Box<dyn AudioEffect>)>,
+    // Indices into `effects` in the order they run
+    order: Vec<usize>,
+}
+
+impl EffectChain {
+    pub fn new(
+        control: Arc<EffectChainControl>,
+        effects: Vec<(EffectKind, Box<dyn AudioEffect>)>,
+        chain: &[EffectKind],
+    ) -> Self {
+        let mut effect_chain = Self {
+            control,
+            effects,
+            order: Vec::new(),
+        };
+        effect_chain.set_chain(chain);
+        effect_chain
+    }
+
+    fn set_chain(&mut self, chain: &[EffectKind]) {
+        self.order.clear();
+        for kind in chain {
+            let Some(idx) = self.effects.iter().position(|(known, _)| known == kind) else {
+                continue;
+            };
+            // Each effect has one state, so it can only run once
+            if !self.order.contains(&idx) {
+                self.order.push(idx);
+            }
+        }
+    }
+
+    /// Runs `frames` through the chain without an audio device, as fast as it can.
+    pub fn render(&mut self, frames: &mut [Frame], sample_rate: u32) {
+        self.init(sample_rate);
+        for block in frames.chunks_mut(OFFLINE_BLOCK) {
+            self.on_start_processing();
+            for &idx in &self.order {
+                self.effects[idx].1.process(block);
+            }
+        }
+    }
+}
+
+impl Effect for EffectChain {
+    fn init(&mut self, sample_rate: u32) {
+        for (_, effect) in &mut self.effects {
+            effect.set_sample_rate(sample_rate as f32);
+        }
+    }
+
+    fn on_change_sample_rate(&mut self, sample_rate: u32) {
+        self.init(sample_rate);
+    }
+
+    fn on_start_processing(&mut self) {
+        // Picked up like the effects' settings, see `AudioEffect::update`
+        let pending = match self.control.pending.try_lock() {
+            Ok(mut pending) => pending.take(),
+            Err(_) => None,
+        };
+        if let Some(chain) = pending {
+            self.set_chain(&chain);
+        }
+        for (_, effect) in &mut self.effects {
+            effect.update();
+        }
+    }
+
+    fn process(
+        &mut self,
+        input: Frame,
+        _dt: f64,
+        _clock_info_provider: &ClockInfoProvider,
+        _modulator_value_provider: &ModulatorValueProvider,
+    ) -> Frame {
+        let mut frames = [input];
+        for &idx in &self.order {
+            self.effects[idx].1.process(&mut frames);
+        }
+        frames[0]
+    }
+}
@@ -0,0 +1,238 @@
+// audio/equalizer.rs
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use kira::Frame;
+
+use crate::audio::biquad::{Biquad, BiquadState};
+use crate::audio::effect_chain::AudioEffect;
+
+pub const NUM_BANDS: usize = 4;
+pub const MAX_GAIN_DB: f32 = 18.0;
+pub const FREQUENCY_RANGE: (f32, f32) = (20.0, 20_000.0);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BandKind {
+    LowShelf,
+    Peak,
+    HighShelf,
+}
+
+impl fmt::Display for BandKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BandKind::LowShelf => write!(f, "Low shelf"),
+            BandKind::Peak => write!(f, "Peak"),
+            BandKind::HighShelf => write!(f, "High shelf"),
+        }
+    }
+}
+
+// The outer bands shape the lows and highs, the middle two are free to move
+pub const BAND_KINDS: [BandKind; NUM_BANDS] = [
+    BandKind::LowShelf,
+    BandKind::Peak,
+    BandKind::Peak,
+    BandKind::HighShelf,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EqBand {
+    pub frequency: f32,
+    pub gain_db: f32,
+    pub q: f32,
+}
+
+/// Four band parametric EQ, see `BAND_KINDS` for the shape of each band.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EqSettings {
+    pub bands: [EqBand; NUM_BANDS],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EqPreset {
+    Flat,
+    Warm,
+    Bright,
+    Scooped,
+    LessBoxy,
+}
+
+impl EqPreset {
+    pub const ALL: [EqPreset; 5] = [
+        EqPreset::Flat,
+        EqPreset::Warm,
+        EqPreset::Bright,
+        EqPreset::Scooped,
+        EqPreset::LessBoxy,
+    ];
+
+    /// Gains of the four bands at their default frequencies.
+    fn gains(&self) -> [f32; NUM_BANDS] {
+        match self {
+            EqPreset::Flat => [0.0, 0.0, 0.0, 0.0],
+            EqPreset::Warm => [3.0, 1.5, -2.0, -3.0],
+            EqPreset::Bright => [-1.0, 0.0, 2.0, 4.0],
+            EqPreset::Scooped => [3.0, -4.0, -2.0, 3.0],
+            EqPreset::LessBoxy => [0.0, -5.0, 0.0, 1.5],
+        }
+    }
+
+    pub fn settings(&self) -> EqSettings {
+        let mut settings = EqSettings::flat();
+        for (band, gain_db) in settings.bands.iter_mut().zip(self.gains()) {
+            band.gain_db = gain_db;
+        }
+        settings
+    }
+}
+
+impl fmt::Display for EqPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EqPreset::Flat => write!(f, "Flat"),
+            EqPreset::Warm => write!(f, "Warm"),
+            EqPreset::Bright => write!(f, "Bright"),
+            EqPreset::Scooped => write!(f, "Scooped"),
+            EqPreset::LessBoxy => write!(f, "Less boxy"),
+        }
+    }
+}
+
+impl EqSettings {
+    pub fn flat() -> Self {
+        let band = |frequency| EqBand {
+            frequency,
+            gain_db: 0.0,
+            q: 0.9,
+        };
+        Self {
+            bands: [band(120.0), band(350.0), band(2_500.0), band(6_000.0)],
+        }
+    }
+
+    pub fn is_flat(&self) -> bool {
+        self.bands.iter().all(|band| band.gain_db == 0.0)
+    }
+
+    /// Gain of the whole EQ at `frequency` in dB, for drawing its curve.
+    pub fn response_db(&self, frequency: f32, sample_rate: f32) -> f32 {
+        self.bands
+            .iter()
+            .zip(BAND_KINDS)
+            .map(|(band, kind)| {
+                band_filter(kind, band, sample_rate).response_db(frequency, sample_rate)
+            })
+            .sum()
+    }
+}
+
+fn band_filter(kind: BandKind, band: &EqBand, sample_rate: f32) -> Biquad {
+    let frequency = band.frequency.min(sample_rate * 0.49);
+    let q = band.q.max(0.05);
+    match kind {
+        BandKind::LowShelf => Biquad::low_shelf(frequency, band.gain_db, q, sample_rate),
+        BandKind::Peak => Biquad::peak(frequency, band.gain_db, q, sample_rate),
+        BandKind::HighShelf => Biquad::high_shelf(frequency, band.gain_db, q, sample_rate),
+    }
+}
+
+/// EQ settings waiting to be picked up by the audio thread.
+#[derive(Default)]
+pub struct EqualizerControl {
+    pending: Mutex<Option<EqSettings>>,
+}
+
+impl EqualizerControl {
+    pub fn set(&self, settings: &EqSettings) {
+        if let Ok(mut pending) = self.pending.lock() {
+            *pending = Some(*settings);
+        }
+    }
+}
+
+/// The profile's EQ on the guitar track.
+pub struct EqualizerEffect {
+    control: Arc<EqualizerControl>,
+    settings: EqSettings,
+    sample_rate: f32,
+    filters: [Biquad; NUM_BANDS],
+    states: [BiquadState<Frame>; NUM_BANDS],
+    bypass: bool,
+}
+
+impl EqualizerEffect {
+    pub fn new(control: Arc<EqualizerControl>, settings: EqSettings) -> Self {
+        let mut effect = Self {
+            control,
+            settings,
+            sample_rate: 44_100.0,
+            filters: [Biquad::IDENTITY; NUM_BANDS],
+            states: [BiquadState::default(); NUM_BANDS],
+            bypass: true,
+        };
+        effect.update_filters();
+        effect
+    }
+
+    fn update_filters(&mut self) {
+        for ((filter, band), kind) in self
+            .filters
+            .iter_mut()
+            .zip(&self.settings.bands)
+            .zip(BAND_KINDS)
+        {
+            *filter = band_filter(kind, band, self.sample_rate);
+        }
+        self.bypass = self.settings.is_flat();
+    }
+}
+
+impl AudioEffect for EqualizerEffect {
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.update_filters();
+    }
+
+    fn update(&mut self) {
+        let pending = match self.control.pending.try_lock() {
+            Ok(mut pending) => pending.take(),
+            Err(_) => None,
+        };
+        if let Some(settings) = pending {
+            self.settings = settings;
+            self.update_filters();
+        }
+    }
+
+    fn process(&mut self, frames: &mut [Frame]) {
+        if self.bypass {
+            return;
+        }
+        for frame in frames {
+            let mut signal = *frame;
+            for (filter, state) in self.filters.iter().zip(&mut self.states) {
+                signal = state.process(filter, signal);
+            }
+            *frame = signal;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn peak_band_boosts_its_frequency_only() {
+        let mut settings = EqSettings::flat();
+        settings.bands[1] = EqBand {
+            frequency: 1_000.0,
+            gain_db: 6.0,
+            q: 1.0,
+        };
+        assert!((settings.response_db(1_000.0, 44_100.0) - 6.0).abs() < 0.1);
+        assert!(settings.response_db(50.0, 44_100.0).abs() < 0.5);
+    }
+}
@@ -0,0 +1,109 @@
+// audio/input_capture.rs
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample, Stream, StreamConfig};
+
+use crate::audio::input_filter::{InputFilter, InputFilterSettings};
+use crate::audio::output_devices::find_input_device;
+
+/// Recent microphone input, mono and filtered, shared with the input stream's callback.
+pub type InputBuffer = Arc<Mutex<VecDeque<f32>>>;
+
+/// Starts recording the named input device, or the default one, into `samples`, keeping at most `max_seconds` of
+/// input the reader hasn't taken yet. Returns the stream, which records while it is alive, and
+/// the input's sample rate.
+pub fn start_input_stream(
+    device: Option<&str>,
+    filter: InputFilterSettings,
+    samples: InputBuffer,
+    max_seconds: f32,
+) -> Result<(Stream, u32), String> {
+    let device = match device {
+        Some(name) => find_input_device(name)?,
+        None => cpal::default_host()
+            .default_input_device()
+            .ok_or("No input device")?,
+    };
+    let supported = device.default_input_config().map_err(|e| e.to_string())?;
+    let config: StreamConfig = supported.config();
+    let rate = config.sample_rate.0;
+    let max_samples = (max_seconds * rate as f32) as usize;
+
+    let filter = InputFilter::new(filter, rate as f32);
+    let stream = match supported.sample_format() {
+        SampleFormat::F32 => build_stream::<f32>(&device, &config, samples, max_samples, filter),
+        SampleFormat::I16 => build_stream::<i16>(&device, &config, samples, max_samples, filter),
+        SampleFormat::U16 => build_stream::<u16>(&device, &config, samples, max_samples, filter),
+        format => Err(format!("Unsupported input format {}", format)),
+    }?;
+    stream.play().map_err(|e| e.to_string())?;
+    Ok((stream, rate))
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    samples: InputBuffer,
+    max_samples: usize,
+    mut filter: InputFilter,
+) -> Result<Stream, String>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let channels = config.channels.max(1) as usize;
+    device
+        .build_input_stream(
+            config,
+            move |data: &[T], _| {
+                let Ok(mut samples) = samples.lock() else {
+                    return;
+                };
+                // Mix all input channels down to mono
+                samples.extend(data.chunks(channels).map(|frame| {
+                    let mono =
+                        frame.iter().map(|&s| s.to_sample::<f32>()).sum::<f32>() / channels as f32;
+                    filter.process(mono)
+                }));
+                let excess = samples.len().saturating_sub(max_samples);
+                samples.drain(..excess);
+            },
+            |e| eprintln!("Input stream error: {}", e),
+            None,
+        )
+        .map_err(|e| e.to_string())
+}
+
+/// Records the microphone for the UI to analyse, without playing it back.
+pub struct InputCapture {
+    _stream: Stream,
+    samples: InputBuffer,
+    pub sample_rate: u32,
+}
+
+impl InputCapture {
+    // Enough for a few frames at a low frame rate
+    const MAX_SECONDS: f32 = 0.5;
+
+    pub fn start(device: Option<&str>, filter: InputFilterSettings) -> Result<Self, String> {
+        let samples = InputBuffer::default();
+        let (stream, sample_rate) =
+            start_input_stream(device, filter, samples.clone(), Self::MAX_SECONDS)?;
+        Ok(Self {
+            _stream: stream,
+            samples,
+            sample_rate,
+        })
+    }
+
+    /// Input recorded since the last call.
+    pub fn take(&self) -> Vec<f32> {
+        match self.samples.lock() {
+            Ok(mut samples) => samples.drain(..).collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
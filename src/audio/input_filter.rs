@@ -0,0 +1,95 @@
+// audio/input_filter.rs
+
+use std::fmt;
+
+use crate::audio::biquad::{Biquad, BiquadState};
+
+// Below the low E string (82 Hz), above most DC drift and handling rumble
+const HIGH_PASS_HZ: f32 = 40.0;
+const HIGH_PASS_Q: f32 = std::f32::consts::FRAC_1_SQRT_2;
+// Narrow enough to leave the notes next to the mains frequency alone
+const NOTCH_Q: f32 = 10.0;
+// Mains hum also has strong odd and even harmonics
+const NOTCH_HARMONICS: usize = 3;
+
+/// Mains frequency to remove, which depends on the country.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HumFilter {
+    Off,
+    Hz50,
+    Hz60,
+}
+
+impl fmt::Display for HumFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HumFilter::Off => write!(f, "Off"),
+            HumFilter::Hz50 => write!(f, "50 Hz"),
+            HumFilter::Hz60 => write!(f, "60 Hz"),
+        }
+    }
+}
+
+impl HumFilter {
+    fn frequency(&self) -> Option<f32> {
+        match self {
+            HumFilter::Off => None,
+            HumFilter::Hz50 => Some(50.0),
+            HumFilter::Hz60 => Some(60.0),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InputFilterSettings {
+    pub high_pass: bool,
+    pub hum: HumFilter,
+}
+
+impl Default for InputFilterSettings {
+    fn default() -> Self {
+        Self {
+            high_pass: true,
+            hum: HumFilter::Off,
+        }
+    }
+}
+
+/// Cleans up microphone input: removes DC offset and rumble, and optionally mains hum.
+pub struct InputFilter {
+    stages: Vec<(Biquad, BiquadState<f32>)>,
+}
+
+impl InputFilter {
+    pub fn new(settings: InputFilterSettings, sample_rate: f32) -> Self {
+        let mut stages = Vec::new();
+        if settings.high_pass {
+            stages.push((
+                Biquad::high_pass(HIGH_PASS_HZ, HIGH_PASS_Q, sample_rate),
+                BiquadState::default(),
+            ));
+        }
+        if let Some(frequency) = settings.hum.frequency() {
+            stages.extend(
+                (1..=NOTCH_HARMONICS)
+                    .map(|harmonic| frequency * harmonic as f32)
+                    .filter(|&f| f < sample_rate / 2.0)
+                    .map(|f| {
+                        (
+                            Biquad::notch(f, NOTCH_Q, sample_rate),
+                            BiquadState::default(),
+                        )
+                    }),
+            );
+        }
+        Self { stages }
+    }
+
+    pub fn process(&mut self, sample: f32) -> f32 {
+        self.stages
+            .iter_mut()
+            .fold(sample, |sample, (filter, state)| {
+                state.process(filter, sample)
+            })
+    }
+}
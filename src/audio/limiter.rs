@@ -0,0 +1,277 @@
+// audio/limiter.rs
+
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+
+use kira::clock::clock_info::ClockInfoProvider;
+use kira::effect::Effect;
+use kira::modulator::value_provider::ModulatorValueProvider;
+use kira::Frame;
+
+// How far ahead the limiter sees peaks coming, so it can turn down before they arrive
+const LOOKAHEAD_MS: f32 = 5.0;
+const LIMITER_RELEASE_MS: f32 = 80.0;
+// The compressor follows the level slowly enough to leave the attack of each pluck alone
+const COMPRESSOR_ATTACK_MS: f32 = 10.0;
+const COMPRESSOR_RELEASE_MS: f32 = 150.0;
+pub const MIN_CEILING_DB: f32 = -12.0;
+pub const MIN_THRESHOLD_DB: f32 = -40.0;
+pub const MAX_RATIO: f32 = 8.0;
+
+/// What keeps the summed output of all tracks from clipping: an optional gentle compressor
+/// evening out dense passages, then a limiter holding the peaks under a ceiling.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LimiterSettings {
+    pub limit: bool,
+    /// Highest peak let through, in dBFS
+    pub ceiling_db: f32,
+    pub compress: bool,
+    /// Level above which the compressor turns down, in dBFS
+    pub threshold_db: f32,
+    /// Decibels in above the threshold for each decibel out
+    pub ratio: f32,
+}
+
+impl Default for LimiterSettings {
+    fn default() -> Self {
+        Self {
+            limit: true,
+            ceiling_db: -1.0,
+            compress: false,
+            threshold_db: -18.0,
+            ratio: 2.0,
+        }
+    }
+}
+
+fn gain_of_db(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+fn time_coefficient(milliseconds: f32, sample_rate: f32) -> f32 {
+    1.0 - (-1000.0 / (milliseconds * sample_rate)).exp()
+}
+
+/// The compressor and lookahead limiter running on frames, delaying them by the lookahead.
+pub struct Limiter {
+    settings: LimiterSettings,
+    sample_rate: f32,
+    // Frames waiting to come out once the gain is ready for them
+    delay: Vec<Frame>,
+    // Gain each waiting frame needs to stay under the ceiling
+    needed: Vec<f32>,
+    // The needed gain with its release applied, averaged over the lookahead to smooth it
+    released: Vec<f32>,
+    release_gain: f32,
+    position: usize,
+    envelope: f32,
+    /// Gain reduction of the latest frame, in dB below 0
+    pub reduction_db: f32,
+}
+
+impl Limiter {
+    pub fn new(settings: LimiterSettings, sample_rate: f32) -> Self {
+        let mut limiter = Self {
+            settings,
+            sample_rate: 0.0,
+            delay: Vec::new(),
+            needed: Vec::new(),
+            released: Vec::new(),
+            release_gain: 1.0,
+            position: 0,
+            envelope: 0.0,
+            reduction_db: 0.0,
+        };
+        limiter.set_sample_rate(sample_rate);
+        limiter
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        let length = ((LOOKAHEAD_MS / 1000.0 * sample_rate) as usize).max(1);
+        self.delay = vec![Frame::ZERO; length];
+        self.needed = vec![1.0; length];
+        self.released = vec![1.0; length];
+        self.release_gain = 1.0;
+        self.position = 0;
+    }
+
+    pub fn set_settings(&mut self, settings: LimiterSettings) {
+        self.settings = settings;
+    }
+
+    /// Gain of the compressor for `input`, following its level.
+    fn compressor_gain(&mut self, input: Frame) -> f32 {
+        let level = input.left.abs().max(input.right.abs());
+        let time = if level > self.envelope {
+            COMPRESSOR_ATTACK_MS
+        } else {
+            COMPRESSOR_RELEASE_MS
+        };
+        self.envelope += (level - self.envelope) * time_coefficient(time, self.sample_rate);
+        let level_db = 20.0 * self.envelope.max(1e-6).log10();
+        let over = level_db - self.settings.threshold_db;
+        if over <= 0.0 {
+            return 1.0;
+        }
+        gain_of_db(-over * (1.0 - 1.0 / self.settings.ratio.max(1.0)))
+    }
+
+    pub fn process(&mut self, input: Frame) -> Frame {
+        let LimiterSettings {
+            limit, compress, ..
+        } = self.settings;
+        let compressor = if compress {
+            self.compressor_gain(input)
+        } else {
+            1.0
+        };
+        let input = input * compressor;
+        let ceiling = gain_of_db(self.settings.ceiling_db);
+        let peak = input.left.abs().max(input.right.abs());
+        let needed = if limit && peak > ceiling {
+            ceiling / peak
+        } else {
+            1.0
+        };
+
+        let length = self.delay.len();
+        let output = std::mem::replace(&mut self.delay[self.position], input);
+        // Turns down at once for a peak anywhere from the frame coming out to the one going in,
+        // and comes back up slowly
+        let hold = self.needed.iter().copied().fold(needed, f32::min);
+        self.needed[self.position] = needed;
+        self.release_gain = if hold < self.release_gain {
+            hold
+        } else {
+            self.release_gain
+                + (hold - self.release_gain)
+                    * time_coefficient(LIMITER_RELEASE_MS, self.sample_rate)
+        };
+        // Averaged over the lookahead the gain glides down, and still reaches each peak's
+        // gain by the time the peak comes out
+        self.released[self.position] = self.release_gain;
+        self.position = (self.position + 1) % length;
+        let limiter = (self.released.iter().sum::<f32>() / length as f32).min(1.0);
+
+        self.reduction_db = 20.0 * (compressor * limiter).max(1e-6).log10();
+        output * limiter
+    }
+}
+
+/// Limiter settings shared between the GUI and the audio thread, and the gain reduction the
+/// audio thread reports back.
+pub struct LimiterControl {
+    limit: AtomicBool,
+    ceiling_db: AtomicU32,
+    compress: AtomicBool,
+    threshold_db: AtomicU32,
+    ratio: AtomicU32,
+    reduction_db: AtomicU32,
+}
+
+impl LimiterControl {
+    pub fn new(settings: &LimiterSettings) -> Self {
+        let control = Self {
+            limit: AtomicBool::new(false),
+            ceiling_db: AtomicU32::new(0),
+            compress: AtomicBool::new(false),
+            threshold_db: AtomicU32::new(0),
+            ratio: AtomicU32::new(0),
+            reduction_db: AtomicU32::new(0.0f32.to_bits()),
+        };
+        control.set(settings);
+        control
+    }
+
+    pub fn set(&self, settings: &LimiterSettings) {
+        self.limit.store(settings.limit, Ordering::Relaxed);
+        self.ceiling_db
+            .store(settings.ceiling_db.to_bits(), Ordering::Relaxed);
+        self.compress.store(settings.compress, Ordering::Relaxed);
+        self.threshold_db
+            .store(settings.threshold_db.to_bits(), Ordering::Relaxed);
+        self.ratio
+            .store(settings.ratio.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> LimiterSettings {
+        LimiterSettings {
+            limit: self.limit.load(Ordering::Relaxed),
+            ceiling_db: f32::from_bits(self.ceiling_db.load(Ordering::Relaxed)),
+            compress: self.compress.load(Ordering::Relaxed),
+            threshold_db: f32::from_bits(self.threshold_db.load(Ordering::Relaxed)),
+            ratio: f32::from_bits(self.ratio.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Gain reduction of the latest batch, in dB below 0.
+    pub fn reduction_db(&self) -> f32 {
+        f32::from_bits(self.reduction_db.load(Ordering::Relaxed))
+    }
+}
+
+/// The limiter on the main track, where the guitar, the backing track and the clicks sum.
+pub struct LimiterEffect {
+    control: Arc<LimiterControl>,
+    limiter: Limiter,
+}
+
+impl LimiterEffect {
+    pub fn new(control: Arc<LimiterControl>) -> Self {
+        Self {
+            limiter: Limiter::new(control.get(), 44_100.0),
+            control,
+        }
+    }
+}
+
+impl Effect for LimiterEffect {
+    fn init(&mut self, sample_rate: u32) {
+        self.limiter.set_sample_rate(sample_rate as f32);
+    }
+
+    fn on_change_sample_rate(&mut self, sample_rate: u32) {
+        self.limiter.set_sample_rate(sample_rate as f32);
+    }
+
+    fn on_start_processing(&mut self) {
+        self.limiter.set_settings(self.control.get());
+        self.control
+            .reduction_db
+            .store(self.limiter.reduction_db.to_bits(), Ordering::Relaxed);
+    }
+
+    fn process(
+        &mut self,
+        input: Frame,
+        _dt: f64,
+        _clock_info_provider: &ClockInfoProvider,
+        _modulator_value_provider: &ModulatorValueProvider,
+    ) -> Frame {
+        self.limiter.process(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn peaks_stay_under_the_ceiling() {
+        let settings = LimiterSettings::default();
+        let mut limiter = Limiter::new(settings, 44_100.0);
+        let ceiling = gain_of_db(settings.ceiling_db);
+        // A quiet note, then a chord four times too loud for the output
+        let loudest = (0..44_100)
+            .map(|idx| {
+                let amplitude = if idx < 10_000 { 0.3 } else { 4.0 };
+                let sample = amplitude * (idx as f32 * 0.05).sin();
+                limiter.process(Frame::new(sample, -sample))
+            })
+            .map(|frame| frame.left.abs().max(frame.right.abs()))
+            .fold(0.0, f32::max);
+        assert!(loudest <= ceiling + 1e-4, "{} over {}", loudest, ceiling);
+        assert!(loudest > ceiling * 0.9);
+    }
+}
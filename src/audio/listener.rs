@@ -0,0 +1,191 @@
+// audio/listener.rs
+
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+use instant::Instant;
+
+use crate::audio::input_capture::InputCapture;
+use crate::audio::input_filter::InputFilterSettings;
+use crate::audio::signal_history::SignalHistory;
+use crate::audio::spectrum::{hann_window, magnitude_spectrum};
+use crate::music_representation::{midi_note, Note};
+
+const FRAME_SIZE: usize = 4096; // about 90 ms at 44.1 kHz, enough to resolve the low E
+                                // Range holding the fundamentals and lower harmonics of the guitar
+const MIN_FREQUENCY: f32 = 70.0;
+const MAX_FREQUENCY: f32 = 2000.0;
+// Below this level the input counts as silence rather than a wrong note
+const SILENCE_RMS: f32 = 0.005;
+/// Similarity from which played notes count as a match.
+pub const MATCH_THRESHOLD: f32 = 0.6;
+/// Seconds of input and chroma kept for the plots unless asked for more.
+pub const DEFAULT_HISTORY_SECONDS: f32 = 1.0;
+const PITCH_CLASSES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+/// Energy in each pitch class of `samples`, C first.
+fn chroma(samples: &[f32], sample_rate: u32) -> [f32; 12] {
+    let spectrum = magnitude_spectrum(&hann_window(samples));
+    let bin_width = sample_rate as f32 / (2 * spectrum.len()) as f32;
+    let mut chroma = [0.0; 12];
+    for (bin, magnitude) in spectrum.iter().enumerate().skip(1) {
+        let frequency = bin as f32 * bin_width;
+        if !(MIN_FREQUENCY..=MAX_FREQUENCY).contains(&frequency) {
+            continue;
+        }
+        let midi = 69.0 + 12.0 * (frequency / 440.0).log2();
+        chroma[(midi.round() as i32).rem_euclid(12) as usize] += magnitude * magnitude;
+    }
+    chroma
+}
+
+/// One comparison of the input with the expected notes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChromaFrame {
+    /// Seconds since the listener started
+    pub seconds: f32,
+    pub similarity: f32,
+    /// Energy heard in each pitch class, C first, zero when the input was silent
+    pub heard: [f32; 12],
+    /// 1 for the pitch classes of the expected notes
+    pub expected: [f32; 12],
+}
+
+/// Cosine similarity between the pitch classes heard in `samples` and the ones of `expected`,
+/// from 0 for nothing in common to 1 for exactly the expected notes.
+pub fn similarity(samples: &[f32], sample_rate: u32, expected: &[Note], capo_fret: u8) -> f32 {
+    compare(samples, sample_rate, expected, capo_fret).similarity
+}
+
+/// The similarity together with the pitch classes it was worked out from.
+fn compare(samples: &[f32], sample_rate: u32, expected: &[Note], capo_fret: u8) -> ChromaFrame {
+    let mut target = [0.0f32; 12];
+    for midi in expected
+        .iter()
+        .filter_map(|note| midi_note(note, capo_fret))
+    {
+        target[(midi % 12) as usize] = 1.0;
+    }
+    let mut frame = ChromaFrame {
+        seconds: 0.0,
+        similarity: 0.0,
+        heard: [0.0; 12],
+        expected: target,
+    };
+    if !is_sounding(samples) || target.iter().all(|&t| t == 0.0) {
+        return frame;
+    }
+
+    let heard = chroma(samples, sample_rate);
+    let dot: f32 = heard.iter().zip(&target).map(|(h, t)| h * t).sum();
+    let norm = |v: &[f32; 12]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let denominator = norm(&heard) * norm(&target);
+    frame.heard = heard;
+    if denominator > 0.0 {
+        frame.similarity = dot / denominator;
+    }
+    frame
+}
+
+/// Whether `samples` are loud enough to be anything but silence.
+fn is_sounding(samples: &[f32]) -> bool {
+    let rms = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len().max(1) as f32).sqrt();
+    rms >= SILENCE_RMS
+}
+
+/// `frames` as CSV with a header row, one line per comparison.
+pub fn chroma_csv(frames: &[ChromaFrame]) -> String {
+    let mut csv = String::from("seconds,similarity");
+    for prefix in ["heard", "expected"] {
+        for name in PITCH_CLASSES {
+            let _ = write!(csv, ",{}_{}", prefix, name);
+        }
+    }
+    csv.push('\n');
+    for frame in frames {
+        let _ = write!(csv, "{:.4},{:.4}", frame.seconds, frame.similarity);
+        for value in frame.heard.iter().chain(&frame.expected) {
+            let _ = write!(csv, ",{}", value);
+        }
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Compares the microphone with the notes being played.
+pub struct Listener {
+    capture: InputCapture,
+    // The latest FRAME_SIZE samples of input
+    frame: Vec<f32>,
+    /// Milliseconds the latest analysis took, from taking the input to its result
+    pub latency: f32,
+    /// Whether the latest input was anything but silence
+    pub sounding: bool,
+    /// Everything taken from the input, for plotting and exporting
+    pub input: SignalHistory,
+    chroma: VecDeque<ChromaFrame>,
+    history_seconds: f32,
+    started: Instant,
+}
+
+impl Listener {
+    pub fn start(device: Option<&str>, filter: InputFilterSettings) -> Result<Self, String> {
+        let capture = InputCapture::start(device, filter)?;
+        let capacity = (DEFAULT_HISTORY_SECONDS * capture.sample_rate as f32) as usize;
+        Ok(Self {
+            input: SignalHistory::new(capacity, capture.sample_rate as usize / 100, 6000),
+            capture,
+            frame: Vec::with_capacity(FRAME_SIZE),
+            latency: 0.0,
+            sounding: false,
+            chroma: VecDeque::new(),
+            history_seconds: DEFAULT_HISTORY_SECONDS,
+            started: Instant::now(),
+        })
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.capture.sample_rate
+    }
+
+    /// Keeps `seconds` of input and of chroma frames from now on.
+    pub fn set_history_seconds(&mut self, seconds: f32) {
+        self.history_seconds = seconds;
+        self.input
+            .set_capacity((seconds * self.capture.sample_rate as f32) as usize);
+    }
+
+    /// The comparisons within the history, oldest first.
+    pub fn chroma_frames(&self) -> Vec<ChromaFrame> {
+        self.chroma.iter().copied().collect()
+    }
+
+    /// Similarity of the latest input to `expected`, see [`similarity`].
+    pub fn listen(&mut self, expected: &[Note], capo_fret: u8) -> f32 {
+        let start = Instant::now();
+        let samples = self.capture.take();
+        self.input.push(&samples);
+        self.frame.extend(samples);
+        let excess = self.frame.len().saturating_sub(FRAME_SIZE);
+        self.frame.drain(..excess);
+        self.sounding = is_sounding(&self.frame);
+        let mut result = compare(&self.frame, self.capture.sample_rate, expected, capo_fret);
+        self.latency = start.elapsed().as_secs_f32() * 1000.0;
+
+        // Frozen along with the input, so both show the same moment
+        if !self.input.frozen {
+            result.seconds = self.started.elapsed().as_secs_f32();
+            self.chroma.push_back(result);
+            while self
+                .chroma
+                .front()
+                .is_some_and(|oldest| result.seconds - oldest.seconds > self.history_seconds)
+            {
+                self.chroma.pop_front();
+            }
+        }
+        result.similarity
+    }
+}
@@ -0,0 +1,258 @@
+// audio/loopback.rs
+
+use std::f32::consts::{PI, TAU};
+use std::fmt;
+use std::sync::Arc;
+
+use kira::sound::static_sound::{StaticSoundData, StaticSoundSettings};
+use kira::Frame;
+
+use crate::audio::audio_player::AudioPlayer;
+use crate::audio::input_capture::InputCapture;
+use crate::audio::input_filter::InputFilterSettings;
+
+const SAMPLE_RATE: u32 = 44_100;
+// Spread over the guitar's range, the last one tells whether the highs come through
+const TONES: [f32; 3] = [220.0, 1_000.0, 4_000.0];
+const TONE_SECONDS: f32 = 0.4;
+const GAP_SECONDS: f32 = 0.1;
+// Silence before the first tone, the start of it is where the noise floor is measured
+const LEAD_SECONDS: f32 = 0.5;
+const NOISE_SECONDS: f32 = 0.2;
+// Recorded after the last tone, room for the latency of the slowest Bluetooth speakers
+const TAIL_SECONDS: f32 = 0.5;
+const FADE_SECONDS: f32 = 0.005;
+const AMPLITUDE: f32 = 0.5;
+// A tone counts as heard this far above the noise floor
+const HEARD_MARGIN_DB: f32 = 12.0;
+const SILENCE_DB: f32 = -70.0;
+// Windows the onset of the first tone is searched for with
+const ONSET_WINDOW_SECONDS: f32 = 0.01;
+const ONSET_HOP_SECONDS: f32 = 0.001;
+
+fn to_db(amplitude: f32) -> f32 {
+    20.0 * amplitude.max(1e-6).log10()
+}
+
+/// Peak amplitude of the sine at `frequency` in `samples`, by the Goertzel algorithm.
+fn tone_amplitude(samples: &[f32], sample_rate: u32, frequency: f32) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let coefficient = 2.0 * (TAU * frequency / sample_rate as f32).cos();
+    let (mut previous, mut before) = (0.0f32, 0.0f32);
+    for &sample in samples {
+        let current = sample + coefficient * previous - before;
+        before = previous;
+        previous = current;
+    }
+    let power = previous * previous + before * before - coefficient * previous * before;
+    2.0 * power.max(0.0).sqrt() / samples.len() as f32
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    (samples.iter().map(|s| s * s).sum::<f32>() / samples.len().max(1) as f32).sqrt()
+}
+
+/// Seconds from the start of the test sound to the start of tone `idx`.
+fn tone_start(idx: usize) -> f32 {
+    LEAD_SECONDS + idx as f32 * (TONE_SECONDS + GAP_SECONDS)
+}
+
+fn test_seconds() -> f32 {
+    tone_start(TONES.len()) + TAIL_SECONDS
+}
+
+/// The tones one after the other, faded in and out so they don't click.
+fn synthesize() -> Arc<[Frame]> {
+    let length = (tone_start(TONES.len()) * SAMPLE_RATE as f32) as usize;
+    let tone_length = (TONE_SECONDS * SAMPLE_RATE as f32) as usize;
+    let fade_length = FADE_SECONDS * SAMPLE_RATE as f32;
+    let mut frames = vec![Frame::ZERO; length];
+    for (idx, &frequency) in TONES.iter().enumerate() {
+        let start = (tone_start(idx) * SAMPLE_RATE as f32) as usize;
+        for i in 0..tone_length {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            let edge = i.min(tone_length - 1 - i) as f32;
+            let fade = if edge < fade_length {
+                0.5 - 0.5 * (PI * edge / fade_length).cos()
+            } else {
+                1.0
+            };
+            frames[start + i] = Frame::from_mono(AMPLITUDE * fade * (TAU * frequency * t).sin());
+        }
+    }
+    frames.into()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToneLevel {
+    pub frequency: f32,
+    /// Level of the tone in the recording in dB relative to full scale
+    pub level_db: f32,
+    pub heard: bool,
+}
+
+/// What the input heard of the test tones.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoopbackReport {
+    pub tones: Vec<ToneLevel>,
+    pub noise_floor_db: f32,
+    /// Milliseconds from playing the first tone to hearing it, through the output, the air
+    /// and the input
+    pub latency_ms: Option<f32>,
+}
+
+impl LoopbackReport {
+    pub fn all_heard(&self) -> bool {
+        self.tones.iter().all(|tone| tone.heard)
+    }
+}
+
+impl fmt::Display for LoopbackReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for tone in &self.tones {
+            writeln!(
+                f,
+                "{:.0} Hz: {:.1} dBFS{}",
+                tone.frequency,
+                tone.level_db,
+                if tone.heard { "" } else { " (not heard)" }
+            )?;
+        }
+        writeln!(f, "Noise floor: {:.1} dBFS", self.noise_floor_db)?;
+        match self.latency_ms {
+            Some(latency) => write!(f, "Round trip latency: {:.0} ms", latency),
+            None => write!(
+                f,
+                "Round trip latency: unknown, the first tone wasn't heard"
+            ),
+        }
+    }
+}
+
+/// Measures the tones in `samples`, recorded from the moment the test sound started playing.
+pub fn analyse(samples: &[f32], sample_rate: u32) -> LoopbackReport {
+    let seconds = |s: f32| ((s * sample_rate as f32) as usize).min(samples.len());
+    let noise = &samples[..seconds(NOISE_SECONDS)];
+    let noise_floor_db = to_db(rms(noise)).max(SILENCE_DB);
+    let threshold_db = noise_floor_db + HEARD_MARGIN_DB;
+
+    // The first moment the first tone rises above the noise
+    let window = seconds(ONSET_WINDOW_SECONDS).max(1);
+    let hop = seconds(ONSET_HOP_SECONDS).max(1);
+    let onset = (seconds(NOISE_SECONDS)..samples.len().saturating_sub(window))
+        .step_by(hop)
+        .find(|&start| {
+            let amplitude = tone_amplitude(&samples[start..start + window], sample_rate, TONES[0]);
+            to_db(amplitude) > threshold_db
+        });
+    // The tone only just reaches into the first window loud enough, so it starts near its end
+    let latency = onset
+        .map(|start| (start + window) as f32 / sample_rate as f32 - LEAD_SECONDS)
+        .filter(|&latency| latency >= 0.0);
+
+    let tones = TONES
+        .iter()
+        .enumerate()
+        .map(|(idx, &frequency)| {
+            // The middle of the tone, clear of its fades and of any echo of the previous one
+            let start = tone_start(idx) + latency.unwrap_or(0.0) + 0.2 * TONE_SECONDS;
+            let tone = &samples[seconds(start)..seconds(start + 0.6 * TONE_SECONDS)];
+            let level_db = to_db(tone_amplitude(tone, sample_rate, frequency));
+            ToneLevel {
+                frequency,
+                level_db,
+                heard: level_db > threshold_db,
+            }
+        })
+        .collect();
+    LoopbackReport {
+        tones,
+        noise_floor_db,
+        latency_ms: latency.map(|latency| latency * 1000.0),
+    }
+}
+
+/// Plays test tones through the output and records them with the input, to check that the
+/// listener can hear the guitar.
+pub struct LoopbackTest {
+    capture: InputCapture,
+    recorded: Vec<f32>,
+    // Samples recorded before the tones started playing
+    played_at: Option<usize>,
+}
+
+impl LoopbackTest {
+    pub fn start(device: Option<&str>, filter: InputFilterSettings) -> Result<Self, String> {
+        Ok(Self {
+            capture: InputCapture::start(device, filter)?,
+            recorded: Vec::new(),
+            played_at: None,
+        })
+    }
+
+    /// Records what came in since the last call, and plays the tones once the input is
+    /// running. Returns the report once the tones are over.
+    pub fn update(
+        &mut self,
+        audio_player: &mut AudioPlayer,
+    ) -> Option<Result<LoopbackReport, String>> {
+        self.recorded.extend(self.capture.take());
+        let sample_rate = self.capture.sample_rate;
+        match self.played_at {
+            // Start counting the latency only once input arrives
+            None if !self.recorded.is_empty() => {
+                let sound = StaticSoundData {
+                    sample_rate: SAMPLE_RATE,
+                    frames: synthesize(),
+                    settings: StaticSoundSettings::new(),
+                    slice: None,
+                };
+                if let Err(e) = audio_player.play_sound_data(sound) {
+                    return Some(Err(e));
+                }
+                self.played_at = Some(self.recorded.len());
+                None
+            }
+            Some(played_at) => {
+                let needed = (test_seconds() * sample_rate as f32) as usize;
+                (self.recorded.len() >= played_at + needed)
+                    .then(|| Ok(analyse(&self.recorded[played_at..], sample_rate)))
+            }
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delayed_tones_report_their_latency_and_level() {
+        let delay = 0.05;
+        let frames = synthesize();
+        let offset = (delay * SAMPLE_RATE as f32) as usize;
+        let length = (test_seconds() * SAMPLE_RATE as f32) as usize;
+        let mut samples: Vec<f32> = (0..length)
+            .map(|i| (i as f32 * 0.37).sin() * 0.001)
+            .collect();
+        for (i, frame) in frames.iter().enumerate() {
+            // The room and microphone halve the level
+            samples[offset + i] += frame.left * 0.5;
+        }
+
+        let report = analyse(&samples, SAMPLE_RATE);
+        let latency = report.latency_ms.expect("first tone heard");
+        assert!((latency - delay * 1000.0).abs() < 5.0, "{}", latency);
+        assert!(report.all_heard());
+        for tone in &report.tones {
+            assert!(
+                (tone.level_db - to_db(AMPLITUDE * 0.5)).abs() < 1.0,
+                "{:?}",
+                tone
+            );
+        }
+    }
+}
@@ -0,0 +1,138 @@
+// audio/metronome.rs
+
+use std::f32::consts::TAU;
+use std::sync::Arc;
+
+use kira::sound::static_sound::{StaticSoundData, StaticSoundSettings};
+use kira::Frame;
+
+use crate::audio::audio_player::AudioPlayer;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::audio::output_devices::find_output_device;
+#[cfg(not(target_arch = "wasm32"))]
+use kira::manager::backend::cpal::{CpalBackend, CpalBackendSettings};
+#[cfg(not(target_arch = "wasm32"))]
+use kira::manager::{AudioManager, AudioManagerSettings};
+
+const SAMPLE_RATE: u32 = 44_100;
+const CLICK_SECONDS: f32 = 0.03;
+const CUE_SECONDS: f32 = 0.12;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cue {
+    /// First beat of a measure
+    Accent,
+    Beat,
+    /// Playback started
+    Start,
+    /// Playback stopped
+    Stop,
+}
+
+impl Cue {
+    fn tone(&self) -> (f32, f32) {
+        // (frequency, seconds)
+        match self {
+            Cue::Accent => (1_600.0, CLICK_SECONDS),
+            Cue::Beat => (1_000.0, CLICK_SECONDS),
+            Cue::Start => (880.0, CUE_SECONDS),
+            Cue::Stop => (440.0, CUE_SECONDS),
+        }
+    }
+}
+
+/// A sine burst with an exponential decay, short enough not to mask the guitar.
+fn synthesize(cue: Cue) -> Arc<[Frame]> {
+    let (frequency, seconds) = cue.tone();
+    let samples = (seconds * SAMPLE_RATE as f32) as usize;
+    let decay = 5.0 / samples as f32;
+    (0..samples)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            Frame::from_mono((TAU * frequency * t).sin() * (-decay * i as f32).exp())
+        })
+        .collect()
+}
+
+/// Beat clicks and transport cues, played through the guitar's output or a separate device
+/// such as a drummer's in-ears.
+pub struct Metronome {
+    pub enabled: bool,
+    pub transport_cues: bool,
+    pub volume: f32,
+    cues: [(Cue, Arc<[Frame]>); 4],
+    #[cfg(not(target_arch = "wasm32"))]
+    device: Option<(String, AudioManager)>,
+}
+
+impl Metronome {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            transport_cues: false,
+            volume: 0.8,
+            cues: [Cue::Accent, Cue::Beat, Cue::Start, Cue::Stop].map(|cue| (cue, synthesize(cue))),
+            #[cfg(not(target_arch = "wasm32"))]
+            device: None,
+        }
+    }
+
+    /// Name of the separate output device, or `None` when sharing the guitar's output.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn device_name(&self) -> Option<&str> {
+        self.device.as_ref().map(|(name, _)| name.as_str())
+    }
+
+    /// Opens `name` as the metronome's own output, or shares the guitar's output for `None`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_device(&mut self, name: Option<String>) -> Result<(), String> {
+        self.device = None;
+        if let Some(name) = name {
+            let device = find_output_device(&name)?;
+            let manager = AudioManager::<CpalBackend>::new(AudioManagerSettings {
+                backend_settings: CpalBackendSettings {
+                    device: Some(device),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .map_err(|e| e.to_string())?;
+            self.device = Some((name, manager));
+        }
+        Ok(())
+    }
+
+    pub fn play(&mut self, cue: Cue, main: &mut AudioPlayer) {
+        let wanted = match cue {
+            Cue::Accent | Cue::Beat => self.enabled,
+            Cue::Start | Cue::Stop => self.transport_cues,
+        };
+        if !wanted {
+            return;
+        }
+        let Some((_, frames)) = self.cues.iter().find(|(c, _)| *c == cue) else {
+            return;
+        };
+        let sound = StaticSoundData {
+            sample_rate: SAMPLE_RATE,
+            frames: frames.clone(),
+            settings: StaticSoundSettings::new().volume(self.volume as f64),
+            slice: None,
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some((_, manager)) = &mut self.device {
+            if let Err(e) = manager.play(sound) {
+                eprintln!("Failed to play metronome: {}", e);
+            }
+            return;
+        }
+        main.play_sound(sound);
+    }
+}
+
+impl Default for Metronome {
+    fn default() -> Self {
+        Self::new()
+    }
+}
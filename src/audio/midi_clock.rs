@@ -0,0 +1,150 @@
+// audio/midi_clock.rs
+
+use std::collections::VecDeque;
+use std::sync::mpsc::{channel, Receiver};
+
+use midir::{Ignore, MidiInput, MidiInputConnection};
+
+// MIDI clock sends 24 ticks per quarter note
+const TICKS_PER_QUARTER: usize = 24;
+const CLOCK: u8 = 0xF8;
+const START: u8 = 0xFA;
+const CONTINUE: u8 = 0xFB;
+const STOP: u8 = 0xFC;
+
+/// What the device sending the clock asks the transport to do.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClockEvent {
+    Start,
+    Continue,
+    Stop,
+    /// Quarter notes per minute, reported once per quarter note
+    Tempo(f32),
+}
+
+/// Tempo of the incoming clock, averaged over the last quarter note so a jittery sender
+/// doesn't make the tempo wobble.
+#[derive(Default)]
+pub struct TempoTracker {
+    // Arrival of the latest ticks in microseconds
+    ticks: VecDeque<u64>,
+    ticks_since_report: usize,
+}
+
+impl TempoTracker {
+    /// Takes a tick that arrived at `timestamp` microseconds. Returns the tempo once per
+    /// quarter note, as soon as a whole quarter note of ticks has been seen.
+    pub fn tick(&mut self, timestamp: u64) -> Option<f32> {
+        self.ticks.push_back(timestamp);
+        if self.ticks.len() > TICKS_PER_QUARTER + 1 {
+            self.ticks.pop_front();
+        }
+        self.ticks_since_report += 1;
+        if self.ticks.len() <= TICKS_PER_QUARTER || self.ticks_since_report < TICKS_PER_QUARTER {
+            return None;
+        }
+        self.ticks_since_report = 0;
+        let quarter = self.ticks.back()? - self.ticks.front()?;
+        (quarter > 0).then(|| 60_000_000.0 / quarter as f32)
+    }
+
+    /// Forgets the ticks seen so far, for when the clock starts over.
+    pub fn reset(&mut self) {
+        self.ticks.clear();
+        self.ticks_since_report = 0;
+    }
+}
+
+/// Names of the MIDI inputs a clock or notes can be received from.
+pub fn midi_input_names() -> Vec<String> {
+    match MidiInput::new("cdefgab") {
+        Ok(input) => input
+            .ports()
+            .iter()
+            .filter_map(|port| input.port_name(port).ok())
+            .collect(),
+        Err(e) => {
+            eprintln!("Failed to list MIDI inputs: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Receives MIDI clock, start, continue and stop messages from a MIDI input, like a DAW or a
+/// drum machine acting as the band's master clock.
+pub struct MidiClock {
+    pub port_name: String,
+    events: Receiver<ClockEvent>,
+    // Receives messages for as long as it lives
+    _connection: MidiInputConnection<()>,
+}
+
+impl MidiClock {
+    pub fn connect(port_name: &str) -> Result<Self, String> {
+        let mut input = MidiInput::new("cdefgab").map_err(|e| e.to_string())?;
+        // Clock messages are ignored unless asked for
+        input.ignore(Ignore::None);
+        let port = input
+            .ports()
+            .into_iter()
+            .find(|port| input.port_name(port).is_ok_and(|name| name == port_name))
+            .ok_or_else(|| format!("MIDI input '{}' not found", port_name))?;
+
+        let (sender, events) = channel();
+        let mut tempo = TempoTracker::default();
+        let connection = input
+            .connect(
+                &port,
+                "cdefgab-clock",
+                move |timestamp, message, _| {
+                    let event = match message.first() {
+                        Some(&CLOCK) => tempo.tick(timestamp).map(ClockEvent::Tempo),
+                        Some(&START) => {
+                            tempo.reset();
+                            Some(ClockEvent::Start)
+                        }
+                        Some(&CONTINUE) => {
+                            tempo.reset();
+                            Some(ClockEvent::Continue)
+                        }
+                        Some(&STOP) => Some(ClockEvent::Stop),
+                        _ => None,
+                    };
+                    if let Some(event) = event {
+                        let _ = sender.send(event);
+                    }
+                },
+                (),
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            port_name: port_name.to_string(),
+            events,
+            _connection: connection,
+        })
+    }
+
+    /// Events received since the last call.
+    pub fn events(&self) -> Vec<ClockEvent> {
+        self.events.try_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_at_120_bpm_report_120_bpm() {
+        let mut tracker = TempoTracker::default();
+        // Half a second per quarter note
+        let interval = 500_000 / TICKS_PER_QUARTER as u64;
+        let tempos: Vec<f32> = (0..3 * TICKS_PER_QUARTER as u64 + 1)
+            .filter_map(|tick| tracker.tick(tick * interval))
+            .collect();
+        assert_eq!(tempos.len(), 3);
+        for tempo in tempos {
+            assert!((tempo - 120.0).abs() < 0.5, "{}", tempo);
+        }
+    }
+}
@@ -0,0 +1,109 @@
+// audio/midi_input.rs
+
+use std::sync::mpsc::{channel, Receiver};
+
+use midir::{MidiInput, MidiInputConnection};
+
+use crate::music_representation::{Note, OPEN_STRING_MIDI};
+
+const NOTE_ON: u8 = 0x90;
+const MAX_FRET: u8 = 24;
+
+/// A key pressed on a MIDI controller.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyPress {
+    pub key: u8,
+    /// How hard the key was struck, from 0 to 1
+    pub velocity: f32,
+}
+
+/// Receives the keys pressed on a MIDI controller, to pluck them on the guitar.
+pub struct MidiKeyboard {
+    pub port_name: String,
+    presses: Receiver<KeyPress>,
+    // Receives messages for as long as it lives
+    _connection: MidiInputConnection<()>,
+}
+
+impl MidiKeyboard {
+    pub fn connect(port_name: &str) -> Result<Self, String> {
+        let input = MidiInput::new("cdefgab").map_err(|e| e.to_string())?;
+        let port = input
+            .ports()
+            .into_iter()
+            .find(|port| input.port_name(port).is_ok_and(|name| name == port_name))
+            .ok_or_else(|| format!("MIDI input '{}' not found", port_name))?;
+
+        let (sender, presses) = channel();
+        let connection = input
+            .connect(
+                &port,
+                "cdefgab-keyboard",
+                move |_, message, _| {
+                    // A note-on without velocity is a note-off, which a plucked string ignores
+                    if let &[status, key, velocity] = message {
+                        if status & 0xF0 == NOTE_ON && velocity > 0 {
+                            let _ = sender.send(KeyPress {
+                                key,
+                                velocity: velocity as f32 / 127.0,
+                            });
+                        }
+                    }
+                },
+                (),
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            port_name: port_name.to_string(),
+            presses,
+            _connection: connection,
+        })
+    }
+
+    /// Keys pressed since the last call.
+    pub fn presses(&self) -> Vec<KeyPress> {
+        self.presses.try_iter().collect()
+    }
+}
+
+/// Where the guitar plays `key` with a capo on `capo`: moved by octaves into its range, on
+/// the string that reaches it lowest on the neck.
+pub fn note_for_key(key: u8, capo: u8) -> Option<Note> {
+    let lowest = OPEN_STRING_MIDI[5] + capo;
+    let highest = OPEN_STRING_MIDI[0] + capo + MAX_FRET;
+    let mut key = key;
+    while key < lowest {
+        key += 12;
+    }
+    while key > highest {
+        key -= 12;
+    }
+    OPEN_STRING_MIDI
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, &open)| {
+            let fret = key.checked_sub(open + capo)?;
+            (fret <= MAX_FRET).then_some((idx, fret))
+        })
+        .min_by_key(|&(_, fret)| fret)
+        .map(|(idx, fret)| Note::new(idx as u8 + 1, fret, 0, 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_land_low_on_the_neck() {
+        // Middle C on the B string's first fret, the low E open
+        let middle_c = note_for_key(60, 0).unwrap();
+        assert_eq!((middle_c.string, middle_c.fret), (Some(2), Some(1)));
+        let low_e = note_for_key(40, 0).unwrap();
+        assert_eq!((low_e.string, low_e.fret), (Some(6), Some(0)));
+        // Below the guitar's range comes up an octave, above the capo counts from it
+        let low = note_for_key(28, 0).unwrap();
+        assert_eq!((low.string, low.fret), (Some(6), Some(0)));
+        let capo = note_for_key(42, 2).unwrap();
+        assert_eq!((capo.string, capo.fret), (Some(6), Some(0)));
+    }
+}
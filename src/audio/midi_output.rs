@@ -0,0 +1,176 @@
+// audio/midi_output.rs
+
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use midir::{MidiOutput, MidiOutputConnection};
+
+use crate::music_representation::{midi_note, Articulation, Note};
+
+const NOTE_ON: u8 = 0x90;
+const NOTE_OFF: u8 = 0x80;
+const VELOCITY: u8 = 90;
+const ACCENT_VELOCITY: u8 = 110;
+const STRONG_ACCENT_VELOCITY: u8 = 127;
+// Longest the sending thread sleeps with nothing due, so it notices the connection closing
+const IDLE_WAIT: Duration = Duration::from_millis(100);
+
+/// Names of the MIDI outputs playback can be sent to.
+pub fn midi_output_names() -> Vec<String> {
+    match MidiOutput::new("cdefgab") {
+        Ok(output) => output
+            .ports()
+            .iter()
+            .filter_map(|port| output.port_name(port).ok())
+            .collect(),
+        Err(e) => {
+            eprintln!("Failed to list MIDI outputs: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// A note to be sent, from its note-on to its note-off.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct OutgoingNote {
+    channel: u8,
+    key: u8,
+    velocity: u8,
+    on: Instant,
+    off: Instant,
+    started: bool,
+}
+
+enum OutputCommand {
+    Send(Vec<OutgoingNote>),
+    /// Drops the notes not started yet, the ones playing end as planned
+    Unschedule,
+    /// Ends the notes playing at once and drops the rest
+    Release,
+}
+
+fn velocity(note: &Note) -> u8 {
+    if note.articulations.contains(&Articulation::StrongAccent) {
+        STRONG_ACCENT_VELOCITY
+    } else if note.articulations.contains(&Articulation::Accent) {
+        ACCENT_VELOCITY
+    } else {
+        VELOCITY
+    }
+}
+
+/// Sends the notes of playback to a MIDI output, for a software instrument or a hardware synth
+/// to play. A thread of its own sends each message when it is due, so the notes keep time
+/// however late they are handed over.
+pub struct MidiOut {
+    pub port_name: String,
+    /// MIDI channel the notes go out on, from 0 to 15 and shown as 1 to 16
+    pub channel: u8,
+    commands: Sender<OutputCommand>,
+}
+
+impl MidiOut {
+    pub fn connect(port_name: &str) -> Result<Self, String> {
+        let output = MidiOutput::new("cdefgab").map_err(|e| e.to_string())?;
+        let port = output
+            .ports()
+            .into_iter()
+            .find(|port| output.port_name(port).is_ok_and(|name| name == port_name))
+            .ok_or_else(|| format!("MIDI output '{}' not found", port_name))?;
+        let connection = output
+            .connect(&port, "cdefgab-playback")
+            .map_err(|e| e.to_string())?;
+
+        let (commands, receiver) = channel();
+        thread::spawn(move || {
+            let mut connection = connection;
+            let mut notes: Vec<OutgoingNote> = Vec::new();
+            let send = |connection: &mut MidiOutputConnection, status: u8, note: &OutgoingNote| {
+                let message = [status | note.channel, note.key, note.velocity];
+                if let Err(e) = connection.send(&message) {
+                    eprintln!("Failed to send MIDI: {}", e);
+                }
+            };
+            loop {
+                let now = Instant::now();
+                for note in &mut notes {
+                    if !note.started && note.on <= now {
+                        note.started = true;
+                        send(&mut connection, NOTE_ON, note);
+                    }
+                }
+                notes.retain(|note| {
+                    let ended = note.started && note.off <= now;
+                    if ended {
+                        send(&mut connection, NOTE_OFF, note);
+                    }
+                    !ended
+                });
+
+                let next = notes
+                    .iter()
+                    .map(|note| if note.started { note.off } else { note.on })
+                    .min();
+                let wait = next.map_or(IDLE_WAIT, |next| {
+                    next.saturating_duration_since(Instant::now())
+                        .min(IDLE_WAIT)
+                });
+                match receiver.recv_timeout(wait) {
+                    Ok(OutputCommand::Send(sent)) => notes.extend(sent),
+                    Ok(OutputCommand::Unschedule) => notes.retain(|note| note.started),
+                    Ok(OutputCommand::Release) => {
+                        for note in notes.drain(..).filter(|note| note.started) {
+                            send(&mut connection, NOTE_OFF, &note);
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    // The output was closed, end what is still playing
+                    Err(RecvTimeoutError::Disconnected) => {
+                        for note in notes.iter().filter(|note| note.started) {
+                            send(&mut connection, NOTE_OFF, note);
+                        }
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(Self {
+            port_name: port_name.to_string(),
+            channel: 0,
+            commands,
+        })
+    }
+
+    /// Sends `notes` starting at `at` and lasting `duration` seconds, pitched for a capo on
+    /// `capo_fret`. Notes without a string and fret are left out.
+    pub fn send_notes(&self, notes: &[Note], capo_fret: u8, duration: f32, at: Instant) {
+        let off = at + Duration::from_secs_f32(duration.max(0.0));
+        let notes: Vec<OutgoingNote> = notes
+            .iter()
+            .filter_map(|note| {
+                Some(OutgoingNote {
+                    channel: self.channel & 0x0F,
+                    key: midi_note(note, capo_fret)?,
+                    velocity: velocity(note),
+                    on: at,
+                    off,
+                    started: false,
+                })
+            })
+            .collect();
+        if !notes.is_empty() {
+            let _ = self.commands.send(OutputCommand::Send(notes));
+        }
+    }
+
+    /// Drops the notes sent for later, like the synth when the transport stops or jumps.
+    pub fn unschedule(&self) {
+        let _ = self.commands.send(OutputCommand::Unschedule);
+    }
+
+    /// Ends the notes playing now.
+    pub fn release(&self) {
+        let _ = self.commands.send(OutputCommand::Release);
+    }
+}
@@ -0,0 +1,66 @@
+// audio/mixer.rs
+
+use crate::audio::audio_player::AudioPlayer;
+use crate::audio::limiter::LimiterSettings;
+
+/// Level and stereo position of one source of sound.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MixerChannel {
+    pub volume: f32,
+    /// 0 is hard left, 0.5 center and 1 hard right, like kira's panning
+    pub pan: f32,
+    pub mute: bool,
+    pub solo: bool,
+}
+
+impl MixerChannel {
+    pub fn new(volume: f32) -> Self {
+        Self {
+            volume,
+            pan: 0.5,
+            mute: false,
+            solo: false,
+        }
+    }
+}
+
+/// Balance between the synthesized score and the backing track, and the limiter on their sum.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mixer {
+    pub guitar: MixerChannel,
+    pub backing_track: MixerChannel,
+    pub limiter: LimiterSettings,
+}
+
+impl Mixer {
+    pub fn new() -> Self {
+        Self {
+            guitar: MixerChannel::new(1.0),
+            backing_track: MixerChannel::new(0.8),
+            limiter: LimiterSettings::default(),
+        }
+    }
+
+    /// Volume a channel actually plays at. While any channel is soloed, the others are silent.
+    pub fn volume(&self, channel: &MixerChannel) -> f32 {
+        let any_solo = self.guitar.solo || self.backing_track.solo;
+        if channel.mute || (any_solo && !channel.solo) {
+            0.0
+        } else {
+            channel.volume
+        }
+    }
+
+    pub fn apply(&self, audio_player: &mut AudioPlayer) {
+        audio_player.set_guitar_mix(self.volume(&self.guitar), self.guitar.pan);
+        audio_player
+            .set_backing_track_mix(self.volume(&self.backing_track), self.backing_track.pan);
+        audio_player.set_limiter(&self.limiter);
+    }
+}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
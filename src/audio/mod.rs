@@ -1 +1,41 @@
 pub mod audio_player;
+pub mod backing_track;
+pub mod beat_detection;
+pub mod biquad;
+pub mod callback_timer;
+pub mod chorus;
+pub mod convolution;
+pub mod drive;
+pub mod effect_chain;
+pub mod equalizer;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod input_capture;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod input_filter;
+pub mod limiter;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod listener;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod loopback;
+pub mod metronome;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod midi_clock;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod midi_input;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod midi_output;
+pub mod mixer;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod monitor;
+pub mod offline_render;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod output_devices;
+pub mod polyphony;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod rumble;
+pub mod section_detection;
+pub mod signal_history;
+pub mod spectrum;
+pub mod synth_stream;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod tuner;
@@ -0,0 +1,138 @@
+// audio/monitor.rs
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+
+use cpal::Stream;
+use kira::clock::clock_info::ClockInfoProvider;
+use kira::modulator::value_provider::ModulatorValueProvider;
+use kira::sound::{Sound, SoundData};
+use kira::{Frame, OutputDestination};
+
+use crate::audio::audio_player::AudioPlayer;
+use crate::audio::input_capture::{start_input_stream, InputBuffer};
+use crate::audio::input_filter::InputFilterSettings;
+
+// Input older than this is dropped so the monitor never lags behind the player
+const MAX_LATENCY_SECONDS: f32 = 0.02;
+
+/// State shared between the input stream's callback and the sound playing it back.
+struct MonitorShared {
+    samples: InputBuffer,
+    gain: AtomicU32,
+    stopped: AtomicBool,
+    input_rate: u32,
+}
+
+/// Plays the microphone through the output while it is alive, for hearing yourself with
+/// headphones on.
+pub struct InputMonitor {
+    _stream: Stream,
+    shared: Arc<MonitorShared>,
+}
+
+impl InputMonitor {
+    pub fn start(
+        gain: f32,
+        device: Option<&str>,
+        filter: InputFilterSettings,
+        player: &mut AudioPlayer,
+    ) -> Result<Self, String> {
+        let samples = InputBuffer::default();
+        let (stream, input_rate) =
+            start_input_stream(device, filter, samples.clone(), MAX_LATENCY_SECONDS)?;
+        let shared = Arc::new(MonitorShared {
+            samples,
+            gain: AtomicU32::new(gain.to_bits()),
+            stopped: AtomicBool::new(false),
+            input_rate,
+        });
+
+        player.play_sound_data(MonitorSoundData(shared.clone()))?;
+        Ok(Self {
+            _stream: stream,
+            shared,
+        })
+    }
+
+    pub fn set_gain(&self, gain: f32) {
+        self.shared.gain.store(gain.to_bits(), Ordering::Relaxed);
+    }
+}
+
+impl Drop for InputMonitor {
+    fn drop(&mut self) {
+        self.shared.stopped.store(true, Ordering::Relaxed);
+    }
+}
+
+struct MonitorSoundData(Arc<MonitorShared>);
+
+impl SoundData for MonitorSoundData {
+    type Error = String;
+    type Handle = ();
+
+    fn into_sound(self) -> Result<(Box<dyn Sound>, Self::Handle), Self::Error> {
+        let capacity = (MAX_LATENCY_SECONDS * self.0.input_rate as f32) as usize * 2;
+        Ok((
+            Box::new(MonitorSound {
+                shared: self.0,
+                pending: VecDeque::with_capacity(capacity),
+                gain: 0.0,
+                position: 0.0,
+                previous: 0.0,
+                next: 0.0,
+            }),
+            (),
+        ))
+    }
+}
+
+struct MonitorSound {
+    shared: Arc<MonitorShared>,
+    // Input taken from the shared buffer at the start of every batch
+    pending: VecDeque<f32>,
+    gain: f32,
+    // Fraction of the way from the previous to the next input sample
+    position: f64,
+    previous: f32,
+    next: f32,
+}
+
+impl Sound for MonitorSound {
+    fn output_destination(&mut self) -> OutputDestination {
+        OutputDestination::MAIN_TRACK
+    }
+
+    fn on_start_processing(&mut self) {
+        self.gain = f32::from_bits(self.shared.gain.load(Ordering::Relaxed));
+        // Never block the audio thread, a busy buffer is picked up next batch
+        if let Ok(mut samples) = self.shared.samples.try_lock() {
+            let room = self.pending.capacity() - self.pending.len();
+            let take = samples.len().min(room);
+            self.pending.extend(samples.drain(..take));
+        }
+    }
+
+    fn process(
+        &mut self,
+        dt: f64,
+        _clock_info_provider: &ClockInfoProvider,
+        _modulator_value_provider: &ModulatorValueProvider,
+    ) -> Frame {
+        // Linear interpolation between input samples converts to the output rate
+        self.position += self.shared.input_rate as f64 * dt;
+        while self.position >= 1.0 {
+            self.previous = self.next;
+            self.next = self.pending.pop_front().unwrap_or(0.0);
+            self.position -= 1.0;
+        }
+        let sample = self.previous + (self.next - self.previous) * self.position as f32;
+        Frame::from_mono(sample * self.gain)
+    }
+
+    fn finished(&self) -> bool {
+        self.shared.stopped.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,355 @@
+// audio/offline_render.rs
+
+use std::f32::consts::{FRAC_PI_2, SQRT_2};
+use std::ops::{AddAssign, Mul};
+
+use kira::Frame;
+use rayon::prelude::*;
+
+use crate::guitar::guitar::GuitarConfig;
+use crate::karplus_strong::karplus_strong::KarplusStrong;
+use crate::music_representation::{calculate_frequency, EventKind, Note, Score, Timeline};
+
+// Level of an open string that shares every harmonic with the plucked note
+const SYMPATHETIC_LEVEL: f32 = 0.08;
+// Harmonics compared between the plucked note and each open string
+const SYMPATHETIC_HARMONICS: u32 = 6;
+// Largest relative mistuning between two harmonics that still excites the open string
+const SYMPATHETIC_TOLERANCE: f32 = 0.01;
+// The open strings build up instead of starting with a pluck
+const SYMPATHETIC_ATTACK_SECONDS: f32 = 0.05;
+
+/// Synthesizes a single plucked note at the configured volume, reproducibly when given a seed.
+pub fn render_note(
+    note: &Note,
+    duration: f32,
+    sample_rate: f32,
+    config: &GuitarConfig,
+    seed: Option<u64>,
+) -> Vec<f32> {
+    PluckedNote::new(note, duration, sample_rate, config, seed).render_all()
+}
+
+/// A plucked note and the open strings ringing along with it, synthesized a block at a time so
+/// playback can stream it instead of rendering it up front.
+pub struct PluckedNote {
+    string: KarplusStrong,
+    // Open strings and the level each rings at
+    resonators: Vec<(KarplusStrong, f32)>,
+    attack_samples: f32,
+    rendered_samples: usize,
+    volume: f32,
+    pan: f32,
+}
+
+impl PluckedNote {
+    pub fn new(
+        note: &Note,
+        duration: f32,
+        sample_rate: f32,
+        config: &GuitarConfig,
+        seed: Option<u64>,
+    ) -> Self {
+        let frequency = calculate_frequency(note, config.scale_length, config.capo_fret);
+        let string = match seed {
+            Some(seed) => KarplusStrong::seeded(frequency, duration, sample_rate, config, seed),
+            None => KarplusStrong::new(frequency, duration, sample_rate, config),
+        };
+        let resonators = if config.sympathetic_resonance {
+            sympathetic_resonators(note, frequency, duration, sample_rate, config, seed)
+        } else {
+            Vec::new()
+        };
+        Self {
+            string,
+            resonators,
+            attack_samples: SYMPATHETIC_ATTACK_SECONDS * sample_rate,
+            rendered_samples: 0,
+            volume: config.volume,
+            pan: string_pan(note.string, config.stereo_width),
+        }
+    }
+
+    pub fn remaining_samples(&self) -> usize {
+        self.string.remaining_samples()
+    }
+
+    /// Where the note sits between the speakers, from 0 for left to 1 for right.
+    pub fn pan(&self) -> f32 {
+        self.pan
+    }
+
+    /// Gains of the left and right channel for the note's pan, equal power and at unity in
+    /// the middle like kira's panning.
+    pub fn pan_gains(&self) -> (f32, f32) {
+        let angle = self.pan * FRAC_PI_2;
+        (angle.cos() * SQRT_2, angle.sin() * SQRT_2)
+    }
+
+    fn render_all(&mut self) -> Vec<f32> {
+        let mut audio_data = vec![0.0; self.remaining_samples()];
+        let mut scratch = vec![0.0; audio_data.len()];
+        self.render_block(&mut audio_data, &mut scratch);
+        audio_data
+    }
+
+    /// Fills `out` with the next samples and returns how many were written, fewer than
+    /// `out.len()` once the note has ended. `scratch` must be at least as long as `out`.
+    pub fn render_block(&mut self, out: &mut [f32], scratch: &mut [f32]) -> usize {
+        let count = self.string.render_block(out);
+        for (resonator, level) in &mut self.resonators {
+            let resonance = &mut scratch[..count];
+            let rendered = resonator.render_block(resonance);
+            for (idx, (sample, resonance)) in out.iter_mut().zip(&resonance[..rendered]).enumerate()
+            {
+                let attack = ((self.rendered_samples + idx) as f32 / self.attack_samples).min(1.0);
+                *sample += resonance * *level * attack;
+            }
+        }
+        for sample in &mut out[..count] {
+            *sample *= self.volume;
+        }
+        self.rendered_samples += count;
+        count
+    }
+}
+
+/// Pan position of `string`, the low E string furthest left and the high E string furthest
+/// right, `width` apart around the middle.
+fn string_pan(string: Option<u8>, width: f32) -> f32 {
+    let Some(string) = string.filter(|string| (1..=6).contains(string)) else {
+        return 0.5;
+    };
+    let position = (6 - string) as f32 / 5.0;
+    0.5 + (position - 0.5) * width.clamp(0.0, 1.0)
+}
+
+/// How strongly a string tuned to `open` rings along with a note at `plucked`, from the
+/// harmonics the two have in common. Low harmonics count the most.
+fn sympathetic_coupling(plucked: f32, open: f32) -> f32 {
+    let mut coupling = 0.0;
+    for n in 1..=SYMPATHETIC_HARMONICS {
+        for m in 1..=SYMPATHETIC_HARMONICS {
+            let (a, b) = (plucked * n as f32, open * m as f32);
+            if (a - b).abs() < SYMPATHETIC_TOLERANCE * b {
+                coupling += 1.0 / (n * m) as f32;
+            }
+        }
+    }
+    coupling.min(1.0)
+}
+
+/// The other open strings of a plucked note, each as loud as its coupling to the note.
+fn sympathetic_resonators(
+    note: &Note,
+    frequency: f32,
+    duration: f32,
+    sample_rate: f32,
+    config: &GuitarConfig,
+    seed: Option<u64>,
+) -> Vec<(KarplusStrong, f32)> {
+    let mut resonators = Vec::new();
+    for string in (1..=6u8).filter(|&string| Some(string) != note.string) {
+        let open_note = Note {
+            string: Some(string),
+            fret: Some(0),
+            ..note.clone()
+        };
+        let open = calculate_frequency(&open_note, config.scale_length, config.capo_fret);
+        let level = SYMPATHETIC_LEVEL * sympathetic_coupling(frequency, open);
+        if level == 0.0 {
+            continue;
+        }
+        let resonator = match seed {
+            Some(seed) => KarplusStrong::seeded(
+                open,
+                duration,
+                sample_rate,
+                config,
+                seed.wrapping_add(string as u64),
+            ),
+            None => KarplusStrong::new(open, duration, sample_rate, config),
+        };
+        resonators.push((resonator, level));
+    }
+    resonators
+}
+
+/// Seed of one note derived from the score's seed and where the note is, so the result doesn't
+/// depend on the order the measures are rendered in.
+fn note_seed(seed: u64, measure: usize, division: usize, note: usize) -> u64 {
+    // SplitMix64 steps, mixing in one coordinate at a time
+    let mut x = seed;
+    for value in [measure, division, note] {
+        x = x
+            .wrapping_add(0x9E37_79B9_7F4A_7C15)
+            .wrapping_add(value as u64);
+        x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        x ^= x >> 31;
+    }
+    x
+}
+
+/// Renders the whole score without an audio device. The notes struck together are synthesized
+/// in parallel, each group into its own buffer, and mixed into the result at the end. With a seed the output is the
+/// same on every run.
+pub fn render_score(
+    score: &Score,
+    config: &GuitarConfig,
+    tempo: usize,
+    sample_rate: f32,
+    seed: Option<u64>,
+) -> Vec<f32> {
+    render_struck(score, config, tempo, sample_rate, seed, |_| 1.0)
+}
+
+/// The whole score in stereo with each string at its pan, like playback before the effects.
+pub fn render_score_stereo(
+    score: &Score,
+    config: &GuitarConfig,
+    tempo: usize,
+    sample_rate: f32,
+    seed: Option<u64>,
+) -> Vec<Frame> {
+    render_struck(score, config, tempo, sample_rate, seed, |plucked| {
+        let (left, right) = plucked.pan_gains();
+        Frame::new(left, right)
+    })
+}
+
+/// Renders the notes struck together in parallel and mixes them at their start, each note
+/// scaled by its `gain`.
+fn render_struck<T>(
+    score: &Score,
+    config: &GuitarConfig,
+    tempo: usize,
+    sample_rate: f32,
+    seed: Option<u64>,
+    gain: impl Fn(&PluckedNote) -> T + Sync,
+) -> Vec<T>
+where
+    T: Copy + Default + Send + AddAssign + Mul<f32, Output = T>,
+{
+    let timeline = Timeline::from_score(score);
+    let seconds_per_division = timeline.seconds_per_tick(tempo);
+    let samples_per_division = seconds_per_division * sample_rate;
+
+    let note_ons: Vec<_> = timeline
+        .events
+        .iter()
+        .filter(|event| event.kind == EventKind::NoteOn)
+        .collect();
+    let rendered: Vec<(usize, Vec<T>)> = note_ons
+        .chunk_by(|a, b| a.tick == b.tick)
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|struck| {
+            // Like playback, all notes struck together ring as long as the first one
+            let duration = seconds_per_division * struck[0].note.sounding_duration as f32;
+            let mut buffer: Vec<T> = Vec::new();
+            for (note_idx, event) in struck.iter().enumerate() {
+                let seed =
+                    seed.map(|seed| note_seed(seed, event.measure, event.division, note_idx));
+                let mut plucked =
+                    PluckedNote::new(&event.note, duration, sample_rate, config, seed);
+                let gain = gain(&plucked);
+                let samples: Vec<T> = plucked
+                    .render_all()
+                    .into_iter()
+                    .map(|sample| gain * sample)
+                    .collect();
+                mix_into(&mut buffer, 0, &samples);
+            }
+            let start = (struck[0].tick as f32 * samples_per_division) as usize;
+            (start, buffer)
+        })
+        .collect();
+
+    let mut output = Vec::new();
+    for (start, buffer) in rendered {
+        mix_into(&mut output, start, &buffer);
+    }
+    output
+}
+
+fn mix_into<T: Copy + Default + AddAssign>(target: &mut Vec<T>, offset: usize, samples: &[T]) {
+    if target.len() < offset + samples.len() {
+        target.resize(offset + samples.len(), T::default());
+    }
+    for (mixed, &sample) in target[offset..].iter_mut().zip(samples) {
+        *mixed += sample;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score() -> Score {
+        Score::parse_from_musicxml(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/chords.xml"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn seeded_render_is_reproducible() {
+        let config = GuitarConfig::acoustic();
+        let first = render_score(&score(), &config, 120, 44_100.0, Some(7));
+        let second = render_score(&score(), &config, 120, 44_100.0, Some(7));
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn different_seeds_render_differently() {
+        let config = GuitarConfig::acoustic();
+        let first = render_score(&score(), &config, 120, 44_100.0, Some(1));
+        let second = render_score(&score(), &config, 120, 44_100.0, Some(2));
+        assert_eq!(first.len(), second.len());
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn streaming_in_blocks_matches_rendering_at_once() {
+        let config = GuitarConfig::acoustic();
+        let note = &score().measures[0].positions[0][0];
+        let whole = render_note(note, 0.5, 44_100.0, &config, Some(5));
+        let mut plucked = PluckedNote::new(note, 0.5, 44_100.0, &config, Some(5));
+        let mut streamed = Vec::new();
+        let (mut block, mut scratch) = ([0.0; 128], [0.0; 128]);
+        loop {
+            let rendered = plucked.render_block(&mut block, &mut scratch);
+            streamed.extend_from_slice(&block[..rendered]);
+            if rendered < block.len() {
+                break;
+            }
+        }
+        assert_eq!(whole, streamed);
+    }
+
+    #[test]
+    fn only_related_strings_resonate() {
+        // An octave above the A string excites it, a semitone above doesn't
+        assert!(sympathetic_coupling(220.0, 110.0) > 0.0);
+        assert_eq!(sympathetic_coupling(116.54, 110.0), 0.0);
+    }
+
+    #[test]
+    fn feedback_sustains_held_notes() {
+        let note = &score().measures[0].positions[0][0];
+        let tail_peak = |config: &GuitarConfig| {
+            let samples = render_note(note, 4.0, 44_100.0, config, Some(3));
+            samples[samples.len() - 4410..]
+                .iter()
+                .fold(0.0f32, |peak, s| peak.max(s.abs()))
+        };
+        let dry = GuitarConfig::electric();
+        let feedback = GuitarConfig {
+            feedback: 1.0,
+            ..GuitarConfig::electric()
+        };
+        assert!(tail_peak(&feedback) > 2.0 * tail_peak(&dry));
+    }
+}
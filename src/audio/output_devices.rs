@@ -0,0 +1,42 @@
+// audio/output_devices.rs
+
+use cpal::traits::{DeviceTrait, HostTrait};
+use cpal::Device;
+
+/// Names of the output devices the default host offers, in the order it lists them.
+pub fn output_device_names() -> Vec<String> {
+    match cpal::default_host().output_devices() {
+        Ok(devices) => devices.filter_map(|device| device.name().ok()).collect(),
+        Err(e) => {
+            eprintln!("Failed to list output devices: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Names of the input devices the default host offers, such as microphones and USB interfaces.
+pub fn input_device_names() -> Vec<String> {
+    match cpal::default_host().input_devices() {
+        Ok(devices) => devices.filter_map(|device| device.name().ok()).collect(),
+        Err(e) => {
+            eprintln!("Failed to list input devices: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+pub fn find_input_device(name: &str) -> Result<Device, String> {
+    cpal::default_host()
+        .input_devices()
+        .map_err(|e| e.to_string())?
+        .find(|device| device.name().is_ok_and(|device_name| device_name == name))
+        .ok_or_else(|| format!("Input device '{}' not found", name))
+}
+
+pub fn find_output_device(name: &str) -> Result<Device, String> {
+    cpal::default_host()
+        .output_devices()
+        .map_err(|e| e.to_string())?
+        .find(|device| device.name().is_ok_and(|device_name| device_name == name))
+        .ok_or_else(|| format!("Output device '{}' not found", name))
+}
@@ -0,0 +1,252 @@
+// audio/polyphony.rs
+
+use std::fmt;
+
+use crate::audio::offline_render::PluckedNote;
+
+pub const DEFAULT_MAX_VOICES: usize = 32;
+// Most voices the pool makes room for up front, so striking never allocates on the audio thread
+pub const VOICE_LIMIT: usize = 120;
+// Most notes the audio thread holds at once, sounding, scheduled or waiting to be freed by the
+// player. Retiring a note never needs more room than this.
+pub const RETIRED_CAPACITY: usize = 6 * VOICE_LIMIT;
+// Short enough to free the voice at once, long enough not to click
+const STEAL_FADE_SECONDS: f32 = 0.01;
+// Notes cut off by a jump in playback fade a little slower, like a hand muting the strings
+const RELEASE_FADE_SECONDS: f32 = 0.04;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VoiceStealing {
+    Oldest,
+    Quietest,
+}
+
+impl fmt::Display for VoiceStealing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VoiceStealing::Oldest => write!(f, "Oldest"),
+            VoiceStealing::Quietest => write!(f, "Quietest"),
+        }
+    }
+}
+
+struct Voice {
+    note: PluckedNote,
+    // Order the voice was struck in, to find the oldest
+    age: u64,
+    // Peak of the latest block, to find the quietest voice
+    loudness: f32,
+    // Gain and how much it drops per sample while the voice is fading out
+    fade: Option<(f32, f32)>,
+    // Gains of the left and right channel for the note's pan position
+    pan_gains: (f32, f32),
+}
+
+/// The plucked notes currently sounding, mixed a block at a time by the streaming synthesizer.
+/// Notes that have ended are kept rather than freed, see [`Self::hand_over_retired`].
+pub struct VoicePool {
+    pub max_voices: usize,
+    pub stealing: VoiceStealing,
+    sample_rate: f32,
+    voices: Vec<Voice>,
+    struck: u64,
+    retired: Vec<PluckedNote>,
+}
+
+impl VoicePool {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            max_voices: DEFAULT_MAX_VOICES,
+            stealing: VoiceStealing::Oldest,
+            sample_rate,
+            // Stolen voices still fading out come on top of the ones sounding
+            voices: Vec::with_capacity(2 * VOICE_LIMIT),
+            struck: 0,
+            retired: Vec::with_capacity(RETIRED_CAPACITY),
+        }
+    }
+
+    /// Voices sounding, not counting the ones fading out.
+    pub fn len(&self) -> usize {
+        self.voices
+            .iter()
+            .filter(|voice| voice.fade.is_none())
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Notes held by the pool, the voices fading out and the ones retired included.
+    pub fn notes(&self) -> usize {
+        self.voices.len() + self.retired.len()
+    }
+
+    /// Starts `note`, fading out whichever voice must make room for it.
+    pub fn strike(&mut self, note: PluckedNote) {
+        while self.len() >= self.max_voices.max(1) {
+            let victim = self
+                .voices
+                .iter()
+                .enumerate()
+                .filter(|(_, voice)| voice.fade.is_none());
+            let victim = match self.stealing {
+                VoiceStealing::Oldest => victim
+                    .min_by_key(|(_, voice)| voice.age)
+                    .map(|(idx, _)| idx),
+                VoiceStealing::Quietest => victim
+                    .min_by(|(_, a), (_, b)| a.loudness.total_cmp(&b.loudness))
+                    .map(|(idx, _)| idx),
+            };
+            let Some(victim) = victim else {
+                break;
+            };
+            self.voices[victim].fade = Some((1.0, 1.0 / (STEAL_FADE_SECONDS * self.sample_rate)));
+        }
+        // Full of fading voices, the oldest of them has to go at once
+        if self.voices.len() == self.voices.capacity() {
+            let oldest = self
+                .voices
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, voice)| voice.age)
+                .map(|(idx, _)| idx);
+            if let Some(oldest) = oldest {
+                let voice = self.voices.swap_remove(oldest);
+                self.retire(voice.note);
+            }
+        }
+        self.struck += 1;
+        self.voices.push(Voice {
+            pan_gains: note.pan_gains(),
+            note,
+            age: self.struck,
+            loudness: f32::MAX,
+            fade: None,
+        });
+    }
+
+    /// Keeps `note` until it can be freed off the audio thread. There is always room as long as
+    /// no more than [`RETIRED_CAPACITY`] notes are held at once, see
+    /// [`crate::audio::synth_stream`].
+    pub fn retire(&mut self, note: PluckedNote) {
+        debug_assert!(self.retired.len() < self.retired.capacity());
+        self.retired.push(note);
+    }
+
+    /// Moves as many of the notes that have ended as fit into `to` without it growing, so the
+    /// audio thread never frees a note's buffers.
+    pub fn hand_over_retired(&mut self, to: &mut Vec<PluckedNote>) {
+        let room = to.capacity() - to.len();
+        let from = self.retired.len().saturating_sub(room);
+        to.extend(self.retired.drain(from..));
+    }
+
+    /// Fades out every voice, for when playback jumps and the notes ringing no longer belong.
+    pub fn release_all(&mut self) {
+        let step = 1.0 / (RELEASE_FADE_SECONDS * self.sample_rate);
+        for voice in &mut self.voices {
+            let gain = voice.fade.map_or(1.0, |(gain, _)| gain);
+            voice.fade = Some((gain, step));
+        }
+    }
+
+    /// Mixes the next samples of every voice into `left` and `right`, which are cleared first
+    /// and of the same length, each voice at its pan position. Retires the voices that have
+    /// ended. `scratch` and `voice_out` must be at least as long as `left`.
+    pub fn render(
+        &mut self,
+        left: &mut [f32],
+        right: &mut [f32],
+        voice_out: &mut [f32],
+        scratch: &mut [f32],
+    ) {
+        left.fill(0.0);
+        right.fill(0.0);
+        for voice in &mut self.voices {
+            let voice_out = &mut voice_out[..left.len()];
+            voice_out.fill(0.0);
+            let rendered = voice.note.render_block(voice_out, scratch);
+            let (left_gain, right_gain) = voice.pan_gains;
+            let mut peak = 0.0f32;
+            let mixed = left.iter_mut().zip(right.iter_mut());
+            for ((left, right), sample) in mixed.zip(&voice_out[..rendered]) {
+                let gain = match &mut voice.fade {
+                    Some((gain, step)) => {
+                        *gain = (*gain - *step).max(0.0);
+                        *gain
+                    }
+                    None => 1.0,
+                };
+                *left += sample * gain * left_gain;
+                *right += sample * gain * right_gain;
+                peak = peak.max(sample.abs());
+            }
+            voice.loudness = peak;
+        }
+        let mut idx = 0;
+        while idx < self.voices.len() {
+            let voice = &self.voices[idx];
+            if voice.note.remaining_samples() > 0 && voice.fade.is_none_or(|(gain, _)| gain > 0.0) {
+                idx += 1;
+            } else {
+                let voice = self.voices.swap_remove(idx);
+                self.retire(voice.note);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::guitar::guitar::GuitarConfig;
+    use crate::music_representation::Score;
+
+    fn pluck(duration: f32) -> PluckedNote {
+        let score = Score::parse_from_musicxml(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/chords.xml"
+        ))
+        .unwrap();
+        let note = &score.measures[0].positions[0][0];
+        PluckedNote::new(note, duration, 44_100.0, &GuitarConfig::acoustic(), Some(3))
+    }
+
+    #[test]
+    fn ended_and_stolen_voices_are_handed_over_instead_of_freed() {
+        let mut pool = VoicePool::new(44_100.0);
+        pool.max_voices = 1;
+        pool.strike(pluck(0.01));
+        pool.strike(pluck(1.0));
+        assert_eq!(pool.len(), 1);
+
+        let mut block = [0.0; 128];
+        let (mut right, mut voice_out, mut scratch) = ([0.0; 128], [0.0; 128], [0.0; 128]);
+        // Long enough for the stolen voice to fade out
+        for _ in 0..10 {
+            pool.render(&mut block, &mut right, &mut voice_out, &mut scratch);
+        }
+        let mut retired = Vec::with_capacity(RETIRED_CAPACITY);
+        pool.hand_over_retired(&mut retired);
+        assert_eq!(retired.len(), 1);
+        assert_eq!(pool.len(), 1);
+    }
+
+    #[test]
+    fn the_oldest_voice_is_stolen_first() {
+        let mut pool = VoicePool::new(44_100.0);
+        pool.max_voices = 2;
+        for _ in 0..3 {
+            pool.strike(pluck(1.0));
+        }
+        let fading: Vec<_> = pool
+            .voices
+            .iter()
+            .filter(|voice| voice.fade.is_some())
+            .map(|voice| voice.age)
+            .collect();
+        assert_eq!(fading, [1]);
+    }
+}
@@ -0,0 +1,66 @@
+// audio/rumble.rs
+
+use gilrs::ff::{BaseEffect, BaseEffectType, Effect, EffectBuilder, Replay, Ticks};
+use gilrs::{GamepadId, Gilrs};
+
+// Long enough to feel, short enough that beats at fast tempos stay apart
+const PULSE_MILLIS: u32 = 80;
+// Plain beats are felt weaker than the first beat of a measure
+const BEAT_STRENGTH: f32 = 0.5;
+
+/// Beats felt through the rumble motors of connected gamepads, for players who can't hear the
+/// click.
+pub struct Rumble {
+    gilrs: Gilrs,
+    pub strength: f32,
+    // Stops rumbling when dropped, so kept until the next pulse replaces it
+    effect: Option<Effect>,
+}
+
+impl Rumble {
+    pub fn start() -> Result<Self, String> {
+        Ok(Self {
+            gilrs: Gilrs::new().map_err(|e| e.to_string())?,
+            strength: 1.0,
+            effect: None,
+        })
+    }
+
+    /// Connected gamepads that can rumble.
+    pub fn gamepads(&mut self) -> Vec<GamepadId> {
+        // Connections and disconnections only show up once their events are taken
+        while self.gilrs.next_event().is_some() {}
+        self.gilrs
+            .gamepads()
+            .filter(|(_, gamepad)| gamepad.is_ff_supported())
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Rumbles once for a beat, harder when `accent` marks the first beat of a measure.
+    pub fn pulse(&mut self, accent: bool) {
+        let gamepads = self.gamepads();
+        if gamepads.is_empty() {
+            return;
+        }
+        let strength = if accent { 1.0 } else { BEAT_STRENGTH };
+        let magnitude = (self.strength * strength).clamp(0.0, 1.0) * u16::MAX as f32;
+        let effect = EffectBuilder::new()
+            .add_effect(BaseEffect {
+                kind: BaseEffectType::Strong {
+                    magnitude: magnitude as u16,
+                },
+                scheduling: Replay {
+                    play_for: Ticks::from_ms(PULSE_MILLIS),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .gamepads(&gamepads)
+            .finish(&mut self.gilrs);
+        match effect.and_then(|effect| effect.play().map(|_| effect)) {
+            Ok(effect) => self.effect = Some(effect),
+            Err(e) => eprintln!("Failed to rumble: {}", e),
+        }
+    }
+}
@@ -0,0 +1,151 @@
+// audio/section_detection.rs
+
+use kira::Frame;
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+
+// Spectrum analysis parameters, in samples at the track's sample rate
+const SPECTRUM_WINDOW: usize = 4096;
+const SPECTRUM_HOP: usize = 2048;
+// Spectra averaged into one feature block, about half a second at 44.1 kHz
+const SPECTRA_PER_BLOCK: usize = 10;
+const NUM_BANDS: usize = 24;
+const MIN_FREQUENCY: f32 = 60.0;
+const MAX_FREQUENCY: f32 = 8000.0;
+// Half width of the checkerboard kernel in blocks; also the minimum distance between boundaries
+const KERNEL_HALF_WIDTH: usize = 16;
+// Boundaries must stand out this many standard deviations above the mean novelty
+const NOVELTY_THRESHOLD: f32 = 0.5;
+
+/// Finds the times in seconds where the sound of the recording changes the most, like the
+/// boundary between a verse and a chorus.
+pub fn detect_section_boundaries(frames: &[Frame], sample_rate: u32) -> Vec<f32> {
+    let blocks = feature_blocks(frames, sample_rate);
+    let novelty = novelty_curve(&blocks);
+    if novelty.is_empty() {
+        return Vec::new();
+    }
+
+    let mean = novelty.iter().sum::<f32>() / novelty.len() as f32;
+    let variance = novelty.iter().map(|n| (n - mean).powi(2)).sum::<f32>() / novelty.len() as f32;
+    let threshold = mean + NOVELTY_THRESHOLD * variance.sqrt();
+
+    let seconds_per_block = (SPECTRUM_HOP * SPECTRA_PER_BLOCK) as f32 / sample_rate as f32;
+    (KERNEL_HALF_WIDTH..novelty.len().saturating_sub(KERNEL_HALF_WIDTH))
+        .filter(|&i| {
+            let neighbourhood = i.saturating_sub(KERNEL_HALF_WIDTH)..=i + KERNEL_HALF_WIDTH;
+            novelty[i] > threshold
+                && neighbourhood
+                    .filter(|&j| j != i && j < novelty.len())
+                    // Ties go to the earliest block so a flat peak yields a single boundary
+                    .all(|j| novelty[j] < novelty[i] || (novelty[j] == novelty[i] && j > i))
+        })
+        .map(|i| i as f32 * seconds_per_block)
+        .collect()
+}
+
+/// Log energies in logarithmically spaced bands, averaged over blocks and normalized to unit
+/// length so blocks can be compared by their dot product.
+fn feature_blocks(frames: &[Frame], sample_rate: u32) -> Vec<[f32; NUM_BANDS]> {
+    if frames.len() < SPECTRUM_WINDOW {
+        return Vec::new();
+    }
+
+    let mut planner = FftPlanner::<f32>::new();
+    let fft = planner.plan_fft_forward(SPECTRUM_WINDOW);
+    let window: Vec<f32> = (0..SPECTRUM_WINDOW)
+        .map(|i| {
+            let phase = i as f32 / SPECTRUM_WINDOW as f32;
+            0.5 - 0.5 * (2.0 * std::f32::consts::PI * phase).cos()
+        })
+        .collect();
+
+    let bin_frequency = sample_rate as f32 / SPECTRUM_WINDOW as f32;
+    let band_of_bin: Vec<Option<usize>> = (0..SPECTRUM_WINDOW / 2)
+        .map(|bin| {
+            let frequency = bin as f32 * bin_frequency;
+            if !(MIN_FREQUENCY..MAX_FREQUENCY).contains(&frequency) {
+                return None;
+            }
+            let position = (frequency / MIN_FREQUENCY).ln() / (MAX_FREQUENCY / MIN_FREQUENCY).ln();
+            Some(((position * NUM_BANDS as f32) as usize).min(NUM_BANDS - 1))
+        })
+        .collect();
+
+    let mut buffer = vec![Complex::new(0.0, 0.0); SPECTRUM_WINDOW];
+    let mut blocks = Vec::new();
+    let mut block = [0.0f32; NUM_BANDS];
+    let mut spectra_in_block = 0;
+
+    for start in (0..=frames.len() - SPECTRUM_WINDOW).step_by(SPECTRUM_HOP) {
+        for (i, value) in buffer.iter_mut().enumerate() {
+            let frame = frames[start + i];
+            *value = Complex::new((frame.left + frame.right) * 0.5 * window[i], 0.0);
+        }
+        fft.process(&mut buffer);
+
+        let mut bands = [0.0f32; NUM_BANDS];
+        for (bin, band) in band_of_bin.iter().enumerate() {
+            if let Some(band) = band {
+                bands[*band] += buffer[bin].norm_sqr();
+            }
+        }
+        for (sum, energy) in block.iter_mut().zip(bands) {
+            *sum += (1.0 + energy).ln();
+        }
+
+        spectra_in_block += 1;
+        if spectra_in_block == SPECTRA_PER_BLOCK {
+            let length = block.iter().map(|v| v * v).sum::<f32>().sqrt();
+            if length > 0.0 {
+                block.iter_mut().for_each(|v| *v /= length);
+            }
+            blocks.push(block);
+            block = [0.0; NUM_BANDS];
+            spectra_in_block = 0;
+        }
+    }
+
+    blocks
+}
+
+/// Correlates the self-similarity matrix with a Gaussian tapered checkerboard kernel along its
+/// diagonal, which peaks where the blocks before and after a point differ from each other.
+fn novelty_curve(blocks: &[[f32; NUM_BANDS]]) -> Vec<f32> {
+    let n = blocks.len();
+    if n < 2 * KERNEL_HALF_WIDTH {
+        return Vec::new();
+    }
+
+    let similarity =
+        |a: usize, b: usize| -> f32 { blocks[a].iter().zip(&blocks[b]).map(|(x, y)| x * y).sum() };
+    let taper = |offset: f32| {
+        let sigma = KERNEL_HALF_WIDTH as f32 / 2.0;
+        (-0.5 * (offset / sigma).powi(2)).exp()
+    };
+
+    (0..n)
+        .map(|center| {
+            if center < KERNEL_HALF_WIDTH || center + KERNEL_HALF_WIDTH > n {
+                return 0.0;
+            }
+            let mut novelty = 0.0;
+            for i in 0..2 * KERNEL_HALF_WIDTH {
+                for j in 0..2 * KERNEL_HALF_WIDTH {
+                    let a = center + i - KERNEL_HALF_WIDTH;
+                    let b = center + j - KERNEL_HALF_WIDTH;
+                    // Positive within the past and within the future, negative across them
+                    let sign = if (i < KERNEL_HALF_WIDTH) == (j < KERNEL_HALF_WIDTH) {
+                        1.0
+                    } else {
+                        -1.0
+                    };
+                    let weight = taper(i as f32 - KERNEL_HALF_WIDTH as f32 + 0.5)
+                        * taper(j as f32 - KERNEL_HALF_WIDTH as f32 + 0.5);
+                    novelty += sign * weight * similarity(a, b);
+                }
+            }
+            novelty
+        })
+        .collect()
+}
@@ -0,0 +1,146 @@
+// audio/signal_history.rs
+
+/// Fixed-size ring buffer that overwrites its oldest values.
+struct Ring<T> {
+    values: Vec<T>,
+    next: usize,
+    full: bool,
+}
+
+impl<T: Copy + Default> Ring<T> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            values: vec![T::default(); capacity.max(1)],
+            next: 0,
+            full: false,
+        }
+    }
+
+    fn len(&self) -> usize {
+        if self.full {
+            self.values.len()
+        } else {
+            self.next
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        self.values.len()
+    }
+
+    /// A ring holding `capacity` values, starting with the newest ones of this one.
+    fn resized(&self, capacity: usize) -> Self {
+        let mut ring = Self::new(capacity);
+        for value in self.latest(capacity) {
+            ring.push(value);
+        }
+        ring
+    }
+
+    fn push(&mut self, value: T) {
+        self.values[self.next] = value;
+        self.next += 1;
+        if self.next == self.values.len() {
+            self.next = 0;
+            self.full = true;
+        }
+    }
+
+    /// The newest `count` values, oldest first.
+    fn latest(&self, count: usize) -> impl Iterator<Item = T> + '_ {
+        let count = count.min(self.len());
+        let start = (self.next + self.values.len() - count) % self.values.len();
+        (0..count).map(move |i| self.values[(start + i) % self.values.len()])
+    }
+}
+
+/// Recent output samples at full resolution, plus a min/max overview of a much longer span.
+/// Memory use is fixed, so adding samples never allocates or shifts old ones.
+pub struct SignalHistory {
+    samples: Ring<f32>,
+    overview: Ring<(f32, f32)>,
+    samples_per_bucket: usize,
+    // Extremes of the samples not yet summarized in the overview
+    bucket: (f32, f32),
+    bucket_len: usize,
+    total_samples: u64,
+    /// Ignores new samples while set, to look at what was captured at leisure
+    pub frozen: bool,
+}
+
+impl SignalHistory {
+    pub fn new(capacity: usize, samples_per_bucket: usize, overview_buckets: usize) -> Self {
+        Self {
+            samples: Ring::new(capacity),
+            overview: Ring::new(overview_buckets),
+            samples_per_bucket: samples_per_bucket.max(1),
+            bucket: (f32::MAX, f32::MIN),
+            bucket_len: 0,
+            total_samples: 0,
+            frozen: false,
+        }
+    }
+
+    /// Number of samples kept at full resolution.
+    pub fn capacity(&self) -> usize {
+        self.samples.capacity()
+    }
+
+    /// Keeps `capacity` samples at full resolution from now on, without losing the newest ones.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        if capacity != self.capacity() {
+            self.samples = self.samples.resized(capacity);
+        }
+    }
+
+    pub fn push(&mut self, samples: &[f32]) {
+        if self.frozen {
+            return;
+        }
+        for &sample in samples {
+            self.samples.push(sample);
+            self.bucket = (self.bucket.0.min(sample), self.bucket.1.max(sample));
+            self.bucket_len += 1;
+            if self.bucket_len == self.samples_per_bucket {
+                self.overview.push(self.bucket);
+                self.bucket = (f32::MAX, f32::MIN);
+                self.bucket_len = 0;
+            }
+        }
+        self.total_samples += samples.len() as u64;
+    }
+
+    /// Number of samples pushed since the start, to place the latest ones in time.
+    pub fn total_samples(&self) -> u64 {
+        self.total_samples
+    }
+
+    /// The newest `count` samples, oldest first.
+    pub fn latest(&self, count: usize) -> Vec<f32> {
+        self.samples.latest(count).collect()
+    }
+
+    /// `count` recent samples starting at the latest rising zero crossing that leaves room for
+    /// all of them, so a periodic signal stands still from one frame to the next. Falls back to
+    /// the newest samples when there is no crossing.
+    pub fn triggered(&self, count: usize) -> Vec<f32> {
+        let mut samples = self.latest(2 * count);
+        let latest_start = samples.len().saturating_sub(count);
+        let crossing = (1..=latest_start)
+            .rev()
+            .find(|&i| samples[i - 1] < 0.0 && samples[i] >= 0.0);
+        let start = crossing.unwrap_or(latest_start);
+        samples.truncate(start + count);
+        samples.drain(..start);
+        samples
+    }
+
+    pub fn samples_per_bucket(&self) -> usize {
+        self.samples_per_bucket
+    }
+
+    /// Minimum and maximum of every bucket in the overview, oldest first.
+    pub fn overview(&self) -> Vec<(f32, f32)> {
+        self.overview.latest(usize::MAX).collect()
+    }
+}
@@ -0,0 +1,36 @@
+// audio/spectrum.rs
+
+use rustfft::num_complex::Complex32;
+use rustfft::FftPlanner;
+
+/// Magnitudes of the first half of the FFT of `samples`, zero-padded to a power of two.
+/// Bin `i` is at `i * sample_rate / (2 * result.len())` Hz.
+pub fn magnitude_spectrum(samples: &[f32]) -> Vec<f32> {
+    let fft_size = samples.len().next_power_of_two();
+    let mut planner = FftPlanner::<f32>::new();
+    let fft = planner.plan_fft_forward(fft_size);
+
+    let mut buffer: Vec<Complex32> = samples
+        .iter()
+        .map(|&sample| Complex32::new(sample, 0.0))
+        .collect();
+    buffer.resize(fft_size, Complex32::new(0.0, 0.0));
+    fft.process(&mut buffer);
+
+    buffer[..fft_size / 2].iter().map(|c| c.norm()).collect()
+}
+
+/// `samples` tapered with a Hann window, so a frame cut out of a longer signal doesn't smear
+/// across the spectrum.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn hann_window(samples: &[f32]) -> Vec<f32> {
+    let last = samples.len().saturating_sub(1).max(1) as f32;
+    samples
+        .iter()
+        .enumerate()
+        .map(|(i, &sample)| {
+            let weight = 0.5 - 0.5 * (std::f32::consts::TAU * i as f32 / last).cos();
+            sample * weight
+        })
+        .collect()
+}
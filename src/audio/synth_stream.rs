@@ -0,0 +1,385 @@
+// audio/synth_stream.rs
+
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use kira::clock::clock_info::ClockInfoProvider;
+use kira::modulator::value_provider::ModulatorValueProvider;
+use kira::sound::{Sound, SoundData};
+use kira::{Frame, OutputDestination};
+
+use instant::Instant;
+
+use crate::audio::offline_render::PluckedNote;
+use crate::audio::polyphony::{
+    VoicePool, VoiceStealing, DEFAULT_MAX_VOICES, RETIRED_CAPACITY, VOICE_LIMIT,
+};
+
+// Samples synthesized at a time, small enough for a note to start within a few milliseconds
+const BLOCK: usize = 128;
+// Notes waiting for the audio thread, a chord or two per batch at most
+const COMMAND_CAPACITY: usize = 4 * VOICE_LIMIT;
+
+/// What the player asks of the synthesizer running on the audio thread.
+pub enum SynthCommand {
+    /// Starts the note as soon as possible
+    Strike(PluckedNote),
+    /// Starts the note on the sample the transport reaches `seconds` at
+    Schedule { note: PluckedNote, seconds: f64 },
+    /// The transport is at the given seconds right now, or stopped for `None`. Notes scheduled
+    /// against the old transport are dropped.
+    Transport(Option<f64>),
+    /// Fades out every note still ringing
+    Release,
+}
+
+impl SynthCommand {
+    fn has_note(&self) -> bool {
+        matches!(
+            self,
+            SynthCommand::Strike(_) | SynthCommand::Schedule { .. }
+        )
+    }
+}
+
+/// State shared between the player and the sound streaming its notes.
+pub struct SynthShared {
+    commands: Mutex<Vec<SynthCommand>>,
+    // Samples synthesized since the player last took them, for the output plot
+    tap: Mutex<Vec<f32>>,
+    // Notes that have ended, for the player to free off the audio thread
+    retired: Mutex<Vec<PluckedNote>>,
+    volume: AtomicU32,
+    panning: AtomicU32,
+    max_voices: AtomicUsize,
+    quietest_first: AtomicBool,
+    /// Voices sounding as of the latest batch
+    pub active_voices: AtomicUsize,
+    // Longest a block took to synthesize since the player last asked, in microseconds
+    block_micros: AtomicU32,
+    sample_rate: f32,
+}
+
+impl SynthShared {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            commands: Mutex::new(Vec::with_capacity(COMMAND_CAPACITY)),
+            tap: Mutex::new(Vec::with_capacity(sample_rate as usize)),
+            retired: Mutex::new(Vec::with_capacity(RETIRED_CAPACITY)),
+            volume: AtomicU32::new(1.0f32.to_bits()),
+            panning: AtomicU32::new(0.5f32.to_bits()),
+            max_voices: AtomicUsize::new(DEFAULT_MAX_VOICES),
+            quietest_first: AtomicBool::new(false),
+            active_voices: AtomicUsize::new(0),
+            block_micros: AtomicU32::new(0),
+            sample_rate,
+        }
+    }
+
+    pub fn send(&self, commands: impl IntoIterator<Item = SynthCommand>) {
+        if let Ok(mut queue) = self.commands.lock() {
+            queue.extend(commands);
+        }
+    }
+
+    /// Volume from 0 and panning from 0 for left to 1 for right, applied to every note at once.
+    pub fn set_mix(&self, volume: f32, panning: f32) {
+        self.volume.store(volume.to_bits(), Ordering::Relaxed);
+        self.panning.store(panning.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn set_polyphony(&self, max_voices: usize, stealing: VoiceStealing) {
+        self.max_voices.store(max_voices, Ordering::Relaxed);
+        self.quietest_first
+            .store(stealing == VoiceStealing::Quietest, Ordering::Relaxed);
+    }
+
+    pub fn voice_stealing(&self) -> VoiceStealing {
+        if self.quietest_first.load(Ordering::Relaxed) {
+            VoiceStealing::Quietest
+        } else {
+            VoiceStealing::Oldest
+        }
+    }
+
+    /// The samples synthesized since the last call.
+    pub fn take_output(&self) -> Vec<f32> {
+        self.tap
+            .lock()
+            .map(|mut tap| tap.drain(..).collect())
+            .unwrap_or_default()
+    }
+
+    /// Frees the notes the audio thread is done with.
+    pub fn free_retired(&self) {
+        if let Ok(mut retired) = self.retired.lock() {
+            retired.clear();
+        }
+    }
+
+    /// Longest a block took to synthesize since the last call, in milliseconds.
+    pub fn take_block_time(&self) -> f32 {
+        self.block_micros.swap(0, Ordering::Relaxed) as f32 / 1000.0
+    }
+}
+
+/// Starts the sound streaming the notes of `shared` on `destination` for as long as the audio
+/// manager lives.
+pub struct SynthSoundData {
+    pub shared: Arc<SynthShared>,
+    pub destination: OutputDestination,
+}
+
+impl SoundData for SynthSoundData {
+    type Error = String;
+    type Handle = ();
+
+    fn into_sound(self) -> Result<(Box<dyn Sound>, Self::Handle), Self::Error> {
+        Ok((Box::new(SynthSound::new(self.shared, self.destination)), ()))
+    }
+}
+
+/// A never ending sound mixing the voices of the pool. Notes are synthesized at the player's
+/// rate as they play and interpolated to the output's.
+struct SynthSound {
+    shared: Arc<SynthShared>,
+    destination: OutputDestination,
+    pool: VoicePool,
+    left: [f32; BLOCK],
+    right: [f32; BLOCK],
+    voice_block: [f32; BLOCK],
+    scratch: [f32; BLOCK],
+    // Index of the next sample of the block to play
+    block_position: usize,
+    // Notes waiting for their start, by the stream sample they start at
+    scheduled: Vec<(u64, PluckedNote)>,
+    // Stream sample the transport was at zero seconds at
+    transport_origin: Option<f64>,
+    // Samples synthesized so far, and the ones of them taken for output
+    played: u64,
+    rendered: u64,
+    volume: f32,
+    panning: f32,
+    // Fraction of the way from the previous to the next synthesized sample
+    position: f64,
+    previous: Frame,
+    next: Frame,
+}
+
+impl SynthSound {
+    fn new(shared: Arc<SynthShared>, destination: OutputDestination) -> Self {
+        Self {
+            pool: VoicePool::new(shared.sample_rate),
+            shared,
+            destination,
+            left: [0.0; BLOCK],
+            right: [0.0; BLOCK],
+            voice_block: [0.0; BLOCK],
+            scratch: [0.0; BLOCK],
+            block_position: BLOCK,
+            scheduled: Vec::with_capacity(COMMAND_CAPACITY),
+            transport_origin: None,
+            played: 0,
+            rendered: 0,
+            volume: 1.0,
+            panning: 0.5,
+            position: 0.0,
+            previous: Frame::ZERO,
+            next: Frame::ZERO,
+        }
+    }
+
+    /// Synthesizes the next block, striking every scheduled note on its own sample.
+    fn render_block(&mut self) {
+        let start = Instant::now();
+        let block_end = self.rendered + BLOCK as u64;
+        let mut offset = 0;
+        while offset < BLOCK {
+            let until = self
+                .scheduled
+                .iter()
+                .map(|&(at, _)| at)
+                .filter(|&at| at < block_end)
+                .min()
+                .map_or(BLOCK, |at| at.saturating_sub(self.rendered) as usize)
+                .max(offset);
+            if until > offset {
+                self.pool.render(
+                    &mut self.left[offset..until],
+                    &mut self.right[offset..until],
+                    &mut self.voice_block,
+                    &mut self.scratch,
+                );
+                offset = until;
+            }
+            // Late notes start right away rather than not at all
+            let now = self.rendered + offset as u64;
+            while let Some(idx) = self.scheduled.iter().position(|&(at, _)| at <= now) {
+                let (_, note) = self.scheduled.swap_remove(idx);
+                self.pool.strike(note);
+            }
+        }
+        self.rendered = block_end;
+        let micros = start.elapsed().as_micros() as u32;
+        self.shared
+            .block_micros
+            .fetch_max(micros, Ordering::Relaxed);
+        self.block_position = 0;
+
+        // Never block the audio thread, and drop what the plot hasn't taken in time
+        if let Ok(mut tap) = self.shared.tap.try_lock() {
+            let room = tap.capacity() - tap.len();
+            let mono = self
+                .left
+                .iter()
+                .zip(&self.right)
+                .map(|(l, r)| (l + r) / 2.0);
+            tap.extend(mono.take(room));
+        }
+    }
+
+    fn next_sample(&mut self) -> Frame {
+        if self.block_position == BLOCK {
+            self.render_block();
+        }
+        let sample = Frame::new(
+            self.left[self.block_position],
+            self.right[self.block_position],
+        );
+        self.block_position += 1;
+        self.played += 1;
+        sample
+    }
+}
+
+impl Sound for SynthSound {
+    fn output_destination(&mut self) -> OutputDestination {
+        self.destination
+    }
+
+    fn on_start_processing(&mut self) {
+        let shared = &self.shared;
+        let sample_rate = shared.sample_rate as f64;
+        self.volume = f32::from_bits(shared.volume.load(Ordering::Relaxed));
+        self.panning = f32::from_bits(shared.panning.load(Ordering::Relaxed));
+        self.pool.max_voices = shared.max_voices.load(Ordering::Relaxed);
+        self.pool.stealing = shared.voice_stealing();
+        // Handed over first, the room it leaves is what new notes may take
+        if let Ok(mut retired) = shared.retired.try_lock() {
+            self.pool.hand_over_retired(&mut retired);
+        }
+        // A busy queue is picked up next batch
+        if let Ok(mut commands) = shared.commands.try_lock() {
+            // Notes beyond what fits wait in the queue until the player has freed some, so
+            // retiring a note never runs out of room
+            let mut room =
+                RETIRED_CAPACITY.saturating_sub(self.pool.notes() + self.scheduled.len());
+            let taken = commands
+                .iter()
+                .take_while(|command| {
+                    if !command.has_note() {
+                        return true;
+                    }
+                    let fits = room > 0;
+                    room = room.saturating_sub(1);
+                    fits
+                })
+                .count();
+            for command in commands.drain(..taken) {
+                match command {
+                    SynthCommand::Strike(note) => self.pool.strike(note),
+                    SynthCommand::Schedule { note, seconds } => {
+                        let Some(origin) = self.transport_origin else {
+                            self.pool.retire(note);
+                            continue;
+                        };
+                        let at = (origin + seconds * sample_rate).max(0.0) as u64;
+                        // For when the queue was busy for longer than expected
+                        if self.scheduled.len() < self.scheduled.capacity() {
+                            self.scheduled.push((at, note));
+                        } else {
+                            self.pool.strike(note);
+                        }
+                    }
+                    SynthCommand::Transport(seconds) => {
+                        for (_, note) in self.scheduled.drain(..) {
+                            self.pool.retire(note);
+                        }
+                        self.transport_origin =
+                            seconds.map(|seconds| self.played as f64 - seconds * sample_rate);
+                    }
+                    SynthCommand::Release => self.pool.release_all(),
+                }
+            }
+        }
+        shared
+            .active_voices
+            .store(self.pool.len(), Ordering::Relaxed);
+    }
+
+    fn process(
+        &mut self,
+        dt: f64,
+        _clock_info_provider: &ClockInfoProvider,
+        _modulator_value_provider: &ModulatorValueProvider,
+    ) -> Frame {
+        // Linear interpolation between synthesized samples converts to the output rate
+        self.position += self.shared.sample_rate as f64 * dt;
+        while self.position >= 1.0 {
+            self.previous = self.next;
+            self.next = self.next_sample();
+            self.position -= 1.0;
+        }
+        let sample = self.previous + (self.next - self.previous) * self.position as f32;
+        (sample * self.volume).panned(self.panning)
+    }
+
+    fn finished(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::guitar::guitar::GuitarConfig;
+    use crate::music_representation::Score;
+
+    #[test]
+    fn notes_past_what_can_be_retired_wait_in_the_queue() {
+        let score = Score::parse_from_musicxml(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/chords.xml"
+        ))
+        .unwrap();
+        let note = &score.measures[0].positions[0][0];
+        let config = GuitarConfig::acoustic();
+        let shared = Arc::new(SynthShared::new(44_100.0));
+        let mut sound = SynthSound::new(shared.clone(), OutputDestination::MAIN_TRACK);
+
+        // Without a transport every scheduled note is retired at once
+        let extra = 5;
+        shared.send(
+            (0..RETIRED_CAPACITY + extra).map(|_| SynthCommand::Schedule {
+                note: PluckedNote::new(note, 0.01, 44_100.0, &config, Some(1)),
+                seconds: 0.0,
+            }),
+        );
+        sound.on_start_processing();
+        assert_eq!(sound.pool.notes(), RETIRED_CAPACITY);
+        assert_eq!(shared.commands.lock().unwrap().len(), extra);
+
+        // The player hasn't freed anything yet, so only the handed over notes make room
+        sound.on_start_processing();
+        assert_eq!(shared.retired.lock().unwrap().len(), RETIRED_CAPACITY);
+        assert_eq!(sound.pool.notes(), extra);
+        assert!(shared.commands.lock().unwrap().is_empty());
+
+        sound.on_start_processing();
+        assert_eq!(sound.pool.notes(), extra);
+        shared.free_retired();
+        sound.on_start_processing();
+        assert_eq!(sound.pool.notes(), 0);
+        assert_eq!(shared.retired.lock().unwrap().len(), extra);
+    }
+}
@@ -0,0 +1,67 @@
+// audio/tuner.rs
+
+// Lowest and highest open strings to tune, with room for a low B or a capo high up
+const MIN_FREQUENCY: f32 = 55.0;
+const MAX_FREQUENCY: f32 = 500.0;
+const SILENCE_RMS: f32 = 0.005;
+// How closely the input has to repeat itself to have a pitch at all
+const MIN_CLARITY: f32 = 0.6;
+// Peaks this close to the highest count as the period, so the fundamental wins over its
+// octaves below
+const PEAK_RATIO: f32 = 0.9;
+/// Samples looked at for one reading, a few periods of the lowest string.
+pub const TUNER_WINDOW: usize = 2048;
+
+/// Frequency in Hz of the string ringing in `samples`, or `None` for silence or noise. The
+/// period is where the normalized square difference of the input with itself peaks first.
+pub fn detect_frequency(samples: &[f32], sample_rate: u32) -> Option<f32> {
+    let rate = sample_rate as f32;
+    let min_lag = (rate / MAX_FREQUENCY) as usize;
+    let max_lag = ((rate / MIN_FREQUENCY) as usize).min(samples.len() / 2);
+    if min_lag < 2 || max_lag <= min_lag + 2 {
+        return None;
+    }
+    let rms = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+    if rms < SILENCE_RMS {
+        return None;
+    }
+
+    // 1 where the input repeats itself exactly after `lag` samples, -1 where it inverts
+    let nsdf: Vec<f32> = (0..=max_lag + 1)
+        .map(|lag| {
+            let (head, tail) = (&samples[..samples.len() - lag], &samples[lag..]);
+            let correlation: f32 = head.iter().zip(tail).map(|(a, b)| a * b).sum();
+            let energy: f32 = head.iter().chain(tail).map(|s| s * s).sum();
+            if energy > 0.0 {
+                2.0 * correlation / energy
+            } else {
+                0.0
+            }
+        })
+        .collect();
+    let is_peak = |lag: usize| nsdf[lag] >= nsdf[lag - 1] && nsdf[lag] > nsdf[lag + 1];
+    let highest = (min_lag..=max_lag)
+        .filter(|&lag| is_peak(lag))
+        .map(|lag| nsdf[lag])
+        .fold(f32::MIN, f32::max);
+    if highest < MIN_CLARITY {
+        return None;
+    }
+    let lag = (min_lag..=max_lag).find(|&lag| is_peak(lag) && nsdf[lag] >= highest * PEAK_RATIO)?;
+
+    // The top of the parabola through the peak and its neighbours, between two samples
+    let (before, at, after) = (nsdf[lag - 1], nsdf[lag], nsdf[lag + 1]);
+    let curvature = before - 2.0 * at + after;
+    let offset = if curvature < 0.0 {
+        0.5 * (before - after) / curvature
+    } else {
+        0.0
+    };
+    Some(rate / (lag as f32 + offset))
+}
+
+/// How far `frequency` is from the MIDI note `target`, in cents, sharp above 0.
+pub fn cents_off(frequency: f32, target: u8) -> f32 {
+    let target = 440.0 * 2f32.powf((target as f32 - 69.0) / 12.0);
+    1200.0 * (frequency / target).log2()
+}
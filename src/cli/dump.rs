@@ -0,0 +1,10 @@
+// cli/dump.rs
+
+use crate::music_representation::Score;
+
+/// Prints the parsed structure of the score at `path` as JSON.
+pub fn run(path: &str) -> Result<(), String> {
+    let score = Score::parse_from_musicxml(path)?;
+    println!("{}", score.to_json()?);
+    Ok(())
+}
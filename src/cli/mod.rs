@@ -0,0 +1,3 @@
+pub mod dump;
+pub mod play;
+pub mod video;
@@ -0,0 +1,259 @@
+// cli/play.rs
+
+use std::io::Write;
+use std::time::Duration;
+
+use instant::Instant;
+
+use crate::audio::audio_player::AudioPlayer;
+use crate::gui::position_report::format_time;
+use crate::guitar::guitar::GuitarConfig;
+use crate::library::watch::{equivalent_position, ScoreWatcher};
+use crate::music_representation::{Measure, Score, Technique, Timeline};
+
+const STRING_NAMES: [&str; 6] = ["e", "B", "G", "D", "A", "E"];
+// Used when the terminal doesn't report its width
+const DEFAULT_WIDTH: usize = 80;
+// Lets the last notes ring out before exiting
+const RING_OUT_SECONDS: f32 = 2.0;
+
+const USAGE: &str = "Usage: cdefgab play <file.xml> [--tempo <bpm>] [--watch]";
+
+/// Arguments of `cdefgab play`.
+struct PlayOptions {
+    path: String,
+    tempo: Option<usize>,
+    // Reload the file when it changes and repeat the score until interrupted
+    watch: bool,
+}
+
+impl PlayOptions {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut path = None;
+        let mut tempo = None;
+        let mut watch = false;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--tempo" => {
+                    let value = args.next().ok_or(USAGE)?;
+                    let bpm = value
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|&bpm| bpm > 0)
+                        .ok_or_else(|| format!("Invalid tempo '{}'", value))?;
+                    tempo = Some(bpm);
+                }
+                "--watch" => watch = true,
+                _ if path.is_none() => path = Some(arg.clone()),
+                _ => return Err(USAGE.to_string()),
+            }
+        }
+        Ok(Self {
+            path: path.ok_or(USAGE)?,
+            tempo,
+            watch,
+        })
+    }
+}
+
+/// Plays a score through the audio engine while drawing its tab in the terminal, with a cursor
+/// under the division being played. `args` are the arguments after `play`.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let options = PlayOptions::parse(args)?;
+    let mut score = Score::parse_from_musicxml(&options.path)?;
+    if score.measures.is_empty() {
+        return Err(format!("{} has no measures", options.path));
+    }
+    let mut watcher = options
+        .watch
+        .then(|| ScoreWatcher::new(options.path.clone().into()));
+
+    let mut audio_player = AudioPlayer::new(GuitarConfig::acoustic());
+    audio_player.start().map_err(|e| e.to_string())?;
+
+    let tempo = |score: &Score| options.tempo.unwrap_or(score.tempo);
+    let mut timeline = Timeline::from_score(&score);
+    let mut layout = TerminalTab::new(&score, terminal_width());
+    let mut start = Instant::now();
+    let mut last_played = None;
+    let mut status = String::new();
+
+    loop {
+        let mut reloaded = false;
+        if let Some(result) = watcher.as_mut().and_then(ScoreWatcher::poll) {
+            match result {
+                Ok(new_score) if !new_score.measures.is_empty() => {
+                    // Continue from the same measure and division of the new version
+                    let (measure, division) = last_played.unwrap_or((0, 0));
+                    let (measure, division) = equivalent_position(&new_score, measure, division);
+                    timeline = Timeline::from_score(&new_score);
+                    let offset = timeline.tick_of(measure, division) as f32
+                        * timeline.seconds_per_tick(tempo(&new_score));
+                    start = Instant::now() - Duration::from_secs_f32(offset);
+                    layout = TerminalTab::new(&new_score, terminal_width());
+                    score = new_score;
+                    last_played = Some((measure, division));
+                    status = String::from("  (reloaded)");
+                    reloaded = true;
+                }
+                Ok(_) => status = String::from("  (reload failed: no measures)"),
+                Err(e) => status = format!("  (reload failed: {})", e),
+            }
+        }
+
+        let seconds_per_division = timeline.seconds_per_tick(tempo(&score));
+        let elapsed = start.elapsed().as_secs_f32();
+        let tick = (elapsed / seconds_per_division) as usize;
+        let position = timeline.position_at(tick);
+        let Some((measure_idx, division_idx)) = position else {
+            if watcher.is_some() {
+                start = Instant::now();
+                continue;
+            }
+            break;
+        };
+        if reloaded || last_played != Some((measure_idx, division_idx)) {
+            // A reload redraws the division already played without playing it again
+            if !reloaded {
+                if let Some((struck, divisions)) = timeline.struck_at(tick) {
+                    let duration = seconds_per_division * divisions as f32;
+                    audio_player.play_notes(&struck, duration);
+                }
+            }
+            last_played = Some((measure_idx, division_idx));
+            let total = seconds_per_division * timeline.total_ticks as f32;
+            let header = format!(
+                "{}  measure {}/{}  {} / {}{}",
+                score.title.as_deref().unwrap_or(&options.path),
+                measure_idx + 1,
+                score.measures.len(),
+                format_time(elapsed),
+                format_time(total),
+                status,
+            );
+            redraw(&header, &layout.lines(&score, measure_idx, division_idx));
+        }
+        std::thread::sleep(Duration::from_millis(2));
+    }
+
+    std::thread::sleep(Duration::from_secs_f32(RING_OUT_SECONDS));
+    println!();
+    Ok(())
+}
+
+fn terminal_width() -> usize {
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+/// Moves to the top left of the terminal and replaces what was there.
+fn redraw(header: &str, lines: &[String]) {
+    let mut out = std::io::stdout().lock();
+    let _ = write!(out, "\x1b[H\x1b[J{}\r\n\r\n", header);
+    for line in lines {
+        let _ = write!(out, "{}\r\n", line);
+    }
+    let _ = out.flush();
+}
+
+/// How the tab is split into rows of measures that fit the terminal.
+struct TerminalTab {
+    // Divisions per character column, so long measures still fit
+    divisions_per_column: usize,
+    // Characters per column, enough for the widest fret number and technique mark
+    column_width: usize,
+    measures_per_row: usize,
+}
+
+impl TerminalTab {
+    fn new(score: &Score, width: usize) -> Self {
+        // A column per sixteenth note
+        let divisions_per_column = (score.divisions_per_quarter as usize / 4).max(1);
+        let column_width = score
+            .measures
+            .iter()
+            .flat_map(|measure| measure.positions.iter().flatten())
+            .map(|note| {
+                let fret = note.fret.map_or(1, |fret| fret.to_string().len());
+                fret + usize::from(note.technique != Technique::None)
+            })
+            .max()
+            .unwrap_or(1)
+            + 1;
+        let widest_measure = score
+            .measures
+            .iter()
+            .map(|measure| measure.positions.len().div_ceil(divisions_per_column))
+            .max()
+            .unwrap_or(1);
+        // Every measure ends with a bar line, and every row starts with the string name
+        let measure_width = widest_measure * column_width + 1;
+        let measures_per_row = (width.saturating_sub(2) / measure_width).max(1);
+        Self {
+            divisions_per_column,
+            column_width,
+            measures_per_row,
+        }
+    }
+
+    fn columns(&self, measure: &Measure) -> usize {
+        measure.positions.len().div_ceil(self.divisions_per_column)
+    }
+
+    /// The row of measures containing `measure_idx`, as six string lines and a cursor line.
+    fn lines(&self, score: &Score, measure_idx: usize, division_idx: usize) -> Vec<String> {
+        let first = measure_idx / self.measures_per_row * self.measures_per_row;
+        let last = (first + self.measures_per_row).min(score.measures.len());
+
+        let mut lines: Vec<String> = STRING_NAMES
+            .iter()
+            .map(|name| format!("{}|", name))
+            .collect();
+        let mut cursor = String::from("  ");
+        for (idx, measure) in score.measures[first..last].iter().enumerate() {
+            let columns = self.columns(measure);
+            let mut cells = vec![vec![String::new(); columns]; STRING_NAMES.len()];
+            for (division, notes) in measure.positions.iter().enumerate() {
+                let column = division / self.divisions_per_column;
+                for note in notes {
+                    let (Some(string), Some(fret)) = (note.string, note.fret) else {
+                        continue;
+                    };
+                    let Some(cell) = cells
+                        .get_mut((string as usize).wrapping_sub(1))
+                        .map(|string| &mut string[column])
+                    else {
+                        continue;
+                    };
+                    let mark = match note.technique {
+                        Technique::HammerOn => "h",
+                        Technique::PullOff => "p",
+                        Technique::None => "",
+                    };
+                    *cell = format!("{}{}", mark, fret);
+                }
+            }
+            for (line, cells) in lines.iter_mut().zip(&cells) {
+                for cell in cells {
+                    line.push_str(cell);
+                    line.push_str(&"-".repeat(self.column_width - cell.len()));
+                }
+                line.push('|');
+            }
+
+            let playing =
+                (first + idx == measure_idx).then_some(division_idx / self.divisions_per_column);
+            for column in 0..columns {
+                let mark = if playing == Some(column) { "^" } else { " " };
+                cursor.push_str(mark);
+                cursor.push_str(&" ".repeat(self.column_width - 1));
+            }
+            cursor.push(' ');
+        }
+        lines.push(cursor);
+        lines
+    }
+}
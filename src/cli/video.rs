@@ -0,0 +1,109 @@
+// cli/video.rs
+
+use std::path::Path;
+
+use crate::audio::offline_render::render_score;
+use crate::export::video::{VideoRenderer, VideoSettings};
+use crate::guitar::guitar::GuitarConfig;
+use crate::music_representation::Score;
+use crate::renderer::renderer::Renderer;
+
+const SAMPLE_RATE: u32 = 44_100;
+const MEASURES_PER_ROW: usize = 4;
+const DASHES_PER_DIVISION: usize = 4;
+// Video containers ffmpeg is asked to encode, any other output is a folder of PNG frames
+const VIDEO_EXTENSIONS: [&str; 4] = ["mp4", "mkv", "mov", "webm"];
+
+const USAGE: &str = "Usage: cdefgab video <file.xml> <output.mp4 | folder> [--tempo <bpm>] \
+                     [--fps <n>] [--rows <n>] [--scale <n>]";
+
+/// Arguments of `cdefgab video`.
+struct VideoOptions {
+    path: String,
+    output: String,
+    tempo: Option<usize>,
+    settings: VideoSettings,
+}
+
+fn parse_number<T: std::str::FromStr + PartialOrd + Default>(
+    name: &str,
+    value: Option<&String>,
+) -> Result<T, String> {
+    let value = value.ok_or(USAGE)?;
+    value
+        .parse::<T>()
+        .ok()
+        .filter(|number| *number > T::default())
+        .ok_or_else(|| format!("Invalid {} '{}'", name, value))
+}
+
+impl VideoOptions {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut paths = Vec::new();
+        let mut tempo = None;
+        let mut settings = VideoSettings::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--tempo" => tempo = Some(parse_number("tempo", args.next())?),
+                "--fps" => settings.fps = parse_number("frame rate", args.next())?,
+                "--rows" => settings.rows_visible = parse_number("row count", args.next())?,
+                "--scale" => settings.scale = parse_number("scale", args.next())?,
+                _ if paths.len() < 2 => paths.push(arg.clone()),
+                _ => return Err(USAGE.to_string()),
+            }
+        }
+        let [path, output]: [String; 2] = paths.try_into().map_err(|_| USAGE.to_string())?;
+        Ok(Self {
+            path,
+            output,
+            tempo,
+            settings,
+        })
+    }
+}
+
+/// Renders the scrolling tab of a score with its cursor in sync with the synthesized audio,
+/// either into a video through ffmpeg or as PNG frames next to a WAV of the audio. `args` are
+/// the arguments after `video`.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let options = VideoOptions::parse(args)?;
+    let score = Score::parse_from_musicxml(&options.path)?;
+    if score.measures.is_empty() {
+        return Err(format!("{} has no measures", options.path));
+    }
+    let tempo = options.tempo.unwrap_or(score.tempo);
+    let renderer = Renderer::new(MEASURES_PER_ROW, DASHES_PER_DIVISION);
+    let video = VideoRenderer::new(&renderer, &score, tempo, options.settings);
+
+    let audio = render_score(
+        &score,
+        &GuitarConfig::acoustic(),
+        tempo,
+        SAMPLE_RATE as f32,
+        None,
+    );
+    let output = Path::new(&options.output);
+    let is_video = output
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| VIDEO_EXTENSIONS.contains(&extension.to_lowercase().as_str()));
+    if is_video {
+        video.export_video(output, &audio, SAMPLE_RATE)?;
+        println!("Wrote {}", output.display());
+    } else {
+        let seconds = audio.len() as f32 / SAMPLE_RATE as f32;
+        let frames = video.export_png_sequence(output, seconds)?;
+        let wav = output.join("audio.wav");
+        std::fs::write(&wav, crate::export::wav::encode_wav(&audio, SAMPLE_RATE))
+            .map_err(|e| e.to_string())?;
+        println!(
+            "Wrote {} frames at {} fps and {} to {}",
+            frames,
+            options.settings.fps,
+            wav.display(),
+            output.display()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,114 @@
+// export/compressed_audio.rs
+
+use std::fmt;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use kira::Frame;
+
+use crate::export::wav::{encode_stereo_wav, BitDepth};
+
+/// Compressed formats audio can be exported to, encoded by ffmpeg.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioFormat {
+    Ogg,
+    Flac,
+    Mp3,
+}
+
+impl AudioFormat {
+    pub const ALL: [AudioFormat; 3] = [AudioFormat::Ogg, AudioFormat::Flac, AudioFormat::Mp3];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioFormat::Ogg => "ogg",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Mp3 => "mp3",
+        }
+    }
+
+    fn codec_args(&self) -> &'static [&'static str] {
+        match self {
+            AudioFormat::Ogg => &["-c:a", "libvorbis", "-q:a", "6"],
+            AudioFormat::Flac => &["-c:a", "flac"],
+            AudioFormat::Mp3 => &["-c:a", "libmp3lame", "-q:a", "2"],
+        }
+    }
+
+    /// Tag the tempo goes in, ID3's frame for MP3 and the Vorbis comment for the others.
+    fn tempo_tag(&self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "TBPM",
+            AudioFormat::Ogg | AudioFormat::Flac => "BPM",
+        }
+    }
+}
+
+impl fmt::Display for AudioFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AudioFormat::Ogg => write!(f, "OGG Vorbis"),
+            AudioFormat::Flac => write!(f, "FLAC"),
+            AudioFormat::Mp3 => write!(f, "MP3"),
+        }
+    }
+}
+
+/// What the exported file says about the music in it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AudioTags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    /// Beats per minute the score was rendered at
+    pub tempo: usize,
+}
+
+/// Encodes stereo frames as `format` with `tags` using ffmpeg, which has to be installed.
+pub fn encode_compressed(
+    frames: &[Frame],
+    sample_rate: u32,
+    format: AudioFormat,
+    tags: &AudioTags,
+) -> Result<Vec<u8>, String> {
+    let mut wav = tempfile::Builder::new()
+        .prefix("tablature")
+        .suffix(".wav")
+        .tempfile()
+        .map_err(|e| e.to_string())?;
+    // 24 bits so only the encoder loses anything
+    wav.write_all(&encode_stereo_wav(
+        frames,
+        sample_rate,
+        BitDepth::TwentyFour,
+    ))
+    .map_err(|e| e.to_string())?;
+    // ffmpeg picks the container from the extension and needs to seek back to finish FLAC
+    let encoded = tempfile::Builder::new()
+        .prefix("tablature")
+        .suffix(&format!(".{}", format.extension()))
+        .tempfile()
+        .map_err(|e| e.to_string())?;
+
+    let mut ffmpeg = Command::new("ffmpeg");
+    ffmpeg
+        .args(["-y", "-loglevel", "error", "-i"])
+        .arg(wav.path())
+        .args(format.codec_args());
+    if let Some(title) = &tags.title {
+        ffmpeg.arg("-metadata").arg(format!("title={}", title));
+    }
+    if let Some(artist) = &tags.artist {
+        ffmpeg.arg("-metadata").arg(format!("artist={}", artist));
+    }
+    let status = ffmpeg
+        .arg("-metadata")
+        .arg(format!("{}={}", format.tempo_tag(), tags.tempo))
+        .arg(encoded.path())
+        .stdin(Stdio::null())
+        .status()
+        .map_err(|e| format!("Failed to start ffmpeg, is it installed? {}", e))?;
+    if !status.success() {
+        return Err(format!("ffmpeg failed with {}", status));
+    }
+    std::fs::read(encoded.path()).map_err(|e| e.to_string())
+}
@@ -0,0 +1,9 @@
+#[cfg(not(target_arch = "wasm32"))]
+pub mod compressed_audio;
+pub mod musicxml;
+pub mod print;
+pub mod tab_image;
+pub mod tab_pdf;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod video;
+pub mod wav;
@@ -0,0 +1,464 @@
+// export/musicxml.rs
+
+use std::fmt::Write;
+
+use crate::annotations::bundle::{Comment, LoopRegion};
+use crate::music_representation::{
+    midi_note, Articulation, Chord, Note, Score, Technique, OPEN_STRING_MIDI,
+};
+use crate::renderer::renderer::NUM_STRINGS;
+
+const SHARP_NAMES: [(char, i8); 12] = [
+    ('C', 0),
+    ('C', 1),
+    ('D', 0),
+    ('D', 1),
+    ('E', 0),
+    ('F', 0),
+    ('F', 1),
+    ('G', 0),
+    ('G', 1),
+    ('A', 0),
+    ('A', 1),
+    ('B', 0),
+];
+const FLAT_NAMES: [(char, i8); 12] = [
+    ('C', 0),
+    ('D', -1),
+    ('D', 0),
+    ('E', -1),
+    ('E', 0),
+    ('F', 0),
+    ('G', -1),
+    ('G', 0),
+    ('A', -1),
+    ('A', 0),
+    ('B', -1),
+    ('B', 0),
+];
+
+/// User markers written into the file so other editors show them too.
+#[derive(Default)]
+pub struct ExportMarks<'a> {
+    /// Start as a rehearsal mark, end as a text direction
+    pub loops: &'a [LoopRegion],
+    /// Text directions above their measure
+    pub comments: &'a [Comment],
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A run of notes, chords and rests in one voice of a measure.
+struct Voice {
+    // Division, duration and the notes sounding together, rests have no notes
+    events: Vec<(usize, u32, Vec<Note>)>,
+    end: usize,
+}
+
+/// Splits the positions of a measure into voices that never overlap, with every chord in one
+/// voice. Notes ringing past the end of the measure are cut at the bar line.
+fn voices(positions: &[Vec<Note>]) -> Vec<Voice> {
+    let length = positions.len();
+    let mut voices: Vec<Voice> = Vec::new();
+    for (division, notes) in positions.iter().enumerate() {
+        // The same note from several staves of the source file is written once
+        let mut unique: Vec<&Note> = Vec::new();
+        for note in notes {
+            if !unique
+                .iter()
+                .any(|known| (known.string, known.fret) == (note.string, note.fret))
+            {
+                unique.push(note);
+            }
+        }
+        let notes = unique;
+        let mut durations: Vec<u32> = notes.iter().map(|note| note.duration.max(1)).collect();
+        durations.sort_unstable();
+        durations.dedup();
+        for duration in durations {
+            let duration = duration.min((length - division) as u32);
+            let chord: Vec<Note> = notes
+                .iter()
+                .filter(|note| note.duration.max(1).min((length - division) as u32) == duration)
+                .map(|&note| note.clone())
+                .collect();
+            let voice = match voices.iter_mut().position(|voice| voice.end <= division) {
+                Some(idx) => &mut voices[idx],
+                None => {
+                    voices.push(Voice {
+                        events: Vec::new(),
+                        end: 0,
+                    });
+                    voices.last_mut().unwrap()
+                }
+            };
+            if voice.end < division {
+                voice
+                    .events
+                    .push((voice.end, (division - voice.end) as u32, Vec::new()));
+            }
+            voice.events.push((division, duration, chord));
+            voice.end = division + duration as usize;
+        }
+    }
+    if voices.is_empty() {
+        voices.push(Voice {
+            events: Vec::new(),
+            end: 0,
+        });
+    }
+    for voice in &mut voices {
+        if voice.end < length {
+            voice
+                .events
+                .push((voice.end, (length - voice.end) as u32, Vec::new()));
+            voice.end = length;
+        }
+    }
+    voices
+}
+
+/// Rests in the first voice are split where a chord symbol starts, since the symbol is
+/// attached to the note or rest that follows it.
+fn split_at_symbols(voice: &mut Voice, symbols: &[(usize, String)]) {
+    let mut events = Vec::new();
+    for (division, duration, notes) in voice.events.drain(..) {
+        if !notes.is_empty() {
+            events.push((division, duration, notes));
+            continue;
+        }
+        let end = division + duration as usize;
+        let mut start = division;
+        for &(symbol_division, _) in symbols {
+            if symbol_division > start && symbol_division < end {
+                events.push((start, (symbol_division - start) as u32, Vec::new()));
+                start = symbol_division;
+            }
+        }
+        events.push((start, (end - start) as u32, Vec::new()));
+    }
+    voice.events = events;
+}
+
+fn harmony_kind(suffix: &str) -> &'static str {
+    match suffix {
+        "" => "major",
+        "m" => "minor",
+        "7" => "dominant",
+        "maj7" => "major-seventh",
+        "m7" => "minor-seventh",
+        "dim" => "diminished",
+        "dim7" => "diminished-seventh",
+        "aug" => "augmented",
+        "m7b5" => "half-diminished",
+        "sus2" => "suspended-second",
+        "sus4" => "suspended-fourth",
+        "6" => "major-sixth",
+        "m6" => "minor-sixth",
+        "9" => "dominant-ninth",
+        "5" => "power",
+        _ => "other",
+    }
+}
+
+fn write_harmony(xml: &mut String, symbol: &str) {
+    let Some(step) = symbol.chars().next().filter(|c| c.is_ascii_alphabetic()) else {
+        return;
+    };
+    if Chord::from_symbol(symbol).is_none() {
+        return;
+    }
+    let rest = &symbol[1..];
+    let (alter, suffix) = match rest.chars().next() {
+        Some('#') => (1, &rest[1..]),
+        Some('b') => (-1, &rest[1..]),
+        _ => (0, rest),
+    };
+    // Slash bass notes are kept in the printed text only
+    let kind = harmony_kind(suffix.split('/').next().unwrap_or_default());
+    let _ = write!(
+        xml,
+        "      <harmony>\n        <root>\n          <root-step>{}</root-step>\n",
+        step.to_ascii_uppercase()
+    );
+    if alter != 0 {
+        let _ = writeln!(xml, "          <root-alter>{}</root-alter>", alter);
+    }
+    let _ = writeln!(
+        xml,
+        "        </root>\n        <kind text=\"{}\">{}</kind>\n      </harmony>",
+        escape(suffix),
+        kind
+    );
+}
+
+fn write_direction(xml: &mut String, element: &str) {
+    let _ = writeln!(
+        xml,
+        "      <direction placement=\"above\">\n        <direction-type>\n          {}\n        </direction-type>\n      </direction>",
+        element
+    );
+}
+
+fn write_note(
+    xml: &mut String,
+    note: Option<&Note>,
+    duration: u32,
+    in_chord: bool,
+    voice: usize,
+    capo: u8,
+    flats: bool,
+) {
+    xml.push_str("      <note>\n");
+    if in_chord {
+        xml.push_str("        <chord/>\n");
+    }
+    match note.and_then(|note| Some((note, midi_note(note, capo)?))) {
+        Some((note, midi)) => {
+            let (step, alter) = if flats {
+                FLAT_NAMES[midi as usize % 12]
+            } else {
+                SHARP_NAMES[midi as usize % 12]
+            };
+            let _ = write!(xml, "        <pitch>\n          <step>{}</step>\n", step);
+            if alter != 0 {
+                let _ = writeln!(xml, "          <alter>{}</alter>", alter);
+            }
+            let _ = writeln!(
+                xml,
+                "          <octave>{}</octave>\n        </pitch>",
+                midi as i32 / 12 - 1
+            );
+            let _ = writeln!(xml, "        <duration>{}</duration>", duration);
+            let ties: Vec<&str> = [(note.tie.stop, "stop"), (note.tie.start, "start")]
+                .into_iter()
+                .filter_map(|(tied, kind)| tied.then_some(kind))
+                .collect();
+            for kind in &ties {
+                let _ = writeln!(xml, "        <tie type=\"{}\"/>", kind);
+            }
+            let _ = writeln!(xml, "        <voice>{}</voice>", voice);
+            xml.push_str("        <notations>\n");
+            for kind in &ties {
+                let _ = writeln!(xml, "          <tied type=\"{}\"/>", kind);
+            }
+            let articulations: Vec<&str> = note
+                .articulations
+                .iter()
+                .filter_map(|articulation| match articulation {
+                    Articulation::Staccato => Some("staccato"),
+                    Articulation::Accent => Some("accent"),
+                    Articulation::StrongAccent => Some("strong-accent"),
+                    Articulation::Tenuto => Some("tenuto"),
+                    // Written with the other technical marks
+                    Articulation::Harmonic => None,
+                })
+                .collect();
+            if !articulations.is_empty() {
+                xml.push_str("          <articulations>\n");
+                for articulation in articulations {
+                    let _ = writeln!(xml, "            <{}/>", articulation);
+                }
+                xml.push_str("          </articulations>\n");
+            }
+            xml.push_str("          <technical>\n");
+            if note.articulations.contains(&Articulation::Harmonic) {
+                xml.push_str("            <harmonic/>\n");
+            }
+            match note.technique {
+                Technique::HammerOn => xml.push_str("            <hammer-on type=\"stop\"/>\n"),
+                Technique::PullOff => xml.push_str("            <pull-off type=\"stop\"/>\n"),
+                Technique::None => {}
+            }
+            if let Some(finger) = note.finger {
+                let finger = if finger == 0 {
+                    "t".to_string()
+                } else {
+                    finger.to_string()
+                };
+                let _ = writeln!(xml, "            <fingering>{}</fingering>", finger);
+            }
+            let _ = writeln!(
+                xml,
+                "            <string>{}</string>\n            <fret>{}</fret>",
+                note.string.unwrap_or(1),
+                note.fret.unwrap_or(0)
+            );
+            xml.push_str("          </technical>\n        </notations>\n");
+        }
+        None => {
+            let _ = writeln!(
+                xml,
+                "        <rest/>\n        <duration>{}</duration>\n        <voice>{}</voice>",
+                duration, voice
+            );
+        }
+    }
+    xml.push_str("      </note>\n");
+}
+
+fn write_attributes(xml: &mut String, score: &Score, capo: u8) {
+    let _ = writeln!(
+        xml,
+        "      <attributes>\n        <divisions>{}</divisions>",
+        score.divisions_per_quarter
+    );
+    if let Some(key) = score.key {
+        let _ = writeln!(
+            xml,
+            "        <key>\n          <fifths>{}</fifths>\n          <mode>{}</mode>\n        </key>",
+            key.fifths,
+            if key.minor { "minor" } else { "major" }
+        );
+    }
+    let _ = writeln!(
+        xml,
+        "        <time>\n          <beats>{}</beats>\n          <beat-type>{}</beat-type>\n        </time>",
+        score.time_signature.beats_per_measure, score.time_signature.beat_value
+    );
+    xml.push_str(
+        "        <clef>\n          <sign>TAB</sign>\n          <line>5</line>\n        </clef>\n",
+    );
+    let _ = writeln!(
+        xml,
+        "        <staff-details>\n          <staff-lines>{}</staff-lines>",
+        NUM_STRINGS
+    );
+    // Lines are numbered from the bottom, so the low E comes first
+    let tuning = score.tuning.unwrap_or(OPEN_STRING_MIDI);
+    for (line, &open) in tuning.iter().rev().enumerate() {
+        let (step, alter) = SHARP_NAMES[open as usize % 12];
+        let _ = write!(
+            xml,
+            "          <staff-tuning line=\"{}\">\n            <tuning-step>{}</tuning-step>\n",
+            line + 1,
+            step
+        );
+        if alter != 0 {
+            let _ = writeln!(xml, "            <tuning-alter>{}</tuning-alter>", alter);
+        }
+        let _ = writeln!(
+            xml,
+            "            <tuning-octave>{}</tuning-octave>\n          </staff-tuning>",
+            open as i32 / 12 - 1
+        );
+    }
+    if capo > 0 {
+        let _ = writeln!(xml, "          <capo>{}</capo>", capo);
+    }
+    xml.push_str("        </staff-details>\n      </attributes>\n");
+}
+
+/// The score as a MusicXML partwise document with one tab staff. Sections become rehearsal marks
+/// and `marks` are added as rehearsal marks and text directions.
+pub fn score_to_musicxml(score: &Score, capo: u8, marks: &ExportMarks) -> String {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<!DOCTYPE score-partwise PUBLIC \"-//Recordare//DTD MusicXML 4.0 Partwise//EN\" \"http://www.musicxml.org/dtds/partwise.dtd\">\n");
+    xml.push_str("<score-partwise version=\"4.0\">\n");
+    if let Some(title) = &score.title {
+        let _ = writeln!(
+            xml,
+            "  <work>\n    <work-title>{}</work-title>\n  </work>",
+            escape(title)
+        );
+    }
+    if let Some(composer) = &score.composer {
+        let _ = writeln!(
+            xml,
+            "  <identification>\n    <creator type=\"composer\">{}</creator>\n  </identification>",
+            escape(composer)
+        );
+    }
+    xml.push_str("  <part-list>\n    <score-part id=\"P1\">\n      <part-name>Guitar</part-name>\n    </score-part>\n  </part-list>\n");
+    xml.push_str("  <part id=\"P1\">\n");
+
+    let flats = score.key.is_some_and(|key| key.fifths < 0);
+    for (measure_idx, measure) in score.measures.iter().enumerate() {
+        let number = measure_idx + 1;
+        let _ = writeln!(xml, "    <measure number=\"{}\">", number);
+        if measure_idx == 0 {
+            write_attributes(&mut xml, score, capo);
+            let _ = writeln!(
+                xml,
+                "      <direction placement=\"above\">\n        <direction-type>\n          <metronome>\n            <beat-unit>quarter</beat-unit>\n            <per-minute>{}</per-minute>\n          </metronome>\n        </direction-type>\n        <sound tempo=\"{}\"/>\n      </direction>",
+                score.tempo, score.tempo
+            );
+        }
+
+        for section in score.sections.iter().filter(|s| s.measure == measure_idx) {
+            write_direction(
+                &mut xml,
+                &format!("<rehearsal>{}</rehearsal>", escape(&section.name)),
+            );
+        }
+        for region in marks.loops {
+            if region.start_measure == number {
+                write_direction(
+                    &mut xml,
+                    &format!("<rehearsal>{}</rehearsal>", escape(&region.name)),
+                );
+                let _ = writeln!(
+                    xml,
+                    "      <!-- loop \"{}\" measures {}-{} -->",
+                    escape(&region.name).replace("--", "- -"),
+                    region.start_measure,
+                    region.end_measure
+                );
+            }
+            if region.end_measure == number {
+                write_direction(
+                    &mut xml,
+                    &format!("<words>End of {}</words>", escape(&region.name)),
+                );
+            }
+        }
+        for comment in marks.comments.iter().filter(|c| c.measure == number) {
+            write_direction(
+                &mut xml,
+                &format!("<words>{}</words>", escape(&comment.text)),
+            );
+        }
+
+        let mut voices = voices(&measure.positions);
+        split_at_symbols(&mut voices[0], &measure.chord_symbols);
+        for (voice_idx, voice) in voices.iter().enumerate() {
+            if voice_idx > 0 {
+                let _ = writeln!(
+                    xml,
+                    "      <backup>\n        <duration>{}</duration>\n      </backup>",
+                    measure.positions.len()
+                );
+            }
+            for (division, duration, notes) in &voice.events {
+                if voice_idx == 0 {
+                    for (_, symbol) in measure.chord_symbols.iter().filter(|(d, _)| d == division) {
+                        write_harmony(&mut xml, symbol);
+                    }
+                }
+                if notes.is_empty() {
+                    write_note(&mut xml, None, *duration, false, voice_idx + 1, capo, flats);
+                }
+                for (idx, note) in notes.iter().enumerate() {
+                    write_note(
+                        &mut xml,
+                        Some(note),
+                        *duration,
+                        idx > 0,
+                        voice_idx + 1,
+                        capo,
+                        flats,
+                    );
+                }
+            }
+        }
+        xml.push_str("    </measure>\n");
+    }
+    xml.push_str("  </part>\n</score-partwise>\n");
+    xml
+}
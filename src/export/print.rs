@@ -0,0 +1,44 @@
+// print.rs
+
+/// Writes the PDF to a temporary file and hands it to the operating system, which opens its
+/// print dialog on Windows and the default PDF viewer elsewhere.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn print_pdf(data: &[u8]) -> Result<(), String> {
+    use std::io::Write;
+    use std::process::Command;
+
+    let mut file = tempfile::Builder::new()
+        .prefix("tablature")
+        .suffix(".pdf")
+        .tempfile()
+        .map_err(|e| e.to_string())?;
+    file.write_all(data).map_err(|e| e.to_string())?;
+    // The viewer opens the file after we return, so it must outlive this function
+    let (_, path) = file.keep().map_err(|e| e.to_string())?;
+
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = Command::new("powershell");
+        command.args(["-NoProfile", "-Command"]);
+        command.arg(format!(
+            "Start-Process -Verb Print -FilePath '{}'",
+            path.display()
+        ));
+        command
+    } else if cfg!(target_os = "macos") {
+        let mut command = Command::new("open");
+        command.arg(&path);
+        command
+    } else {
+        let mut command = Command::new("xdg-open");
+        command.arg(&path);
+        command
+    };
+    command.spawn().map(|_| ()).map_err(|e| e.to_string())
+}
+
+/// Opens the browser's print dialog for the current page.
+#[cfg(target_arch = "wasm32")]
+pub fn print_page() -> Result<(), String> {
+    let window = web_sys::window().ok_or("No browser window")?;
+    window.print().map_err(|e| format!("{:?}", e))
+}
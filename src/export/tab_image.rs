@@ -0,0 +1,327 @@
+// tab_image.rs
+
+use std::fmt;
+use std::fmt::Write;
+
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont};
+use egui::{Align, Align2, Color32, Pos2, Vec2};
+use tiny_skia::{FillRule, Paint, PathBuilder, Pixmap, PremultipliedColorU8, Stroke, Transform};
+
+use crate::renderer::renderer::TabShape;
+
+/// Resolution that corresponds to one pixel per layout unit.
+pub const BASE_DPI: f32 = 96.0;
+/// Multiples of `BASE_DPI` offered for sharp images on high resolution screens and print.
+pub const EXPORT_SCALES: [f32; 3] = [1.0, 2.0, 4.0];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    Svg,
+    Png,
+}
+
+impl ImageFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Svg => "svg",
+            ImageFormat::Png => "png",
+        }
+    }
+}
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageFormat::Svg => write!(f, "SVG"),
+            ImageFormat::Png => write!(f, "PNG"),
+        }
+    }
+}
+
+/// Encodes the laid out tab in the requested format. `dpi` only affects raster output.
+pub fn export_tab_image(
+    shapes: &[TabShape],
+    size: Vec2,
+    format: ImageFormat,
+    dpi: f32,
+) -> Result<Vec<u8>, String> {
+    match format {
+        ImageFormat::Svg => Ok(tab_to_svg(shapes, size).into_bytes()),
+        ImageFormat::Png => tab_to_png(shapes, size, dpi / BASE_DPI),
+    }
+}
+
+pub fn tab_to_svg(shapes: &[TabShape], size: Vec2) -> String {
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+        w = size.x,
+        h = size.y
+    );
+    let _ = writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#);
+
+    for shape in shapes {
+        match shape {
+            TabShape::Line {
+                points,
+                width,
+                color,
+            } => {
+                let _ = writeln!(
+                    svg,
+                    r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}" stroke-width="{}"/>"#,
+                    points[0].x,
+                    points[0].y,
+                    points[1].x,
+                    points[1].y,
+                    svg_color(*color),
+                    width
+                );
+            }
+            TabShape::Text {
+                pos,
+                anchor,
+                text,
+                size,
+                color,
+            } => {
+                let text_anchor = match anchor.x() {
+                    Align::Min => "start",
+                    Align::Center => "middle",
+                    Align::Max => "end",
+                };
+                let baseline = match anchor.y() {
+                    Align::Min => "hanging",
+                    Align::Center => "central",
+                    Align::Max => "text-after-edge",
+                };
+                let _ = writeln!(
+                    svg,
+                    r#"<text x="{}" y="{}" font-family="monospace" font-size="{}" fill="{}" text-anchor="{}" dominant-baseline="{}">{}</text>"#,
+                    pos.x,
+                    pos.y,
+                    size,
+                    svg_color(*color),
+                    text_anchor,
+                    baseline,
+                    escape_xml(text)
+                );
+            }
+            TabShape::Curve {
+                points,
+                width,
+                color,
+            } => {
+                let _ = writeln!(
+                    svg,
+                    r#"<path d="M {} {} Q {} {} {} {}" fill="none" stroke="{}" stroke-width="{}"/>"#,
+                    points[0].x,
+                    points[0].y,
+                    points[1].x,
+                    points[1].y,
+                    points[2].x,
+                    points[2].y,
+                    svg_color(*color),
+                    width
+                );
+            }
+            TabShape::Dot {
+                center,
+                radius,
+                color,
+            } => {
+                let _ = writeln!(
+                    svg,
+                    r#"<circle cx="{}" cy="{}" r="{}" fill="{}"/>"#,
+                    center.x,
+                    center.y,
+                    radius,
+                    svg_color(*color)
+                );
+            }
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Rasterizes the tab, `scale` being the number of pixels per layout unit.
+pub fn tab_to_png(shapes: &[TabShape], size: Vec2, scale: f32) -> Result<Vec<u8>, String> {
+    let width = (size.x * scale).ceil().max(1.0) as u32;
+    let height = (size.y * scale).ceil().max(1.0) as u32;
+    let mut pixmap = Pixmap::new(width, height).ok_or("Image is too large to export")?;
+    pixmap.fill(tiny_skia::Color::WHITE);
+
+    let fonts = egui::FontDefinitions::default();
+    let font_data = fonts
+        .font_data
+        .get("Hack")
+        .ok_or("Monospace font is missing")?;
+    let font = FontRef::try_from_slice(&font_data.font).map_err(|e| e.to_string())?;
+
+    let transform = Transform::from_scale(scale, scale);
+
+    for shape in shapes {
+        match shape {
+            TabShape::Line {
+                points,
+                width,
+                color,
+            } => {
+                let mut pb = PathBuilder::new();
+                pb.move_to(points[0].x, points[0].y);
+                pb.line_to(points[1].x, points[1].y);
+                if let Some(path) = pb.finish() {
+                    stroke(&mut pixmap, &path, *width, *color, transform);
+                }
+            }
+            TabShape::Curve {
+                points,
+                width,
+                color,
+            } => {
+                let mut pb = PathBuilder::new();
+                pb.move_to(points[0].x, points[0].y);
+                pb.quad_to(points[1].x, points[1].y, points[2].x, points[2].y);
+                if let Some(path) = pb.finish() {
+                    stroke(&mut pixmap, &path, *width, *color, transform);
+                }
+            }
+            TabShape::Text {
+                pos,
+                anchor,
+                text,
+                size,
+                color,
+            } => {
+                let pos = Pos2::new(pos.x * scale, pos.y * scale);
+                draw_text(&mut pixmap, &font, pos, *anchor, text, size * scale, *color);
+            }
+            TabShape::Dot {
+                center,
+                radius,
+                color,
+            } => {
+                if let Some(path) = PathBuilder::from_circle(center.x, center.y, *radius) {
+                    let [r, g, b, a] = color.to_srgba_unmultiplied();
+                    let mut paint = Paint::default();
+                    paint.set_color_rgba8(r, g, b, a);
+                    paint.anti_alias = true;
+                    pixmap.fill_path(&path, &paint, FillRule::Winding, transform, None);
+                }
+            }
+        }
+    }
+
+    pixmap.encode_png().map_err(|e| e.to_string())
+}
+
+fn stroke(
+    pixmap: &mut Pixmap,
+    path: &tiny_skia::Path,
+    width: f32,
+    color: Color32,
+    transform: Transform,
+) {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(r, g, b, a);
+    paint.anti_alias = true;
+    let stroke = Stroke {
+        width,
+        ..Default::default()
+    };
+    pixmap.stroke_path(path, &paint, &stroke, transform, None);
+}
+
+/// Draws `text` in pixel coordinates.
+fn draw_text(
+    pixmap: &mut Pixmap,
+    font: &FontRef,
+    pos: Pos2,
+    anchor: Align2,
+    text: &str,
+    size_px: f32,
+    color: Color32,
+) {
+    let scaled = font.as_scaled(PxScale::from(size_px));
+    let text_width: f32 = text
+        .chars()
+        .map(|c| scaled.h_advance(scaled.glyph_id(c)))
+        .sum();
+    let text_height = scaled.ascent() - scaled.descent();
+
+    // Anchor the text box the same way egui's painter does
+    let x = pos.x
+        - match anchor.x() {
+            Align::Min => 0.0,
+            Align::Center => text_width / 2.0,
+            Align::Max => text_width,
+        };
+    let top = pos.y
+        - match anchor.y() {
+            Align::Min => 0.0,
+            Align::Center => text_height / 2.0,
+            Align::Max => text_height,
+        };
+    let baseline = top + scaled.ascent();
+
+    let pixmap_width = pixmap.width() as i32;
+    let pixmap_height = pixmap.height() as i32;
+    let pixels = pixmap.pixels_mut();
+
+    let mut caret = x;
+    for c in text.chars() {
+        let glyph = scaled
+            .glyph_id(c)
+            .with_scale_and_position(size_px, ab_glyph::point(caret, baseline));
+        caret += scaled.h_advance(glyph.id);
+
+        if let Some(outlined) = font.outline_glyph(glyph) {
+            let bounds = outlined.px_bounds();
+            outlined.draw(|gx, gy, coverage| {
+                let px = bounds.min.x as i32 + gx as i32;
+                let py = bounds.min.y as i32 + gy as i32;
+                if px < 0 || py < 0 || px >= pixmap_width || py >= pixmap_height {
+                    return;
+                }
+                let index = (py * pixmap_width + px) as usize;
+                pixels[index] = blend(pixels[index], color, coverage);
+            });
+        }
+    }
+}
+
+/// Source-over blending of `color` with the given coverage onto a premultiplied pixel.
+fn blend(dst: PremultipliedColorU8, color: Color32, coverage: f32) -> PremultipliedColorU8 {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    let alpha = coverage.clamp(0.0, 1.0) * a as f32 / 255.0;
+    let mix = |src: u8, dst: u8| (src as f32 * alpha + dst as f32 * (1.0 - alpha)).round() as u8;
+    let out_alpha = mix(255, dst.alpha());
+    // Premultiplied channels never exceed alpha
+    let channel = |src: u8, dst: u8| mix(src, dst).min(out_alpha);
+    PremultipliedColorU8::from_rgba(
+        channel(r, dst.red()),
+        channel(g, dst.green()),
+        channel(b, dst.blue()),
+        out_alpha,
+    )
+    .unwrap_or(dst)
+}
+
+fn svg_color(color: Color32) -> String {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    if a == 255 {
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    } else {
+        format!("rgba({},{},{},{:.3})", r, g, b, a as f32 / 255.0)
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
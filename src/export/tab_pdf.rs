@@ -0,0 +1,352 @@
+// tab_pdf.rs
+
+use std::fmt;
+use std::fmt::Write;
+
+use egui::{pos2, Align, Color32, Pos2};
+
+use crate::music_representation::Score;
+use crate::renderer::renderer::{score_info, Renderer, TabShape, TAB_PADDING};
+
+// Courier glyphs are 600 units wide in a 1000 unit em
+const COURIER_ADVANCE: f32 = 0.6;
+const HEADER_FONT_SIZE: f32 = 10.0;
+const HEADER_LINE_HEIGHT: f32 = 13.0;
+const FOOTER_FONT_SIZE: f32 = 9.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaperSize {
+    A4,
+    Letter,
+}
+
+impl PaperSize {
+    /// Page size in PostScript points.
+    pub fn size_points(&self) -> (f32, f32) {
+        match self {
+            PaperSize::A4 => (595.0, 842.0),
+            PaperSize::Letter => (612.0, 792.0),
+        }
+    }
+}
+
+impl fmt::Display for PaperSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaperSize::A4 => write!(f, "A4"),
+            PaperSize::Letter => write!(f, "Letter"),
+        }
+    }
+}
+
+/// Page margin used unless the caller has a reason to pick another.
+pub const DEFAULT_MARGIN: f32 = 40.0;
+
+pub struct PdfSettings {
+    pub paper_size: PaperSize,
+    /// Page margin in points on every side
+    pub margin: f32,
+}
+
+impl PdfSettings {
+    /// Width available to the tab in points, which map one to one onto layout units.
+    pub fn content_width(&self) -> f32 {
+        self.paper_size.size_points().0 - 2.0 * self.margin
+    }
+}
+
+/// Renders the score as a multi-page PDF, placing as many whole tab rows on each page as fit.
+pub fn export_tab_pdf(score: &Score, renderer: &Renderer, settings: &PdfSettings) -> Vec<u8> {
+    let (page_width, page_height) = settings.paper_size.size_points();
+    let margin = settings.margin;
+    let content_width = settings.content_width();
+
+    // Shrink rows that are wider than the page; never enlarge them
+    let tab_width = renderer.calculate_tab_size(score).x - 2.0 * TAB_PADDING;
+    let scale = if tab_width > content_width {
+        content_width / tab_width
+    } else {
+        1.0
+    };
+    let row_height = renderer.row_height() * scale;
+
+    let header: Vec<String> = score_info(score).lines().map(str::to_string).collect();
+    let header_height = header.len() as f32 * HEADER_LINE_HEIGHT + HEADER_LINE_HEIGHT;
+    let footer_height = 2.0 * FOOTER_FONT_SIZE;
+
+    let total_rows = renderer.total_rows(score);
+    let mut pages: Vec<(usize, usize)> = Vec::new();
+    let mut row = 0;
+    while row < total_rows || pages.is_empty() {
+        let available = page_height
+            - 2.0 * margin
+            - footer_height
+            - if pages.is_empty() { header_height } else { 0.0 };
+        let rows_on_page = ((available / row_height) as usize).max(1);
+        let end = (row + rows_on_page).min(total_rows);
+        pages.push((row, end));
+        row = end;
+    }
+
+    let mut contents = Vec::with_capacity(pages.len());
+    for (page_idx, &(first_row, end_row)) in pages.iter().enumerate() {
+        let mut page = PdfPage::new(page_height);
+        let mut top = margin;
+
+        if page_idx == 0 {
+            for line in &header {
+                top += HEADER_LINE_HEIGHT;
+                page.text(pos2(margin, top), line, HEADER_FONT_SIZE, Color32::BLACK);
+            }
+            top += HEADER_LINE_HEIGHT;
+        }
+
+        // Lay the rows out in tab coordinates, then map them onto the page
+        let shapes = renderer.layout_rows(score, Pos2::ZERO, first_row..end_row);
+        let to_page = |p: Pos2| pos2(margin + p.x * scale, top + p.y * scale);
+        for shape in &shapes {
+            page.shape(shape, to_page, scale);
+        }
+
+        let footer = format!("Page {} of {}", page_idx + 1, pages.len());
+        let footer_x =
+            page_width / 2.0 - footer.len() as f32 * COURIER_ADVANCE * FOOTER_FONT_SIZE / 2.0;
+        page.text(
+            pos2(footer_x, page_height - margin),
+            &footer,
+            FOOTER_FONT_SIZE,
+            Color32::DARK_GRAY,
+        );
+
+        contents.push(page.content);
+    }
+
+    write_document(&contents, page_width, page_height)
+}
+
+/// Content stream of a single page. Coordinates passed in are top-down like egui's.
+struct PdfPage {
+    height: f32,
+    content: String,
+}
+
+impl PdfPage {
+    fn new(height: f32) -> Self {
+        Self {
+            height,
+            content: String::new(),
+        }
+    }
+
+    fn shape(&mut self, shape: &TabShape, to_page: impl Fn(Pos2) -> Pos2, scale: f32) {
+        match shape {
+            TabShape::Line {
+                points,
+                width,
+                color,
+            } => {
+                let (a, b) = (to_page(points[0]), to_page(points[1]));
+                self.stroke_color(*color, width * scale);
+                let _ = writeln!(
+                    self.content,
+                    "{:.2} {:.2} m {:.2} {:.2} l S",
+                    a.x,
+                    self.height - a.y,
+                    b.x,
+                    self.height - b.y
+                );
+            }
+            TabShape::Curve {
+                points,
+                width,
+                color,
+            } => {
+                let [p0, p1, p2] = points.map(&to_page);
+                // Raise the quadratic bezier to the cubic one PDF supports
+                let c1 = p0 + (p1 - p0) * (2.0 / 3.0);
+                let c2 = p2 + (p1 - p2) * (2.0 / 3.0);
+                self.stroke_color(*color, width * scale);
+                let _ = writeln!(
+                    self.content,
+                    "{:.2} {:.2} m {:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c S",
+                    p0.x,
+                    self.height - p0.y,
+                    c1.x,
+                    self.height - c1.y,
+                    c2.x,
+                    self.height - c2.y,
+                    p2.x,
+                    self.height - p2.y
+                );
+            }
+            TabShape::Text {
+                pos,
+                anchor,
+                text,
+                size,
+                color,
+            } => {
+                let size = size * scale;
+                let width = text.chars().count() as f32 * COURIER_ADVANCE * size;
+                let pos = to_page(*pos);
+                let x = pos.x
+                    - match anchor.x() {
+                        Align::Min => 0.0,
+                        Align::Center => width / 2.0,
+                        Align::Max => width,
+                    };
+                // Approximate the baseline from Courier's cap height and descent
+                let baseline = pos.y
+                    + match anchor.y() {
+                        Align::Min => 0.6 * size,
+                        Align::Center => 0.3 * size,
+                        Align::Max => -0.2 * size,
+                    };
+                self.text(pos2(x, baseline), text, size, *color);
+            }
+            TabShape::Dot {
+                center,
+                radius,
+                color,
+            } => {
+                let c = to_page(*center);
+                let (x, y, r) = (c.x, self.height - c.y, radius * scale);
+                // Four cubic beziers approximate the circle, one per quadrant
+                let k = 0.5523 * r;
+                let [red, green, blue, _] = color.to_normalized_gamma_f32();
+                let _ = writeln!(
+                    self.content,
+                    "{:.3} {:.3} {:.3} rg {:.2} {:.2} m \
+                     {:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c \
+                     {:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c \
+                     {:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c \
+                     {:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c f",
+                    red,
+                    green,
+                    blue,
+                    x + r,
+                    y,
+                    x + r,
+                    y + k,
+                    x + k,
+                    y + r,
+                    x,
+                    y + r,
+                    x - k,
+                    y + r,
+                    x - r,
+                    y + k,
+                    x - r,
+                    y,
+                    x - r,
+                    y - k,
+                    x - k,
+                    y - r,
+                    x,
+                    y - r,
+                    x + k,
+                    y - r,
+                    x + r,
+                    y - k,
+                    x + r,
+                    y
+                );
+            }
+        }
+    }
+
+    /// Draws left aligned text with its baseline at `pos`.
+    fn text(&mut self, pos: Pos2, text: &str, size: f32, color: Color32) {
+        let [r, g, b, _] = color.to_normalized_gamma_f32();
+        let _ = writeln!(
+            self.content,
+            "BT {:.3} {:.3} {:.3} rg /F1 {:.2} Tf {:.2} {:.2} Td ({}) Tj ET",
+            r,
+            g,
+            b,
+            size,
+            pos.x,
+            self.height - pos.y,
+            escape_pdf_string(text)
+        );
+    }
+
+    fn stroke_color(&mut self, color: Color32, width: f32) {
+        let [r, g, b, _] = color.to_normalized_gamma_f32();
+        let _ = writeln!(self.content, "{:.3} {:.3} {:.3} RG {:.2} w", r, g, b, width);
+    }
+}
+
+/// Assembles catalog, page tree, font and page objects with a cross-reference table.
+fn write_document(pages: &[String], page_width: f32, page_height: f32) -> Vec<u8> {
+    let mut objects: Vec<String> = Vec::new();
+    // Object ids: 1 catalog, 2 page tree, 3 font, then a page and its content stream per page
+    let page_ids: Vec<usize> = (0..pages.len()).map(|i| 4 + 2 * i).collect();
+
+    objects.push("<< /Type /Catalog /Pages 2 0 R >>".to_string());
+    let kids: Vec<String> = page_ids.iter().map(|id| format!("{} 0 R", id)).collect();
+    objects.push(format!(
+        "<< /Type /Pages /Kids [{}] /Count {} >>",
+        kids.join(" "),
+        pages.len()
+    ));
+    objects.push(
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Courier /Encoding /WinAnsiEncoding >>"
+            .to_string(),
+    );
+    for (content, page_id) in pages.iter().zip(&page_ids) {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+             /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            page_width,
+            page_height,
+            page_id + 1
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ));
+    }
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (idx, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        let _ = write!(pdf, "{} 0 obj\n{}\nendobj\n", idx + 1, object);
+    }
+
+    let xref_offset = pdf.len();
+    let _ = write!(pdf, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        let _ = writeln!(pdf, "{:010} 00000 n ", offset);
+    }
+    let _ = write!(
+        pdf,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref_offset
+    );
+
+    pdf.into_bytes()
+}
+
+/// Escapes a string literal. Latin-1 characters map directly onto WinAnsiEncoding, anything
+/// else is replaced since the font is not embedded.
+fn escape_pdf_string(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c if c.is_ascii() && !c.is_ascii_control() => escaped.push(c),
+            c if ('\u{a0}'..='\u{ff}').contains(&c) => {
+                let _ = write!(escaped, "\\{:03o}", c as u32);
+            }
+            _ => escaped.push('?'),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,245 @@
+// export/video.rs
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use egui::{pos2, Color32, Vec2};
+use rayon::prelude::*;
+
+use crate::export::tab_image::tab_to_png;
+use crate::export::wav::encode_wav;
+use crate::music_representation::{Score, Timeline};
+use crate::renderer::renderer::{Renderer, TabShape, NOTE_SPACING, TAB_PADDING};
+
+const CURSOR_WIDTH: f32 = 2.0;
+// How long the tab takes to scroll up to the next row
+const SCROLL_SECONDS: f32 = 0.3;
+// Frames rendered in parallel before they are handed to ffmpeg in order
+const BATCH_FRAMES: usize = 64;
+
+/// Size and speed of the rendered video.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VideoSettings {
+    pub fps: u32,
+    /// Rows of tab in view, the one being played on top
+    pub rows_visible: usize,
+    /// Pixels per layout unit
+    pub scale: f32,
+}
+
+impl Default for VideoSettings {
+    fn default() -> Self {
+        Self {
+            fps: 30,
+            rows_visible: 3,
+            scale: 2.0,
+        }
+    }
+}
+
+/// Where in the score the cursor is `seconds` into playback: the measure, the division in it
+/// and how far playback has moved through that division.
+fn position_at(
+    timeline: &Timeline,
+    seconds_per_division: f32,
+    seconds: f32,
+) -> (usize, usize, f32) {
+    let ticks = seconds.max(0.0) / seconds_per_division;
+    match timeline.position_at(ticks as usize) {
+        Some((measure, division)) => (measure, division, ticks.fract()),
+        // Past the end the cursor rests on the last division
+        None => {
+            let last = timeline.total_ticks.saturating_sub(1);
+            let (measure, division) = timeline.position_at(last).unwrap_or((0, 0));
+            (measure, division, 1.0)
+        }
+    }
+}
+
+/// Renders frames of the tab scrolling along with the playback cursor, for videos that show
+/// the tab of a recorded performance.
+pub struct VideoRenderer<'a> {
+    renderer: &'a Renderer,
+    score: &'a Score,
+    timeline: Timeline,
+    settings: VideoSettings,
+    seconds_per_division: f32,
+    size: Vec2,
+}
+
+impl<'a> VideoRenderer<'a> {
+    pub fn new(
+        renderer: &'a Renderer,
+        score: &'a Score,
+        tempo: usize,
+        settings: VideoSettings,
+    ) -> Self {
+        let timeline = Timeline::from_score(score);
+        let seconds_per_division = timeline.seconds_per_tick(tempo);
+        let size = Vec2::new(
+            renderer.calculate_tab_size(score).x,
+            settings.rows_visible.max(1) as f32 * renderer.row_height() + 2.0 * TAB_PADDING,
+        );
+        Self {
+            renderer,
+            score,
+            timeline,
+            settings,
+            seconds_per_division,
+            size,
+        }
+    }
+
+    /// Frames needed to cover `seconds` of audio.
+    pub fn frame_count(&self, seconds: f32) -> usize {
+        (seconds * self.settings.fps as f32).ceil() as usize
+    }
+
+    /// Seconds into playback at which `measure` starts.
+    fn measure_start(&self, measure: usize) -> f32 {
+        self.timeline.tick_of(measure, 0) as f32 * self.seconds_per_division
+    }
+
+    /// Shapes of frame `frame`, with the top left corner of the frame at the origin.
+    pub fn frame_shapes(&self, frame: usize) -> Vec<TabShape> {
+        let seconds = frame as f32 / self.settings.fps as f32;
+        let (measure, division, progress) =
+            position_at(&self.timeline, self.seconds_per_division, seconds);
+        let measures_per_row = self.renderer.measures_per_row;
+        let row = measure / measures_per_row;
+        let row_height = self.renderer.row_height();
+
+        // Glide up from the previous row during the first moments of a new one
+        let into_row = seconds - self.measure_start(row * measures_per_row);
+        let scroll_rows = if row > 0 && into_row < SCROLL_SECONDS {
+            let t = into_row / SCROLL_SECONDS;
+            (row - 1) as f32 + t * t * (3.0 - 2.0 * t)
+        } else {
+            row as f32
+        };
+        let scroll = scroll_rows * row_height;
+
+        let first_row = scroll_rows.floor() as usize;
+        let rows = first_row..first_row + self.settings.rows_visible + 1;
+        let row_origin = pos2(
+            TAB_PADDING,
+            TAB_PADDING + first_row as f32 * row_height - scroll,
+        );
+        let mut shapes = self.renderer.layout_rows(self.score, row_origin, rows);
+
+        // The cursor moves smoothly through the division instead of jumping from one to the next
+        let origin = pos2(TAB_PADDING, TAB_PADDING - scroll);
+        let [top, bottom] = self
+            .renderer
+            .playback_indicator(self.score, origin, measure, division);
+        let division_width = self.renderer.dashes_per_division as f32 * NOTE_SPACING;
+        let x = top.x + progress * division_width;
+        shapes.push(TabShape::Line {
+            points: [pos2(x, top.y), pos2(x, bottom.y)],
+            width: CURSOR_WIDTH,
+            color: Color32::RED,
+        });
+        shapes
+    }
+
+    pub fn render_frame(&self, frame: usize) -> Result<Vec<u8>, String> {
+        tab_to_png(&self.frame_shapes(frame), self.size, self.settings.scale)
+    }
+
+    /// Writes `frame_00000.png` and onwards into `dir`, enough frames for `seconds`.
+    pub fn export_png_sequence(&self, dir: &Path, seconds: f32) -> Result<usize, String> {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        let frames = self.frame_count(seconds);
+        (0..frames).into_par_iter().try_for_each(|frame| {
+            let png = self.render_frame(frame)?;
+            std::fs::write(dir.join(format!("frame_{:05}.png", frame)), png)
+                .map_err(|e| e.to_string())
+        })?;
+        Ok(frames)
+    }
+
+    /// Encodes the frames together with the mono `audio` into a video file with ffmpeg, which
+    /// has to be installed. The container is picked by ffmpeg from the extension of `path`.
+    pub fn export_video(&self, path: &Path, audio: &[f32], sample_rate: u32) -> Result<(), String> {
+        let mut wav = tempfile::Builder::new()
+            .prefix("tablature")
+            .suffix(".wav")
+            .tempfile()
+            .map_err(|e| e.to_string())?;
+        wav.write_all(&encode_wav(audio, sample_rate))
+            .map_err(|e| e.to_string())?;
+
+        let mut ffmpeg = Command::new("ffmpeg")
+            .args(["-y", "-loglevel", "error", "-f", "image2pipe", "-framerate"])
+            .arg(self.settings.fps.to_string())
+            .args(["-i", "-", "-i"])
+            .arg(wav.path())
+            // H.264 needs even dimensions
+            .args(["-vf", "pad=ceil(iw/2)*2:ceil(ih/2)*2:color=white"])
+            .args([
+                "-c:v",
+                "libx264",
+                "-pix_fmt",
+                "yuv420p",
+                "-c:a",
+                "aac",
+                "-shortest",
+            ])
+            .arg(path)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to start ffmpeg, is it installed? {}", e))?;
+        let mut stdin = ffmpeg.stdin.take().ok_or("Failed to open ffmpeg's input")?;
+
+        let frames = self.frame_count(audio.len() as f32 / sample_rate as f32);
+        for batch in (0..frames).collect::<Vec<_>>().chunks(BATCH_FRAMES) {
+            let pngs: Vec<Vec<u8>> = batch
+                .par_iter()
+                .map(|&frame| self.render_frame(frame))
+                .collect::<Result<_, _>>()?;
+            for png in pngs {
+                stdin
+                    .write_all(&png)
+                    .map_err(|e| format!("ffmpeg stopped reading frames: {}", e))?;
+            }
+        }
+        drop(stdin);
+
+        let status = ffmpeg.wait().map_err(|e| e.to_string())?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(format!("ffmpeg failed with {}", status))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score() -> Score {
+        Score::parse_from_musicxml(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/chords.xml"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn frames_follow_the_divisions() {
+        let score = score();
+        let timeline = Timeline::from_score(&score);
+        let seconds_per_division = 0.25;
+        let first = score.measures[0].positions.len();
+        assert_eq!(
+            position_at(&timeline, seconds_per_division, 0.0),
+            (0, 0, 0.0)
+        );
+        let (measure, division, progress) =
+            position_at(&timeline, seconds_per_division, (first as f32 + 1.5) * 0.25);
+        assert_eq!((measure, division), (1, 1));
+        assert!((progress - 0.5).abs() < 1e-4);
+    }
+}
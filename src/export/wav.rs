@@ -0,0 +1,83 @@
+// export/wav.rs
+
+use std::fmt;
+
+use kira::Frame;
+
+/// Resolution of each sample in an exported WAV file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BitDepth {
+    #[default]
+    Sixteen,
+    TwentyFour,
+}
+
+impl BitDepth {
+    pub const ALL: [BitDepth; 2] = [BitDepth::Sixteen, BitDepth::TwentyFour];
+
+    fn bits(&self) -> u16 {
+        match self {
+            BitDepth::Sixteen => 16,
+            BitDepth::TwentyFour => 24,
+        }
+    }
+}
+
+impl fmt::Display for BitDepth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} bit", self.bits())
+    }
+}
+
+/// Encodes mono samples as a 16 bit PCM WAV file, clipping anything outside -1..1.
+pub fn encode_wav(samples: &[f32], sample_rate: u32) -> Vec<u8> {
+    encode_pcm(samples, 1, sample_rate, BitDepth::Sixteen)
+}
+
+/// Encodes stereo frames as a PCM WAV file at `bit_depth`, clipping anything outside -1..1.
+pub fn encode_stereo_wav(frames: &[Frame], sample_rate: u32, bit_depth: BitDepth) -> Vec<u8> {
+    let samples: Vec<f32> = frames
+        .iter()
+        .flat_map(|frame| [frame.left, frame.right])
+        .collect();
+    encode_pcm(&samples, 2, sample_rate, bit_depth)
+}
+
+/// `samples` interleaved across `channels`.
+fn encode_pcm(samples: &[f32], channels: u16, sample_rate: u32, bit_depth: BitDepth) -> Vec<u8> {
+    let bits_per_sample = bit_depth.bits();
+    let block_align = channels * bits_per_sample / 8;
+    let data_len = samples.len() as u32 * (bits_per_sample / 8) as u32;
+
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVE");
+
+    wav.extend_from_slice(b"fmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&channels.to_le_bytes());
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    wav.extend_from_slice(&block_align.to_le_bytes());
+    wav.extend_from_slice(&bits_per_sample.to_le_bytes());
+
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        let sample = sample.clamp(-1.0, 1.0);
+        match bit_depth {
+            BitDepth::Sixteen => {
+                let value = (sample * i16::MAX as f32) as i16;
+                wav.extend_from_slice(&value.to_le_bytes());
+            }
+            BitDepth::TwentyFour => {
+                // The low three bytes of the sample scaled to 24 bits
+                let value = (sample * 8_388_607.0) as i32;
+                wav.extend_from_slice(&value.to_le_bytes()[..3]);
+            }
+        }
+    }
+    wav
+}
@@ -0,0 +1,154 @@
+// generation/capo.rs
+
+use crate::music_representation::{
+    midi_note, pitch_class_weights, Chord, Note, Score, OPEN_STRING_MIDI,
+};
+use crate::renderer::chord_diagram::ChordShape;
+use crate::renderer::renderer::NUM_STRINGS;
+
+// Capos above this make the guitar sound thin and leave too little neck
+const MAX_CAPO: u8 = 7;
+const MAX_FRET: u8 = 24;
+const MAX_SUGGESTIONS: usize = 3;
+const SHAPES_SHOWN: usize = 4;
+
+/// A capo position and what the chords of the score become with it.
+pub struct CapoSuggestion {
+    pub capo_fret: u8,
+    pub open_chords: usize,
+    pub barre_chords: usize,
+    /// The most common chord shapes with this capo and the chords they are relative to it
+    pub shapes: Vec<(ChordShape, Option<String>)>,
+}
+
+/// Barre chords fret every string they play, open chords ring at least one open string low
+/// on the neck.
+fn is_barre(shape: &ChordShape) -> bool {
+    shape.played_strings() >= 4 && shape.frets.iter().flatten().all(|&fret| fret > 0)
+}
+
+fn is_open(shape: &ChordShape) -> bool {
+    shape.frets.iter().flatten().any(|&fret| fret == 0)
+        && shape.frets.iter().flatten().all(|&fret| fret <= 4)
+}
+
+/// The same pitches played with the capo at `to` instead of `from`, keeping every note on its
+/// string where it can. Notes that would fall below the capo or off the fretboard move to the
+/// closest free string. Returns `None` if some note can't be played.
+pub fn refinger(notes: &[Note], from: u8, to: u8) -> Option<Vec<Note>> {
+    let fret_on = |idx: usize, midi: u8| {
+        midi.checked_sub(OPEN_STRING_MIDI[idx] + to)
+            .filter(|&fret| fret <= MAX_FRET)
+    };
+    let mut used = [false; NUM_STRINGS];
+    for note in notes {
+        if let Some(string) = note.string.filter(|s| (1..=NUM_STRINGS as u8).contains(s)) {
+            used[string as usize - 1] = true;
+        }
+    }
+
+    let mut result = Vec::with_capacity(notes.len());
+    for note in notes {
+        let (Some(string), Some(midi)) = (note.string, midi_note(note, from)) else {
+            result.push(note.clone());
+            continue;
+        };
+        let string_idx = string as usize - 1;
+        let (idx, fret) = match fret_on(string_idx, midi) {
+            Some(fret) => (string_idx, fret),
+            None => {
+                let mut candidates: Vec<usize> = (0..NUM_STRINGS).collect();
+                candidates.sort_by_key(|&idx| idx.abs_diff(string_idx));
+                let (idx, fret) = candidates.into_iter().find_map(|idx| {
+                    let fret = fret_on(idx, midi)?;
+                    (!used[idx]).then_some((idx, fret))
+                })?;
+                used[idx] = true;
+                (idx, fret)
+            }
+        };
+        result.push(Note {
+            string: Some(idx as u8 + 1),
+            fret: Some(fret),
+            ..note.clone()
+        });
+    }
+    Some(result)
+}
+
+/// Moves the whole score to the capo at `to`, or `None` if some notes don't fit.
+pub fn apply_capo(score: &Score, from: u8, to: u8) -> Option<Score> {
+    let mut score = score.clone();
+    for measure in &mut score.measures {
+        for notes in &mut measure.positions {
+            if notes.len() > 1 || notes.iter().any(|note| note.fret.is_some()) {
+                *notes = refinger(notes, from, to)?;
+            }
+        }
+    }
+    score.capo = score.capo.map(|_| to);
+    Some(score)
+}
+
+fn evaluate(score: &Score, from: u8, to: u8) -> Option<CapoSuggestion> {
+    let mut open_chords = 0;
+    let mut barre_chords = 0;
+    let mut shape_counts: Vec<(ChordShape, Option<String>, usize)> = Vec::new();
+    for notes in score
+        .measures
+        .iter()
+        .flat_map(|measure| measure.positions.iter())
+    {
+        let notes = refinger(notes, from, to)?;
+        let Some(shape) = ChordShape::from_notes(&notes) else {
+            continue;
+        };
+        if shape.played_strings() < 3 {
+            continue;
+        }
+        open_chords += is_open(&shape) as usize;
+        barre_chords += is_barre(&shape) as usize;
+        match shape_counts
+            .iter_mut()
+            .find(|(known, _, _)| *known == shape)
+        {
+            Some((_, _, count)) => *count += 1,
+            None => {
+                // Named as the shape is fingered, as if there were no capo
+                let (weights, bass) = pitch_class_weights(notes.iter(), 0);
+                let name = Chord::detect(&weights, bass).map(|chord| chord.to_string());
+                shape_counts.push((shape, name, 1));
+            }
+        }
+    }
+    shape_counts.sort_by_key(|(_, _, count)| std::cmp::Reverse(*count));
+    Some(CapoSuggestion {
+        capo_fret: to,
+        open_chords,
+        barre_chords,
+        shapes: shape_counts
+            .into_iter()
+            .take(SHAPES_SHOWN)
+            .map(|(shape, name, _)| (shape, name))
+            .collect(),
+    })
+}
+
+/// Capo positions that turn more of the score's barre chords into open shapes than the
+/// current one, best first.
+pub fn capo_suggestions(score: &Score, current_capo: u8) -> Vec<CapoSuggestion> {
+    let Some(current) = evaluate(score, current_capo, current_capo) else {
+        return Vec::new();
+    };
+    let mut suggestions: Vec<CapoSuggestion> = (0..=MAX_CAPO)
+        .filter(|&capo| capo != current_capo)
+        .filter_map(|capo| evaluate(score, current_capo, capo))
+        .filter(|s| {
+            s.barre_chords < current.barre_chords
+                || (s.barre_chords == current.barre_chords && s.open_chords > current.open_chords)
+        })
+        .collect();
+    suggestions.sort_by_key(|s| (s.barre_chords, std::cmp::Reverse(s.open_chords)));
+    suggestions.truncate(MAX_SUGGESTIONS);
+    suggestions
+}
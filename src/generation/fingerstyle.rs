@@ -0,0 +1,146 @@
+// generation/fingerstyle.rs
+
+use crate::music_representation::{
+    chord_at, chord_progression, midi_note, Note, Score, OPEN_STRING_MIDI,
+};
+use crate::renderer::renderer::NUM_STRINGS;
+
+// The melody stays on the three treble strings and the bass on the three bass strings
+const MELODY_STRINGS: [usize; 3] = [0, 1, 2];
+const BASS_STRINGS: [usize; 3] = [5, 4, 3];
+// Chord tones filling in beats without a melody note are played in between
+const FILL_STRINGS: [usize; 2] = [2, 1];
+const MAX_MELODY_FRET: u8 = 12;
+const MAX_BASS_FRET: u8 = 5;
+
+/// The lowest fret for `midi` on one of `strings` not already used.
+fn place(
+    midi: u8,
+    strings: &[usize],
+    used: &[bool; NUM_STRINGS],
+    capo: u8,
+    max_fret: u8,
+) -> Option<(usize, u8)> {
+    strings
+        .iter()
+        .filter(|&&idx| !used[idx])
+        .filter_map(|&idx| {
+            let fret = midi.checked_sub(OPEN_STRING_MIDI[idx] + capo)?;
+            (fret <= max_fret).then_some((idx, fret))
+        })
+        .min_by_key(|&(_, fret)| fret)
+}
+
+/// The lowest pitch of `pitch_class` that one of `strings` can play within `max_fret`.
+fn place_pitch_class(
+    pitch_class: u8,
+    strings: &[usize],
+    used: &[bool; NUM_STRINGS],
+    capo: u8,
+    max_fret: u8,
+) -> Option<(usize, u8)> {
+    strings
+        .iter()
+        .filter(|&&idx| !used[idx])
+        .map(|&idx| {
+            let open = OPEN_STRING_MIDI[idx] + capo;
+            (idx, (pitch_class + 12 - open % 12) % 12)
+        })
+        .filter(|&(_, fret)| fret <= max_fret)
+        .min_by_key(|&(idx, fret)| (OPEN_STRING_MIDI[idx] + capo + fret, fret))
+}
+
+fn note(string_idx: usize, fret: u8, duration: u32) -> Note {
+    Note::new(string_idx as u8 + 1, fret, 0, duration)
+}
+
+/// The highest note of every position is taken as the melody line.
+fn melody_note(notes: &[Note], capo: u8) -> Option<(u8, &Note)> {
+    notes
+        .iter()
+        .filter_map(|note| Some((midi_note(note, capo)?, note)))
+        .max_by_key(|(midi, _)| *midi)
+}
+
+/// A basic fingerstyle arrangement of the score's chords and melody: the chord's root and fifth
+/// alternating in the bass on every beat, the melody on top, and a chord tone on beats where the
+/// melody rests.
+pub fn fingerstyle(score: &Score, capo: u8) -> Score {
+    let spans = chord_progression(score, capo);
+    let divisions_per_beat = (score.divisions_per_quarter as usize * 4
+        / score.time_signature.beat_value.max(1) as usize)
+        .max(1);
+    // The melody is kept above the open G string, clear of the bass
+    let melody_low = OPEN_STRING_MIDI[2] + capo;
+    let melody_high = OPEN_STRING_MIDI[0] + capo + MAX_MELODY_FRET;
+
+    let mut arranged = score.clone();
+    arranged.title = Some(format!(
+        "{} (fingerstyle)",
+        score.title.as_deref().unwrap_or("Untitled")
+    ));
+    for (measure_idx, measure) in arranged.measures.iter_mut().enumerate() {
+        let original = &score.measures[measure_idx];
+        for (division, notes) in measure.positions.iter_mut().enumerate() {
+            let mut used = [false; NUM_STRINGS];
+            let mut arranged_notes = Vec::new();
+
+            if let Some((mut midi, melody)) = melody_note(&original.positions[division], capo) {
+                while midi < melody_low {
+                    midi += 12;
+                }
+                while midi > melody_high {
+                    midi -= 12;
+                }
+                if let Some((idx, fret)) =
+                    place(midi, &MELODY_STRINGS, &used, capo, MAX_MELODY_FRET)
+                {
+                    used[idx] = true;
+                    arranged_notes.push(note(idx, fret, melody.duration));
+                }
+            }
+
+            let has_melody = !arranged_notes.is_empty();
+            let chord = chord_at(&spans, measure_idx, division);
+            if let (Some(chord), true) = (chord, division.is_multiple_of(divisions_per_beat)) {
+                let beat = division / divisions_per_beat;
+                let intervals = chord.quality.intervals();
+                // Root on the strong beats, fifth (or the highest chord tone) on the others
+                let bass_interval = if beat.is_multiple_of(2) {
+                    0
+                } else {
+                    if intervals.contains(&7) {
+                        7
+                    } else {
+                        intervals[intervals.len() - 1]
+                    }
+                };
+                let bass_pitch_class = (chord.root + bass_interval) % 12;
+                let duration = divisions_per_beat as u32;
+                if let Some((idx, fret)) =
+                    place_pitch_class(bass_pitch_class, &BASS_STRINGS, &used, capo, MAX_BASS_FRET)
+                {
+                    used[idx] = true;
+                    arranged_notes.push(note(idx, fret, duration));
+                }
+                if !has_melody {
+                    // The third colours the chord best, the fifth stands in for power chords
+                    let fill_interval = intervals.get(1).copied().unwrap_or(7);
+                    let fill_pitch_class = (chord.root + fill_interval) % 12;
+                    if let Some((idx, fret)) = place_pitch_class(
+                        fill_pitch_class,
+                        &FILL_STRINGS,
+                        &used,
+                        capo,
+                        MAX_BASS_FRET,
+                    ) {
+                        arranged_notes.push(note(idx, fret, duration));
+                    }
+                }
+            }
+            *notes = arranged_notes;
+        }
+    }
+    arranged.resolve_timing();
+    arranged
+}
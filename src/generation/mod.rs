@@ -0,0 +1,6 @@
+pub mod capo;
+pub mod fingerstyle;
+pub mod positions;
+pub mod simplify;
+pub mod thin;
+pub mod warm_up;
@@ -0,0 +1,127 @@
+// generation/positions.rs
+
+use crate::music_representation::{midi_note, Measure, Note, OPEN_STRING_MIDI};
+use crate::renderer::renderer::NUM_STRINGS;
+
+const MAX_FRET: u8 = 24;
+// Frets a hand in position covers without moving, from the index finger on
+const POSITION_SPAN: u8 = 4;
+// An open string in a high position means reaching back, but it is often the easier choice
+const OPEN_STRING_COST: u8 = 2;
+
+/// String and fret of every note of `measure`, division by division, to store a fingering of
+/// it apart from the score.
+pub fn measure_frets(measure: &Measure) -> Vec<(Option<u8>, Option<u8>)> {
+    measure
+        .positions
+        .iter()
+        .flatten()
+        .map(|note| (note.string, note.fret))
+        .collect()
+}
+
+/// Puts the strings and frets of `frets` back on the notes of `measure`. Returns false and
+/// leaves the measure alone if they were taken from a measure with other notes.
+pub fn apply_frets(measure: &mut Measure, frets: &[(Option<u8>, Option<u8>)]) -> bool {
+    if measure.positions.iter().flatten().count() != frets.len() {
+        return false;
+    }
+    for (note, &(string, fret)) in measure.positions.iter_mut().flatten().zip(frets) {
+        note.string = string;
+        note.fret = fret;
+    }
+    true
+}
+
+/// How far the hand has to stretch or shift from `position` to play `fret`.
+fn reach(fret: u8, position: u8) -> u8 {
+    if fret == 0 {
+        if position <= 1 {
+            0
+        } else {
+            OPEN_STRING_COST
+        }
+    } else if fret < position {
+        position - fret
+    } else {
+        fret.saturating_sub(position + POSITION_SPAN - 1)
+    }
+}
+
+/// Tries every string for the notes from `idx` on, keeping the cheapest as `best`.
+fn assign(
+    frets: &[[Option<u8>; NUM_STRINGS]],
+    current: &[usize],
+    position: u8,
+    idx: usize,
+    chosen: &mut Vec<usize>,
+    best: &mut Option<((u32, usize), Vec<usize>)>,
+) {
+    if idx == frets.len() {
+        let cost = chosen
+            .iter()
+            .enumerate()
+            .map(|(note, &string)| reach(frets[note][string].unwrap_or(0), position) as u32)
+            .sum();
+        // Among equally easy fingerings, the one moving the fewest notes
+        let moved = chosen.iter().zip(current).filter(|(a, b)| a != b).count();
+        if best.as_ref().is_none_or(|(best, _)| (cost, moved) < *best) {
+            *best = Some(((cost, moved), chosen.clone()));
+        }
+        return;
+    }
+    for string in 0..NUM_STRINGS {
+        if frets[idx][string].is_none() || chosen.contains(&string) {
+            continue;
+        }
+        chosen.push(string);
+        assign(frets, current, position, idx + 1, chosen, best);
+        chosen.pop();
+    }
+}
+
+/// The notes played together fingered as close to `position` as they go, each on a string of
+/// its own. Returns `None` if they can't all be played.
+fn finger_in_position(notes: &[Note], position: u8, capo: u8) -> Option<Vec<Note>> {
+    let fretted: Vec<usize> = (0..notes.len())
+        .filter(|&idx| notes[idx].string.is_some() && notes[idx].fret.is_some())
+        .collect();
+    let mut frets = Vec::with_capacity(fretted.len());
+    let mut current = Vec::with_capacity(fretted.len());
+    for &idx in &fretted {
+        let midi = midi_note(&notes[idx], capo)?;
+        let mut on_string = [None; NUM_STRINGS];
+        for (string, &open) in OPEN_STRING_MIDI.iter().enumerate() {
+            on_string[string] = midi
+                .checked_sub(open + capo)
+                .filter(|&fret| fret <= MAX_FRET);
+        }
+        frets.push(on_string);
+        current.push((notes[idx].string? as usize).saturating_sub(1));
+    }
+
+    let mut best = None;
+    assign(&frets, &current, position, 0, &mut Vec::new(), &mut best);
+    let (_, strings) = best?;
+    let mut result = notes.to_vec();
+    for ((&idx, on_string), string) in fretted.iter().zip(&frets).zip(strings) {
+        result[idx].string = Some(string as u8 + 1);
+        result[idx].fret = on_string[string];
+    }
+    Some(result)
+}
+
+/// The strings and frets of `measure` played in `position`, with the index finger at that fret
+/// and 0 for open position, in the order of `measure_frets`. Returns `None` if some notes can't
+/// be played at the same time there.
+pub fn frets_in_position(
+    measure: &Measure,
+    position: u8,
+    capo: u8,
+) -> Option<Vec<(Option<u8>, Option<u8>)>> {
+    let mut fingered = measure.clone();
+    for notes in &mut fingered.positions {
+        *notes = finger_in_position(notes, position, capo)?;
+    }
+    Some(measure_frets(&fingered))
+}
@@ -0,0 +1,107 @@
+// generation/simplify.rs
+
+use crate::music_representation::{midi_note, Note, Score, Technique, OPEN_STRING_MIDI};
+use crate::renderer::renderer::NUM_STRINGS;
+
+// Everything stays in the first position
+const MAX_FRET: u8 = 5;
+
+/// Slurred notes and notes shorter than a 32nd are embellishments of the notes around them.
+fn is_embellishment(note: &Note, divisions_per_quarter: u8, alone: bool) -> bool {
+    let slurred = matches!(note.technique, Technique::HammerOn | Technique::PullOff);
+    (slurred && alone) || note.duration * 8 < divisions_per_quarter as u32
+}
+
+/// The lowest note and the lowest fifth above it, or just the lowest note without a fifth.
+fn root_and_fifth(notes: Vec<(u8, Note)>) -> Vec<(u8, Note)> {
+    let Some(root) = notes.iter().map(|(midi, _)| *midi).min() else {
+        return notes;
+    };
+    let bass = notes.iter().find(|(midi, _)| *midi == root).cloned();
+    let fifth = notes
+        .iter()
+        .filter(|(midi, _)| *midi > root && (midi - root) % 12 == 7)
+        .min_by_key(|(midi, _)| *midi)
+        .cloned();
+    bass.into_iter().chain(fifth).collect()
+}
+
+/// Moves `midi` by octaves until it can be played in the first frets, then picks the string
+/// closest to `preferred` that is still free.
+fn place(midi: u8, preferred: usize, used: &[bool; NUM_STRINGS], capo: u8) -> Option<(u8, u8, i8)> {
+    let lowest = OPEN_STRING_MIDI[NUM_STRINGS - 1] + capo;
+    let highest = OPEN_STRING_MIDI[0] + capo + MAX_FRET;
+    let mut octaves = 0i8;
+    let mut midi = midi;
+    while midi < lowest {
+        midi += 12;
+        octaves += 1;
+    }
+    while midi > highest {
+        midi -= 12;
+        octaves -= 1;
+    }
+    let mut candidates: Vec<usize> = (0..NUM_STRINGS).filter(|&idx| !used[idx]).collect();
+    candidates.sort_by_key(|&idx| idx.abs_diff(preferred));
+    candidates.into_iter().find_map(|idx| {
+        let fret = midi.checked_sub(OPEN_STRING_MIDI[idx] + capo)?;
+        (fret <= MAX_FRET).then_some((idx as u8 + 1, fret, octaves))
+    })
+}
+
+fn simplify_position(notes: &[Note], divisions_per_quarter: u8, capo: u8) -> Vec<Note> {
+    let alone = notes.len() == 1;
+    let mut pitched: Vec<(u8, Note)> = Vec::new();
+    for note in notes {
+        if is_embellishment(note, divisions_per_quarter, alone) {
+            continue;
+        }
+        if let Some(midi) = midi_note(note, capo) {
+            // Doubled notes from several voices are played once
+            if !pitched.iter().any(|(known, _)| *known == midi) {
+                pitched.push((midi, note.clone()));
+            }
+        }
+    }
+    if pitched.len() >= 3 {
+        pitched = root_and_fifth(pitched);
+    }
+
+    let mut used = [false; NUM_STRINGS];
+    pitched
+        .into_iter()
+        .filter_map(|(midi, note)| {
+            let preferred = note.string.map_or(0, |string| string as usize - 1);
+            let (string, fret, octaves) = place(midi, preferred, &used, capo)?;
+            used[string as usize - 1] = true;
+            let pitch = note.pitch.map(|mut pitch| {
+                pitch.octave = (pitch.octave as i8 + octaves) as u8;
+                pitch
+            });
+            Some(Note {
+                string: Some(string),
+                fret: Some(fret),
+                pitch,
+                technique: Technique::None,
+                finger: None,
+                ..note
+            })
+        })
+        .collect()
+}
+
+/// An easier arrangement of `score` for beginners: no embellishments, chords reduced to root and
+/// fifth, and every note within the first five frets.
+pub fn simplify(score: &Score, capo: u8) -> Score {
+    let mut simplified = score.clone();
+    for measure in &mut simplified.measures {
+        for notes in &mut measure.positions {
+            *notes = simplify_position(notes, score.divisions_per_quarter, capo);
+        }
+    }
+    simplified.title = Some(format!(
+        "{} (simplified)",
+        score.title.as_deref().unwrap_or("Untitled")
+    ));
+    simplified
+}
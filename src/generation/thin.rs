@@ -0,0 +1,58 @@
+// generation/thin.rs
+
+use crate::music_representation::{
+    chord_at, chord_progression, midi_note, Chord, ChordFunction, Note, Score,
+};
+
+/// Highest simplification level, leaving little more than a note on every beat.
+pub const MAX_SIMPLIFICATION: u8 = 3;
+
+/// How much a note carries the music: notes on the beat, notes lasting a beat or more and
+/// notes of the chord sounding count. A short passing note off the beat counts nothing.
+fn importance(note: &Note, division: usize, beat: usize, chord: Option<Chord>, capo: u8) -> u8 {
+    let metric = if division.is_multiple_of(beat) {
+        2
+    } else if (2 * division).is_multiple_of(beat) {
+        1
+    } else {
+        0
+    };
+    let long = note.duration as usize >= beat;
+    // Without a chord to compare with, every note belongs to the harmony
+    let chord_tone = match (chord, midi_note(note, capo)) {
+        (Some(chord), Some(midi)) => chord.function_of(midi % 12) != ChordFunction::Tension,
+        _ => true,
+    };
+    metric + long as u8 + chord_tone as u8
+}
+
+/// `score` with its ornamental notes thinned out, fewer the higher `level`, so a player can
+/// start with its outline and fill in the fast notes later. Level 0 leaves it as written.
+/// The lowest note on each beat and tied notes are kept always, the first hold the harmony
+/// together and dropping the others would break their ties.
+pub fn thin_out(score: &Score, level: u8, capo: u8) -> Score {
+    let mut thinned = score.clone();
+    if level == 0 {
+        return thinned;
+    }
+    let spans = chord_progression(score, capo);
+    let beat = (score.divisions_per_quarter as usize).max(1);
+    for (measure_idx, measure) in thinned.measures.iter_mut().enumerate() {
+        for (division, notes) in measure.positions.iter_mut().enumerate() {
+            let chord = chord_at(&spans, measure_idx, division);
+            let bass = notes
+                .iter()
+                .filter_map(|note| midi_note(note, capo))
+                .min()
+                .filter(|_| division.is_multiple_of(beat));
+            notes.retain(|note| {
+                note.tie.start
+                    || note.tie.stop
+                    || (bass.is_some() && midi_note(note, capo) == bass)
+                    || importance(note, division, beat, chord, capo) > level
+            });
+        }
+    }
+    thinned.resolve_timing();
+    thinned
+}
@@ -0,0 +1,209 @@
+// generation/warm_up.rs
+
+use crate::music_representation::{
+    key_tonic, KeySignature, Measure, Note, ScaleKind, Score, Section, TimeSignature,
+    OPEN_STRING_MIDI,
+};
+use crate::renderer::chord_diagram::ChordShape;
+
+// Eighth notes in 4/4
+const DIVISIONS_PER_QUARTER: u8 = 2;
+const DIVISIONS_PER_MEASURE: usize = 8;
+const STRUM_DIVISIONS: u32 = 2;
+
+const MAX_CHORDS: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WarmUpSettings {
+    pub chromatic: bool,
+    pub scale: bool,
+    pub chords: bool,
+    /// Times the piece's chord changes are played through
+    pub chord_repeats: usize,
+    /// Warm-up tempo relative to the song's tempo
+    pub tempo_percent: usize,
+}
+
+impl Default for WarmUpSettings {
+    fn default() -> Self {
+        Self {
+            chromatic: true,
+            scale: true,
+            chords: true,
+            chord_repeats: 2,
+            tempo_percent: 75,
+        }
+    }
+}
+
+fn note(string: u8, fret: u8, duration: u32) -> Note {
+    Note::new(string, fret, 0, duration)
+}
+
+/// Puts single notes one division apart into full measures.
+fn measures_from_line(notes: Vec<Note>) -> Vec<Measure> {
+    notes
+        .chunks(DIVISIONS_PER_MEASURE)
+        .map(|chunk| {
+            let mut measure = Measure::new(DIVISIONS_PER_MEASURE);
+            for (division, note) in chunk.iter().enumerate() {
+                measure.positions[division].push(note.clone());
+            }
+            measure
+        })
+        .collect()
+}
+
+/// The "spider": frets 1 to 4 on every string up from the low E, then back down.
+fn chromatic_run() -> Vec<Note> {
+    let up = (1..=6u8)
+        .rev()
+        .flat_map(|string| (1..=4).map(move |fret| note(string, fret, 1)));
+    let down = (1..=6u8).flat_map(|string| (1..=4).rev().map(move |fret| note(string, fret, 1)));
+    up.chain(down).collect()
+}
+
+/// Two octaves up and down in the key, kept within one hand position where possible.
+fn scale_run(key: KeySignature) -> Vec<Note> {
+    let (tonic, minor) = key_tonic(key);
+    let steps = if minor {
+        ScaleKind::NaturalMinor.intervals()
+    } else {
+        ScaleKind::Major.intervals()
+    };
+    // Lowest tonic on the low E string, C is MIDI 48 and the open low E is 40
+    let root = 40 + (tonic + 12 - 4) % 12;
+    let position = root - 40;
+
+    let pitches: Vec<u8> = (0..2)
+        .flat_map(|octave| steps.iter().map(move |step| root + 12 * octave + step))
+        .chain(std::iter::once(root + 24))
+        .collect();
+    pitches
+        .iter()
+        .chain(pitches.iter().rev().skip(1))
+        .filter_map(|&midi| fret_near(midi, position).map(|(string, fret)| note(string, fret, 1)))
+        .collect()
+}
+
+/// String and fret for `midi`, preferring frets from one below `position` to four above it.
+fn fret_near(midi: u8, position: u8) -> Option<(u8, u8)> {
+    let candidates = OPEN_STRING_MIDI
+        .iter()
+        .enumerate()
+        .filter(|&(_, &open)| midi >= open && midi - open <= 24)
+        .map(|(idx, &open)| (idx as u8 + 1, midi - open));
+    candidates.min_by_key(|&(string, fret)| {
+        let distance = if fret + 1 < position {
+            position - fret - 1
+        } else {
+            fret.saturating_sub(position + 4)
+        };
+        // Among equally close frets, the lower string keeps the line moving across the neck
+        (distance, std::cmp::Reverse(string))
+    })
+}
+
+/// Every chord of the piece in the order it first appears, with its symbol if the score names it.
+fn chords_in(score: &Score) -> Vec<(ChordShape, Option<String>)> {
+    let mut chords: Vec<(ChordShape, Option<String>)> = Vec::new();
+    for measure in &score.measures {
+        for (division, notes) in measure.positions.iter().enumerate() {
+            let symbol = measure
+                .chord_symbols
+                .iter()
+                .find(|(d, _)| *d == division)
+                .map(|(_, name)| name.clone());
+            let Some(shape) = ChordShape::from_notes(notes) else {
+                continue;
+            };
+            if shape.played_strings() < 3 || chords.iter().any(|(known, _)| *known == shape) {
+                continue;
+            }
+            chords.push((shape, symbol));
+            if chords.len() == MAX_CHORDS {
+                return chords;
+            }
+        }
+    }
+    chords
+}
+
+/// A measure of quarter note strums per chord.
+fn chord_changes(chords: &[(ChordShape, Option<String>)], repeats: usize) -> Vec<Measure> {
+    let strums = DIVISIONS_PER_MEASURE / STRUM_DIVISIONS as usize;
+    (0..repeats)
+        .flat_map(|_| chords.iter())
+        .map(|(shape, symbol)| {
+            let mut measure = Measure::new(DIVISIONS_PER_MEASURE);
+            let notes: Vec<Note> = shape
+                .frets
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, fret)| Some(note(idx as u8 + 1, (*fret)?, STRUM_DIVISIONS)))
+                .collect();
+            for strum in 0..strums {
+                measure.positions[strum * STRUM_DIVISIONS as usize] = notes.clone();
+            }
+            if let Some(symbol) = symbol {
+                measure.chord_symbols.push((0, symbol.clone()));
+            }
+            measure
+        })
+        .collect()
+}
+
+/// A short exercise session for `score`, meant to be played before it.
+pub fn generate_warm_up(score: &Score, settings: &WarmUpSettings) -> Score {
+    let key = score.key.unwrap_or(KeySignature {
+        fifths: 0,
+        minor: false,
+    });
+    let mut measures = Vec::new();
+    let mut sections = Vec::new();
+    let mut add_part = |name: &str, part: Vec<Measure>| {
+        if !part.is_empty() {
+            sections.push(Section {
+                measure: measures.len(),
+                name: name.to_string(),
+            });
+            measures.extend(part);
+        }
+    };
+
+    if settings.chromatic {
+        add_part("Chromatic", measures_from_line(chromatic_run()));
+    }
+    if settings.scale {
+        add_part(&key.to_string(), measures_from_line(scale_run(key)));
+    }
+    if settings.chords {
+        add_part(
+            "Chord changes",
+            chord_changes(&chords_in(score), settings.chord_repeats),
+        );
+    }
+
+    let mut warm_up = Score {
+        title: Some(format!(
+            "Warm-up for {}",
+            score.title.as_deref().unwrap_or("the song")
+        )),
+        composer: None,
+        key: Some(key),
+        measures,
+        sections,
+        time_signature: TimeSignature {
+            beats_per_measure: 4,
+            beat_value: 4,
+        },
+        tempo: (score.tempo * settings.tempo_percent / 100).max(20),
+        divisions_per_quarter: DIVISIONS_PER_QUARTER,
+        divisions_per_measure: DIVISIONS_PER_MEASURE as u8,
+        // Played on the guitar as set up for the song
+        capo: score.capo,
+        tuning: score.tuning,
+    };
+    warm_up.resolve_timing();
+    warm_up
+}
@@ -0,0 +1,147 @@
+// arrangement.rs
+
+use crate::annotations::bundle::score_fingerprint;
+use crate::generation::fingerstyle::fingerstyle;
+use crate::generation::simplify::simplify;
+use crate::generation::thin::thin_out;
+use crate::music_representation::Score;
+
+/// The score as written or an arrangement generated from it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Arrangement {
+    AsWritten,
+    Simplified,
+    Fingerstyle,
+}
+
+impl Arrangement {
+    pub const ALL: [Arrangement; 3] = [
+        Arrangement::AsWritten,
+        Arrangement::Simplified,
+        Arrangement::Fingerstyle,
+    ];
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Arrangement::AsWritten => "The score as it was loaded",
+            Arrangement::Simplified => {
+                "No embellishments, chords as root and fifth, everything in the first five frets"
+            }
+            Arrangement::Fingerstyle => {
+                "Alternating bass on every beat under the melody, built from the chords"
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Arrangement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Arrangement::AsWritten => write!(f, "As written"),
+            Arrangement::Simplified => write!(f, "Simplified"),
+            Arrangement::Fingerstyle => write!(f, "Fingerstyle"),
+        }
+    }
+}
+
+/// Which arrangement of the open score is shown, and the fingerprint of the score as written
+/// that its annotations are kept under whatever is shown.
+pub struct ScoreArrangement {
+    pub arrangement: Arrangement,
+    pub simplification: u8,
+    fingerprint: Option<String>,
+    // The score as written while an arrangement of it is shown
+    written: Option<Score>,
+}
+
+impl ScoreArrangement {
+    /// Starts over with `score` shown as written, `None` when no score is open.
+    pub fn open(score: Option<&Score>) -> Self {
+        Self {
+            arrangement: Arrangement::AsWritten,
+            simplification: 0,
+            fingerprint: score.map(score_fingerprint),
+            written: None,
+        }
+    }
+
+    pub fn fingerprint(&self) -> Option<&String> {
+        self.fingerprint.as_ref()
+    }
+
+    /// The score as written, which is `shown` unless an arrangement of it is.
+    pub fn written<'a>(&'a self, shown: Option<&'a Score>) -> Option<&'a Score> {
+        self.written.as_ref().or(shown)
+    }
+
+    /// The score to show in place of `shown` for `arrangement`, with its ornamental notes thinned
+    /// out to `simplification`. Always arranged from the score as written, which is kept aside.
+    pub fn arrange(
+        &mut self,
+        shown: Score,
+        arrangement: Arrangement,
+        simplification: u8,
+        capo_fret: u8,
+    ) -> Score {
+        let written = self.written.take().unwrap_or(shown);
+        self.arrangement = arrangement;
+        self.simplification = simplification;
+        let arranged = match arrangement {
+            Arrangement::AsWritten if simplification == 0 => return written,
+            Arrangement::AsWritten => written.clone(),
+            Arrangement::Simplified => simplify(&written, capo_fret),
+            Arrangement::Fingerstyle => fingerstyle(&written, capo_fret),
+        };
+        let arranged = thin_out(&arranged, simplification, capo_fret);
+        self.written = Some(written);
+        arranged
+    }
+
+    /// Shows the score as written again after it was replaced by a copy with its notes moved,
+    /// such as to another capo position. The annotations stay where they are.
+    pub fn rewritten(&mut self) {
+        self.arrangement = Arrangement::AsWritten;
+        self.simplification = 0;
+        self.written = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score() -> Score {
+        Score::parse_from_musicxml(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/chords.xml"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn annotations_keep_their_key_across_arrangements() {
+        let written = score();
+        let mut arrangement = ScoreArrangement::open(Some(&written));
+        let key = arrangement.fingerprint().cloned();
+        assert_eq!(key, Some(score_fingerprint(&written)));
+
+        let mut shown = written.clone();
+        let mut rearranged = false;
+        for (option, simplification) in [
+            (Arrangement::Simplified, 0),
+            (Arrangement::Fingerstyle, 2),
+            (Arrangement::AsWritten, 1),
+        ] {
+            shown = arrangement.arrange(shown, option, simplification, 0);
+            rearranged |= score_fingerprint(&shown) != score_fingerprint(&written);
+            assert_eq!(arrangement.fingerprint(), key.as_ref());
+            let written_again = arrangement.written(Some(&shown)).map(score_fingerprint);
+            assert_eq!(written_again, key);
+        }
+        assert!(rearranged);
+
+        let shown = arrangement.arrange(shown, Arrangement::AsWritten, 0, 0);
+        assert_eq!(score_fingerprint(&shown), score_fingerprint(&written));
+        assert_eq!(arrangement.fingerprint(), key.as_ref());
+    }
+}
@@ -0,0 +1,117 @@
+// diagnostics.rs
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+use eframe::egui;
+use egui_plot::{Line, Plot, PlotPoints};
+use instant::Instant;
+
+use crate::audio::audio_player::AudioPlayer;
+use crate::audio::callback_timer::CallbackMeasurement;
+
+const FRAME_HISTORY: usize = 240;
+
+/// Performance numbers shown in the diagnostics overlay.
+#[derive(Default)]
+pub struct Diagnostics {
+    pub visible: bool,
+    last_frame: Option<Instant>,
+    // Milliseconds between consecutive frames
+    frame_times: VecDeque<f32>,
+    // Milliseconds egui spent on the previous frame
+    cpu_time: f32,
+    /// Milliseconds spent laying out the tab in the latest frame
+    pub layout_time: Cell<f32>,
+    callback: CallbackMeasurement,
+    /// Milliseconds from audio input to a finished analysis, when listening
+    pub listener_latency: Option<f32>,
+}
+
+impl Diagnostics {
+    pub fn begin_frame(&mut self, cpu_usage: Option<f32>, audio_player: &AudioPlayer) {
+        audio_player.callback_stats.set_enabled(self.visible);
+        if !self.visible {
+            self.last_frame = None;
+            return;
+        }
+
+        let now = Instant::now();
+        if let Some(last_frame) = self.last_frame {
+            if self.frame_times.len() == FRAME_HISTORY {
+                self.frame_times.pop_front();
+            }
+            self.frame_times
+                .push_back(now.duration_since(last_frame).as_secs_f32() * 1000.0);
+        }
+        self.last_frame = Some(now);
+        self.cpu_time = cpu_usage.unwrap_or(0.0) * 1000.0;
+        self.callback = audio_player.callback_stats.take();
+    }
+
+    fn report(&self, audio_player: &AudioPlayer) -> String {
+        let mut report = String::new();
+        let average = self.frame_times.iter().sum::<f32>() / self.frame_times.len().max(1) as f32;
+        let worst = self.frame_times.iter().copied().fold(0.0, f32::max);
+        let _ = writeln!(
+            report,
+            "Frame time: {:.1} ms average, {:.1} ms worst",
+            average, worst
+        );
+        let _ = writeln!(report, "Frame CPU time: {:.2} ms", self.cpu_time);
+        let _ = writeln!(report, "Layout time: {:.2} ms", self.layout_time.get());
+        let _ = writeln!(
+            report,
+            "Audio callback: {:.2} ms ({:.2} ms peak) of {:.2} ms",
+            self.callback.duration_ms, self.callback.peak_ms, self.callback.budget_ms
+        );
+        let _ = writeln!(report, "Synthesis: {:.2} ms", audio_player.synthesis_time);
+        let _ = writeln!(report, "Voices: {}", audio_player.voice_count());
+        match self.listener_latency {
+            Some(latency) => {
+                let _ = write!(report, "Listener latency: {:.1} ms", latency);
+            }
+            None => report.push_str("Listener latency: not listening"),
+        }
+        report
+    }
+
+    pub fn show(&self, ctx: &egui::Context, audio_player: &AudioPlayer) {
+        if !self.visible {
+            return;
+        }
+        egui::Area::new(egui::Id::new("diagnostics_overlay"))
+            .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-10.0, 10.0))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    let report = self.report(audio_player);
+                    ui.monospace(&report);
+
+                    let points: PlotPoints = self
+                        .frame_times
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &ms)| [i as f64, ms as f64])
+                        .collect();
+                    Plot::new("frame_time_plot")
+                        .width(260.0)
+                        .height(80.0)
+                        .include_y(0.0)
+                        .include_y(33.0)
+                        .show_axes([false, true])
+                        .allow_drag(false)
+                        .allow_zoom(false)
+                        .allow_scroll(false)
+                        .show(ui, |plot_ui| {
+                            plot_ui.line(Line::new(points).name("Frame ms"))
+                        });
+
+                    // Meant to be pasted into bug reports
+                    if ui.button("Copy").clicked() {
+                        ui.ctx().copy_text(report);
+                    }
+                });
+            });
+    }
+}
@@ -0,0 +1,171 @@
+// eq_editor.rs
+
+use eframe::egui;
+use egui::{pos2, Align2, Color32, FontId, Rect, Sense, Stroke, Vec2};
+
+use crate::audio::equalizer::{EqPreset, EqSettings, BAND_KINDS, FREQUENCY_RANGE, MAX_GAIN_DB};
+
+const HEIGHT: f32 = 120.0;
+const HANDLE_RADIUS: f32 = 6.0;
+const CURVE_POINTS: usize = 128;
+const Q_RANGE: (f32, f32) = (0.1, 10.0);
+const BAND_COLORS: [Color32; 4] = [
+    Color32::from_rgb(214, 39, 40),
+    Color32::from_rgb(255, 127, 14),
+    Color32::from_rgb(44, 160, 44),
+    Color32::from_rgb(31, 119, 180),
+];
+
+/// Logarithmic frequency axis from 0 to 1 across the editor.
+fn frequency_fraction(frequency: f32) -> f32 {
+    let (low, high) = FREQUENCY_RANGE;
+    (frequency / low).log10() / (high / low).log10()
+}
+
+fn fraction_frequency(fraction: f32) -> f32 {
+    let (low, high) = FREQUENCY_RANGE;
+    low * (high / low).powf(fraction.clamp(0.0, 1.0))
+}
+
+/// Curve of the EQ's frequency response with a handle per band: drag to move its frequency
+/// and gain, scroll over it to change its width. Sliders below set each band exactly. Returns
+/// whether anything changed.
+pub fn ui_eq_editor(ui: &mut egui::Ui, eq: &mut EqSettings, sample_rate: f32) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_salt("eq_preset")
+            .selected_text("Preset")
+            .show_ui(ui, |ui| {
+                for preset in EqPreset::ALL {
+                    if ui.selectable_label(false, preset.to_string()).clicked() {
+                        *eq = preset.settings();
+                        changed = true;
+                    }
+                }
+            });
+        if ui
+            .add_enabled(!eq.is_flat(), egui::Button::new("Flat"))
+            .clicked()
+        {
+            *eq = EqSettings::flat();
+            changed = true;
+        }
+    });
+
+    let (rect, _) = ui.allocate_exact_size(Vec2::new(ui.available_width(), HEIGHT), Sense::hover());
+    let to_x = |frequency: f32| rect.left() + frequency_fraction(frequency) * rect.width();
+    let to_y = |gain_db: f32| rect.center().y - gain_db / MAX_GAIN_DB * rect.height() / 2.0;
+
+    let painter = ui.painter_at(rect);
+    let visuals = ui.visuals();
+    painter.rect_filled(rect, 2.0, visuals.extreme_bg_color);
+    let grid = Stroke::new(1.0, visuals.faint_bg_color);
+    for frequency in [100.0, 1_000.0, 10_000.0] {
+        let x = to_x(frequency);
+        painter.line_segment([pos2(x, rect.top()), pos2(x, rect.bottom())], grid);
+        let label = if frequency >= 1_000.0 {
+            format!("{}k", frequency / 1_000.0)
+        } else {
+            format!("{}", frequency)
+        };
+        painter.text(
+            pos2(x + 2.0, rect.bottom() - 2.0),
+            Align2::LEFT_BOTTOM,
+            label,
+            FontId::proportional(9.0),
+            visuals.weak_text_color(),
+        );
+    }
+    let zero = to_y(0.0);
+    painter.line_segment([pos2(rect.left(), zero), pos2(rect.right(), zero)], grid);
+
+    let curve: Vec<_> = (0..CURVE_POINTS)
+        .map(|idx| {
+            let fraction = idx as f32 / (CURVE_POINTS - 1) as f32;
+            let gain_db = eq.response_db(fraction_frequency(fraction), sample_rate);
+            pos2(
+                rect.left() + fraction * rect.width(),
+                to_y(gain_db.clamp(-MAX_GAIN_DB, MAX_GAIN_DB)),
+            )
+        })
+        .collect();
+    painter.add(egui::Shape::line(
+        curve,
+        Stroke::new(2.0, visuals.strong_text_color()),
+    ));
+
+    for (idx, (band, kind)) in eq.bands.iter_mut().zip(BAND_KINDS).enumerate() {
+        let center = pos2(to_x(band.frequency), to_y(band.gain_db));
+        let handle = Rect::from_center_size(center, Vec2::splat(2.0 * HANDLE_RADIUS + 4.0));
+        let response = ui
+            .interact(handle, ui.id().with(("eq_band", idx)), Sense::drag())
+            .on_hover_text(format!(
+                "{}: {:.0} Hz, {:+.1} dB, Q {:.2}",
+                kind, band.frequency, band.gain_db, band.q
+            ));
+        if response.dragged() {
+            if let Some(pointer) = response.interact_pointer_pos() {
+                let fraction = (pointer.x - rect.left()) / rect.width();
+                band.frequency = fraction_frequency(fraction).min(sample_rate * 0.45);
+                band.gain_db = ((rect.center().y - pointer.y) / (rect.height() / 2.0)
+                    * MAX_GAIN_DB)
+                    .clamp(-MAX_GAIN_DB, MAX_GAIN_DB);
+                changed = true;
+            }
+        }
+        if response.hovered() {
+            let scroll = ui.input(|i| i.smooth_scroll_delta.y);
+            if scroll != 0.0 {
+                band.q = (band.q * (scroll * 0.01).exp()).clamp(Q_RANGE.0, Q_RANGE.1);
+                changed = true;
+            }
+        }
+        let radius = if response.hovered() || response.dragged() {
+            HANDLE_RADIUS + 2.0
+        } else {
+            HANDLE_RADIUS
+        };
+        painter.circle_filled(center, radius, BAND_COLORS[idx]);
+    }
+
+    changed |= ui_band_sliders(ui, eq, sample_rate);
+    changed
+}
+
+/// Frequency, gain and width of every band set exactly, for what is fiddly to drag.
+fn ui_band_sliders(ui: &mut egui::Ui, eq: &mut EqSettings, sample_rate: f32) -> bool {
+    let mut changed = false;
+    let highest = FREQUENCY_RANGE.1.min(sample_rate * 0.45);
+    egui::Grid::new("eq_bands")
+        .num_columns(4)
+        .striped(true)
+        .show(ui, |ui| {
+            for (idx, (band, kind)) in eq.bands.iter_mut().zip(BAND_KINDS).enumerate() {
+                ui.colored_label(BAND_COLORS[idx], kind.to_string());
+                changed |= ui
+                    .add(
+                        egui::Slider::new(&mut band.frequency, FREQUENCY_RANGE.0..=highest)
+                            .logarithmic(true)
+                            .suffix(" Hz")
+                            .max_decimals(0),
+                    )
+                    .changed();
+                changed |= ui
+                    .add(
+                        egui::Slider::new(&mut band.gain_db, -MAX_GAIN_DB..=MAX_GAIN_DB)
+                            .suffix(" dB")
+                            .step_by(0.1),
+                    )
+                    .changed();
+                changed |= ui
+                    .add(
+                        egui::Slider::new(&mut band.q, Q_RANGE.0..=Q_RANGE.1)
+                            .logarithmic(true)
+                            .text("Q"),
+                    )
+                    .changed();
+                ui.end_row();
+            }
+        });
+    changed
+}
@@ -0,0 +1,89 @@
+// error_map.rs
+
+use std::ops::RangeInclusive;
+
+/// How often the listener missed the notes of each measure during a run, to point out the bars
+/// that need work on the next pass.
+pub struct ErrorMap {
+    attempts: Vec<u32>,
+    misses: Vec<u32>,
+    // Divisions during which nothing at all came from the input
+    silent: Vec<u32>,
+    // Measure of the notes being listened for, whether they have been heard yet and whether
+    // anything was heard
+    pending: Option<(usize, bool, bool)>,
+}
+
+impl ErrorMap {
+    pub fn new(measures: usize) -> Self {
+        Self {
+            attempts: vec![0; measures],
+            misses: vec![0; measures],
+            silent: vec![0; measures],
+            pending: None,
+        }
+    }
+
+    /// The notes of a new division in `measure` are expected from now on.
+    pub fn expect(&mut self, measure: usize) {
+        self.finish();
+        if measure < self.attempts.len() {
+            self.pending = Some((measure, false, false));
+        }
+    }
+
+    /// Takes the listener's verdict on the latest input, and whether it was anything but silence.
+    pub fn heard(&mut self, is_match: bool, sounding: bool) {
+        if let Some((_, heard, sounded)) = &mut self.pending {
+            *heard |= is_match;
+            *sounded |= sounding;
+        }
+    }
+
+    /// Counts the notes being listened for, for when the run is over.
+    pub fn finish(&mut self) {
+        if let Some((measure, heard, sounded)) = self.pending.take() {
+            self.attempts[measure] += 1;
+            if !heard {
+                self.misses[measure] += 1;
+            }
+            if !sounded {
+                self.silent[measure] += 1;
+            }
+        }
+    }
+
+    /// Share of the notes of `measure` that were missed, if any were played.
+    pub fn miss_rate(&self, measure: usize) -> Option<f32> {
+        let attempts = *self.attempts.get(measure)?;
+        (attempts > 0).then(|| self.misses[measure] as f32 / attempts as f32)
+    }
+
+    pub fn measures_played(&self) -> usize {
+        self.attempts
+            .iter()
+            .filter(|&&attempts| attempts > 0)
+            .count()
+    }
+
+    /// Measures where at least one division was missed.
+    pub fn measures_missed(&self) -> usize {
+        self.misses.iter().filter(|&&misses| misses > 0).count()
+    }
+
+    /// Runs of measures, by index, through which the input stayed silent: the player stopped
+    /// rather than played wrong notes, so these are the ones to loop on their own.
+    pub fn skipped(&self) -> Vec<RangeInclusive<usize>> {
+        let mut runs: Vec<RangeInclusive<usize>> = Vec::new();
+        for (measure, (&attempts, &silent)) in self.attempts.iter().zip(&self.silent).enumerate() {
+            if attempts == 0 || silent < attempts {
+                continue;
+            }
+            match runs.last_mut() {
+                Some(run) if *run.end() + 1 == measure => *run = *run.start()..=measure,
+                _ => runs.push(measure..=measure),
+            }
+        }
+        runs
+    }
+}
@@ -1,22 +1,110 @@
 // gui.rs
 
-// use crate::audio::audio_listener::AudioListener;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::annotations::bundle::ANNOTATIONS_FILE;
+use crate::annotations::bundle::{
+    AnnotationBundle, AnnotationStore, Annotations, Comment, FingeringSuggestion, FingeringVariant,
+    Flag, Goal, LoopRegion, BUNDLE_EXTENSION,
+};
 use crate::audio::audio_player::AudioPlayer;
+use crate::audio::backing_track::{
+    process_track, BackingTrack, ProcessedFrames, ProcessingJob, TrackProcessing,
+};
+use crate::audio::beat_detection::{detect_beats, BeatAlignment};
+use crate::audio::chorus::{MAX_DELAY_MS, MAX_DEPTH_MS, MAX_FEEDBACK, MIN_DELAY_MS};
+use crate::audio::convolution::ImpulseResponse;
+use crate::audio::effect_chain::EffectKind;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::audio::input_capture::InputCapture;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::audio::input_filter::{HumFilter, InputFilterSettings};
+use crate::audio::limiter::{Limiter, MAX_RATIO, MIN_CEILING_DB, MIN_THRESHOLD_DB};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::audio::listener::{chroma_csv, Listener, MATCH_THRESHOLD};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::audio::loopback::{LoopbackReport, LoopbackTest};
+use crate::audio::metronome::{Cue, Metronome};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::audio::midi_clock::{midi_input_names, ClockEvent, MidiClock};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::audio::midi_input::{note_for_key, MidiKeyboard};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::audio::midi_output::{midi_output_names, MidiOut};
+use crate::audio::mixer::{Mixer, MixerChannel};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::audio::monitor::InputMonitor;
+use crate::audio::offline_render::render_score_stereo;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::audio::output_devices::{input_device_names, output_device_names};
+use crate::audio::polyphony::{VoiceStealing, DEFAULT_MAX_VOICES, VOICE_LIMIT};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::audio::rumble::Rumble;
+use crate::audio::section_detection::detect_section_boundaries;
+use crate::audio::spectrum::magnitude_spectrum;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::audio::tuner::{cents_off, detect_frequency, TUNER_WINDOW};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::export::compressed_audio::{encode_compressed, AudioFormat, AudioTags};
+use crate::export::musicxml::{score_to_musicxml, ExportMarks};
+use crate::export::print;
+use crate::export::tab_image::{export_tab_image, ImageFormat, BASE_DPI, EXPORT_SCALES};
+use crate::export::tab_pdf::{export_tab_pdf, PaperSize, PdfSettings, DEFAULT_MARGIN};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::export::wav::encode_wav;
+use crate::export::wav::{encode_stereo_wav, BitDepth};
+use crate::generation::capo::{apply_capo, capo_suggestions, CapoSuggestion};
+use crate::generation::positions::{apply_frets, frets_in_position, measure_frets};
+use crate::generation::thin::MAX_SIMPLIFICATION;
+use crate::generation::warm_up::{generate_warm_up, WarmUpSettings};
+use crate::gui::arrangement::{Arrangement, ScoreArrangement};
+use crate::gui::diagnostics::Diagnostics;
+use crate::gui::eq_editor::ui_eq_editor;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::gui::error_map::ErrorMap;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::gui::media_keys::{skip_target, MediaKey, MediaKeys};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::gui::miss_hint::{MissHint, HINT_AFTER_MISSES};
+use crate::gui::position_report::{format_time, PositionReport};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::gui::position_report::{PositionServer, APP_TITLE, DEFAULT_POSITION_PORT};
+use crate::gui::setlist::{Setlist, SetlistEntry};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::gui::spectrogram::Spectrogram;
+use crate::gui::tab_cache::{to_shape, TabCache, TabCacheKey};
 use crate::guitar::guitar::{GuitarConfig, GuitarType};
-use crate::music_representation::{Measure, Note, Score, Technique};
-use crate::renderer::renderer::{score_info, Renderer};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::library::folder::FolderIndex;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::library::store::LIBRARY_FILE;
+use crate::library::store::{measure_difficulty, Library, LibraryEntry, MAX_DIFFICULTY};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::library::watch::{equivalent_position, ScoreWatcher};
+use crate::music_representation::{
+    chord_progression, score_key, tuning_name, ChordFunction, ChordSpan, KeySignature, Note, Scale,
+    ScaleKind, Score, Section, Timeline, OPEN_STRING_MIDI, PITCH_CLASS_NAMES,
+};
+use crate::renderer::chord_diagram::{chord_diagram_size, layout_chord_diagram, ChordShape};
+use crate::renderer::fretboard::{layout_fretboard, FRETBOARD_HEIGHT};
+use crate::renderer::piano_roll::PianoRoll;
+use crate::renderer::renderer::{
+    score_info, NoteColoring, Renderer, TabFont, TabShape, TabStyle, TAB_PADDING,
+};
 
 use eframe::egui;
-use egui::epaint::{PathStroke, QuadraticBezierShape};
 use egui::{Margin, RichText, ScrollArea, Vec2};
+#[cfg(not(target_arch = "wasm32"))]
+use egui_plot::{Bar, BarChart};
 use egui_plot::{Line, Plot, PlotPoints};
 use instant::Instant;
-use rustfft::num_complex::Complex32;
-use rustfft::FftPlanner;
 
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::PathBuf;
 use std::sync::mpsc::Receiver;
 use std::sync::mpsc::{channel, Sender};
+use std::sync::Arc;
+use std::time::Duration;
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
@@ -25,6 +113,43 @@ use wasm_bindgen::JsCast;
 #[cfg(target_arch = "wasm32")]
 use web_sys::{Event, HtmlInputElement};
 
+// How long the notes missed in practice mode sound as a hint
+#[cfg(not(target_arch = "wasm32"))]
+const MIN_HINT_SECONDS: f32 = 0.5;
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_HINT_SECONDS: f32 = 2.0;
+// The tuner shows a string as in tune this close to its note
+#[cfg(not(target_arch = "wasm32"))]
+const IN_TUNE_CENTS: f32 = 5.0;
+// How long a key pressed on a MIDI controller lets its string ring
+#[cfg(not(target_arch = "wasm32"))]
+const MIDI_KEY_SECONDS: f32 = 2.0;
+// Silence rendered after the last note of an exported score for its effects to ring out
+const EXPORT_TAIL_SECONDS: f32 = 2.0;
+// Padding around the tab in the central panel
+const TAB_MARGIN: f32 = 20.0;
+// Size of the fingering variant selector drawn above a measure
+const FINGERING_SELECTOR_WIDTH: f32 = 110.0;
+const FINGERING_SELECTOR_HEIGHT: f32 = 16.0;
+// Central panel width below which the layout makes room for the tab
+const NARROW_WIDTH: f32 = 600.0;
+// Transport buttons of the touch layout, big enough for a finger
+const TOUCH_BUTTON_SIZE: Vec2 = Vec2::new(64.0, 48.0);
+const TOUCH_ICON_SIZE: f32 = 24.0;
+// Width of the playback cursor in the performance view, readable from a music stand
+const PERFORMANCE_CURSOR_WIDTH: f32 = 6.0;
+const TOUCH_ZOOM_RANGE: std::ops::RangeInclusive<f32> = 0.5..=3.0;
+// How often an idle window still updates, to pick up finished file dialogs and the like
+const IDLE_REPAINT_INTERVAL: Duration = Duration::from_millis(250);
+// Drops a flag on the measure being played
+const FLAG_KEY: egui::Key = egui::Key::F;
+// How far ahead of the playback position notes are handed to the audio thread, enough to ride
+// out a few slow frames
+const SCHEDULE_AHEAD_SECONDS: f32 = 0.2;
+// How long the window border lights up on a beat
+const BEAT_FLASH_DURATION: Duration = Duration::from_millis(150);
+const BEAT_FLASH_WIDTH: f32 = 12.0;
+
 #[cfg(target_arch = "wasm32")]
 pub const DEFAULT_MUSICXML: &str = include_str!("../../assets/silent_night.xml");
 
@@ -37,6 +162,102 @@ pub struct Configs {
     pub dashes_per_division: usize,
     pub guitar_configs: Vec<GuitarConfig>,
     pub active_guitar: usize,
+    /// Profiles to switch between with the A/B buttons, as indices into `guitar_configs`
+    pub ab_comparison: Option<(usize, usize)>,
+    pub export_image_format: ImageFormat,
+    pub export_dpi: f32,
+    pub export_paper_size: PaperSize,
+    pub mixer: Mixer,
+    pub stretch_backing_track: bool,
+    pub pitch_shift_backing_track: bool,
+    pub show_fretboard: bool,
+    pub show_chord_timeline: bool,
+    /// Tints every measure of the tab by how hard it is to play
+    pub show_difficulty: bool,
+    /// Lights up the window border on every beat, for players who can't hear the click
+    pub beat_flash: bool,
+    pub fretboard_scale: Option<Scale>,
+    pub left_handed: bool,
+    pub tab_style: TabStyle,
+    pub warm_up: WarmUpSettings,
+    pub score_view: ScoreView,
+    /// Large transport buttons and pinch zoom for phones and tablets
+    pub touch_layout: bool,
+    pub max_voices: usize,
+    /// Seed for exported audio, so exporting twice gives identical files
+    pub synthesis_seed: Option<u64>,
+    pub export_bit_depth: BitDepth,
+    /// Compressed format to export audio as, WAV when None
+    #[cfg(not(target_arch = "wasm32"))]
+    pub export_audio_format: Option<AudioFormat>,
+    pub voice_stealing: VoiceStealing,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub monitor_gain: f32,
+    /// Whether the internal synth plays along with a MIDI output or leaves the notes to it
+    #[cfg(not(target_arch = "wasm32"))]
+    pub synth_with_midi_output: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub input_filter: InputFilterSettings,
+    /// Microphone or audio interface to listen to, the system default when None
+    #[cfg(not(target_arch = "wasm32"))]
+    pub input_device: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub serve_position: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub position_port: u16,
+    /// Take play/pause and next/previous from the system's media keys
+    #[cfg(not(target_arch = "wasm32"))]
+    pub media_keys: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScoreView {
+    Tab,
+    PianoRoll,
+}
+
+impl std::fmt::Display for ScoreView {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScoreView::Tab => write!(f, "Tablature"),
+            ScoreView::PianoRoll => write!(f, "Piano roll"),
+        }
+    }
+}
+
+/// The capo and tuning a score asks for that the guitar isn't set up with.
+#[derive(Clone, Copy)]
+struct GuitarSetup {
+    capo: Option<u8>,
+    tuning: Option<[u8; 6]>,
+}
+
+impl std::fmt::Display for GuitarSetup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let capo = self.capo.map(|capo| match capo {
+            0 => "No capo".to_string(),
+            capo => format!("Capo {}", capo),
+        });
+        let tuning = self.tuning.as_ref().map(tuning_name);
+        let parts: Vec<String> = capo.into_iter().chain(tuning).collect();
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// Text typed into the annotation forms before it is added.
+#[derive(Default)]
+struct AnnotationDraft {
+    comment: String,
+    measure: usize,
+    division: usize,
+    string: u8,
+    finger: u8,
+    loop_name: String,
+    loop_end: usize,
+    goal: String,
+    goal_tempo: Option<usize>,
+    // Fret the index finger is at for a new fingering variant, 0 for open position
+    variant_position: u8,
 }
 
 pub struct DisplayMetrics {
@@ -47,17 +268,9 @@ impl Configs {
     pub fn new() -> Self {
         Self {
             active_guitar: 0,
+            ab_comparison: None,
             guitar_configs: vec![
-                GuitarConfig::custom(
-                    0.996, // decay
-                    0.5,   // string_damping
-                    100.0, // body_resonance
-                    0.5,   // body_damping
-                    0.7,   // string_tension
-                    25.5,  // scale_length
-                    0,     // capo_fret
-                    0.5,   // volume
-                ),
+                GuitarConfig::factory(&GuitarType::Custom),
                 GuitarConfig::acoustic(),
                 GuitarConfig::electric(),
                 GuitarConfig::classical(),
@@ -69,12 +282,66 @@ impl Configs {
             file_path: Some(PathBuf::from("silent.xml")),
             measures_per_row: 4,
             dashes_per_division: 2,
+            export_image_format: ImageFormat::Svg,
+            export_dpi: 150.0,
+            export_paper_size: PaperSize::A4,
+            mixer: Mixer::new(),
+            stretch_backing_track: true,
+            pitch_shift_backing_track: false,
+            show_fretboard: true,
+            show_chord_timeline: true,
+            show_difficulty: false,
+            beat_flash: false,
+            fretboard_scale: None,
+            left_handed: false,
+            tab_style: TabStyle::default(),
+            warm_up: WarmUpSettings::default(),
+            score_view: ScoreView::Tab,
+            touch_layout: false,
+            max_voices: DEFAULT_MAX_VOICES,
+            synthesis_seed: None,
+            export_bit_depth: BitDepth::Sixteen,
+            #[cfg(not(target_arch = "wasm32"))]
+            export_audio_format: None,
+            voice_stealing: VoiceStealing::Oldest,
+            #[cfg(not(target_arch = "wasm32"))]
+            monitor_gain: 1.0,
+            #[cfg(not(target_arch = "wasm32"))]
+            synth_with_midi_output: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            input_filter: InputFilterSettings::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            input_device: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            serve_position: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            position_port: DEFAULT_POSITION_PORT,
+            #[cfg(not(target_arch = "wasm32"))]
+            media_keys: false,
         }
     }
 }
 
+impl Default for Configs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// String and fret of every note of a measure as it is written, division by division.
+type WrittenFrets = Vec<(Option<u8>, Option<u8>)>;
+
 pub struct TabApp {
     score: Option<Score>,
+    // The arrangement shown, and the fingerprint of the score as written that identifies it in
+    // the annotation store and in exchanged bundles
+    score_arrangement: ScoreArrangement,
+    // Counts loaded scores so the tab cache knows when to lay out again
+    score_revision: u64,
+    // Note-on and note-off events of the loaded score, which playback follows
+    timeline: Timeline,
+    // The laid out tab, kept between frames since render_tab only borrows self
+    tab_cache: RefCell<Option<TabCache>>,
     renderer: Renderer,
     is_playing: bool,
     configs: Configs,
@@ -82,8 +349,104 @@ pub struct TabApp {
     previous_notes: Option<Vec<Note>>,
     current_notes: Option<Vec<Note>>,
     audio_player: AudioPlayer,
+    metronome: Metronome,
+    // Beats since playback started, counted in the time signature's beat unit
+    last_beat: Option<usize>,
+    #[cfg(not(target_arch = "wasm32"))]
+    output_devices: Vec<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    input_devices: Vec<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    metronome_error: Option<String>,
+    // First tick whose notes haven't been scheduled yet, None when the audio thread's transport
+    // needs to be set again
+    scheduled_until: Option<usize>,
+    // The latest beat, and whether it was the first of its measure, for the border flash
+    beat_flashed_at: Option<(Instant, bool)>,
+    #[cfg(not(target_arch = "wasm32"))]
+    rumble: Option<Rumble>,
+    #[cfg(not(target_arch = "wasm32"))]
+    rumble_error: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    output_device_error: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    input_monitor: Option<InputMonitor>,
+    #[cfg(not(target_arch = "wasm32"))]
+    monitor_error: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    input_capture: Option<InputCapture>,
+    #[cfg(not(target_arch = "wasm32"))]
+    spectrogram: Spectrogram,
+    #[cfg(not(target_arch = "wasm32"))]
+    capture_error: Option<String>,
     is_match: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    listener: Option<Listener>,
+    #[cfg(not(target_arch = "wasm32"))]
+    score_watcher: Option<ScoreWatcher>,
+    side_panel_open: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    listener_error: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    loopback_test: Option<LoopbackTest>,
+    #[cfg(not(target_arch = "wasm32"))]
+    loopback_report: Option<Result<LoopbackReport, String>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    midi_clock: Option<MidiClock>,
+    #[cfg(not(target_arch = "wasm32"))]
+    midi_clock_error: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    midi_output: Option<MidiOut>,
+    #[cfg(not(target_arch = "wasm32"))]
+    midi_output_error: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    midi_keyboard: Option<MidiKeyboard>,
+    #[cfg(not(target_arch = "wasm32"))]
+    midi_keyboard_error: Option<String>,
+    // Where the latest key pressed on the controller was played
+    #[cfg(not(target_arch = "wasm32"))]
+    midi_keyboard_note: Option<Note>,
+    // Where the clock stopped the transport, so that a continue picks up from there
+    #[cfg(not(target_arch = "wasm32"))]
+    clock_stopped_at: Option<(usize, usize)>,
+    /// Practice mode: hold every division with notes until the listener hears them
+    #[cfg(not(target_arch = "wasm32"))]
+    wait_for_notes: bool,
+    /// Whether the synthesizer plays the notes while waiting for them in practice mode
+    #[cfg(not(target_arch = "wasm32"))]
+    practice_play_notes: bool,
+    /// Whether practice mode plays the notes waited for once they were missed a few times
+    #[cfg(not(target_arch = "wasm32"))]
+    hint_missed_notes: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    miss_hint: MissHint,
+    // What the score asks of the guitar, while the prompt to set it up before playing is open
+    setup_prompt: Option<GuitarSetup>,
+    // Open strings the player last tuned the guitar to, high E first
+    tuned_to: [u8; 6],
+    #[cfg(not(target_arch = "wasm32"))]
+    tuner_open: bool,
+    // Latest pitch the tuner heard, smoothed, in Hz
+    #[cfg(not(target_arch = "wasm32"))]
+    tuner_frequency: Option<f32>,
+    // Seconds into playback the transport is held at until the notes are heard
+    #[cfg(not(target_arch = "wasm32"))]
+    waiting_at: Option<f32>,
+    // Misses of the run being played with the listener, and of the last finished one
+    #[cfg(not(target_arch = "wasm32"))]
+    error_recording: Option<ErrorMap>,
+    #[cfg(not(target_arch = "wasm32"))]
+    error_map: Option<ErrorMap>,
+    #[cfg(not(target_arch = "wasm32"))]
+    show_error_map: bool,
+    // Smoothed similarity between the microphone and the notes being played
+    similarity: f32,
+    // Oscilloscope window in samples
     plot_length: usize,
+    oscilloscope_trigger: bool,
+    // Seconds of output and input the plots keep
+    plot_history_seconds: f32,
+    plots_frozen: bool,
     plot_frequency_range: (usize, usize),
     score_channel: (Sender<Score>, Receiver<Score>),
     playback_start_time: Option<Instant>,
@@ -94,6 +457,81 @@ pub struct TabApp {
     last_played_measure_index: Option<usize>,
     last_played_division_index: Option<usize>,
     show_about: bool,
+    backing_track: Option<BackingTrack>,
+    backing_track_channel: (Sender<BackingTrack>, Receiver<BackingTrack>),
+    impulse_response_channel: (Sender<ImpulseResponse>, Receiver<ImpulseResponse>),
+    processing_channel: (Sender<ProcessedFrames>, Receiver<ProcessedFrames>),
+    processing_in_progress: Option<ProcessingJob>,
+    beat_detection_channel: (
+        Sender<Option<BeatAlignment>>,
+        Receiver<Option<BeatAlignment>>,
+    ),
+    detecting_beats: bool,
+    proposed_alignment: Option<BeatAlignment>,
+    section_detection_channel: (Sender<Vec<f32>>, Receiver<Vec<f32>>),
+    detecting_sections: bool,
+    // Measures proposed as section starts, with the time in the recording they were found at
+    suggested_sections: Vec<(usize, f32)>,
+    // Measure the tab view scrolls to on the next frame
+    scroll_to_measure: Option<usize>,
+    // Text of the open "Go to measure" dialog
+    go_to_measure: Option<String>,
+    // First and last measure, one based, to export on their own
+    excerpt_measures: (usize, usize),
+    // Fingering variant played in each measure for this session, by measure index, with the
+    // strings and frets the measure is written with
+    fingering_choices: BTreeMap<usize, (String, WrittenFrets)>,
+    // The flag just dropped, by index into the score's flags, and the note being typed for it
+    flag_note: Option<(usize, String)>,
+    // Chords of the score and the key their roman numerals are relative to
+    chord_progression: Vec<ChordSpan>,
+    progression_key: Option<KeySignature>,
+    // Last chord played and its symbol, if the score names it
+    current_chord: Option<(ChordShape, Option<String>)>,
+    diagnostics: Diagnostics,
+    setlist: Setlist,
+    // The song waiting while its warm-up plays
+    warmed_up_score: Option<Score>,
+    capo_suggestions: Option<Vec<CapoSuggestion>>,
+    // Capo fret waiting for the user to agree to lose the fingerings stored for the old frets
+    capo_confirmation: Option<u8>,
+    annotation_store: AnnotationStore,
+    annotation_channel: (Sender<String>, Receiver<String>),
+    annotation_draft: AnnotationDraft,
+    annotation_message: Option<String>,
+    // Set when the saved annotations couldn't be read nor moved aside, so they aren't saved over
+    #[cfg(not(target_arch = "wasm32"))]
+    annotations_read_only: bool,
+    library: Library,
+    library_channel: (Sender<LibraryEntry>, Receiver<LibraryEntry>),
+    library_query: String,
+    library_max_difficulty: u8,
+    library_tag_filter: BTreeSet<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    library_folder: Option<FolderIndex>,
+    #[cfg(not(target_arch = "wasm32"))]
+    library_folder_channel: (Sender<PathBuf>, Receiver<PathBuf>),
+    #[cfg(not(target_arch = "wasm32"))]
+    library_folder_selected: Option<usize>,
+    // Entry whose tags are being edited and the text being typed
+    editing_tags: Option<(usize, String)>,
+    setlist_channel: (Sender<SetlistEntry>, Receiver<SetlistEntry>),
+    print_mode: bool,
+    // Only the tab, fullscreen, for playing from a music stand
+    performance_mode: bool,
+    position_report: PositionReport,
+    #[cfg(not(target_arch = "wasm32"))]
+    position_server: Option<PositionServer>,
+    #[cfg(not(target_arch = "wasm32"))]
+    media_keys: Option<MediaKeys>,
+    #[cfg(not(target_arch = "wasm32"))]
+    media_keys_error: Option<String>,
+    // Where the media keys paused playback, to carry on from when play is pressed
+    #[cfg(not(target_arch = "wasm32"))]
+    media_paused_at: Option<(usize, usize)>,
+    // Frames left until the browser's print dialog opens, giving the toolbar time to disappear
+    #[cfg(target_arch = "wasm32")]
+    print_pending_frames: u8,
 }
 #[cfg(not(target_arch = "wasm32"))]
 fn execute<F>(f: F)
@@ -112,6 +550,298 @@ where
 fn execute<F: std::future::Future<Output = ()> + 'static>(f: F) {
     wasm_bindgen_futures::spawn_local(f);
 }
+
+/// Adds a section named after the next free rehearsal letter, keeping sections ordered.
+/// The `score` parameter of the page's URL, as in `?score=https://example.com/song.xml`.
+#[cfg(target_arch = "wasm32")]
+fn score_url_from_query() -> Option<String> {
+    let search = web_sys::window()?.location().search().ok()?;
+    let params = web_sys::UrlSearchParams::new_with_str(&search).ok()?;
+    params.get("score").filter(|url| !url.is_empty())
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn fetch_text(url: &str) -> Result<String, String> {
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen_futures::JsFuture;
+
+    let window = web_sys::window().ok_or("No window")?;
+    let response = JsFuture::from(window.fetch_with_str(url))
+        .await
+        .map_err(|e| format!("{:?}", e))?;
+    let response: web_sys::Response = response.dyn_into().map_err(|e| format!("{:?}", e))?;
+    if !response.ok() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+    let text = JsFuture::from(response.text().map_err(|e| format!("{:?}", e))?)
+        .await
+        .map_err(|e| format!("{:?}", e))?;
+    text.as_string()
+        .ok_or_else(|| "Response is not text".to_string())
+}
+
+fn add_section(score: &mut Score, measure: usize) {
+    let letter = (b'A' + (score.sections.len() % 26) as u8) as char;
+    let name = match score.sections.len() / 26 {
+        0 => letter.to_string(),
+        round => format!("{}{}", letter, round + 1),
+    };
+    let idx = score.sections.partition_point(|s| s.measure <= measure);
+    score.sections.insert(idx, Section { measure, name });
+}
+
+/// Paints the renderer's backend independent shapes with egui.
+fn finger_name(finger: u8) -> String {
+    match finger {
+        0 => "T".to_string(),
+        finger => finger.to_string(),
+    }
+}
+
+fn ui_scale_overlay(ui: &mut egui::Ui, scale: &mut Option<Scale>) {
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_salt("scale_overlay_kind")
+            .selected_text(scale.map_or("No scale".to_string(), |s| s.kind.to_string()))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(scale, None, "No scale");
+                let root = scale.map_or(0, |s| s.root);
+                for kind in ScaleKind::ALL {
+                    ui.selectable_value(scale, Some(Scale { root, kind }), kind.to_string());
+                }
+            });
+        if let Some(scale) = scale {
+            egui::ComboBox::from_id_salt("scale_overlay_root")
+                .width(50.0)
+                .selected_text(PITCH_CLASS_NAMES[scale.root as usize])
+                .show_ui(ui, |ui| {
+                    for (root, name) in PITCH_CLASS_NAMES.iter().enumerate() {
+                        ui.selectable_value(&mut scale.root, root as u8, *name);
+                    }
+                });
+        }
+        ui.label("on the fretboard");
+    });
+}
+
+/// Font and line settings of the tab, returning whether anything changed.
+fn ui_tab_style(ui: &mut egui::Ui, style: &mut TabStyle) -> bool {
+    let mut changed = false;
+    egui::CollapsingHeader::new("Typography")
+        .id_salt("tab_typography")
+        .show(ui, |ui| {
+            egui::ComboBox::from_label("Font")
+                .selected_text(style.font.to_string())
+                .show_ui(ui, |ui| {
+                    for font in [TabFont::Monospace, TabFont::Proportional] {
+                        changed |= ui
+                            .selectable_value(&mut style.font, font, font.to_string())
+                            .changed();
+                    }
+                });
+            changed |= ui
+                .add(egui::Slider::new(&mut style.font_size, 8.0..=32.0).text("Font size"))
+                .changed();
+            changed |= ui
+                .add(egui::Slider::new(&mut style.line_width, 0.5..=4.0).text("Line weight"))
+                .changed();
+            changed |= ui
+                .checkbox(&mut style.note_names, "Note names instead of frets")
+                .changed();
+            changed |= ui
+                .checkbox(&mut style.high_contrast, "High contrast")
+                .on_hover_text("Thick black lines and text on white")
+                .changed();
+            egui::ComboBox::from_label("Color notes by")
+                .selected_text(style.coloring.to_string())
+                .show_ui(ui, |ui| {
+                    for coloring in NoteColoring::ALL {
+                        changed |= ui
+                            .selectable_value(&mut style.coloring, coloring, coloring.to_string())
+                            .changed();
+                    }
+                });
+            let palette: Vec<(String, &mut egui::Color32)> = match style.coloring {
+                NoteColoring::None => Vec::new(),
+                NoteColoring::String => ["e", "B", "G", "D", "A", "E"]
+                    .iter()
+                    .map(|name| name.to_string())
+                    .zip(style.string_palette.iter_mut())
+                    .collect(),
+                NoteColoring::Finger => (0..5)
+                    .map(finger_name)
+                    .zip(style.finger_palette.iter_mut())
+                    .collect(),
+                NoteColoring::Interval => ChordFunction::ALL
+                    .iter()
+                    .map(|function| function.to_string())
+                    .zip(style.interval_palette.iter_mut())
+                    .collect(),
+            };
+            ui.horizontal_wrapped(|ui| {
+                for (name, color) in palette {
+                    ui.label(name);
+                    changed |= ui.color_edit_button_srgba(color).changed();
+                }
+            });
+            if ui.button("Reset").clicked() {
+                *style = TabStyle::default();
+                changed = true;
+            }
+        });
+    egui::CollapsingHeader::new("Spacing")
+        .id_salt("tab_spacing")
+        .show(ui, |ui| {
+            changed |= ui
+                .add(
+                    egui::Slider::new(&mut style.string_spacing, 12.0..=48.0)
+                        .text("String spacing"),
+                )
+                .changed();
+            changed |= ui
+                .add(egui::Slider::new(&mut style.row_spacing, 10.0..=150.0).text("Row spacing"))
+                .changed();
+            changed |= ui
+                .add(
+                    egui::Slider::new(&mut style.measure_spacing, 0.0..=60.0)
+                        .text("Measure spacing"),
+                )
+                .changed();
+            let default = TabStyle::default();
+            let is_default = style.string_spacing == default.string_spacing
+                && style.row_spacing == default.row_spacing
+                && style.measure_spacing == default.measure_spacing;
+            if ui
+                .add_enabled(!is_default, egui::Button::new("Reset spacing"))
+                .clicked()
+            {
+                style.string_spacing = default.string_spacing;
+                style.row_spacing = default.row_spacing;
+                style.measure_spacing = default.measure_spacing;
+                changed = true;
+            }
+        });
+    changed
+}
+
+/// The guitar's effects in the order they run, to reorder, remove and add back. Returns
+/// whether the chain changed.
+fn ui_effect_chain(ui: &mut egui::Ui, effects: &mut Vec<EffectKind>) -> bool {
+    // Swaps the effect at the index with the one before it
+    let mut move_up = None;
+    let mut remove = None;
+    for (idx, kind) in effects.iter().enumerate() {
+        ui.horizontal(|ui| {
+            ui.label(format!("{}. {}", idx + 1, kind))
+                .on_hover_text(kind.description());
+            if ui
+                .add_enabled(idx > 0, egui::Button::new("⬆"))
+                .on_hover_text("Run earlier")
+                .clicked()
+            {
+                move_up = Some(idx);
+            }
+            if ui
+                .add_enabled(idx + 1 < effects.len(), egui::Button::new("⬇"))
+                .on_hover_text("Run later")
+                .clicked()
+            {
+                move_up = Some(idx + 1);
+            }
+            if ui
+                .button("✖")
+                .on_hover_text("Take out of the chain")
+                .clicked()
+            {
+                remove = Some(idx);
+            }
+        });
+    }
+    let missing: Vec<EffectKind> = EffectKind::ALL
+        .into_iter()
+        .filter(|kind| !effects.contains(kind))
+        .collect();
+    let mut add = None;
+    ui.add_enabled_ui(!missing.is_empty(), |ui| {
+        egui::ComboBox::from_id_salt("add_effect")
+            .selected_text("Add effect")
+            .show_ui(ui, |ui| {
+                for kind in missing {
+                    if ui
+                        .selectable_label(false, kind.to_string())
+                        .on_hover_text(kind.description())
+                        .clicked()
+                    {
+                        add = Some(kind);
+                    }
+                }
+            });
+    });
+
+    if let Some(idx) = move_up {
+        effects.swap(idx - 1, idx);
+    }
+    if let Some(idx) = remove {
+        effects.remove(idx);
+    }
+    if let Some(kind) = add {
+        effects.push(kind);
+    }
+    move_up.is_some() || remove.is_some() || add.is_some()
+}
+
+/// One row of the mixer grid, returning whether anything changed.
+fn ui_mixer_channel(ui: &mut egui::Ui, name: &str, channel: &mut MixerChannel) -> bool {
+    ui.label(name);
+    let mut changed = ui
+        .add(egui::Slider::new(&mut channel.volume, 0.0..=1.0).show_value(false))
+        .on_hover_text("Volume")
+        .changed();
+    changed |= ui
+        .add(
+            egui::Slider::new(&mut channel.pan, 0.0..=1.0)
+                .show_value(false)
+                .text("L/R"),
+        )
+        .on_hover_text("Pan")
+        .changed();
+    changed |= ui
+        .toggle_value(&mut channel.mute, "M")
+        .on_hover_text("Mute")
+        .changed();
+    changed |= ui
+        .toggle_value(&mut channel.solo, "S")
+        .on_hover_text("Solo")
+        .changed();
+    changed
+}
+
+fn paint_tab_shapes(painter: &egui::Painter, shapes: &[TabShape], family: &egui::FontFamily) {
+    let shapes: Vec<_> = painter.ctx().fonts(|fonts| {
+        shapes
+            .iter()
+            .map(|shape| to_shape(fonts, shape, family))
+            .collect()
+    });
+    painter.extend(shapes);
+}
+
+/// A side panel section that can be folded away, open at first.
+fn panel_section(ui: &mut egui::Ui, title: &str, add_contents: impl FnOnce(&mut egui::Ui)) {
+    egui::CollapsingHeader::new(RichText::new(title).heading())
+        .id_salt(title)
+        .default_open(true)
+        .show(ui, add_contents);
+}
+
+/// Profile name as listed in the guitar picker, marking tweaked profiles.
+fn profile_name(config: &GuitarConfig) -> String {
+    if config.is_factory() {
+        config.name.to_string()
+    } else {
+        format!("{} (modified)", config.name)
+    }
+}
+
 impl TabApp {
     pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
         let configs = Configs::new();
@@ -127,13 +857,21 @@ impl TabApp {
             Some(path) => Score::parse_from_musicxml(path).ok(),
             None => None,
         };
-        let renderer = Renderer::new(configs.measures_per_row, configs.dashes_per_division);
+        let mut renderer = Renderer::new(configs.measures_per_row, configs.dashes_per_division);
+        renderer.left_handed = configs.left_handed;
+        renderer.style = configs.tab_style;
+        renderer.capo_fret = configs.guitar_configs[configs.active_guitar].capo_fret;
 
         let audio_player_configs = configs.guitar_configs[configs.active_guitar].clone();
-        let audio_player = AudioPlayer::new(audio_player_configs);
+        let mut audio_player = AudioPlayer::new(audio_player_configs);
+        configs.mixer.apply(&mut audio_player);
 
         let score_channel = channel();
-        Self {
+        let mut app = Self {
+            score_arrangement: ScoreArrangement::open(score.as_ref()),
+            score_revision: 0,
+            timeline: score.as_ref().map(Timeline::from_score).unwrap_or_default(),
+            tab_cache: RefCell::new(None),
             score,
             renderer,
             is_playing: false,
@@ -143,7 +881,60 @@ impl TabApp {
             current_notes: None,
             audio_player,
             is_match: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            listener: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            score_watcher: None,
+            side_panel_open: true,
+            #[cfg(not(target_arch = "wasm32"))]
+            listener_error: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            loopback_test: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            loopback_report: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            midi_clock: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            wait_for_notes: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            practice_play_notes: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            hint_missed_notes: true,
+            #[cfg(not(target_arch = "wasm32"))]
+            miss_hint: MissHint::default(),
+            setup_prompt: None,
+            tuned_to: OPEN_STRING_MIDI,
+            #[cfg(not(target_arch = "wasm32"))]
+            tuner_open: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            tuner_frequency: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            waiting_at: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            error_recording: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            error_map: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            show_error_map: true,
+            #[cfg(not(target_arch = "wasm32"))]
+            midi_clock_error: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            midi_output: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            midi_output_error: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            midi_keyboard: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            midi_keyboard_error: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            midi_keyboard_note: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            clock_stopped_at: None,
+            similarity: 0.0,
             plot_length: 2048,
+            oscilloscope_trigger: true,
+            plot_history_seconds: 1.0,
+            plots_frozen: false,
             plot_frequency_range: (50, 7500),
             score_channel,
             playback_start_time: None,
@@ -154,34 +945,180 @@ impl TabApp {
             last_played_measure_index: None,
             last_played_division_index: None,
             show_about: true,
+            backing_track: None,
+            backing_track_channel: channel(),
+            impulse_response_channel: channel(),
+            processing_channel: channel(),
+            processing_in_progress: None,
+            beat_detection_channel: channel(),
+            detecting_beats: false,
+            proposed_alignment: None,
+            section_detection_channel: channel(),
+            detecting_sections: false,
+            suggested_sections: Vec::new(),
+            current_chord: None,
+            diagnostics: Diagnostics::default(),
+            setlist: Setlist::new(),
+            scroll_to_measure: None,
+            go_to_measure: None,
+            excerpt_measures: (1, 1),
+            fingering_choices: BTreeMap::new(),
+            flag_note: None,
+            chord_progression: Vec::new(),
+            progression_key: None,
+            warmed_up_score: None,
+            capo_suggestions: None,
+            capo_confirmation: None,
+            metronome: Metronome::new(),
+            last_beat: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            output_devices: Vec::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            input_devices: Vec::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            metronome_error: None,
+            scheduled_until: None,
+            beat_flashed_at: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            rumble: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            rumble_error: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            output_device_error: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            input_monitor: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            monitor_error: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            input_capture: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            spectrogram: Spectrogram::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            capture_error: None,
+            annotation_store: AnnotationStore::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            annotations_read_only: false,
+            annotation_channel: channel(),
+            annotation_draft: AnnotationDraft {
+                measure: 1,
+                string: 1,
+                finger: 1,
+                loop_end: 1,
+                ..Default::default()
+            },
+            annotation_message: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            library: Library::load(std::path::Path::new(LIBRARY_FILE)),
+            #[cfg(target_arch = "wasm32")]
+            library: Library::default(),
+            library_channel: channel(),
+            library_query: String::new(),
+            library_max_difficulty: MAX_DIFFICULTY,
+            library_tag_filter: BTreeSet::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            library_folder: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            library_folder_channel: channel(),
+            #[cfg(not(target_arch = "wasm32"))]
+            library_folder_selected: None,
+            editing_tags: None,
+            setlist_channel: channel(),
+            print_mode: false,
+            performance_mode: false,
+            position_report: PositionReport::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            position_server: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            media_keys: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            media_keys_error: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            media_paused_at: None,
+            #[cfg(target_arch = "wasm32")]
+            print_pending_frames: 0,
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        app.load_annotations();
+        app.update_chord_progression();
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(folder) = app.library.folder.clone() {
+            app.scan_library_folder(&folder);
+        }
+
+        // Shared links open the score they point to instead of the default one
+        #[cfg(target_arch = "wasm32")]
+        if let Some(url) = score_url_from_query() {
+            app.fetch_score(url, &_cc.egui_ctx);
         }
+
+        app
+    }
+
+    /// Downloads and loads the MusicXML at `url`, keeping the current score if that fails.
+    #[cfg(target_arch = "wasm32")]
+    fn fetch_score(&self, url: String, ctx: &egui::Context) {
+        let sender = self.score_channel.0.clone();
+        let ctx = ctx.clone();
+        execute(async move {
+            match fetch_text(&url).await {
+                Ok(xml) => match Score::parse_from_musicxml_str(&xml) {
+                    Ok(score) => {
+                        let _ = sender.send(score);
+                    }
+                    Err(e) => log::error!("Failed to parse score from {}: {}", url, e),
+                },
+                Err(e) => log::error!("Failed to fetch score from {}: {}", url, e),
+            }
+            ctx.request_repaint();
+        });
     }
 
     fn update_playback(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(held) = self.waiting_at {
+            // The listener hearing the hint isn't the player getting the notes right
+            if self.is_match && !self.miss_hint.is_hinting(Instant::now()) {
+                self.waiting_at = None;
+            }
+            // Time stands still until the notes are heard, then carries on from the division
+            self.playback_start_time = Some(Instant::now() - Duration::from_secs_f32(held));
+        }
         if let Some(playback_start_time) = self.playback_start_time {
             let elapsed = playback_start_time.elapsed().as_secs_f32();
             self.current_time = elapsed;
+            let scheduled = self.schedule_notes_ahead(elapsed);
+            // Pulsed once the score isn't borrowed anymore
+            let mut pulse = None;
 
             if let Some(score) = &self.score {
                 let seconds_per_beat = 60.0 / self.tempo as f32;
-                let seconds_per_division = seconds_per_beat / score.divisions_per_quarter as f32;
-                let total_divisions_passed = (elapsed / seconds_per_division) as usize;
-
-                let mut divisions_accum = 0;
-                let mut measure_found = false;
-                for (measure_idx, measure) in score.measures.iter().enumerate() {
-                    let measure_divisions = measure.positions.len();
-                    if divisions_accum + measure_divisions > total_divisions_passed {
-                        self.current_measure_index = measure_idx;
-                        self.current_division_index = total_divisions_passed - divisions_accum;
-                        measure_found = true;
-                        break;
-                    } else {
-                        divisions_accum += measure_divisions;
-                    }
+                let seconds_per_division = self.timeline.seconds_per_tick(self.tempo);
+                let tick = (elapsed / seconds_per_division) as usize;
+
+                let time_signature = &score.time_signature;
+                let seconds_per_click =
+                    seconds_per_beat * 4.0 / time_signature.beat_value.max(1) as f32;
+                let beat = (elapsed / seconds_per_click) as usize;
+                if self.last_beat != Some(beat) {
+                    self.last_beat = Some(beat);
+                    let cue =
+                        if beat.is_multiple_of(time_signature.beats_per_measure.max(1) as usize) {
+                            Cue::Accent
+                        } else {
+                            Cue::Beat
+                        };
+                    self.metronome.play(cue, &mut self.audio_player);
+                    pulse = Some(cue == Cue::Accent);
+                }
+
+                let position = self.timeline.position_at(tick);
+                if let Some((measure, division)) = position {
+                    self.current_measure_index = measure;
+                    self.current_division_index = division;
                 }
 
-                if measure_found {
+                if position.is_some() {
                     // Check if we've moved to a new division
                     if Some(self.current_measure_index) != self.last_played_measure_index
                         || Some(self.current_division_index) != self.last_played_division_index
@@ -190,10 +1127,40 @@ impl TabApp {
                         if self.current_division_index < measure.positions.len() {
                             let notes = measure.positions[self.current_division_index].clone();
 
-                            if !notes.is_empty() {
-                                let duration = seconds_per_division * notes[0].duration as f32;
-                                self.audio_player.play_notes(&notes, duration);
+                            let symbol = measure
+                                .chord_symbols
+                                .iter()
+                                .find(|(division, _)| *division == self.current_division_index)
+                                .map(|(_, name)| name.clone());
+                            if let Some(shape) = ChordShape::from_notes(&notes) {
+                                if symbol.is_some() || shape.played_strings() >= 3 {
+                                    self.current_chord = Some((shape, symbol));
+                                }
+                            }
 
+                            if let Some((struck, divisions)) = self.timeline.struck_at(tick) {
+                                let duration = seconds_per_division * divisions as f32;
+                                #[cfg(not(target_arch = "wasm32"))]
+                                let play_notes = !self.wait_for_notes || self.practice_play_notes;
+                                #[cfg(target_arch = "wasm32")]
+                                let play_notes = true;
+                                if play_notes && !scheduled {
+                                    self.strike_notes(&struck, duration, None);
+                                }
+                                #[cfg(not(target_arch = "wasm32"))]
+                                if let Some(errors) = &mut self.error_recording {
+                                    errors.expect(self.current_measure_index);
+                                }
+                                #[cfg(not(target_arch = "wasm32"))]
+                                if self.wait_for_notes {
+                                    self.waiting_at = Some(tick as f32 * seconds_per_division);
+                                    self.miss_hint.reset();
+                                    // The previous notes still ringing must not count
+                                    self.similarity = 0.0;
+                                    self.is_match = false;
+                                }
+                            }
+                            if !notes.is_empty() {
                                 self.previous_notes = self.current_notes.take();
                                 self.current_notes = Some(notes.clone());
                             }
@@ -204,9 +1171,189 @@ impl TabApp {
                     }
                 } else {
                     self.stop_playback();
+                    if let Some(score) = self.warmed_up_score.take() {
+                        self.set_score(score);
+                        self.start_playback();
+                    } else {
+                        self.setlist.score_finished();
+                    }
+                }
+            }
+            if let Some(accent) = pulse {
+                self.pulse_beat(accent);
+            }
+        }
+    }
+
+    /// Hands the audio thread the notes struck within [`SCHEDULE_AHEAD_SECONDS`] of `elapsed`,
+    /// each with its exact start, so their timing doesn't depend on when frames are drawn.
+    /// Returns false in practice mode, which strikes the notes as it goes since it holds time
+    /// until they are heard.
+    fn schedule_notes_ahead(&mut self, elapsed: f32) -> bool {
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.wait_for_notes {
+            self.unschedule_notes();
+            return false;
+        }
+        let seconds_per_division = self.timeline.seconds_per_tick(self.tempo);
+        let from = match self.scheduled_until {
+            Some(tick) => tick,
+            None => {
+                self.audio_player.set_transport(Some(elapsed));
+                let tick = (elapsed / seconds_per_division) as usize;
+                // The division being played may have been struck already
+                let struck = self
+                    .last_played_measure_index
+                    .zip(self.last_played_division_index);
+                if struck.is_some() && self.timeline.position_at(tick) == struck {
+                    tick + 1
+                } else {
+                    tick
                 }
             }
+        };
+        let until = ((elapsed + SCHEDULE_AHEAD_SECONDS) / seconds_per_division) as usize + 1;
+        let until = until.min(self.timeline.total_ticks).max(from);
+        for tick in from..until {
+            if let Some((struck, divisions)) = self.timeline.struck_at(tick) {
+                self.strike_notes(
+                    &struck,
+                    seconds_per_division * divisions as f32,
+                    Some(tick as f32 * seconds_per_division),
+                );
+            }
+        }
+        self.scheduled_until = Some(until);
+        true
+    }
+
+    /// Strikes `notes` for `duration` seconds, at `seconds` into the transport or at once for
+    /// None. A MIDI output gets them too and takes them from the synth unless it plays along.
+    fn strike_notes(&mut self, notes: &[Note], duration: f32, seconds: Option<f32>) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(output) = &self.midi_output {
+            let at = match (seconds, self.playback_start_time) {
+                (Some(seconds), Some(start)) => start + Duration::from_secs_f32(seconds),
+                _ => Instant::now(),
+            };
+            let capo_fret = self.configs.guitar_configs[self.configs.active_guitar].capo_fret;
+            output.send_notes(notes, capo_fret, duration, at);
+            if !self.configs.synth_with_midi_output {
+                return;
+            }
+        }
+        match seconds {
+            Some(seconds) => self.audio_player.schedule_notes(notes, duration, seconds),
+            None => self.audio_player.play_notes(notes, duration),
+        }
+    }
+
+    /// Drops the notes scheduled but not started, for when the transport stops, jumps or changes
+    /// tempo. Scheduling starts over from the playback position on the next update.
+    fn unschedule_notes(&mut self) {
+        if self.scheduled_until.take().is_some() {
+            self.audio_player.set_transport(None);
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(output) = &self.midi_output {
+                output.unschedule();
+            }
+        }
+    }
+
+    /// Continues playback from the start of `division` in `measure`, starting it if needed. The
+    /// notes still ringing fade out, and the division sought to is struck on the next update as
+    /// if playback had just arrived there.
+    pub fn seek_to(&mut self, measure: usize, division: usize) {
+        if !self.is_playing {
+            self.start_playback();
+        }
+        let Some(score) = &self.score else {
+            return;
+        };
+        if !self.is_playing || measure >= score.measures.len() {
+            return;
+        }
+        let division = division.min(score.measures[measure].positions.len().saturating_sub(1));
+        let seconds = self.timeline.tick_of(measure, division) as f32
+            * self.timeline.seconds_per_tick(self.tempo);
+
+        self.unschedule_notes();
+        self.audio_player.release_notes();
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(output) = &self.midi_output {
+            output.release();
+        }
+        self.playback_start_time = Some(Instant::now() - Duration::from_secs_f32(seconds));
+        self.current_time = seconds;
+        self.current_measure_index = measure;
+        self.current_division_index = division;
+        // Nothing has been played at the new position yet
+        self.last_played_measure_index = None;
+        self.last_played_division_index = None;
+        self.previous_notes = None;
+        self.current_notes = None;
+        self.last_beat = None;
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            // Waiting for the notes of the old position would hold playback there
+            self.waiting_at = None;
+            self.miss_hint.reset();
+        }
+        self.scroll_to_measure = Some(measure);
+        self.play_backing_track_from(seconds);
+    }
+
+    /// Plays the backing track aligned to `seconds` into the score at the playback tempo.
+    fn play_backing_track_from(&mut self, seconds: f32) {
+        let processing = self.backing_track_processing();
+        if let Some(track) = &self.backing_track {
+            if let Some(frames) = track.frames_for(&processing) {
+                self.audio_player.play_backing_track(
+                    frames,
+                    track.sample_rate,
+                    track.offset_seconds / processing.rate + seconds,
+                );
+            }
+        }
+    }
+
+    /// What the score asks of the guitar that it isn't set up with, if anything.
+    fn missing_setup(&self) -> Option<GuitarSetup> {
+        let score = self.score.as_ref()?;
+        let capo_fret = self.configs.guitar_configs[self.configs.active_guitar].capo_fret;
+        let setup = GuitarSetup {
+            capo: score.capo.filter(|&capo| capo != capo_fret),
+            tuning: score.tuning.filter(|&tuning| tuning != self.tuned_to),
+        };
+        (setup.capo.is_some() || setup.tuning.is_some()).then_some(setup)
+    }
+
+    /// Starts playback, first asking the player to set up the guitar if the score names a capo
+    /// or tuning it isn't set up with, so they don't practice in the wrong one.
+    fn request_playback(&mut self) {
+        match self.missing_setup() {
+            Some(setup) => self.setup_prompt = Some(setup),
+            None => self.start_playback(),
+        }
+    }
+
+    /// Puts the capo of `setup` on the active guitar and takes its tuning as the one tuned to.
+    fn apply_setup(&mut self, setup: GuitarSetup) {
+        if let Some(capo) = setup.capo {
+            self.configs.guitar_configs[self.configs.active_guitar].capo_fret = capo;
+            self.apply_guitar_config();
         }
+        if let Some(tuning) = setup.tuning {
+            self.tuned_to = tuning;
+        }
+    }
+
+    /// Hands the settings of the active guitar to the renderer and the audio player.
+    fn apply_guitar_config(&mut self) {
+        let active_guitar_config = self.configs.guitar_configs[self.configs.active_guitar].clone();
+        self.renderer.capo_fret = active_guitar_config.capo_fret;
+        self.audio_player.update_configs(active_guitar_config);
+        self.update_chord_progression();
     }
 
     fn start_playback(&mut self) {
@@ -222,6 +1369,8 @@ impl TabApp {
             }
 
             self.is_playing = true;
+            self.scheduled_until = None;
+            self.metronome.play(Cue::Start, &mut self.audio_player);
             self.playback_start_time = Some(Instant::now());
             self.current_time = 0.0;
             self.current_measure_index = 0;
@@ -233,869 +1382,5163 @@ impl TabApp {
             } else {
                 score.tempo
             };
+
+            #[cfg(not(target_arch = "wasm32"))]
+            if self.listener.is_some() {
+                self.error_recording = Some(ErrorMap::new(score.measures.len()));
+            }
+
+            self.play_backing_track_from(0.0);
         }
     }
 
-    fn stop_playback(&mut self) {
-        if self.is_playing {
-            self.is_playing = false;
-            self.playback_start_time = None;
-            self.current_time = 0.0;
-            self.current_measure_index = 0;
-            self.current_division_index = 0;
-            self.current_notes = None;
-            self.previous_notes = None;
-            self.is_match = false;
-            self.last_played_measure_index = None;
-            self.last_played_division_index = None;
+    /// Speed of the backing track relative to the recording, so that it matches the playback
+    /// tempo. Recordings without a tempo are assumed to follow the score's tempo.
+    fn backing_track_rate(&self) -> f32 {
+        let (Some(score), Some(track)) = (&self.score, &self.backing_track) else {
+            return 1.0;
+        };
+        let playback_tempo = if self.configs.use_custom_tempo {
+            self.configs.custom_tempo
+        } else {
+            score.tempo
+        };
+        let recorded_tempo = track.tempo.unwrap_or(score.tempo as f32);
+        if recorded_tempo > 0.0 && playback_tempo > 0 {
+            playback_tempo as f32 / recorded_tempo
+        } else {
+            1.0
         }
     }
-    fn render_plots(&mut self, ui: &mut egui::Ui) {
-        // Constants
-        let sample_rate = self.audio_player.sample_rate as f64;
-        let output_signal = &self.audio_player.output_signal;
-
-        // Limit the number of samples to plot
-        let max_samples = 2048;
-        let num_samples = output_signal.len().min(max_samples);
-        let start_index = output_signal.len().saturating_sub(max_samples);
 
-        if num_samples > 0 {
-            // Time Domain Plot
-            ui.heading("Time Domain");
-            let time_points: PlotPoints = (start_index..output_signal.len())
-                .map(|i| {
-                    let time = i as f64 / sample_rate;
-                    let sample = output_signal[i] as f64;
-                    [time, sample]
-                })
-                .collect();
+    /// Tempo and key changes the backing track has to follow to stay in sync with the score.
+    fn backing_track_processing(&self) -> TrackProcessing {
+        let rate = if self.configs.stretch_backing_track {
+            self.backing_track_rate()
+        } else {
+            1.0
+        };
+        let semitones = if self.configs.pitch_shift_backing_track {
+            self.configs.guitar_configs[self.configs.active_guitar].capo_fret as i32
+        } else {
+            0
+        };
+        TrackProcessing { rate, semitones }
+    }
 
-            let line = Line::new(time_points);
-            Plot::new("Time Domain")
-                .view_aspect(2.0)
-                .include_y(-1.0)
-                .include_y(1.0)
-                .show(ui, |plot_ui| {
-                    plot_ui.line(line);
-                });
+    /// Starts re-rendering the backing track whenever the tempo or capo no longer matches.
+    fn update_backing_track_processing(&mut self) {
+        let in_progress = |job: &Option<ProcessingJob>| job.as_ref().map(|job| job.processing);
+        if let Ok((processing, frames)) = self.processing_channel.1.try_recv() {
+            // Anything else was cancelled just as it finished, or rendered for a removed track
+            if in_progress(&self.processing_in_progress) == Some(processing) {
+                self.processing_in_progress = None;
+                if let Some(track) = &mut self.backing_track {
+                    track.processed = Some((processing, frames));
+                }
+            }
+        }
 
-            // Frequency Domain Plot
-            ui.heading("Frequency Domain");
-            let fft_size = num_samples.next_power_of_two();
-            let mut planner = FftPlanner::<f32>::new();
-            let fft = planner.plan_fft_forward(fft_size);
+        let processing = self.backing_track_processing();
+        let Some(track) = &self.backing_track else {
+            return;
+        };
+        if track.frames_for(&processing).is_some() {
+            // Back at a tempo that is ready, whatever was rendering isn't needed anymore
+            self.processing_in_progress = None;
+            return;
+        }
+        if in_progress(&self.processing_in_progress) == Some(processing) {
+            return;
+        }
 
-            // Prepare the buffer for FFT
-            let mut buffer: Vec<Complex32> = output_signal[start_index..]
-                .iter()
-                .map(|&sample| Complex32::new(sample, 0.0))
-                .collect();
+        // Replacing the job cancels the one rendering for an earlier tempo or capo
+        let job = ProcessingJob::new(processing);
+        let cancelled = job.cancelled();
+        self.processing_in_progress = Some(job);
+        let frames = track.frames.clone();
+        let sender = self.processing_channel.0.clone();
+        execute(async move {
+            if let Some(processed) = process_track(&frames, &processing, &cancelled) {
+                let _ = sender.send((processing, Arc::from(processed.into_boxed_slice())));
+            }
+        });
+    }
 
-            // Zero-pad if necessary
-            buffer.resize(fft_size, Complex32::new(0.0, 0.0));
-
-            // Perform the FFT
-            fft.process(&mut buffer);
-
-            // Compute magnitude spectrum
-            let freq_points: PlotPoints = buffer[..fft_size / 2]
-                .iter()
-                .enumerate()
-                .map(|(i, c)| {
-                    let freq = i as f64 * sample_rate / fft_size as f64;
-                    let magnitude = c.norm() as f64;
-                    [freq, magnitude]
-                })
-                .collect();
+    /// Publishes the playback position through the window title and the position server.
+    fn update_position_report(&mut self, ctx: &egui::Context) {
+        let report = match &self.score {
+            Some(score) => PositionReport::new(
+                score,
+                self.is_playing,
+                self.current_measure_index,
+                self.current_division_index,
+            ),
+            None => PositionReport::default(),
+        };
+        if report == self.position_report {
+            return;
+        }
 
-            let line = Line::new(freq_points);
-            Plot::new("Frequency Domain")
-                .view_aspect(2.0)
-                .include_y(0.0)
-                .show(ui, |plot_ui| {
-                    plot_ui.line(line);
-                });
-        } else {
-            ui.label("No data to display.");
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(report.window_title()));
+            if let Some(server) = &self.position_server {
+                server.update(&report);
+            }
+            if let Some(media_keys) = &mut self.media_keys {
+                let title = self
+                    .score
+                    .as_ref()
+                    .and_then(|score| score.title.as_deref())
+                    .unwrap_or(APP_TITLE);
+                media_keys.update(title, report.playing);
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = ctx;
+            if let Some(document) = web_sys::window().and_then(|w| w.document()) {
+                document.set_title(&report.window_title());
+            }
         }
+        self.position_report = report;
     }
-    fn render_tab(&self, painter: &egui::Painter, rect: egui::Rect) {
-        // Constants for rendering
-        let num_strings = 6;
-        let string_spacing = 20.0; // pixels between strings
-        let note_spacing = 10.0; // base pixels between dashes
-        let measure_spacing = 10.0; // spacing between measures
-        let row_spacing = 50.0; // vertical spacing between rows
-
-        if let Some(score) = &self.score {
-            let total_measures = score.measures.len();
-            let measures_per_row = self.configs.measures_per_row;
-            let total_rows = (total_measures + measures_per_row - 1) / measures_per_row;
-
-            // Start drawing at rect.min, which already includes the padding
-            let mut y_offset = rect.min.y;
 
-            for row in 0..total_rows {
-                let measures_in_row = if (row + 1) * measures_per_row <= total_measures {
-                    measures_per_row
-                } else {
-                    total_measures % measures_per_row
-                };
-
-                // Calculate the total width of the current row
-                let row_width = self.calculate_row_width(
-                    &score,
-                    row,
-                    measures_in_row,
-                    note_spacing,
-                    measure_spacing,
-                );
+    #[cfg(not(target_arch = "wasm32"))]
+    fn ui_position_server(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui
+                .checkbox(&mut self.configs.serve_position, "Serve position on port")
+                .changed()
+            {
+                self.position_server = None;
+                if self.configs.serve_position {
+                    match PositionServer::start(self.configs.position_port) {
+                        Ok(server) => {
+                            server.update(&self.position_report);
+                            self.position_server = Some(server);
+                        }
+                        Err(e) => {
+                            eprintln!("Failed to start position server: {}", e);
+                            self.configs.serve_position = false;
+                        }
+                    }
+                }
+            }
+            ui.add_enabled(
+                !self.configs.serve_position,
+                egui::DragValue::new(&mut self.configs.position_port).range(1024..=65535),
+            );
+        });
+        if let Some(server) = &self.position_server {
+            ui.hyperlink(format!("http://127.0.0.1:{}/json", server.port));
+        }
+    }
 
-                // Draw strings (horizontal lines) for the current row
-                self.draw_strings(
-                    painter,
-                    rect.min.x, // Start from rect.min.x, padding is already included
-                    y_offset,
-                    row_width,
-                    num_strings,
-                    string_spacing,
-                );
+    /// Changes the tempo, keeping the playback position if playing.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn set_playback_tempo(&mut self, tempo: usize) {
+        self.configs.use_custom_tempo = true;
+        self.configs.custom_tempo = tempo;
+        if !self.is_playing || tempo == self.tempo {
+            return;
+        }
+        // The same point in the score is further in or closer at the new tempo
+        let seconds = self.current_time * self.tempo as f32 / tempo as f32;
+        self.unschedule_notes();
+        self.playback_start_time = Some(Instant::now() - Duration::from_secs_f32(seconds));
+        self.current_time = seconds;
+        self.tempo = tempo;
+    }
 
-                // Adjust x_offset to start from rect.min.x
-                let mut x_offset = rect.min.x;
-                for measure_idx_in_row in 0..measures_in_row {
-                    let measure_idx = row * measures_per_row + measure_idx_in_row;
-                    let measure = &score.measures[measure_idx];
-
-                    // Determine if we need to draw the starting vertical line
-                    let draw_start_line = measure_idx_in_row == 0;
-
-                    // Draw measure
-                    self.draw_measure(
-                        painter,
-                        measure,
-                        x_offset,
-                        y_offset,
-                        num_strings,
-                        string_spacing,
-                        note_spacing,
-                        draw_start_line,
-                    );
+    /// Plucks the keys pressed on the MIDI controller with the active guitar.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_midi_keyboard(&mut self) {
+        let Some(keyboard) = &self.midi_keyboard else {
+            return;
+        };
+        let capo_fret = self.configs.guitar_configs[self.configs.active_guitar].capo_fret;
+        for press in keyboard.presses() {
+            let Some(note) = note_for_key(press.key, capo_fret) else {
+                continue;
+            };
+            self.audio_player
+                .pluck(&note, MIDI_KEY_SECONDS, press.velocity);
+            self.midi_keyboard_note = Some(note);
+        }
+    }
 
-                    // Move x_offset to the end of the measure
-                    let total_dashes = measure.positions.len() * self.configs.dashes_per_division;
-                    x_offset += total_dashes as f32 * note_spacing + measure_spacing;
+    /// Follows the start, stop and tempo of the MIDI clock being received.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_midi_clock(&mut self) {
+        let Some(clock) = &self.midi_clock else {
+            return;
+        };
+        for event in clock.events() {
+            match event {
+                ClockEvent::Start => {
+                    self.stop_playback();
+                    self.start_playback();
+                }
+                ClockEvent::Continue => match self.clock_stopped_at.take() {
+                    Some((measure, division)) => self.seek_to(measure, division),
+                    None => self.start_playback(),
+                },
+                ClockEvent::Stop => {
+                    if self.is_playing {
+                        self.clock_stopped_at =
+                            Some((self.current_measure_index, self.current_division_index));
+                    }
+                    self.stop_playback();
+                }
+                ClockEvent::Tempo(bpm) => {
+                    // Only whole steps, so that jitter around x.5 doesn't flip back and forth
+                    let current = self.configs.custom_tempo as f32;
+                    if !self.configs.use_custom_tempo || (bpm - current).abs() >= 1.0 {
+                        self.set_playback_tempo((bpm.round() as usize).clamp(1, 300));
+                    }
                 }
-
-                y_offset += num_strings as f32 * string_spacing + row_spacing;
             }
+        }
+    }
 
-            // Draw the playback position indicator (if applicable)
-            if self.is_playing {
-                self.draw_playback_indicator(
-                    painter,
-                    rect.min.x,
-                    rect.min.y,
-                    self.current_measure_index,
-                    self.current_division_index,
-                    measures_per_row,
-                    &score,
-                    num_strings,
-                    string_spacing,
-                    note_spacing,
-                    measure_spacing,
-                    row_spacing,
-                );
+    /// Follows the play/pause, stop and skip keys of the keyboard or a headset.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_media_keys(&mut self) {
+        let Some(media_keys) = &self.media_keys else {
+            return;
+        };
+        for key in media_keys.events() {
+            let pause = match key {
+                MediaKey::Play => false,
+                MediaKey::Pause => true,
+                MediaKey::Toggle => self.is_playing,
+                MediaKey::Stop => {
+                    self.media_paused_at = None;
+                    self.stop_playback();
+                    continue;
+                }
+                MediaKey::Next | MediaKey::Previous => {
+                    let Some(score) = &self.score else {
+                        continue;
+                    };
+                    let sections: Vec<usize> = score.sections.iter().map(|s| s.measure).collect();
+                    if let Some(measure) = skip_target(
+                        &sections,
+                        score.measures.len(),
+                        self.current_measure_index,
+                        key,
+                    ) {
+                        self.seek_to(measure, 0);
+                    }
+                    continue;
+                }
+            };
+            if pause && self.is_playing {
+                self.media_paused_at =
+                    Some((self.current_measure_index, self.current_division_index));
+                self.stop_playback();
+            } else if !pause && !self.is_playing {
+                match self.media_paused_at.take() {
+                    Some((measure, division)) => self.seek_to(measure, division),
+                    None => self.request_playback(),
+                }
             }
         }
     }
 
-    /// Calculates the total width of a row of measures.
-    fn calculate_row_width(
-        &self,
-        score: &Score,
-        row: usize,
-        measures_in_row: usize,
-        note_spacing: f32,
-        measure_spacing: f32,
-    ) -> f32 {
-        let measures_per_row = self.configs.measures_per_row;
-        let dashes_per_division = self.configs.dashes_per_division;
-        let mut row_width = 0.0;
-
-        for measure_idx_in_row in 0..measures_in_row {
-            let measure_idx = row * measures_per_row + measure_idx_in_row;
-            let measure = &score.measures[measure_idx];
-            let total_divisions = measure.positions.len();
-            let total_dashes = total_divisions * dashes_per_division;
-            row_width += total_dashes as f32 * note_spacing + measure_spacing;
-        }
-        // Subtract the extra measure_spacing added after the last measure
-        row_width -= measure_spacing;
-
-        row_width
-    }
-
-    /// Draws the strings for a row.
-    fn draw_strings(
-        &self,
-        painter: &egui::Painter,
-        x_start: f32,
-        y_offset: f32,
-        row_width: f32,
-        num_strings: usize,
-        string_spacing: f32,
-    ) {
-        for string_idx in 0..num_strings {
-            let y = y_offset + string_spacing * (string_idx as f32 + 1.0);
-            painter.line_segment(
-                [egui::pos2(x_start, y), egui::pos2(x_start + row_width, y)],
-                egui::Stroke::new(1.0, egui::Color32::BLACK),
+    #[cfg(not(target_arch = "wasm32"))]
+    fn ui_media_keys(&mut self, ui: &mut egui::Ui) {
+        let response = ui
+            .checkbox(&mut self.configs.media_keys, "Media keys")
+            .on_hover_text(
+                "Play/pause from the keyboard or a headset, next and previous skip between \
+                 sections, or measures when there are none",
             );
+        if response.changed() {
+            self.media_keys = None;
+            self.media_keys_error = None;
+            self.media_paused_at = None;
+            if self.configs.media_keys {
+                match MediaKeys::start() {
+                    Ok(mut media_keys) => {
+                        let title = self
+                            .score
+                            .as_ref()
+                            .and_then(|score| score.title.as_deref())
+                            .unwrap_or(APP_TITLE);
+                        media_keys.update(title, self.is_playing);
+                        self.media_keys = Some(media_keys);
+                    }
+                    Err(e) => {
+                        self.media_keys_error = Some(format!("Media keys unavailable: {}", e));
+                        self.configs.media_keys = false;
+                    }
+                }
+            }
+        }
+        if let Some(error) = &self.media_keys_error {
+            ui.colored_label(egui::Color32::RED, error);
         }
     }
 
-    fn draw_measure(
-        &self,
-        painter: &egui::Painter,
-        measure: &Measure,
-        x_offset: f32,
-        y_offset: f32,
-        num_strings: usize,
-        string_spacing: f32,
-        note_spacing: f32,
-        draw_start_line: bool,
-    ) {
-        let dashes_per_division = self.configs.dashes_per_division;
-        let total_divisions = measure.positions.len();
-        let total_dashes = total_divisions * dashes_per_division;
-
-        // Draw vertical line at the start of the measure if needed
-        if draw_start_line {
-            painter.line_segment(
-                [
-                    egui::pos2(x_offset, y_offset + string_spacing),
-                    egui::pos2(x_offset, y_offset + string_spacing * (num_strings as f32)),
-                ],
-                egui::Stroke::new(1.0, egui::Color32::BLACK),
-            );
+    #[cfg(not(target_arch = "wasm32"))]
+    fn ui_midi_clock(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Follow MIDI clock:");
+            let selected = self
+                .midi_clock
+                .as_ref()
+                .map_or("Off".to_string(), |clock| clock.port_name.clone());
+            egui::ComboBox::from_id_salt("midi_clock_input")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    if ui
+                        .selectable_label(self.midi_clock.is_none(), "Off")
+                        .clicked()
+                    {
+                        self.midi_clock = None;
+                        self.midi_clock_error = None;
+                    }
+                    for name in midi_input_names() {
+                        let active = self
+                            .midi_clock
+                            .as_ref()
+                            .is_some_and(|clock| clock.port_name == name);
+                        if ui.selectable_label(active, &name).clicked() && !active {
+                            // Close the old connection before opening the new one
+                            self.midi_clock = None;
+                            match MidiClock::connect(&name) {
+                                Ok(clock) => {
+                                    self.midi_clock = Some(clock);
+                                    self.midi_clock_error = None;
+                                }
+                                Err(e) => self.midi_clock_error = Some(e),
+                            }
+                        }
+                    }
+                });
+        });
+        if let Some(error) = &self.midi_clock_error {
+            ui.colored_label(egui::Color32::RED, error);
         }
+    }
 
-        // Store positions of notes for drawing techniques
-        let mut note_positions: Vec<(egui::Pos2, &Note)> = Vec::new();
-
-        // Draw notes and collect positions
-        for division_idx in 0..total_divisions {
-            let position_in_dashes = division_idx * dashes_per_division;
-            let x = x_offset + position_in_dashes as f32 * note_spacing;
-
-            // Draw notes in this division
-            for note in &measure.positions[division_idx] {
-                if let (Some(string), Some(fret)) = (note.string, note.fret) {
-                    let string_idx = string - 1;
-                    let y = y_offset + string_spacing * (string_idx as f32 + 1.0);
-
-                    // Draw the fret number at (x, y)
-                    let text = fret.to_string();
-                    painter.text(
-                        egui::pos2(x, y),
-                        egui::Align2::LEFT_CENTER,
-                        text,
-                        egui::FontId::monospace(14.0),
-                        egui::Color32::BLACK,
-                    );
-
-                    // Store the position and note
-                    note_positions.push((egui::pos2(x, y), note));
+    /// MIDI output for the notes of playback, the internal synth playing alone when off.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn ui_midi_output(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Send notes to MIDI:");
+            let selected = self
+                .midi_output
+                .as_ref()
+                .map_or("Off".to_string(), |output| output.port_name.clone());
+            egui::ComboBox::from_id_salt("midi_output")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    if ui
+                        .selectable_label(self.midi_output.is_none(), "Off")
+                        .clicked()
+                    {
+                        self.midi_output = None;
+                        self.midi_output_error = None;
+                    }
+                    for name in midi_output_names() {
+                        let active = self
+                            .midi_output
+                            .as_ref()
+                            .is_some_and(|output| output.port_name == name);
+                        if ui.selectable_label(active, &name).clicked() && !active {
+                            let channel = self.midi_output.take().map_or(0, |old| old.channel);
+                            match MidiOut::connect(&name) {
+                                Ok(mut output) => {
+                                    output.channel = channel;
+                                    self.midi_output = Some(output);
+                                    self.midi_output_error = None;
+                                }
+                                Err(e) => self.midi_output_error = Some(e),
+                            }
+                        }
+                    }
+                });
+            if let Some(output) = &mut self.midi_output {
+                let mut channel = output.channel + 1;
+                if ui
+                    .add(
+                        egui::DragValue::new(&mut channel)
+                            .range(1..=16)
+                            .prefix("Channel "),
+                    )
+                    .changed()
+                {
+                    output.channel = channel - 1;
                 }
+                ui.checkbox(
+                    &mut self.configs.synth_with_midi_output,
+                    "Play the synth too",
+                );
             }
+        });
+        if let Some(error) = &self.midi_output_error {
+            ui.colored_label(egui::Color32::RED, error);
         }
+    }
 
-        // After drawing notes, draw hammer-on and pull-off arcs
-        if note_positions.len() >= 2 {
-            for i in 0..note_positions.len() - 1 {
-                let (current_pos, current_note) = note_positions[i];
-                let (next_pos, next_note) = note_positions[i + 1];
-
-                // Only draw if the notes are on the same string and the next note has a technique
-                if current_note.string == next_note.string {
-                    match next_note.technique {
-                        Technique::HammerOn | Technique::PullOff => {
-                            // Draw an arc between current_pos and next_pos
-                            let control_point = egui::pos2(
-                                (current_pos.x + next_pos.x) / 2.0,
-                                current_pos.y - 20.0, // Adjust as needed
-                            );
-
-                            // Construct the QuadraticBezierShape
-                            let bezier = QuadraticBezierShape {
-                                points: [current_pos, control_point, next_pos],
-                                closed: false,
-                                fill: egui::Color32::TRANSPARENT,
-                                stroke: PathStroke::new(1.0, egui::Color32::BLACK),
-                            };
-
-                            // Add the shape to the painter
-                            painter.add(egui::Shape::QuadraticBezier(bezier));
-
-                            // Optionally, label the technique
-                            let label = match next_note.technique {
-                                Technique::HammerOn => "H",
-                                Technique::PullOff => "P",
-                                _ => "",
-                            };
-                            painter.text(
-                                egui::pos2(control_point.x, control_point.y - 5.0),
-                                egui::Align2::CENTER_BOTTOM,
-                                label,
-                                egui::FontId::monospace(12.0),
-                                egui::Color32::BLACK,
-                            );
+    #[cfg(not(target_arch = "wasm32"))]
+    fn ui_midi_keyboard(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Play from MIDI input:");
+            let selected = self
+                .midi_keyboard
+                .as_ref()
+                .map_or("Off".to_string(), |keyboard| keyboard.port_name.clone());
+            egui::ComboBox::from_id_salt("midi_keyboard_input")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    if ui
+                        .selectable_label(self.midi_keyboard.is_none(), "Off")
+                        .clicked()
+                    {
+                        self.midi_keyboard = None;
+                        self.midi_keyboard_error = None;
+                    }
+                    for name in midi_input_names() {
+                        let active = self
+                            .midi_keyboard
+                            .as_ref()
+                            .is_some_and(|keyboard| keyboard.port_name == name);
+                        if ui.selectable_label(active, &name).clicked() && !active {
+                            // Close the old connection before opening the new one
+                            self.midi_keyboard = None;
+                            match MidiKeyboard::connect(&name) {
+                                Ok(keyboard) => {
+                                    self.midi_keyboard = Some(keyboard);
+                                    self.midi_keyboard_error = None;
+                                }
+                                Err(e) => self.midi_keyboard_error = Some(e),
+                            }
                         }
-                        _ => {}
                     }
+                });
+            if self.midi_keyboard.is_some() {
+                if let Some(note) = &self.midi_keyboard_note {
+                    ui.label(note.to_string());
                 }
             }
+        });
+        if let Some(error) = &self.midi_keyboard_error {
+            ui.colored_label(egui::Color32::RED, error);
         }
-
-        // Draw vertical line at the end of the measure
-        let x_end = x_offset + total_dashes as f32 * note_spacing;
-        painter.line_segment(
-            [
-                egui::pos2(x_end, y_offset + string_spacing),
-                egui::pos2(x_end, y_offset + string_spacing * (num_strings as f32)),
-            ],
-            egui::Stroke::new(1.0, egui::Color32::BLACK),
-        );
     }
 
-    /// Draws the playback position indicator (red vertical line).
-    fn draw_playback_indicator(
-        &self,
-        painter: &egui::Painter,
-        x_start: f32,
-        y_start: f32,
-        current_measure: usize,
-        current_division: usize,
-        measures_per_row: usize,
-        score: &Score,
-        num_strings: usize,
-        string_spacing: f32,
-        note_spacing: f32,
-        measure_spacing: f32,
-        row_spacing: f32,
-    ) {
-        let dashes_per_division = self.configs.dashes_per_division;
-
-        let row = current_measure / measures_per_row;
-        let measure_idx_in_row = current_measure % measures_per_row;
-
-        // Recalculate x_offset to find the exact position of the current division
-        let mut x_offset = x_start;
-
-        // Add the widths of the previous measures in the row
-        for idx in 0..measure_idx_in_row {
-            let measure = &score.measures[row * measures_per_row + idx];
-            let total_divisions = measure.positions.len();
-            let total_dashes = total_divisions * dashes_per_division;
-            x_offset += total_dashes as f32 * note_spacing + measure_spacing;
+    /// Opens the score at `path` and reloads it whenever the file is saved.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn watch(mut self, path: PathBuf) -> Self {
+        match Score::parse_from_musicxml(&path) {
+            Ok(score) => self.set_score(score),
+            Err(e) => eprintln!("Failed to load {}: {}", path.display(), e),
         }
-
-        // Add the positions within the current measure
-        let x = x_offset + current_division as f32 * dashes_per_division as f32 * note_spacing;
-
-        let y_offset = y_start + row as f32 * (num_strings as f32 * string_spacing + row_spacing);
-        let y_top = y_offset + string_spacing;
-        let y_bottom = y_top + string_spacing * (num_strings as f32 - 1.0);
-
-        painter.line_segment(
-            [egui::pos2(x, y_top), egui::pos2(x, y_bottom)],
-            egui::Stroke::new(2.0, egui::Color32::RED),
-        );
+        self.score_watcher = Some(ScoreWatcher::new(path));
+        self
     }
 
-    fn update_display_metrics(&mut self) {
-        if let Some(score) = &self.score {
-            let cfg = &self.configs;
-            let seconds_per_beat = if cfg.use_custom_tempo {
-                60.0 / cfg.custom_tempo as f32
-            } else {
-                60.0 / score.tempo as f32
-            };
-            let seconds_per_division = seconds_per_beat / score.divisions_per_quarter as f32;
-            self.display_metrics.total_score_time = score.measures.len() as f32
-                * seconds_per_division
-                * score.divisions_per_measure as f32;
+    /// Swaps in the watched file's new version, playing on from the same position.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_score_watcher(&mut self, ctx: &egui::Context) {
+        let Some(watcher) = &mut self.score_watcher else {
+            return;
+        };
+        // Keep polling while idle
+        ctx.request_repaint_after(Duration::from_millis(250));
+        let score = match watcher.poll() {
+            Some(Ok(score)) => score,
+            Some(Err(e)) => {
+                eprintln!("Failed to reload {}: {}", watcher.path().display(), e);
+                return;
+            }
+            None => return,
+        };
+        let was_playing = self.is_playing;
+        let (measure, division) = equivalent_position(
+            &score,
+            self.current_measure_index,
+            self.current_division_index,
+        );
+        self.set_score(score);
+        if was_playing {
+            self.seek_to(measure, division);
         }
     }
 
-    fn render_tab_view(&self, ui: &mut egui::Ui) {
-        ui.heading("Tablature");
-        if let Some(score) = &self.score {
-            ScrollArea::both()
-                .id_salt("tab_scroll_area")
-                .show(ui, |ui| {
-                    // Wrap the content in a Frame with inner margin
-                    egui::Frame::none()
-                        .inner_margin(Margin::same(20.0)) // Add 20.0 padding to all sides
-                        .show(ui, |ui| {
-                            // Determine the desired size based on the score
-                            let desired_size = self.calculate_tab_size(score);
-                            let (rect, _response) =
-                                ui.allocate_exact_size(desired_size, egui::Sense::hover());
-                            let painter = ui.painter_at(rect);
-                            self.render_tab(&painter, rect);
-                        });
-                });
+    /// Compares the microphone with the notes at the playback position.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_listener(&mut self) {
+        let Some(listener) = &mut self.listener else {
+            self.diagnostics.listener_latency = None;
+            return;
+        };
+        let expected = if self.is_playing {
+            self.current_notes.as_deref().unwrap_or_default()
+        } else {
+            &[]
+        };
+        let similarity = listener.listen(expected, self.renderer.capo_fret);
+        self.diagnostics.listener_latency = Some(listener.latency);
+        // Smooth over a few frames so the gauge doesn't flicker
+        self.similarity += (similarity - self.similarity) * 0.3;
+        self.is_match = self.similarity >= MATCH_THRESHOLD;
+        if let Some(errors) = &mut self.error_recording {
+            errors.heard(self.is_match, listener.sounding);
+        }
+        let sounding = listener.sounding;
+        if self.waiting_at.is_some()
+            && self.hint_missed_notes
+            && self
+                .miss_hint
+                .heard(self.is_match, sounding, Instant::now())
+        {
+            self.play_hint();
         }
     }
 
-    fn calculate_tab_size(&self, score: &Score) -> Vec2 {
-        let num_strings = 6;
-        let string_spacing = 20.0;
-        let note_spacing = 10.0;
-        let measure_spacing = 10.0;
-        let row_spacing = 50.0;
-
-        let measures_per_row = self.configs.measures_per_row;
-        let dashes_per_division = self.configs.dashes_per_division;
-        let total_measures = score.measures.len();
-        let total_rows = (total_measures + measures_per_row - 1) / measures_per_row;
+    /// Sounds the notes practice mode is waiting for, after they were missed a few times.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn play_hint(&mut self) {
+        let tick = self
+            .timeline
+            .tick_of(self.current_measure_index, self.current_division_index);
+        let Some((notes, divisions)) = self.timeline.struck_at(tick) else {
+            return;
+        };
+        // Short notes are drawn out a little to be heard, long ones cut short not to hold up
+        let duration = (self.timeline.seconds_per_tick(self.tempo) * divisions as f32)
+            .clamp(MIN_HINT_SECONDS, MAX_HINT_SECONDS);
+        self.strike_notes(&notes, duration, None);
+        self.miss_hint
+            .hinted(Instant::now(), Duration::from_secs_f32(duration));
+        // What was heard up to now belongs to the misses
+        self.similarity = 0.0;
+        self.is_match = false;
+    }
 
-        // Calculate total width
-        let total_width = (0..measures_per_row)
-            .map(|measure_idx_in_row| {
-                if let Some(measure) = score.measures.get(measure_idx_in_row) {
-                    let total_divisions = measure.positions.len();
-                    total_divisions * dashes_per_division * note_spacing as usize
-                } else {
-                    0
+    #[cfg(not(target_arch = "wasm32"))]
+    fn ui_listener(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let mut listening = self.listener.is_some();
+            if ui
+                .checkbox(&mut listening, "Listen")
+                .on_hover_text("Compare the microphone with the notes being played")
+                .changed()
+            {
+                self.listener = None;
+                self.listener_error = None;
+                self.similarity = 0.0;
+                self.is_match = false;
+                if listening {
+                    self.start_listener();
                 }
-            })
-            .sum::<usize>() as f32
-            + measures_per_row as f32 * measure_spacing;
-
-        let total_height = total_rows as f32 * (num_strings as f32 * string_spacing + row_spacing);
-
-        // Add padding to the total size (20.0 pixels on each side)
-        let padding = 40.0; // 20.0 pixels on each side
-        Vec2::new(total_width + padding, total_height + padding)
+            }
+            if ui
+                .button("Tuner")
+                .on_hover_text("Tune the guitar with the microphone")
+                .clicked()
+            {
+                self.open_tuner();
+            }
+            if self.listener.is_some() {
+                let color = if self.is_match {
+                    egui::Color32::from_rgb(40, 160, 60)
+                } else {
+                    egui::Color32::from_rgb(200, 60, 40)
+                };
+                ui.add(
+                    egui::ProgressBar::new(self.similarity.clamp(0.0, 1.0))
+                        .fill(color)
+                        .text(format!("Match {:.0}%", self.similarity * 100.0)),
+                );
+                ui.ctx().request_repaint();
+            }
+        });
+        if let Some(error) = &self.listener_error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
     }
-}
 
-impl eframe::App for TabApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        if self.is_playing {
-            self.update_playback();
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open_tuner(&mut self) {
+        self.tuner_open = true;
+        if self.listener.is_none() {
+            self.listener_error = None;
+            self.start_listener();
         }
-        self.update_display_metrics();
-
-        let mut changed_config = false;
-        let mut changed_rendered_score = false;
+    }
 
-        // Check if a new score has been received
-        if let Ok(new_score) = self.score_channel.1.try_recv() {
-            self.score = Some(new_score);
-            // Reset any necessary state
-            self.stop_playback();
-            self.previous_notes = None;
-            self.current_notes = None;
-            self.last_played_measure_index = None;
-            self.last_played_division_index = None;
+    /// The open string the microphone hears against the tuning the guitar is tuned to.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn ui_tuner(&mut self, ctx: &egui::Context) {
+        if !self.tuner_open {
+            return;
         }
-        egui::SidePanel::left("left_panel").show(ctx, |ui| {
-            ui.separator();
-            if ui.button("About").clicked() {
-                self.show_about = true;
-            }
-            self.ui_about(ctx);
-            self.ui_playback_controls(ui, &mut changed_config);
-            self.ui_guitar_settings(ui, &mut changed_config);
-            self.ui_render_settings(ui, &mut changed_rendered_score);
-            self.ui_current_notes(ui);
+        let reading = self.listener.as_ref().and_then(|listener| {
+            detect_frequency(&listener.input.latest(TUNER_WINDOW), listener.sample_rate())
         });
-        if changed_config {
-            let active_guitar_config =
-                self.configs.guitar_configs[self.configs.active_guitar].clone();
-            self.audio_player.update_configs(active_guitar_config);
-        }
+        // Readings of the same note are smoothed, a new note is taken right away
+        self.tuner_frequency = match (self.tuner_frequency, reading) {
+            (Some(previous), Some(reading)) if (reading / previous).log2().abs() < 1.0 / 24.0 => {
+                Some(previous + (reading - previous) * 0.3)
+            }
+            (_, reading) => reading,
+        };
 
-        egui::Window::new("Input plot")
-            .fixed_size(Vec2::new(800.0, 800.0))
+        let tuning = self.tuned_to;
+        let frequency = self.tuner_frequency;
+        let mut open = true;
+        egui::Window::new("Tuner")
+            .open(&mut open)
+            .resizable(false)
             .show(ctx, |ui| {
-                self.render_plots(ui);
+                ui.label(tuning_name(&tuning))
+                    .on_hover_text("Tune the open strings before putting the capo on");
+                if let Some(error) = &self.listener_error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+                let Some(frequency) = frequency else {
+                    ui.label("Play an open string");
+                    return;
+                };
+                // The string being tuned is the one the note is closest to
+                let (string, cents) = tuning
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, &open)| (idx, cents_off(frequency, open)))
+                    .min_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))
+                    .unwrap_or((0, 0.0));
+                let color = if cents.abs() < IN_TUNE_CENTS {
+                    egui::Color32::from_rgb(40, 160, 60)
+                } else {
+                    egui::Color32::from_rgb(200, 60, 40)
+                };
+                ui.colored_label(
+                    color,
+                    format!(
+                        "String {} ({}): {:+.0} cents",
+                        string + 1,
+                        PITCH_CLASS_NAMES[tuning[string] as usize % 12],
+                        cents
+                    ),
+                );
+                // A needle from a quarter tone flat to a quarter tone sharp
+                let (rect, _) =
+                    ui.allocate_exact_size(Vec2::new(240.0, 16.0), egui::Sense::hover());
+                let painter = ui.painter_at(rect);
+                painter.line_segment(
+                    [rect.center_top(), rect.center_bottom()],
+                    ui.visuals().widgets.noninteractive.fg_stroke,
+                );
+                let x = rect.center().x + rect.width() / 2.0 * (cents / 50.0).clamp(-1.0, 1.0);
+                painter.circle_filled(egui::pos2(x, rect.center().y), 6.0, color);
+                ui.label(format!("{:.1} Hz", frequency));
             });
+        ctx.request_repaint();
+        if !open {
+            self.tuner_open = false;
+            self.tuner_frequency = None;
+        }
+    }
 
-        // Central panel to display the tabs and other information
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("Parsed Score Info");
-            if let Some(score) = &self.score {
-                ScrollArea::vertical()
-                    .id_salt("score_info_scroll_area")
-                    .show(ui, |ui| {
-                        ui.monospace(score_info(&score));
-                    });
+    fn stop_playback(&mut self) {
+        if self.is_playing {
+            self.is_playing = false;
+            self.playback_start_time = None;
+            self.current_time = 0.0;
+            self.current_measure_index = 0;
+            self.current_division_index = 0;
+            self.current_notes = None;
+            self.previous_notes = None;
+            self.is_match = false;
+            self.similarity = 0.0;
+            self.last_played_measure_index = None;
+            self.last_played_division_index = None;
+            self.last_beat = None;
+            self.current_chord = None;
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                self.waiting_at = None;
+                if let Some(mut errors) = self.error_recording.take() {
+                    errors.finish();
+                    if errors.measures_played() > 0 {
+                        self.error_map = Some(errors);
+                    }
+                }
+            }
+            self.unschedule_notes();
+            // External synths would otherwise ring on until each note's planned note-off
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(output) = &self.midi_output {
+                output.release();
+            }
+            self.audio_player.stop_backing_track();
+            self.metronome.play(Cue::Stop, &mut self.audio_player);
+        }
+    }
+    fn render_plots(&mut self, ui: &mut egui::Ui) {
+        self.ui_plot_history(ui);
+        // Constants
+        let sample_rate = self.audio_player.sample_rate as f64;
+        let history = &self.audio_player.output_signal;
+
+        // Limit the number of samples to plot
+        let max_samples = 2048;
+        let output_signal = history.latest(max_samples);
+        let num_samples = output_signal.len();
+
+        if num_samples > 0 {
+            // Oscilloscope of the latest synthesized output
+            ui.heading("Oscilloscope");
+            ui.horizontal(|ui| {
+                let mut window_ms = self.plot_length as f64 * 1000.0 / sample_rate;
+                if ui
+                    .add(
+                        egui::Slider::new(&mut window_ms, 1.0..=500.0)
+                            .logarithmic(true)
+                            .suffix(" ms")
+                            .text("Window"),
+                    )
+                    .changed()
+                {
+                    self.plot_length = ((window_ms / 1000.0 * sample_rate) as usize).max(2);
+                }
+                ui.checkbox(&mut self.oscilloscope_trigger, "Trigger")
+                    .on_hover_text(
+                        "Start at a rising zero crossing so repeating waveforms hold still",
+                    );
+            });
+            let (window, window_start) = if self.oscilloscope_trigger {
+                (history.triggered(self.plot_length), None)
+            } else {
+                let window = history.latest(self.plot_length);
+                let window_start = history.total_samples() - window.len() as u64;
+                (window, Some(window_start))
+            };
+            let time_points: PlotPoints = window
+                .iter()
+                .enumerate()
+                .map(|(i, &sample)| {
+                    // Triggered windows move around, so they're plotted from their own start
+                    let index = window_start.unwrap_or(0) + i as u64;
+                    [index as f64 * 1000.0 / sample_rate, sample as f64]
+                })
+                .collect();
+
+            let line = Line::new(time_points);
+            Plot::new("Time Domain")
+                .view_aspect(2.0)
+                .include_y(-1.0)
+                .include_y(1.0)
+                .x_axis_label("ms")
+                .show(ui, |plot_ui| {
+                    plot_ui.line(line);
+                });
+
+            // Frequency Domain Plot
+            ui.heading("Frequency Domain");
+            let spectrum = magnitude_spectrum(&output_signal);
+            let fft_size = 2 * spectrum.len();
+            let freq_points: PlotPoints = spectrum
+                .iter()
+                .enumerate()
+                .map(|(i, &magnitude)| {
+                    let freq = i as f64 * sample_rate / fft_size as f64;
+                    [freq, magnitude as f64]
+                })
+                .collect();
+
+            let line = Line::new(freq_points);
+            Plot::new("Frequency Domain")
+                .view_aspect(2.0)
+                .include_y(0.0)
+                .show(ui, |plot_ui| {
+                    plot_ui.line(line);
+                });
+
+            // Envelope of the longer history, one point per bucket
+            ui.heading("Overview");
+            let seconds_per_bucket = history.samples_per_bucket() as f64 / sample_rate;
+            let overview = history.overview();
+            let bucket_time = |i: usize| (i as f64 - overview.len() as f64) * seconds_per_bucket;
+            let maximum: PlotPoints = overview
+                .iter()
+                .enumerate()
+                .map(|(i, &(_, max))| [bucket_time(i), max as f64])
+                .collect();
+            let minimum: PlotPoints = overview
+                .iter()
+                .enumerate()
+                .map(|(i, &(min, _))| [bucket_time(i), min as f64])
+                .collect();
+            Plot::new("Overview")
+                .view_aspect(4.0)
+                .include_y(-1.0)
+                .include_y(1.0)
+                .show(ui, |plot_ui| {
+                    plot_ui.line(Line::new(maximum).name("Max"));
+                    plot_ui.line(Line::new(minimum).name("Min"));
+                });
+        } else {
+            ui.label("No data to display.");
+        }
+    }
+    fn ui_plot_history(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let history = ui
+                .add(
+                    egui::Slider::new(&mut self.plot_history_seconds, 1.0..=60.0)
+                        .suffix(" s")
+                        .text("History"),
+                )
+                .on_hover_text("How much of the output and the microphone input is kept");
+            let freeze = ui
+                .checkbox(&mut self.plots_frozen, "Freeze")
+                .on_hover_text("Stop taking in new samples to look at what was captured");
+            if history.changed() || freeze.changed() {
+                self.apply_plot_history();
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if ui
+                .button("Export capture…")
+                .on_hover_text(
+                    "Saves the microphone input and the synthesized notes as WAV files, and the \
+                     pitch classes the listener compared as CSV",
+                )
+                .clicked()
+            {
+                self.export_capture();
+            }
+        });
+    }
+
+    /// Sizes and freezes the histories behind the plots as set in the plot window.
+    fn apply_plot_history(&mut self) {
+        let output = &mut self.audio_player.output_signal;
+        output.set_capacity((self.plot_history_seconds * self.audio_player.sample_rate) as usize);
+        output.frozen = self.plots_frozen;
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(listener) = &mut self.listener {
+            listener.set_history_seconds(self.plot_history_seconds);
+            listener.input.frozen = self.plots_frozen;
+        }
+    }
+
+    /// The microphone input and the pitch classes the listener heard against the expected ones.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn ui_listener_plots(&self, ui: &mut egui::Ui) {
+        let Some(listener) = &self.listener else {
+            return;
+        };
+        ui.heading("Microphone");
+        let sample_rate = listener.sample_rate() as f64;
+        let window = listener.input.latest(self.plot_length);
+        let window_start = listener.input.total_samples() - window.len() as u64;
+        let points: PlotPoints = window
+            .iter()
+            .enumerate()
+            .map(|(i, &sample)| {
+                let index = window_start + i as u64;
+                [index as f64 * 1000.0 / sample_rate, sample as f64]
+            })
+            .collect();
+        Plot::new("Microphone")
+            .view_aspect(2.0)
+            .include_y(-1.0)
+            .include_y(1.0)
+            .x_axis_label("ms")
+            .show(ui, |plot_ui| plot_ui.line(Line::new(points)));
+
+        ui.heading("Pitch classes");
+        let Some(frame) = listener.chroma_frames().last().copied() else {
+            return;
+        };
+        let loudest = frame.heard.iter().copied().fold(0.0f32, f32::max);
+        let heard: Vec<Bar> = frame
+            .heard
+            .iter()
+            .enumerate()
+            .map(|(i, &energy)| {
+                let level = if loudest > 0.0 { energy / loudest } else { 0.0 };
+                Bar::new(i as f64 - 0.2, level as f64).width(0.4)
+            })
+            .collect();
+        let expected: Vec<Bar> = frame
+            .expected
+            .iter()
+            .enumerate()
+            .map(|(i, &level)| Bar::new(i as f64 + 0.2, level as f64).width(0.4))
+            .collect();
+        Plot::new("Pitch classes")
+            .view_aspect(3.0)
+            .include_y(0.0)
+            .include_y(1.0)
+            .x_axis_formatter(|mark, _| {
+                PITCH_CLASS_NAMES
+                    .get(mark.value.round() as usize)
+                    .map(|name| name.to_string())
+                    .unwrap_or_default()
+            })
+            .show(ui, |plot_ui| {
+                plot_ui.bar_chart(BarChart::new(heard).name("Heard"));
+                plot_ui.bar_chart(BarChart::new(expected).name("Expected"));
+            });
+    }
+
+    /// Writes what the plots hold into a folder: the microphone input, the synthesized output
+    /// the input is checked against and the listener's comparisons.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export_capture(&self) {
+        let output_rate = self.audio_player.sample_rate as u32;
+        let output = self.audio_player.output_signal.latest(usize::MAX);
+        let (input, input_rate, chroma) = match &self.listener {
+            Some(listener) => (
+                listener.input.latest(usize::MAX),
+                listener.sample_rate(),
+                chroma_csv(&listener.chroma_frames()),
+            ),
+            None => (Vec::new(), output_rate, chroma_csv(&[])),
+        };
+        let task = rfd::AsyncFileDialog::new().pick_folder();
+
+        execute(async move {
+            if let Some(folder) = task.await {
+                let files = [
+                    ("input.wav", encode_wav(&input, input_rate)),
+                    ("expected.wav", encode_wav(&output, output_rate)),
+                    ("chroma.csv", chroma.into_bytes()),
+                ];
+                for (name, data) in files {
+                    if let Err(e) = std::fs::write(folder.path().join(name), data) {
+                        eprintln!("Failed to write {}: {}", name, e);
+                    }
+                }
+            }
+        });
+    }
+
+    fn render_tab(&self, painter: &egui::Painter, rect: egui::Rect, cursor_width: f32) {
+        if let Some(score) = &self.score {
+            let piano_roll = self.piano_roll(score);
+            let key = TabCacheKey::new(
+                self.score_revision,
+                &score.sections,
+                piano_roll.is_some(),
+                &self.renderer,
+                self.configs.guitar_configs[self.configs.active_guitar].capo_fret,
+                painter.ctx().pixels_per_point(),
+            );
+            let mut cache = self.tab_cache.borrow_mut();
+            if cache.as_ref().is_none_or(|cache| cache.key != key) {
+                let row_height = match &piano_roll {
+                    Some(piano_roll) => piano_roll.row_height(),
+                    None => self.renderer.row_height(),
+                };
+                let total_rows = self.renderer.total_rows(score);
+                *cache = Some(TabCache::new(key, total_rows, row_height));
+            }
+
+            if self.renderer.style.high_contrast {
+                painter.rect_filled(rect, 0.0, egui::Color32::WHITE);
+            }
+            if self.configs.show_difficulty && piano_roll.is_none() {
+                self.paint_measure_heat(painter, score, rect.min, |measure| {
+                    Some(measure_difficulty(score, measure, self.tempo))
+                });
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if let (Some(errors), true, None) = (&self.error_map, self.show_error_map, &piano_roll)
+            {
+                self.paint_measure_heat(painter, score, rect.min, |measure| {
+                    errors.miss_rate(measure)
+                });
+            }
+            if let Some(cache) = cache.as_mut() {
+                let layout_start = Instant::now();
+                // rect.min already includes the padding
+                let laid_out = cache.paint(
+                    painter,
+                    rect.min,
+                    &self.renderer.style.font.family(),
+                    |row| match &piano_roll {
+                        Some(piano_roll) => {
+                            piano_roll.layout_rows(score, egui::Pos2::ZERO, row..row + 1)
+                        }
+                        None => self
+                            .renderer
+                            .layout_rows(score, egui::Pos2::ZERO, row..row + 1),
+                    },
+                );
+                if laid_out > 0 {
+                    self.diagnostics
+                        .layout_time
+                        .set(layout_start.elapsed().as_secs_f32() * 1000.0);
+                }
+            }
+
+            // Draw the playback position indicator (if applicable)
+            if self.is_playing {
+                let (measure, division) = (self.current_measure_index, self.current_division_index);
+                let points = self.playback_indicator(score, rect.min, measure, division);
+                // A glow behind the cursor shows whether the listener hears the right notes
+                #[cfg(not(target_arch = "wasm32"))]
+                if self.listener.is_some() {
+                    let color = if self.is_match {
+                        egui::Color32::from_rgba_unmultiplied(40, 200, 60, 110)
+                    } else {
+                        egui::Color32::from_rgba_unmultiplied(220, 40, 40, 110)
+                    };
+                    painter.line_segment(points, egui::Stroke::new(cursor_width + 8.0, color));
+                }
+                painter.line_segment(points, egui::Stroke::new(cursor_width, egui::Color32::RED));
+            }
+        }
+    }
+
+    /// Tints the measures within the painter's clip rect by `heat`, from clear at 0 through
+    /// yellow to red at 1. Measures without a value are left alone.
+    fn paint_measure_heat(
+        &self,
+        painter: &egui::Painter,
+        score: &Score,
+        origin: egui::Pos2,
+        heat: impl Fn(usize) -> Option<f32>,
+    ) {
+        let clip = painter.clip_rect();
+        let row_height = self.renderer.row_height();
+        let first_row = ((clip.top() - origin.y) / row_height).floor().max(0.0) as usize;
+        let last_row = ((clip.bottom() - origin.y) / row_height).ceil().max(0.0) as usize;
+        let measures_per_row = self.renderer.measures_per_row;
+        let measures = (first_row * measures_per_row)
+            ..((last_row + 1) * measures_per_row).min(score.measures.len());
+        for measure in measures {
+            let Some(heat) = heat(measure) else {
+                continue;
+            };
+            let rect = self.renderer.measure_rect(score, origin, measure);
+            let green = (255.0 * (1.0 - heat)) as u8;
+            let alpha = (heat * 140.0) as u8;
+            painter.rect_filled(
+                rect,
+                2.0,
+                egui::Color32::from_rgba_unmultiplied(230, green, 40, alpha),
+            );
+        }
+    }
+
+    /// Where the playback indicator is drawn at `division` of `measure` in the current view.
+    fn playback_indicator(
+        &self,
+        score: &Score,
+        origin: egui::Pos2,
+        measure: usize,
+        division: usize,
+    ) -> [egui::Pos2; 2] {
+        match self.piano_roll(score) {
+            Some(piano_roll) => piano_roll.playback_indicator(score, origin, measure, division),
+            None => self
+                .renderer
+                .playback_indicator(score, origin, measure, division),
+        }
+    }
+
+    fn piano_roll<'a>(&'a self, score: &Score) -> Option<PianoRoll<'a>> {
+        let capo_fret = self.configs.guitar_configs[self.configs.active_guitar].capo_fret;
+        (self.configs.score_view == ScoreView::PianoRoll)
+            .then(|| PianoRoll::new(&self.renderer, score, capo_fret))
+    }
+
+    fn update_display_metrics(&mut self) {
+        if let Some(score) = &self.score {
+            let cfg = &self.configs;
+            let seconds_per_beat = if cfg.use_custom_tempo {
+                60.0 / cfg.custom_tempo as f32
+            } else {
+                60.0 / score.tempo as f32
+            };
+            let seconds_per_division = seconds_per_beat / score.divisions_per_quarter as f32;
+            self.display_metrics.total_score_time = score.measures.len() as f32
+                * seconds_per_division
+                * score.divisions_per_measure as f32;
+        }
+    }
+
+    fn render_tab_view(&mut self, ui: &mut egui::Ui) {
+        ui.heading(self.configs.score_view.to_string());
+        self.ui_minimap(ui);
+        let scroll_to_measure = self.scroll_to_measure.take();
+        let mut tab_focused = false;
+        let mut chosen_variant = None;
+        if let Some(score) = &self.score {
+            // Fewer measures per row when the configured number doesn't fit the window
+            let width = ui.available_width() - 2.0 * TAB_MARGIN;
+            self.renderer.measures_per_row = self
+                .renderer
+                .measures_per_row_fitting(score, width)
+                .min(self.configs.measures_per_row);
+            ScrollArea::both()
+                .id_salt("tab_scroll_area")
+                .show(ui, |ui| {
+                    // Wrap the content in a Frame with inner margin
+                    egui::Frame::none()
+                        .inner_margin(Margin::same(TAB_MARGIN))
+                        .show(ui, |ui| {
+                            // Determine the desired size based on the score
+                            let desired_size = match self.piano_roll(score) {
+                                Some(piano_roll) => piano_roll.size(score),
+                                None => self.renderer.calculate_tab_size(score),
+                            };
+                            let (rect, response) = ui.allocate_exact_size(
+                                desired_size,
+                                egui::Sense::focusable_noninteractive(),
+                            );
+                            response.widget_info(|| {
+                                egui::WidgetInfo::labeled(
+                                    egui::WidgetType::Other,
+                                    true,
+                                    self.tab_description(score),
+                                )
+                            });
+                            tab_focused = response.has_focus();
+                            let painter = ui.painter_at(rect);
+                            self.render_tab(&painter, rect, 2.0);
+                            if response.has_focus() {
+                                painter.rect_stroke(rect, 0.0, ui.visuals().selection.stroke);
+                            }
+                            if self.piano_roll(score).is_none() {
+                                chosen_variant = self.ui_fingering_selectors(ui, score, rect.min);
+                            }
+
+                            if let Some(measure) = scroll_to_measure {
+                                let [top, bottom] =
+                                    self.playback_indicator(score, rect.min, measure, 0);
+                                let target = egui::Rect::from_two_pos(top, bottom);
+                                ui.scroll_to_rect(target, Some(egui::Align::Center));
+                            }
+                        });
+                });
+        }
+        if tab_focused {
+            self.handle_tab_keys(ui.ctx());
+        }
+        if let Some((measure, name)) = chosen_variant {
+            self.choose_fingering_variant(measure, name);
+        }
+    }
+
+    /// A small selector above every measure with fingering variants, returning the measure and
+    /// variant picked, `None` for as written.
+    fn ui_fingering_selectors(
+        &self,
+        ui: &mut egui::Ui,
+        score: &Score,
+        origin: egui::Pos2,
+    ) -> Option<(usize, Option<String>)> {
+        let mut measures: Vec<usize> = self
+            .score_arrangement
+            .fingerprint()
+            .and_then(|fingerprint| self.annotation_store.scores.get(fingerprint))
+            .map(|annotations| &annotations.fingering_variants[..])
+            .unwrap_or_default()
+            .iter()
+            .map(|variant| variant.measure - 1)
+            .filter(|&measure| measure < score.measures.len())
+            .collect();
+        measures.sort_unstable();
+        measures.dedup();
+        let mut chosen = None;
+        for measure in measures {
+            let top_left = self
+                .renderer
+                .measure_rect(score, origin, measure)
+                .left_top();
+            let selector = egui::Rect::from_min_size(
+                top_left - egui::vec2(0.0, FINGERING_SELECTOR_HEIGHT),
+                egui::vec2(FINGERING_SELECTOR_WIDTH, FINGERING_SELECTOR_HEIGHT),
+            );
+            if !ui.clip_rect().intersects(selector) {
+                continue;
+            }
+            let current = self.fingering_choices.get(&measure).map(|(name, _)| name);
+            ui.allocate_new_ui(egui::UiBuilder::new().max_rect(selector), |ui| {
+                egui::ComboBox::from_id_salt(("fingering_variant", measure))
+                    .width(FINGERING_SELECTOR_WIDTH)
+                    .selected_text(
+                        egui::RichText::new(current.map_or("As written", |name| name.as_str()))
+                            .small(),
+                    )
+                    .show_ui(ui, |ui| {
+                        if ui
+                            .selectable_label(current.is_none(), "As written")
+                            .clicked()
+                        {
+                            chosen = Some((measure, None));
+                        }
+                        for variant in self.fingering_variants(measure) {
+                            let selected = current == Some(&variant.name);
+                            if ui.selectable_label(selected, &variant.name).clicked() {
+                                chosen = Some((measure, Some(variant.name.clone())));
+                            }
+                        }
+                    });
+            });
+        }
+        chosen
+    }
+}
+
+impl eframe::App for TabApp {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        if ctx.input(|i| i.key_pressed(egui::Key::F3)) {
+            self.diagnostics.visible = !self.diagnostics.visible;
+        }
+        if ctx.input(|i| i.key_pressed(egui::Key::F11)) {
+            self.set_performance_mode(ctx, !self.performance_mode);
+        }
+        let go_to_shortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::G);
+        if self.score.is_some() && ctx.input_mut(|i| i.consume_shortcut(&go_to_shortcut)) {
+            self.go_to_measure = Some(String::new());
+        }
+        // Not while typing, the letter would land in the text as well
+        if self.is_playing && !ctx.wants_keyboard_input() && ctx.input(|i| i.key_pressed(FLAG_KEY))
+        {
+            self.drop_flag();
+        }
+        self.diagnostics
+            .begin_frame(frame.info().cpu_usage, &self.audio_player);
+
+        self.audio_player.collect_output();
+        #[cfg(not(target_arch = "wasm32"))]
+        self.update_midi_clock();
+        #[cfg(not(target_arch = "wasm32"))]
+        self.update_midi_keyboard();
+        #[cfg(not(target_arch = "wasm32"))]
+        self.update_media_keys();
+        if self.is_playing {
+            self.update_playback();
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        self.update_listener();
+        #[cfg(not(target_arch = "wasm32"))]
+        self.update_loopback_test();
+        #[cfg(not(target_arch = "wasm32"))]
+        self.update_score_watcher(ctx);
+        self.update_display_metrics();
+
+        let mut changed_config = false;
+        let mut changed_rendered_score = false;
+
+        // Check if a new score has been received
+        if let Ok(new_score) = self.score_channel.1.try_recv() {
+            self.setlist.current = None;
+            self.warmed_up_score = None;
+            self.set_score(new_score);
+        }
+        while let Ok(entry) = self.setlist_channel.1.try_recv() {
+            self.setlist.entries.push(entry);
+        }
+        let mut library_changed = false;
+        while let Ok(entry) = self.library_channel.1.try_recv() {
+            self.library.add(entry);
+            library_changed = true;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Ok(folder) = self.library_folder_channel.1.try_recv() {
+            self.scan_library_folder(&folder);
+            self.library.folder = Some(folder);
+            library_changed = true;
+        }
+        if library_changed {
+            self.save_library();
+        }
+        if let Ok(json) = self.annotation_channel.1.try_recv() {
+            self.import_annotations(&json);
+        }
+        if let Some(next) = self.setlist.take_due() {
+            self.play_setlist_entry(next);
+        }
+        if let Ok(impulse_response) = self.impulse_response_channel.1.try_recv() {
+            let config = &mut self.configs.guitar_configs[self.configs.active_guitar];
+            config.impulse_response = Some(impulse_response.name.clone());
+            self.audio_player.add_impulse_response(impulse_response);
+            self.audio_player.update_configs(config.clone());
+        }
+        if let Ok(track) = self.backing_track_channel.1.try_recv() {
+            self.stop_playback();
+            self.backing_track = Some(track);
+            self.processing_in_progress = None;
+            self.proposed_alignment = None;
+            self.suggested_sections.clear();
+        }
+        if let Ok(boundaries) = self.section_detection_channel.1.try_recv() {
+            self.detecting_sections = false;
+            self.suggest_sections(&boundaries);
+        }
+        if let Ok(alignment) = self.beat_detection_channel.1.try_recv() {
+            self.detecting_beats = false;
+            if alignment.is_none() {
+                eprintln!("No steady beat found in the backing track");
+            }
+            self.proposed_alignment = alignment;
+        }
+        self.update_backing_track_processing();
+        self.update_position_report(ctx);
+
+        if self.print_mode {
+            self.ui_print_view(ctx);
+            ctx.request_repaint();
+            return;
+        }
+        if self.performance_mode {
+            self.ui_performance_view(ctx);
+            ctx.request_repaint();
+            return;
+        }
+
+        self.ui_about(ctx);
+        self.ui_go_to_measure(ctx);
+        self.ui_setup_prompt(ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.ui_tuner(ctx);
+        self.ui_flag_note(ctx);
+        egui::SidePanel::left("left_panel")
+            .resizable(true)
+            .show_animated(ctx, self.side_panel_open, |ui| {
+                ScrollArea::vertical()
+                    .id_salt("left_panel_scroll_area")
+                    .show(ui, |ui| {
+                        ui.separator();
+                        if ui.button("About").clicked() {
+                            self.show_about = true;
+                        }
+                        panel_section(ui, "Playback Controls", |ui| {
+                            self.ui_playback_controls(ui, &mut changed_config)
+                        });
+                        self.ui_capo_suggestions(ui, &mut changed_config);
+                        #[cfg(not(target_arch = "wasm32"))]
+                        panel_section(ui, "Practice", |ui| self.ui_practice(ui));
+                        panel_section(ui, "Library", |ui| self.ui_library(ui));
+                        self.ui_annotations(ui);
+                        panel_section(ui, "Setlist", |ui| self.ui_setlist(ui));
+                        panel_section(ui, "Mixer", |ui| self.ui_mixer(ui));
+                        panel_section(ui, "Backing Track", |ui| self.ui_backing_track(ui));
+                        panel_section(ui, "Sections", |ui| self.ui_sections(ui));
+                        panel_section(ui, "Guitar Profile", |ui| {
+                            self.ui_guitar_settings(ui, &mut changed_config)
+                        });
+                        panel_section(ui, "Audio Settings", |ui| self.ui_audio_settings(ui));
+                        panel_section(ui, "Accessibility", |ui| self.ui_accessibility(ui));
+                        panel_section(ui, "Render Settings", |ui| {
+                            self.ui_render_settings(ui, &mut changed_rendered_score)
+                        });
+                        panel_section(ui, "Current Notes", |ui| self.ui_current_notes(ui));
+                        self.ui_chord_diagram(ui);
+                    });
+            });
+        if changed_config {
+            self.apply_guitar_config();
+        }
+
+        egui::Window::new("Input plot")
+            .fixed_size(Vec2::new(800.0, 800.0))
+            .show(ctx, |ui| {
+                self.render_plots(ui);
+                #[cfg(not(target_arch = "wasm32"))]
+                self.ui_listener_plots(ui);
+                #[cfg(not(target_arch = "wasm32"))]
+                self.ui_spectrogram(ui);
+            });
+
+        if self.configs.show_fretboard {
+            egui::TopBottomPanel::bottom("fretboard_panel").show(ctx, |ui| {
+                self.ui_fretboard(ui);
+            });
+        }
+
+        self.update_touch(ctx);
+        if self.configs.touch_layout {
+            egui::TopBottomPanel::bottom("touch_transport_panel").show(ctx, |ui| {
+                self.ui_touch_transport(ui);
+            });
+        }
+
+        if self.configs.show_chord_timeline && !self.chord_progression.is_empty() {
+            egui::TopBottomPanel::top("chord_timeline_panel").show(ctx, |ui| {
+                self.ui_chord_timeline(ui);
+            });
+        }
+
+        // Central panel to display the tabs and other information
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let (icon, hint) = if self.side_panel_open {
+                    ("◀", "Hide the side panel")
+                } else {
+                    ("▶", "Show the side panel")
+                };
+                if ui.button(icon).on_hover_text(hint).clicked() {
+                    self.side_panel_open = !self.side_panel_open;
+                }
+                ui.heading("Parsed Score Info");
+            });
+            if let Some(score) = &self.score {
+                // Folded away by default on narrow windows to leave room for the tab
+                let narrow = ui.available_width() < NARROW_WIDTH;
+                egui::CollapsingHeader::new("Details")
+                    .id_salt("score_info")
+                    .default_open(!narrow)
+                    .show(ui, |ui| {
+                        ScrollArea::vertical()
+                            .id_salt("score_info_scroll_area")
+                            .show(ui, |ui| {
+                                ui.monospace(score_info(&score));
+                            });
+                    });
+            }
+
+            self.render_tab_view(ui);
+        });
+
+        self.diagnostics.show(ctx, &self.audio_player);
+        self.paint_beat_flash(ctx);
+
+        if self.needs_continuous_repaint() {
+            ctx.request_repaint();
+        } else {
+            ctx.request_repaint_after(IDLE_REPAINT_INTERVAL);
+        }
+    }
+}
+
+impl TabApp {
+    /// Whether something moves without any input, like the cursor during playback or the
+    /// microphone meters. Otherwise the window only repaints on input and now and then.
+    fn needs_continuous_repaint(&self) -> bool {
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.listener.is_some()
+            || self.input_monitor.is_some()
+            || self.input_capture.is_some()
+            || self.midi_clock.is_some()
+            || self.midi_keyboard.is_some()
+            || self.media_keys.is_some()
+            || self.loopback_test.is_some()
+        {
+            return true;
+        }
+        self.is_playing
+            || self.diagnostics.visible
+            || self.setlist.time_until_next().is_some()
+            || self.processing_in_progress.is_some()
+            || self.detecting_beats
+            || self.detecting_sections
+    }
+
+    /// Switches to the touch layout on the first touch, and zooms the whole UI with a pinch
+    /// while it is on.
+    fn update_touch(&mut self, ctx: &egui::Context) {
+        if !self.configs.touch_layout && ctx.input(|i| i.any_touches()) {
+            self.configs.touch_layout = true;
+            // The tab needs all of a phone's screen
+            self.side_panel_open = false;
+        }
+        if !self.configs.touch_layout {
+            return;
+        }
+        if let Some(touch) = ctx.input(|i| i.multi_touch()) {
+            let zoom = (ctx.zoom_factor() * touch.zoom_delta)
+                .clamp(*TOUCH_ZOOM_RANGE.start(), *TOUCH_ZOOM_RANGE.end());
+            ctx.set_zoom_factor(zoom);
+        }
+    }
+
+    fn ui_touch_transport(&mut self, ui: &mut egui::Ui) {
+        let button = |icon: &str| {
+            egui::Button::new(RichText::new(icon).size(TOUCH_ICON_SIZE)).min_size(TOUCH_BUTTON_SIZE)
+        };
+        ui.horizontal_centered(|ui| {
+            if ui.add(button("☰")).clicked() {
+                self.side_panel_open = !self.side_panel_open;
+            }
+            let has_score = self.score.is_some();
+            let measure = self.current_measure_index;
+            if ui
+                .add_enabled(has_score && self.is_playing, button("⏮"))
+                .clicked()
+            {
+                self.seek_to(measure.saturating_sub(1), 0);
+            }
+            let icon = if self.is_playing { "⏹" } else { "▶" };
+            if ui.add_enabled(has_score, button(icon)).clicked() {
+                if self.is_playing {
+                    self.stop_playback();
+                } else {
+                    self.request_playback();
+                }
+            }
+            if ui
+                .add_enabled(has_score && self.is_playing, button("⏭"))
+                .clicked()
+            {
+                self.seek_to(measure + 1, 0);
+            }
+            let zoom = ui.ctx().zoom_factor();
+            if ui
+                .add(button(&format!("{:.0}%", zoom * 100.0)))
+                .on_hover_text("Pinch the tab to zoom, tap to reset")
+                .clicked()
+            {
+                ui.ctx().set_zoom_factor(1.0);
+            }
+        });
+    }
+
+        fn ui_about(&mut self, ctx: &egui::Context) {
+            egui::Window::new("About")
+                .open(&mut self.show_about)
+                .collapsible(false)
+                .resizable(true) // Allow resizing if the content is extensive
+                .vscroll(true)   // Enable vertical scrolling if content overflows
+                .show(ctx, |ui| {
+                    ui.vertical_centered(|ui| {
+                        ui.heading("Frequently Asked Questions");
+                        ui.separator();
+                    });
+    
+                    ui.add_space(10.0);
+    
+                    // Question 1
+                    ui.label(RichText::new("What is MusicXML?").size(18.0));
+                    ui.label(
+                        "MusicXML is an open, XML-based music notation file format designed for \
+                        the interchange of digital sheet music between applications. It allows for \
+                        the sharing of musical scores between different music notation software. \
+                        For more information, visit the official MusicXML website:",
+                    );
+                    ui.hyperlink("https://www.musicxml.com/");
+    
+                    ui.add_space(10.0);
+    
+                    // Question 2
+                    ui.label(RichText::new("How do I obtain MusicXML files?").size(18.0));
+                    ui.label(
+                        "You can create MusicXML files using music notation software that supports \
+                        exporting to MusicXML format, such as Finale, Sibelius, MuseScore, and others. \
+                        Additionally, you can find MusicXML files from online resources:",
+                    );
+                    ui.hyperlink("https://musescore.com/");
+                    ui.hyperlink("https://www.musicxml.com/music-in-musicxml/");
+    
+                    ui.add_space(10.0);
+    
+                    ui.label(RichText::new("I got a .mxl file instead of .xml file. What do I do?").size(18.0));
+                    ui.label(
+                        "Often when downloading a musicxml file, you get a .mxl file. This is a \
+                        compressed version of musicxml. This is easily converted by renaming the file \
+                        to a .zip file and then extracting it. This gives you a folder your score as .xml \
+                        and another folder that you can ignore. Simply choose the .xml file when uploading \
+                        a file to this application.",
+                    );
+
+                    ui.label(RichText::new("Can I convert other formats to MusicXML?").size(18.0));
+                    ui.label(
+                        "Yes, many music notation programs allow you to import various formats and \
+                        export them as MusicXML. There are also dedicated conversion tools available. \
+                        Check out the following resource for more information:",
+                    );
+                    ui.hyperlink("https://www.musicxml.com/dolet-plugin/");
+    
+                    ui.add_space(10.0);
+    
+                    ui.label(RichText::new("Why use MusicXML in this application?").size(18.0));
+                    ui.label(
+                        "When playing guitar, tablature is a great tool for those not familiar \
+                        with traditional musical notation. It is often easier to find well written \
+                        notation for other instruments. This application tries to understand scores \
+                        written for any instrument and convert it to tablature, and also let you play \
+                        around with playback to let you get a feeling of the expected sound.",
+                    );
+    
+                    ui.add_space(10.0);
+    
+                    // Additional Resources
+                    ui.label(RichText::new("**Additional Resources:**").size(18.0));
+                    ui.hyperlink("MusicXML Tutorial - https://www.musicxml.com/tutorial/");
+                    ui.hyperlink("MusicXML GitHub Repository - https://github.com/w3c/musicxml");
+    
+                    ui.add_space(20.0);
+    
+                    ui.separator();
+    
+                    // Credits or other information
+                    ui.vertical_centered(|ui| {
+                        ui.label(RichText::new("Author: Rasmus Hogslätt").size(16.0));
+                    });
+    
+                    ui.add_space(10.0);
+                });
+        }
+    
+    
+
+    /// Elapsed, total and remaining time and the measure:beat position.
+    fn ui_time_display(&self, ui: &mut egui::Ui) {
+        let total = self.display_metrics.total_score_time;
+        let elapsed = if self.is_playing {
+            self.current_time.min(total)
+        } else {
+            0.0
+        };
+        let position = if self.position_report.playing {
+            format!(
+                "{}:{}",
+                self.position_report.measure, self.position_report.beat
+            )
+        } else {
+            "-:-".to_string()
+        };
+        ui.horizontal(|ui| {
+            ui.monospace(format!("{} / {}", format_time(elapsed), format_time(total)))
+                .on_hover_text("Elapsed / total time");
+            ui.monospace(format!("-{}", format_time(total - elapsed)))
+                .on_hover_text("Remaining time");
+            ui.monospace(position).on_hover_text("Measure:beat");
+        });
+        if total > 0.0 {
+            ui.add(egui::ProgressBar::new(elapsed / total).desired_height(4.0));
+        }
+    }
+
+    fn ui_playback_controls(&mut self, ui: &mut egui::Ui, changed_config: &mut bool) {
+        ui.group(|ui| {
+            ui.horizontal(|ui| {
+                let preparing = self.processing_in_progress.is_some();
+                if ui
+                    .add_enabled(!preparing, egui::Button::new("Play"))
+                    .clicked()
+                {
+                    self.request_playback();
+                }
+                if ui.button("Stop").clicked() {
+                    self.stop_playback();
+                    self.setlist.cancel_advance();
+                    if let Some(score) = self.warmed_up_score.take() {
+                        self.set_score(score);
+                    }
+                }
+                if ui
+                    .add_enabled(
+                        !preparing && self.score.is_some() && self.warmed_up_score.is_none(),
+                        egui::Button::new("Warm up"),
+                    )
+                    .on_hover_text("Plays a warm-up made from the song first, then the song")
+                    .clicked()
+                {
+                    self.start_warm_up();
+                }
+            });
+            let mut arrangement = self.score_arrangement.arrangement;
+            ui.add_enabled_ui(self.score.is_some(), |ui| {
+                egui::ComboBox::from_label("Arrangement")
+                    .selected_text(arrangement.to_string())
+                    .show_ui(ui, |ui| {
+                        for option in Arrangement::ALL {
+                            ui.selectable_value(&mut arrangement, option, option.to_string())
+                                .on_hover_text(option.description());
+                        }
+                    });
+            });
+            let mut simplification = self.score_arrangement.simplification;
+            ui.add_enabled(
+                self.score.is_some(),
+                egui::Slider::new(&mut simplification, 0..=MAX_SIMPLIFICATION)
+                    .text("Simplification level"),
+            )
+            .on_hover_text("Leaves out more of the fast ornamental notes the higher it is");
+            if arrangement != self.score_arrangement.arrangement
+                || simplification != self.score_arrangement.simplification
+            {
+                self.set_arrangement(arrangement, simplification);
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            self.ui_listener(ui);
+            if ui
+                .add_enabled(self.score.is_some(), egui::Button::new("Go to measure…"))
+                .on_hover_text("Ctrl+G")
+                .clicked()
+            {
+                self.go_to_measure = Some(String::new());
+            }
+            egui::CollapsingHeader::new("Warm-up")
+                .id_salt("warm_up_settings")
+                .show(ui, |ui| {
+                    let settings = &mut self.configs.warm_up;
+                    ui.checkbox(&mut settings.chromatic, "Chromatic runs");
+                    ui.checkbox(&mut settings.scale, "Scale in the song's key");
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut settings.chords, "Chord changes");
+                        ui.add_enabled(
+                            settings.chords,
+                            egui::DragValue::new(&mut settings.chord_repeats)
+                                .range(1..=8)
+                                .suffix("×"),
+                        );
+                    });
+                    ui.add(
+                        egui::Slider::new(&mut settings.tempo_percent, 25..=150)
+                            .text("% of song tempo"),
+                    );
+                });
+            ui.horizontal(|ui| {
+                ui.label("Volume:");
+                let active_guitar_config =
+                    &mut self.configs.guitar_configs[self.configs.active_guitar];
+                if ui
+                    .add(
+                        egui::Slider::new(&mut active_guitar_config.volume, 0.0..=1.0)
+                            .step_by(0.01),
+                    )
+                    .changed()
+                {
+                    *changed_config = true;
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Set custom tempo:");
+                ui.checkbox(&mut self.configs.use_custom_tempo, "");
+            });
+            if self.configs.use_custom_tempo {
+                if ui
+                    .add(egui::Slider::new(&mut self.configs.custom_tempo, 1..=240))
+                    .changed()
+                {
+                    *changed_config = true;
+                }
+            }
+            self.ui_time_display(ui);
+            ui.label(self.position_report.text());
+            #[cfg(not(target_arch = "wasm32"))]
+            self.ui_position_server(ui);
+            #[cfg(not(target_arch = "wasm32"))]
+            self.ui_midi_clock(ui);
+            #[cfg(not(target_arch = "wasm32"))]
+            self.ui_midi_output(ui);
+            #[cfg(not(target_arch = "wasm32"))]
+            self.ui_midi_keyboard(ui);
+            #[cfg(not(target_arch = "wasm32"))]
+            self.ui_media_keys(ui);
+            ui.label("Capo fret:");
+            let active_guitar_config = &mut self.configs.guitar_configs[self.configs.active_guitar];
+            if ui
+                .add(egui::Slider::new(&mut active_guitar_config.capo_fret, 0..=24).text("Fret"))
+                .changed()
+            {
+                *changed_config = true;
+            }
+            if ui.button("Choose File").clicked() {
+                self.stop_playback();
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    let sender = self.score_channel.0.clone();
+                    let task = rfd::AsyncFileDialog::new()
+                        .add_filter("MusicXML", &["xml"])
+                        .pick_file();
+                    let ctx = ui.ctx().clone();
+
+                    execute(async move {
+                        if let Some(file) = task.await {
+                            let data = file.read().await;
+                            let xml_string = String::from_utf8_lossy(&data).to_string();
+
+                            if let Ok(new_score) = Score::parse_from_musicxml_str(&xml_string) {
+                                let _ = sender.send(new_score);
+                            }
+                        }
+                        ctx.request_repaint();
+                    });
+                }
+
+                #[cfg(target_arch = "wasm32")]
+                {
+                    use wasm_bindgen::prelude::*;
+                    use wasm_bindgen::JsCast;
+                    use web_sys::{Event, HtmlInputElement};
+
+                    let document = web_sys::window().unwrap().document().unwrap();
+                    let input = document.create_element("input").unwrap();
+                    input.set_attribute("type", "file").unwrap();
+                    input.set_attribute("accept", ".xml").unwrap();
+                    input.set_attribute("style", "display: none;").unwrap();
+                    let input: HtmlInputElement = input.dyn_into().unwrap();
+
+                    let sender = self.score_channel.0.clone();
+                    let ctx = ui.ctx().clone();
+
+                    let closure = Closure::wrap(Box::new(move |event: Event| {
+                        let input: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
+                        if let Some(files) = input.files() {
+                            if let Some(file) = files.get(0) {
+                                let file_reader = web_sys::FileReader::new().unwrap();
+                                let fr_c = file_reader.clone();
+                                let sender_clone = sender.clone(); // Clone sender here
+                                let ctx_clone = ctx.clone(); // Clone ctx if needed in inner closure
+                                let onloadend = Closure::wrap(Box::new(move |_event: Event| {
+                                    let result = fr_c.result().unwrap();
+                                    let array = js_sys::Uint8Array::new(&result);
+                                    let data = array.to_vec();
+                                    let xml_string = String::from_utf8_lossy(&data).to_string();
+
+                                    if let Ok(new_score) =
+                                        Score::parse_from_musicxml_str(&xml_string)
+                                    {
+                                        let _ = sender_clone.send(new_score);
+                                    }
+                                    ctx_clone.request_repaint();
+                                })
+                                    as Box<dyn FnMut(_)>);
+
+                                file_reader.set_onloadend(Some(onloadend.as_ref().unchecked_ref()));
+                                file_reader.read_as_array_buffer(&file).unwrap();
+                                onloadend.forget();
+                            }
+                        }
+                    }) as Box<dyn FnMut(_)>);
+
+                    input.set_onchange(Some(closure.as_ref().unchecked_ref()));
+                    closure.forget();
+
+                    // Add the input to the DOM and trigger the click
+                    document.body().unwrap().append_child(&input).unwrap();
+                    input.click();
+                }
+            }
+        });
+    }
+
+    fn start_warm_up(&mut self) {
+        let Some(score) = &self.score else {
+            return;
+        };
+        let warm_up = generate_warm_up(score, &self.configs.warm_up);
+        if warm_up.measures.is_empty() {
+            return;
+        }
+        self.stop_playback();
+        self.warmed_up_score = self.score.take();
+        self.set_score(warm_up);
+        self.start_playback();
+    }
+
+    /// Shows the score as written or an arrangement generated from it, with its ornamental
+    /// notes thinned out to the simplification level.
+    fn set_arrangement(&mut self, arrangement: Arrangement, simplification: u8) {
+        let Some(shown) = self.score.take() else {
+            return;
+        };
+        let capo_fret = self.configs.guitar_configs[self.configs.active_guitar].capo_fret;
+        let arranged =
+            self.score_arrangement
+                .arrange(shown, arrangement, simplification, capo_fret);
+        self.show_score(arranged);
+    }
+
+    fn set_score(&mut self, score: Score) {
+        self.score_arrangement = ScoreArrangement::open(Some(&score));
+        self.show_score(score);
+    }
+
+    /// Shows `score` in place of the one shown, without touching the annotations or which score
+    /// they belong to, for arrangements and other derived versions of the same score.
+    fn show_score(&mut self, score: Score) {
+        self.score_revision += 1;
+        self.timeline = Timeline::from_score(&score);
+        self.score = Some(score);
+        self.fingering_choices.clear();
+        self.update_chord_progression();
+        self.capo_suggestions = None;
+        self.capo_confirmation = None;
+        // Reset any necessary state
+        self.stop_playback();
+        // The misses were counted on the measures of the previous score
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.error_map = None;
+        }
+        self.previous_notes = None;
+        self.current_notes = None;
+        self.last_played_measure_index = None;
+        self.last_played_division_index = None;
+    }
+
+    fn play_setlist_entry(&mut self, idx: usize) {
+        let Some(entry) = self.setlist.entries.get(idx) else {
+            return;
+        };
+        self.set_score(entry.score.clone());
+        self.setlist.current = Some(idx);
+        self.start_playback();
+    }
+
+    fn add_to_setlist(&mut self, ctx: &egui::Context) {
+        let sender = self.setlist_channel.0.clone();
+        let task = rfd::AsyncFileDialog::new()
+            .add_filter("MusicXML", &["xml"])
+            .pick_files();
+        let ctx = ctx.clone();
+
+        execute(async move {
+            for file in task.await.unwrap_or_default() {
+                let name = file.file_name();
+                let data = file.read().await;
+                match Score::parse_from_musicxml_str(&String::from_utf8_lossy(&data)) {
+                    Ok(score) => {
+                        let _ = sender.send(SetlistEntry { name, score });
+                    }
+                    Err(e) => eprintln!("Failed to load {}: {}", name, e),
+                }
+            }
+            ctx.request_repaint();
+        });
+    }
+
+    fn import_to_library(&mut self, ctx: &egui::Context) {
+        let sender = self.library_channel.0.clone();
+        let task = rfd::AsyncFileDialog::new()
+            .add_filter("MusicXML", &["xml"])
+            .pick_files();
+        let ctx = ctx.clone();
+
+        execute(async move {
+            for file in task.await.unwrap_or_default() {
+                let name = file.file_name();
+                let xml = String::from_utf8_lossy(&file.read().await).to_string();
+                let score = match Score::parse_from_musicxml_str(&xml) {
+                    Ok(score) => score,
+                    Err(e) => {
+                        eprintln!("Failed to import {}: {}", name, e);
+                        continue;
+                    }
+                };
+                let entry = LibraryEntry::new(name, xml, &score);
+                #[cfg(not(target_arch = "wasm32"))]
+                let entry = entry.with_path(file.path().to_path_buf());
+                let _ = sender.send(entry);
+            }
+            ctx.request_repaint();
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn scan_library_folder(&mut self, folder: &std::path::Path) {
+        self.library_folder_selected = None;
+        self.library_folder = match FolderIndex::scan(folder) {
+            Ok(index) => Some(index),
+            Err(e) => {
+                eprintln!("Failed to read library folder {}", e);
+                None
+            }
+        };
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn pick_library_folder(&mut self, ctx: &egui::Context) {
+        let sender = self.library_folder_channel.0.clone();
+        let task = rfd::AsyncFileDialog::new().pick_folder();
+        let ctx = ctx.clone();
+
+        execute(async move {
+            if let Some(folder) = task.await {
+                let _ = sender.send(folder.path().to_path_buf());
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    /// Score files in the chosen folder, searched with the library's query and opened with a
+    /// double-click.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn ui_library_folder(&mut self, ui: &mut egui::Ui) {
+        // Height of a row in the file list
+        const ROW_HEIGHT: f32 = 18.0;
+        // Files read per frame while searching, so results fill in without stalling the UI
+        const PARSE_BUDGET: usize = 8;
+
+        egui::CollapsingHeader::new("Folder")
+            .id_salt("library_folder")
+            .default_open(self.library_folder.is_some())
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    match &self.library_folder {
+                        Some(index) => ui.label(index.root.display().to_string()),
+                        None => ui.label("No folder chosen"),
+                    };
+                    if ui.button("Choose folder…").clicked() {
+                        self.pick_library_folder(ui.ctx());
+                    }
+                    if let Some(root) = self.library_folder.as_ref().map(|i| i.root.clone()) {
+                        if ui.small_button("⟳").on_hover_text("Scan again").clicked() {
+                            self.scan_library_folder(&root);
+                        }
+                    }
+                });
+                let Some(index) = &mut self.library_folder else {
+                    return;
+                };
+                if !self.library_query.trim().is_empty() && index.parse_pending(PARSE_BUDGET) {
+                    ui.ctx().request_repaint();
+                }
+
+                let results = index.search(&self.library_query);
+                let mut open = None;
+                ScrollArea::vertical()
+                    .id_salt("library_folder_scroll_area")
+                    .max_height(200.0)
+                    .show_rows(ui, ROW_HEIGHT, results.len(), |ui, rows| {
+                        for &idx in &results[rows] {
+                            // Only the rows on screen are read
+                            index.parse(idx);
+                            let file = &index.files[idx];
+                            let (label, details) = match (file.entry(), file.error()) {
+                                (Some(entry), _) => (
+                                    format!(
+                                        "{} · {} bpm · {} measures",
+                                        entry.display_title(),
+                                        entry.tempo,
+                                        entry.measures
+                                    ),
+                                    index.relative_path(idx),
+                                ),
+                                (None, Some(e)) => (
+                                    file.file_name.clone(),
+                                    format!("{}\n{}", index.relative_path(idx), e),
+                                ),
+                                (None, None) => (file.file_name.clone(), index.relative_path(idx)),
+                            };
+                            let text = match file.error() {
+                                Some(_) => RichText::new(label).weak(),
+                                None => RichText::new(label),
+                            };
+                            let selected = self.library_folder_selected == Some(idx);
+                            let response =
+                                ui.selectable_label(selected, text).on_hover_text(details);
+                            if response.clicked() {
+                                self.library_folder_selected = Some(idx);
+                            }
+                            if response.double_clicked() {
+                                open = Some(idx);
+                            }
+                        }
+                        if results.is_empty() {
+                            ui.label("No matching files");
+                        }
+                    });
+
+                if let Some(idx) = open {
+                    let file = &index.files[idx];
+                    match file.load_score() {
+                        Ok(score) => {
+                            self.setlist.current = None;
+                            self.set_score(score);
+                        }
+                        Err(e) => eprintln!("Failed to open {}: {}", file.file_name, e),
+                    }
+                }
+            });
+    }
+
+    fn save_library(&self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Err(e) = self.library.save(std::path::Path::new(LIBRARY_FILE)) {
+            eprintln!("Failed to save library: {}", e);
+        }
+    }
+
+    fn ui_library(&mut self, ui: &mut egui::Ui) {
+        ui.group(|ui| {
+            ui.horizontal(|ui| {
+                ui.label("Search:");
+                ui.text_edit_singleline(&mut self.library_query)
+                    .on_hover_text("Title, composer, key or technique, e.g. \"bach minor\"");
+            });
+            ui.add(
+                egui::Slider::new(&mut self.library_max_difficulty, 1..=MAX_DIFFICULTY)
+                    .text("Max difficulty"),
+            );
+
+            let all_tags = self.library.all_tags();
+            // Tags no entry has any more can't be unselected otherwise
+            self.library_tag_filter.retain(|tag| all_tags.contains(tag));
+            if !all_tags.is_empty() {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Tags:");
+                    for tag in &all_tags {
+                        let mut selected = self.library_tag_filter.contains(tag);
+                        if ui.toggle_value(&mut selected, tag).changed() {
+                            if selected {
+                                self.library_tag_filter.insert(tag.clone());
+                            } else {
+                                self.library_tag_filter.remove(tag);
+                            }
+                        }
+                    }
+                });
+            }
+
+            let results = self.library.search(
+                &self.library_query,
+                self.library_max_difficulty,
+                &self.library_tag_filter,
+            );
+            let mut tags_changed = false;
+            let mut open = None;
+            let mut queue = None;
+            let mut append = None;
+            let mut remove = None;
+            let score_open = self.score.is_some();
+            ScrollArea::vertical()
+                .id_salt("library_scroll_area")
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    for &idx in &results {
+                        let entry = &self.library.entries[idx];
+                        ui.horizontal(|ui| {
+                            let label = match &entry.composer {
+                                Some(composer) => {
+                                    format!("{} – {}", entry.display_title(), composer)
+                                }
+                                None => entry.display_title().to_string(),
+                            };
+                            let details = format!(
+                                "{}\nKey: {}\nTempo: {}\nMeasures: {}\nDifficulty: {}/{}\nTechniques: {}",
+                                entry.file_name,
+                                entry.key.as_deref().unwrap_or("unknown"),
+                                entry.tempo,
+                                entry.measures,
+                                entry.difficulty,
+                                MAX_DIFFICULTY,
+                                entry.techniques.join(", "),
+                            );
+                            let response = ui.link(label).on_hover_text(details);
+                            if response.clicked() {
+                                open = Some(idx);
+                            }
+                            response.context_menu(|ui| {
+                                let button = egui::Button::new("Append to open score");
+                                if ui.add_enabled(score_open, button).clicked() {
+                                    append = Some(idx);
+                                    ui.close_menu();
+                                }
+                            });
+                            ui.label("★".repeat(entry.difficulty as usize));
+                            if ui.small_button("+").on_hover_text("Add to setlist").clicked() {
+                                queue = Some(idx);
+                            }
+                            if ui.small_button("🏷").on_hover_text("Edit tags").clicked() {
+                                self.editing_tags = Some((idx, entry.tags.join(", ")));
+                            }
+                            if ui.small_button("🗑").on_hover_text("Remove").clicked() {
+                                remove = Some(idx);
+                            }
+                        });
+
+                        match &mut self.editing_tags {
+                            Some((editing, text)) if *editing == idx => {
+                                ui.horizontal(|ui| {
+                                    let response = ui.text_edit_singleline(text).on_hover_text(
+                                        "Comma separated, e.g. \"fingerstyle, gig set\"",
+                                    );
+                                    let submitted = response.lost_focus()
+                                        && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                                    if submitted || ui.button("Save").clicked() {
+                                        self.library.entries[idx].set_tags(text);
+                                        tags_changed = true;
+                                    }
+                                });
+                                if tags_changed {
+                                    self.editing_tags = None;
+                                }
+                            }
+                            _ => {
+                                let entry = &self.library.entries[idx];
+                                if !entry.tags.is_empty() {
+                                    ui.horizontal_wrapped(|ui| {
+                                        for tag in &entry.tags {
+                                            ui.small(format!("#{}", tag));
+                                        }
+                                    });
+                                }
+                            }
+                        }
+                    }
+                    if results.is_empty() {
+                        ui.label("No matching scores");
+                    }
+                });
+            if ui.button("Import scores…").clicked() {
+                self.import_to_library(ui.ctx());
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            self.ui_library_folder(ui);
+
+            if let Some(idx) = open.or(queue) {
+                let entry = &self.library.entries[idx];
+                match entry.load_score() {
+                    Ok(score) if queue.is_some() => self.setlist.entries.push(SetlistEntry {
+                        name: entry.display_title().to_string(),
+                        score,
+                    }),
+                    Ok(score) => {
+                        self.setlist.current = None;
+                        self.set_score(score);
+                    }
+                    Err(e) => eprintln!("Failed to open {}: {}", entry.file_name, e),
+                }
+            }
+            if let Some(idx) = append {
+                self.append_library_entry(idx);
+            }
+            if let Some(idx) = remove {
+                self.library.entries.remove(idx);
+                self.editing_tags = None;
+                tags_changed = true;
+            }
+            if tags_changed {
+                self.save_library();
+            }
+        });
+    }
+
+    /// Adds the measures of a library score after the open one, as written rather than as
+    /// arranged, and opens the result.
+    fn append_library_entry(&mut self, idx: usize) {
+        let entry = &self.library.entries[idx];
+        let Some(mut score) = self.score_arrangement.written(self.score.as_ref()).cloned() else {
+            return;
+        };
+        let appended = entry.load_score().and_then(|other| score.append(&other));
+        match appended {
+            Ok(()) => {
+                self.setlist.current = None;
+                self.set_score(score);
+            }
+            Err(e) => eprintln!("Failed to append {}: {}", entry.file_name, e),
+        }
+    }
+
+    /// Reads the saved annotations. A file that can't be read is moved aside, or never saved
+    /// over if even that fails, and the annotations panel says so.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_annotations(&mut self) {
+        let path = std::path::Path::new(ANNOTATIONS_FILE);
+        let e = match AnnotationStore::load(path) {
+            Ok(store) => {
+                self.annotation_store = store;
+                return;
+            }
+            Err(e) => e,
+        };
+        let message = match AnnotationStore::move_aside(path) {
+            Ok(backup) => format!(
+                "Saved annotations couldn't be read and were moved to {}: {}",
+                backup.display(),
+                e
+            ),
+            Err(move_error) => {
+                self.annotations_read_only = true;
+                format!(
+                    "Saved annotations couldn't be read, changes won't be saved over them: {} ({})",
+                    e, move_error
+                )
+            }
+        };
+        eprintln!("{}", message);
+        self.annotation_message = Some(message);
+    }
+
+    fn save_annotations(&mut self) {
+        // Scores without any annotations left don't need an entry
+        self.annotation_store
+            .scores
+            .retain(|_, annotations| !annotations.is_empty());
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.annotations_read_only {
+            return;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Err(e) = self
+            .annotation_store
+            .save(std::path::Path::new(ANNOTATIONS_FILE))
+        {
+            eprintln!("Failed to save annotations: {}", e);
+        }
+    }
+
+    fn export_annotations(&self) {
+        let written = self.score_arrangement.written(self.score.as_ref());
+        let (Some(score), Some(fingerprint)) = (written, self.score_arrangement.fingerprint())
+        else {
+            return;
+        };
+        let annotations = self
+            .annotation_store
+            .scores
+            .get(fingerprint)
+            .cloned()
+            .unwrap_or_default();
+        let json = match AnnotationBundle::new(score, &annotations).to_json() {
+            Ok(json) => json,
+            Err(e) => {
+                eprintln!("Failed to export annotations: {}", e);
+                return;
+            }
+        };
+
+        let task = rfd::AsyncFileDialog::new()
+            .add_filter("Annotations", &[BUNDLE_EXTENSION])
+            .set_file_name(format!("annotations.{}", BUNDLE_EXTENSION))
+            .save_file();
+
+        execute(async move {
+            if let Some(file) = task.await {
+                if let Err(e) = file.write(json.as_bytes()).await {
+                    eprintln!("Failed to write annotations: {}", e);
+                }
+            }
+        });
+    }
+
+    fn pick_annotation_bundle(&self, ctx: &egui::Context) {
+        let sender = self.annotation_channel.0.clone();
+        let task = rfd::AsyncFileDialog::new()
+            .add_filter("Annotations", &[BUNDLE_EXTENSION])
+            .pick_file();
+        let ctx = ctx.clone();
+
+        execute(async move {
+            if let Some(file) = task.await {
+                let data = file.read().await;
+                let _ = sender.send(String::from_utf8_lossy(&data).to_string());
+            }
+            ctx.request_repaint();
+        });
+    }
+
+    fn import_annotations(&mut self, json: &str) {
+        let bundle = match AnnotationBundle::from_json(json) {
+            Ok(bundle) => bundle,
+            Err(e) => {
+                self.annotation_message = Some(format!("Not an annotation bundle: {}", e));
+                return;
+            }
+        };
+        let Some(fingerprint) = self.score_arrangement.fingerprint() else {
+            return;
+        };
+        if bundle.score_fingerprint != *fingerprint {
+            self.annotation_message = Some(format!(
+                "These annotations belong to a different score{}",
+                bundle
+                    .score_title
+                    .map(|title| format!(" ({})", title))
+                    .unwrap_or_default()
+            ));
+            return;
+        }
+        self.annotation_store
+            .scores
+            .entry(fingerprint.clone())
+            .or_default()
+            .merge(bundle.annotations);
+        self.annotation_message = Some("Annotations imported".to_string());
+        self.save_annotations();
+    }
+
+    fn ui_annotations(&mut self, ui: &mut egui::Ui) {
+        let Some(fingerprint) = self.score_arrangement.fingerprint().cloned() else {
+            return;
+        };
+        let measures = self
+            .score
+            .as_ref()
+            .map_or(1, |score| score.measures.len().max(1));
+        let mut changed = false;
+        let mut go_to = None;
+        let mut add_variant = None;
+        ui.group(|ui| {
+            ui.heading("Annotations");
+            let draft = &mut self.annotation_draft;
+            let annotations: &mut Annotations =
+                self.annotation_store.scores.entry(fingerprint).or_default();
+            draft.measure = draft.measure.clamp(1, measures);
+
+            ui.horizontal(|ui| {
+                ui.label("Measure:");
+                ui.add(egui::DragValue::new(&mut draft.measure).range(1..=measures));
+                if ui.button("Current").clicked() {
+                    draft.measure = self.current_measure_index + 1;
+                    draft.division = self.current_division_index;
+                }
+            });
+
+            egui::CollapsingHeader::new(format!("Comments ({})", annotations.comments.len()))
+                .id_salt("annotation_comments")
+                .show(ui, |ui| {
+                    annotations.comments.retain(|comment| {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{}: {}", comment.measure, comment.text));
+                            let keep = !ui.small_button("🗑").clicked();
+                            changed |= !keep;
+                            keep
+                        })
+                        .inner
+                    });
+                    ui.horizontal(|ui| {
+                        ui.text_edit_singleline(&mut draft.comment);
+                        if ui
+                            .add_enabled(!draft.comment.trim().is_empty(), egui::Button::new("Add"))
+                            .clicked()
+                        {
+                            annotations.comments.push(Comment {
+                                measure: draft.measure,
+                                text: std::mem::take(&mut draft.comment).trim().to_string(),
+                            });
+                            changed = true;
+                        }
+                    });
+                });
+
+            egui::CollapsingHeader::new(format!("Fingerings ({})", annotations.fingerings.len()))
+                .id_salt("annotation_fingerings")
+                .show(ui, |ui| {
+                    annotations.fingerings.retain(|fingering| {
+                        ui.horizontal(|ui| {
+                            ui.label(format!(
+                                "Measure {}, division {}, string {}: {}",
+                                fingering.measure,
+                                fingering.division + 1,
+                                fingering.string,
+                                finger_name(fingering.finger)
+                            ));
+                            let keep = !ui.small_button("🗑").clicked();
+                            changed |= !keep;
+                            keep
+                        })
+                        .inner
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Division:");
+                        ui.add(egui::DragValue::new(&mut draft.division));
+                        ui.label("String:");
+                        ui.add(egui::DragValue::new(&mut draft.string).range(1..=6));
+                    });
+                    ui.horizontal(|ui| {
+                        for finger in 0..=4 {
+                            ui.selectable_value(&mut draft.finger, finger, finger_name(finger));
+                        }
+                        if ui.button("Add").clicked() {
+                            annotations.merge(Annotations {
+                                fingerings: vec![FingeringSuggestion {
+                                    measure: draft.measure,
+                                    division: draft.division,
+                                    string: draft.string,
+                                    finger: draft.finger,
+                                }],
+                                ..Default::default()
+                            });
+                            changed = true;
+                        }
+                    });
+                });
+
+            let variants = annotations.fingering_variants.len();
+            egui::CollapsingHeader::new(format!("Fingering variants ({})", variants))
+                .id_salt("annotation_fingering_variants")
+                .show(ui, |ui| {
+                    annotations.fingering_variants.retain(|variant| {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("Measure {}: {}", variant.measure, variant.name));
+                            let keep = !ui.small_button("🗑").clicked();
+                            changed |= !keep;
+                            keep
+                        })
+                        .inner
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Position:");
+                        ui.add(egui::DragValue::new(&mut draft.variant_position).range(0..=19))
+                            .on_hover_text("Fret of the index finger, 0 for open position");
+                        if ui.button("Add").clicked() {
+                            add_variant = Some((draft.measure - 1, draft.variant_position));
+                        }
+                    });
+                });
+
+            egui::CollapsingHeader::new(format!("Loops ({})", annotations.loops.len()))
+                .id_salt("annotation_loops")
+                .show(ui, |ui| {
+                    annotations.loops.retain(|region| {
+                        ui.horizontal(|ui| {
+                            ui.label(format!(
+                                "{}: measures {}–{}",
+                                region.name, region.start_measure, region.end_measure
+                            ));
+                            let keep = !ui.small_button("🗑").clicked();
+                            changed |= !keep;
+                            keep
+                        })
+                        .inner
+                    });
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::TextEdit::singleline(&mut draft.loop_name)
+                                .hint_text("Name")
+                                .desired_width(80.0),
+                        );
+                        ui.label("to measure");
+                        draft.loop_end = draft.loop_end.clamp(draft.measure, measures);
+                        ui.add(
+                            egui::DragValue::new(&mut draft.loop_end)
+                                .range(draft.measure..=measures),
+                        );
+                        if ui.button("Add").clicked() {
+                            let name = std::mem::take(&mut draft.loop_name).trim().to_string();
+                            annotations.loops.push(LoopRegion {
+                                name: if name.is_empty() {
+                                    format!("Loop {}", annotations.loops.len() + 1)
+                                } else {
+                                    name
+                                },
+                                start_measure: draft.measure,
+                                end_measure: draft.loop_end,
+                            });
+                            changed = true;
+                        }
+                    });
+                });
+
+            egui::CollapsingHeader::new(format!("Flags ({})", annotations.flags.len()))
+                .id_salt("annotation_flags")
+                .show(ui, |ui| {
+                    if annotations.flags.is_empty() {
+                        ui.label(format!(
+                            "Press {} during playback to flag the measure being played",
+                            FLAG_KEY.name()
+                        ));
+                    }
+                    annotations.flags.retain(|flag| {
+                        ui.horizontal(|ui| {
+                            if ui
+                                .small_button("▶")
+                                .on_hover_text("Play from this measure")
+                                .clicked()
+                            {
+                                go_to = Some(flag.measure - 1);
+                            }
+                            let mut label =
+                                format!("{} at {}", flag.measure, format_time(flag.seconds));
+                            if !flag.note.is_empty() {
+                                label += &format!(": {}", flag.note);
+                            }
+                            ui.label(label);
+                            let keep = !ui.small_button("🗑").clicked();
+                            changed |= !keep;
+                            keep
+                        })
+                        .inner
+                    });
+                });
+
+            egui::CollapsingHeader::new(format!("Goals ({})", annotations.goals.len()))
+                .id_salt("annotation_goals")
+                .show(ui, |ui| {
+                    annotations.goals.retain_mut(|goal| {
+                        ui.horizontal(|ui| {
+                            changed |= ui.checkbox(&mut goal.done, &goal.text).changed();
+                            if let Some(tempo) = goal.target_tempo {
+                                ui.label(format!("at {} BPM", tempo));
+                            }
+                            let keep = !ui.small_button("🗑").clicked();
+                            changed |= !keep;
+                            keep
+                        })
+                        .inner
+                    });
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::TextEdit::singleline(&mut draft.goal)
+                                .hint_text("e.g. Play cleanly")
+                                .desired_width(120.0),
+                        );
+                        let mut has_tempo = draft.goal_tempo.is_some();
+                        if ui.checkbox(&mut has_tempo, "at").changed() {
+                            draft.goal_tempo = has_tempo.then_some(self.tempo.max(1));
+                        }
+                        if let Some(tempo) = &mut draft.goal_tempo {
+                            ui.add(egui::DragValue::new(tempo).range(1..=300).suffix(" BPM"));
+                        }
+                        if ui
+                            .add_enabled(!draft.goal.trim().is_empty(), egui::Button::new("Add"))
+                            .clicked()
+                        {
+                            annotations.goals.push(Goal {
+                                text: std::mem::take(&mut draft.goal).trim().to_string(),
+                                target_tempo: draft.goal_tempo,
+                                done: false,
+                            });
+                            changed = true;
+                        }
+                    });
+                });
+
+            ui.horizontal(|ui| {
+                if ui
+                    .button("Export for student/teacher…")
+                    .on_hover_text("Saves the annotations only, without the score")
+                    .clicked()
+                {
+                    self.export_annotations();
+                }
+                if ui.button("Import…").clicked() {
+                    self.pick_annotation_bundle(ui.ctx());
+                }
+            });
+            if let Some(message) = &self.annotation_message {
+                ui.label(message);
+            }
+        });
+        if let Some((measure, position)) = add_variant {
+            changed |= self.add_fingering_variant(measure, position);
+        }
+        if changed {
+            // Indices past a removed flag no longer point at the flag just dropped
+            self.flag_note = None;
+            // The variant played in a measure may just have been removed
+            let removed: Vec<usize> = self
+                .fingering_choices
+                .iter()
+                .filter(|(&measure, (name, _))| {
+                    !self
+                        .fingering_variants(measure)
+                        .iter()
+                        .any(|variant| &variant.name == name)
+                })
+                .map(|(&measure, _)| measure)
+                .collect();
+            for measure in removed {
+                self.choose_fingering_variant(measure, None);
+            }
+            self.save_annotations();
+        }
+        if let Some(measure) = go_to {
+            self.seek_to(measure, 0);
+        }
+    }
+
+    /// The fingering variants stored for `measure`, by index.
+    fn fingering_variants(&self, measure: usize) -> Vec<&FingeringVariant> {
+        self.score_arrangement
+            .fingerprint()
+            .and_then(|fingerprint| self.annotation_store.scores.get(fingerprint))
+            .map(|annotations| &annotations.fingering_variants[..])
+            .unwrap_or_default()
+            .iter()
+            .filter(|variant| variant.measure == measure + 1)
+            .collect()
+    }
+
+    /// Stores `measure`, by index, as written but fingered in `position`. Returns false if it
+    /// can't be played there or the same fingering is stored already.
+    fn add_fingering_variant(&mut self, measure: usize, position: u8) -> bool {
+        let (Some(score), Some(fingerprint)) = (&self.score, self.score_arrangement.fingerprint())
+        else {
+            return false;
+        };
+        let Some(mut written) = score.measures.get(measure).cloned() else {
+            return false;
+        };
+        if let Some((_, frets)) = self.fingering_choices.get(&measure) {
+            apply_frets(&mut written, frets);
+        }
+        let capo_fret = self.configs.guitar_configs[self.configs.active_guitar].capo_fret;
+        let Some(frets) = frets_in_position(&written, position, capo_fret) else {
+            self.annotation_message = Some(format!(
+                "Measure {} can't be played in position {}",
+                measure + 1,
+                position
+            ));
+            return false;
+        };
+        let annotations = self
+            .annotation_store
+            .scores
+            .entry(fingerprint.clone())
+            .or_default();
+        let known = annotations
+            .fingering_variants
+            .iter()
+            .any(|variant| variant.measure == measure + 1 && variant.frets == frets);
+        if known || frets == measure_frets(&written) {
+            self.annotation_message = Some(format!(
+                "Measure {} has that fingering already",
+                measure + 1
+            ));
+            return false;
+        }
+        annotations.fingering_variants.push(FingeringVariant {
+            measure: measure + 1,
+            name: match position {
+                0 => "Open position".to_string(),
+                position => format!("Position {}", position),
+            },
+            frets,
+        });
+        self.annotation_message = None;
+        true
+    }
+
+    /// Shows and plays `measure`, by index, with the fingering variant named `name`, or as
+    /// written for `None`. The choice lasts for the session, the score itself is left alone.
+    fn choose_fingering_variant(&mut self, measure: usize, name: Option<String>) {
+        let frets = name.as_ref().and_then(|name| {
+            self.fingering_variants(measure)
+                .into_iter()
+                .find(|variant| &variant.name == name)
+                .map(|variant| variant.frets.clone())
+        });
+        let Some(score) = &mut self.score else {
+            return;
+        };
+        let Some(target) = score.measures.get_mut(measure) else {
+            return;
+        };
+        if let Some((_, written)) = self.fingering_choices.remove(&measure) {
+            apply_frets(target, &written);
+        }
+        if let (Some(name), Some(frets)) = (name, frets) {
+            let written = measure_frets(target);
+            if apply_frets(target, &frets) {
+                self.fingering_choices.insert(measure, (name, written));
+            }
+        }
+        // Ties only hold between notes on the same fret
+        score.resolve_timing();
+        self.timeline = Timeline::from_score(score);
+        self.score_revision += 1;
+        // Notes scheduled ahead were taken from the old frets, the next update schedules them
+        // again like after a seek
+        self.unschedule_notes();
+    }
+
+    fn ui_setlist(&mut self, ui: &mut egui::Ui) {
+        ui.group(|ui| {
+            let mut play = None;
+            let mut remove = None;
+            let mut swap = None;
+            let count = self.setlist.entries.len();
+            for (idx, entry) in self.setlist.entries.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    let current = self.setlist.current == Some(idx);
+                    if ui
+                        .selectable_label(current, format!("{}. {}", idx + 1, entry.name))
+                        .on_hover_text("Play from here")
+                        .clicked()
+                    {
+                        play = Some(idx);
+                    }
+                    if ui.add_enabled(idx > 0, egui::Button::new("⏶")).clicked() {
+                        swap = Some((idx - 1, idx));
+                    }
+                    if ui
+                        .add_enabled(idx + 1 < count, egui::Button::new("⏷"))
+                        .clicked()
+                    {
+                        swap = Some((idx, idx + 1));
+                    }
+                    if ui.button("🗑").clicked() {
+                        remove = Some(idx);
+                    }
+                });
+            }
+            if count == 0 {
+                ui.label("No scores queued");
+            }
+            if let Some((a, b)) = swap {
+                self.setlist.swap(a, b);
+            }
+            if let Some(idx) = remove {
+                self.setlist.remove(idx);
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("Add scores…").clicked() {
+                    self.add_to_setlist(ui.ctx());
+                }
+                if ui
+                    .add_enabled(count > 0, egui::Button::new("Play setlist"))
+                    .clicked()
+                {
+                    play = Some(0);
+                }
+            });
+            ui.checkbox(&mut self.setlist.auto_advance, "Advance automatically");
+            ui.add_enabled(
+                self.setlist.auto_advance,
+                egui::Slider::new(&mut self.setlist.gap_seconds, 0.0..=30.0)
+                    .step_by(0.5)
+                    .text("Gap [s]"),
+            );
+            if let Some(seconds) = self.setlist.time_until_next() {
+                ui.horizontal(|ui| {
+                    ui.label(format!("Next score in {:.0} seconds", seconds.ceil()));
+                    if ui.button("Cancel").clicked() {
+                        self.setlist.cancel_advance();
+                    }
+                });
+            }
+
+            if let Some(idx) = play {
+                self.setlist.cancel_advance();
+                self.play_setlist_entry(idx);
+            }
+        });
+    }
+
+    fn ui_backing_track(&mut self, ui: &mut egui::Ui) {
+        ui.group(|ui| {
+            if let Some(track) = &self.backing_track {
+                ui.label(format!(
+                    "{} ({:.1} seconds)",
+                    track.name,
+                    track.duration_seconds()
+                ));
+            } else {
+                ui.label("No backing track loaded");
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Load audio…").clicked() {
+                    self.load_backing_track(ui.ctx());
+                }
+                if self.backing_track.is_some() && ui.button("Remove").clicked() {
+                    self.stop_playback();
+                    self.backing_track = None;
+                    self.processing_in_progress = None;
+                    self.proposed_alignment = None;
+                    self.suggested_sections.clear();
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Volume:");
+                if ui
+                    .add(
+                        egui::Slider::new(&mut self.configs.mixer.backing_track.volume, 0.0..=1.0)
+                            .step_by(0.01),
+                    )
+                    .changed()
+                {
+                    self.configs.mixer.apply(&mut self.audio_player);
+                }
+            });
+            ui.checkbox(
+                &mut self.configs.stretch_backing_track,
+                "Follow playback tempo (time-stretch)",
+            );
+            ui.checkbox(
+                &mut self.configs.pitch_shift_backing_track,
+                "Follow capo (pitch-shift)",
+            );
+            if self.processing_in_progress.is_some() {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Preparing backing track…");
+                });
+            }
+            self.ui_backing_track_alignment(ui);
+        });
+    }
+
+    fn ui_backing_track_alignment(&mut self, ui: &mut egui::Ui) {
+        let rate = self.backing_track_processing().rate;
+        let score_tempo = self.score.as_ref().map_or(120, |score| score.tempo) as f32;
+        let Some(track) = &mut self.backing_track else {
+            return;
+        };
+
+        ui.separator();
+        ui.label("Alignment");
+        let previous_offset = track.offset_seconds;
+        ui.horizontal(|ui| {
+            ui.label("First beat at:");
+            if ui.small_button("-10 ms").clicked() {
+                track.offset_seconds -= 0.01;
+            }
+            ui.add(
+                egui::DragValue::new(&mut track.offset_seconds)
+                    .speed(0.005)
+                    .fixed_decimals(3)
+                    .suffix(" s"),
+            );
+            if ui.small_button("+10 ms").clicked() {
+                track.offset_seconds += 0.01;
+            }
+        });
+        // Nudge the running track instead of waiting for the next playback
+        let nudge = track.offset_seconds - previous_offset;
+        if nudge != 0.0 && self.is_playing {
+            self.audio_player.seek_backing_track_by(nudge / rate);
+        }
+
+        ui.horizontal(|ui| {
+            let mut has_tempo = track.tempo.is_some();
+            if ui.checkbox(&mut has_tempo, "Recording tempo:").changed() {
+                track.tempo = has_tempo.then_some(score_tempo);
+            }
+            if let Some(tempo) = &mut track.tempo {
+                ui.add(
+                    egui::DragValue::new(tempo)
+                        .speed(0.1)
+                        .range(20.0..=300.0)
+                        .fixed_decimals(1)
+                        .suffix(" BPM"),
+                );
+            } else {
+                ui.label("same as score");
+            }
+        });
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!self.detecting_beats, egui::Button::new("Detect beats"))
+                .clicked()
+            {
+                self.detecting_beats = true;
+                let frames = track.frames.clone();
+                let sample_rate = track.sample_rate;
+                let sender = self.beat_detection_channel.0.clone();
+                let ctx = ui.ctx().clone();
+                execute(async move {
+                    let _ = sender.send(detect_beats(&frames, sample_rate, score_tempo));
+                    ctx.request_repaint();
+                });
+            }
+            if self.detecting_beats {
+                ui.spinner();
+            }
+        });
+
+        if let Some(alignment) = self.proposed_alignment {
+            ui.label(format!(
+                "Detected {:.1} BPM, first beat at {:.3} s",
+                alignment.tempo, alignment.offset_seconds
+            ));
+            ui.horizontal(|ui| {
+                if ui.button("Apply").clicked() {
+                    track.offset_seconds = alignment.offset_seconds;
+                    track.tempo = Some(alignment.tempo);
+                    self.proposed_alignment = None;
+                }
+                if ui.button("Dismiss").clicked() {
+                    self.proposed_alignment = None;
+                }
+            });
+        }
+    }
+
+    /// Maps boundaries found in the backing track onto measures that don't start a section yet.
+    fn suggest_sections(&mut self, boundaries: &[f32]) {
+        self.suggested_sections.clear();
+        let (Some(score), Some(track)) = (&self.score, &self.backing_track) else {
+            return;
+        };
+        for &seconds in boundaries {
+            let Some(measure) = track.measure_at(score, seconds) else {
+                continue;
+            };
+            let known = score.sections.iter().any(|s| s.measure == measure)
+                || self.suggested_sections.iter().any(|&(m, _)| m == measure);
+            if !known {
+                self.suggested_sections.push((measure, seconds));
+            }
+        }
+        if boundaries.is_empty() {
+            eprintln!("No section changes found in the backing track");
+        }
+    }
+
+    fn ui_sections(&mut self, ui: &mut egui::Ui) {
+        ui.group(|ui| {
+            let Some(score) = &mut self.score else {
+                ui.label("No score loaded");
+                return;
+            };
+
+            let mut removed = None;
+            for (idx, section) in score.sections.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut section.name).desired_width(80.0));
+                    ui.label("at measure");
+                    // Shown one based like the measure numbers musicians use
+                    let mut measure = section.measure + 1;
+                    if ui
+                        .add(egui::DragValue::new(&mut measure).range(1..=score.measures.len()))
+                        .changed()
+                    {
+                        section.measure = measure - 1;
+                    }
+                    if ui.small_button("✖").clicked() {
+                        removed = Some(idx);
+                    }
+                });
+            }
+            if let Some(idx) = removed {
+                score.sections.remove(idx);
+            }
+
+            if ui.button("Add at current measure").clicked() {
+                add_section(score, self.current_measure_index);
+            }
+
+            if self.backing_track.is_some() {
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(
+                            !self.detecting_sections,
+                            egui::Button::new("Suggest from backing track"),
+                        )
+                        .clicked()
+                    {
+                        if let Some(track) = &self.backing_track {
+                            self.detecting_sections = true;
+                            let frames = track.frames.clone();
+                            let sample_rate = track.sample_rate;
+                            let sender = self.section_detection_channel.0.clone();
+                            let ctx = ui.ctx().clone();
+                            execute(async move {
+                                let _ =
+                                    sender.send(detect_section_boundaries(&frames, sample_rate));
+                                ctx.request_repaint();
+                            });
+                        }
+                    }
+                    if self.detecting_sections {
+                        ui.spinner();
+                    }
+                });
+            }
+
+            let mut accepted = Vec::new();
+            let mut dismissed = None;
+            for (idx, &(measure, seconds)) in self.suggested_sections.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "Measure {} ({}:{:04.1})",
+                        measure + 1,
+                        (seconds / 60.0) as u32,
+                        seconds % 60.0
+                    ));
+                    if ui.small_button("Add").clicked() {
+                        accepted.push(idx);
+                    }
+                    if ui.small_button("✖").clicked() {
+                        dismissed = Some(idx);
+                    }
+                });
+            }
+            if !self.suggested_sections.is_empty() && ui.button("Add all suggestions").clicked() {
+                accepted = (0..self.suggested_sections.len()).collect();
+            }
+            for &idx in &accepted {
+                add_section(score, self.suggested_sections[idx].0);
+            }
+            let mut idx = 0;
+            self.suggested_sections.retain(|_| {
+                idx += 1;
+                !accepted.contains(&(idx - 1)) && dismissed != Some(idx - 1)
+            });
+        });
+    }
+
+    fn load_backing_track(&mut self, ctx: &egui::Context) {
+        self.stop_playback();
+        let sender = self.backing_track_channel.0.clone();
+        let task = rfd::AsyncFileDialog::new()
+            .add_filter("Audio", &["wav", "ogg", "mp3", "flac"])
+            .pick_file();
+        let ctx = ctx.clone();
+
+        execute(async move {
+            if let Some(file) = task.await {
+                let name = file.file_name();
+                let data = file.read().await;
+                match BackingTrack::from_bytes(name, data) {
+                    Ok(track) => {
+                        let _ = sender.send(track);
+                    }
+                    Err(e) => eprintln!("Failed to load backing track: {}", e),
+                }
+            }
+            ctx.request_repaint();
+        });
+    }
+
+    fn load_impulse_response(&mut self, ctx: &egui::Context) {
+        let sender = self.impulse_response_channel.0.clone();
+        let sample_rate = self.audio_player.sample_rate;
+        let task = rfd::AsyncFileDialog::new()
+            .add_filter("Audio", &["wav", "ogg", "flac"])
+            .pick_file();
+        let ctx = ctx.clone();
+
+        execute(async move {
+            if let Some(file) = task.await {
+                let name = file.file_name();
+                let data = file.read().await;
+                match ImpulseResponse::from_bytes(name, data, sample_rate) {
+                    Ok(impulse_response) => {
+                        let _ = sender.send(impulse_response);
+                    }
+                    Err(e) => eprintln!("Failed to load impulse response: {}", e),
+                }
+            }
+            ctx.request_repaint();
+        });
+    }
+
+    /// Two profiles to flip between while playing. Notes already ringing keep their sound and
+    /// the next ones use the other profile, so the switch crossfades on its own.
+    fn ui_ab_comparison(&mut self, ui: &mut egui::Ui, changed_config: &mut bool) {
+        let configs = &mut self.configs;
+        ui.horizontal(|ui| {
+            let mut enabled = configs.ab_comparison.is_some();
+            if ui
+                .checkbox(&mut enabled, "A/B")
+                .on_hover_text("Switch between two profiles during playback")
+                .changed()
+            {
+                configs.ab_comparison = enabled.then_some((configs.active_guitar, 0));
+            }
+            let Some((a, b)) = &mut configs.ab_comparison else {
+                return;
+            };
+            for (label, profile) in [("A", &mut *a), ("B", &mut *b)] {
+                egui::ComboBox::from_id_salt(("ab_profile", label))
+                    .selected_text(format!(
+                        "{}: {}",
+                        label,
+                        profile_name(&configs.guitar_configs[*profile])
+                    ))
+                    .show_ui(ui, |ui| {
+                        for (index, guitar) in configs.guitar_configs.iter().enumerate() {
+                            ui.selectable_value(profile, index, profile_name(guitar));
+                        }
+                    });
+            }
+            let (a, b) = (*a, *b);
+            for (label, profile) in [("A", a), ("B", b)] {
+                if ui
+                    .selectable_label(configs.active_guitar == profile, label)
+                    .clicked()
+                    && configs.active_guitar != profile
+                {
+                    configs.active_guitar = profile;
+                    *changed_config = true;
+                }
+            }
+        });
+    }
+
+    fn ui_guitar_settings(&mut self, ui: &mut egui::Ui, changed_config: &mut bool) {
+        ui.group(|ui| {
+            egui::ComboBox::from_label("Guitar Type")
+                .selected_text(profile_name(
+                    &self.configs.guitar_configs[self.configs.active_guitar],
+                ))
+                .show_ui(ui, |ui| {
+                    for (index, guitar) in self.configs.guitar_configs.iter().enumerate() {
+                        let checked = index == self.configs.active_guitar;
+                        if ui.selectable_label(checked, profile_name(guitar)).clicked() {
+                            self.configs.active_guitar = index;
+                            *changed_config = true;
+                        }
+                    }
+                });
+
+            self.ui_ab_comparison(ui, changed_config);
+
+            // Every profile can be tweaked, the factory values are only a starting point
+            egui::Grid::new("guitar_config")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    let config = &mut self.configs.guitar_configs[self.configs.active_guitar];
+
+                    ui.label("Decay:");
+                    if ui
+                        .add(egui::Slider::new(&mut config.decay, 0.9..=1.0).step_by(0.001))
+                        .changed()
+                    {
+                        *changed_config = true;
+                    }
+                    ui.end_row();
+
+                    ui.label("String Damping:");
+                    if ui
+                        .add(
+                            egui::Slider::new(&mut config.string_damping, 0.0..=1.0).step_by(0.001),
+                        )
+                        .changed()
+                    {
+                        *changed_config = true;
+                    }
+                    ui.end_row();
+
+                    ui.label("Body Damping:");
+                    if ui
+                        .add(egui::Slider::new(&mut config.body_damping, 0.0..=1.0).step_by(0.001))
+                        .changed()
+                    {
+                        *changed_config = true;
+                    }
+                    ui.end_row();
+
+                    ui.label("Body Resonance:");
+                    if ui
+                        .add(
+                            egui::Slider::new(&mut config.body_resonance, 0.0..=500.0).step_by(0.1),
+                        )
+                        .changed()
+                    {
+                        *changed_config = true;
+                    }
+                    ui.end_row();
+
+                    ui.label("String Tension:");
+                    if ui
+                        .add(
+                            egui::Slider::new(&mut config.string_tension, 0.0..=1.0).step_by(0.001),
+                        )
+                        .changed()
+                    {
+                        *changed_config = true;
+                    }
+                    ui.end_row();
+
+                    ui.label("Scale Length [inch]:");
+                    if ui
+                        .add(egui::Slider::new(&mut config.scale_length, 10.0..=50.0).step_by(0.1))
+                        .changed()
+                    {
+                        *changed_config = true;
+                    }
+                    ui.end_row();
+
+                    ui.label("Pick Noise:");
+                    if ui
+                        .add(egui::Slider::new(&mut config.pick_noise, 0.0..=1.0).step_by(0.01))
+                        .on_hover_text("Scrape of the pick at the start of every note")
+                        .changed()
+                    {
+                        *changed_config = true;
+                    }
+                    ui.end_row();
+
+                    ui.label("Stereo Width:");
+                    if ui
+                        .add(egui::Slider::new(&mut config.stereo_width, 0.0..=1.0).step_by(0.01))
+                        .on_hover_text("Low strings to the left and high ones to the right")
+                        .changed()
+                    {
+                        *changed_config = true;
+                    }
+                    ui.end_row();
+
+                    ui.label("Body/Cabinet IR:");
+                    egui::ComboBox::from_id_salt("impulse_response")
+                        .selected_text(config.impulse_response.as_deref().unwrap_or("None"))
+                        .show_ui(ui, |ui| {
+                            *changed_config |= ui
+                                .selectable_value(&mut config.impulse_response, None, "None")
+                                .changed();
+                            for impulse_response in &self.audio_player.impulse_responses {
+                                let name = &impulse_response.name;
+                                *changed_config |= ui
+                                    .selectable_value(
+                                        &mut config.impulse_response,
+                                        Some(name.clone()),
+                                        name,
+                                    )
+                                    .changed();
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("IR Mix:");
+                    if ui
+                        .add_enabled(
+                            config.impulse_response.is_some(),
+                            egui::Slider::new(&mut config.impulse_response_mix, 0.0..=1.0)
+                                .step_by(0.01),
+                        )
+                        .changed()
+                    {
+                        *changed_config = true;
+                    }
+                    ui.end_row();
+
+                    ui.label("Chorus Mix:");
+                    if ui
+                        .add(egui::Slider::new(&mut config.chorus.mix, 0.0..=1.0).step_by(0.01))
+                        .changed()
+                    {
+                        *changed_config = true;
+                    }
+                    ui.end_row();
+
+                    ui.label("Chorus Rate [Hz]:");
+                    if ui
+                        .add_enabled(
+                            config.chorus.mix > 0.0,
+                            egui::Slider::new(&mut config.chorus.rate, 0.05..=5.0)
+                                .logarithmic(true),
+                        )
+                        .changed()
+                    {
+                        *changed_config = true;
+                    }
+                    ui.end_row();
+
+                    ui.label("Chorus Depth [ms]:");
+                    if ui
+                        .add_enabled(
+                            config.chorus.mix > 0.0,
+                            egui::Slider::new(&mut config.chorus.depth, 0.0..=MAX_DEPTH_MS)
+                                .step_by(0.1),
+                        )
+                        .changed()
+                    {
+                        *changed_config = true;
+                    }
+                    ui.end_row();
+
+                    ui.label("Chorus Delay [ms]:");
+                    if ui
+                        .add_enabled(
+                            config.chorus.mix > 0.0,
+                            egui::Slider::new(
+                                &mut config.chorus.delay,
+                                MIN_DELAY_MS..=MAX_DELAY_MS,
+                            )
+                            .step_by(0.1),
+                        )
+                        .on_hover_text("A millisecond or two with some feedback makes a flanger")
+                        .changed()
+                    {
+                        *changed_config = true;
+                    }
+                    ui.end_row();
+
+                    ui.label("Chorus Feedback:");
+                    if ui
+                        .add_enabled(
+                            config.chorus.mix > 0.0,
+                            egui::Slider::new(&mut config.chorus.feedback, 0.0..=MAX_FEEDBACK)
+                                .step_by(0.01),
+                        )
+                        .changed()
+                    {
+                        *changed_config = true;
+                    }
+                    ui.end_row();
+
+                    if config.name == GuitarType::Electric {
+                        ui.label("Sustain/Feedback:");
+                        if ui
+                            .add(egui::Slider::new(&mut config.feedback, 0.0..=1.0).step_by(0.01))
+                            .on_hover_text("Held notes swell with feedback instead of dying away")
+                            .changed()
+                        {
+                            *changed_config = true;
+                        }
+                        ui.end_row();
+
+                        ui.label("Drive:");
+                        if ui
+                            .add(egui::Slider::new(&mut config.drive.gain, 0.0..=1.0).step_by(0.01))
+                            .on_hover_text("Overdrive of the amp, 0 for a clean sound")
+                            .changed()
+                        {
+                            *changed_config = true;
+                        }
+                        ui.end_row();
+
+                        ui.label("Drive Tone:");
+                        if ui
+                            .add_enabled(
+                                config.drive.gain > 0.0,
+                                egui::Slider::new(&mut config.drive.tone, 0.0..=1.0).step_by(0.01),
+                            )
+                            .changed()
+                        {
+                            *changed_config = true;
+                        }
+                        ui.end_row();
+
+                        ui.label("Drive Level:");
+                        if ui
+                            .add_enabled(
+                                config.drive.gain > 0.0,
+                                egui::Slider::new(&mut config.drive.level, 0.0..=1.0).step_by(0.01),
+                            )
+                            .changed()
+                        {
+                            *changed_config = true;
+                        }
+                        ui.end_row();
+                    }
+                });
+            let sample_rate = self.audio_player.sample_rate;
+            let config = &mut self.configs.guitar_configs[self.configs.active_guitar];
+            egui::CollapsingHeader::new("EQ")
+                .id_salt("guitar_eq")
+                .show(ui, |ui| {
+                    *changed_config |= ui_eq_editor(ui, &mut config.eq, sample_rate);
+                });
+            egui::CollapsingHeader::new("Effects chain")
+                .id_salt("guitar_effects")
+                .show(ui, |ui| {
+                    *changed_config |= ui_effect_chain(ui, &mut config.effects);
+                });
+            if ui
+                .button("Load IR…")
+                .on_hover_text("Play through the body or speaker cabinet of an impulse response")
+                .clicked()
+            {
+                self.load_impulse_response(ui.ctx());
+            }
+            let config = &mut self.configs.guitar_configs[self.configs.active_guitar];
+            if ui
+                .checkbox(&mut config.sympathetic_resonance, "Sympathetic resonance")
+                .on_hover_text("Open strings ring along with the notes, at some CPU cost")
+                .changed()
+            {
+                *changed_config = true;
+            }
+            if ui
+                .add_enabled(!config.is_factory(), egui::Button::new("Reset to factory"))
+                .clicked()
+            {
+                config.reset_to_factory();
+                *changed_config = true;
             }
+        });
+    }
 
-            self.render_tab_view(ui);
+    fn ui_mixer(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        ui.group(|ui| {
+            egui::Grid::new("mixer_grid").num_columns(5).show(ui, |ui| {
+                let mixer = &mut self.configs.mixer;
+                for (name, channel) in [
+                    ("Guitar", &mut mixer.guitar),
+                    ("Backing track", &mut mixer.backing_track),
+                ] {
+                    changed |= ui_mixer_channel(ui, name, channel);
+                    ui.end_row();
+                }
+            });
+            ui.separator();
+            let limiter = &mut self.configs.mixer.limiter;
+            ui.horizontal(|ui| {
+                changed |= ui
+                    .checkbox(&mut limiter.limit, "Limiter")
+                    .on_hover_text("Holds the peaks of dense chords under the ceiling")
+                    .changed();
+                changed |= ui
+                    .add_enabled(
+                        limiter.limit,
+                        egui::Slider::new(&mut limiter.ceiling_db, MIN_CEILING_DB..=0.0)
+                            .text("Ceiling [dB]")
+                            .step_by(0.1),
+                    )
+                    .changed();
+            });
+            ui.horizontal(|ui| {
+                changed |= ui
+                    .checkbox(&mut limiter.compress, "Compressor")
+                    .on_hover_text("Evens out loud and quiet passages before the limiter")
+                    .changed();
+                changed |= ui
+                    .add_enabled(
+                        limiter.compress,
+                        egui::Slider::new(&mut limiter.threshold_db, MIN_THRESHOLD_DB..=0.0)
+                            .text("Threshold [dB]")
+                            .step_by(0.5),
+                    )
+                    .changed();
+                changed |= ui
+                    .add_enabled(
+                        limiter.compress,
+                        egui::Slider::new(&mut limiter.ratio, 1.0..=MAX_RATIO)
+                            .text("Ratio")
+                            .step_by(0.1),
+                    )
+                    .changed();
+            });
+            if self.is_playing && (limiter.limit || limiter.compress) {
+                ui.label(format!(
+                    "Gain reduction: {:.1} dB",
+                    self.audio_player.limiter_reduction_db()
+                ));
+            }
         });
+        if changed {
+            self.configs.mixer.apply(&mut self.audio_player);
+        }
+    }
 
-        ctx.request_repaint();
+    fn ui_audio_settings(&mut self, ui: &mut egui::Ui) {
+        ui.group(|ui| {
+            ui.horizontal(|ui| {
+                ui.label("Max voices:");
+                if ui
+                    .add(egui::Slider::new(
+                        &mut self.configs.max_voices,
+                        1..=VOICE_LIMIT,
+                    ))
+                    .changed()
+                {
+                    self.audio_player.set_max_voices(self.configs.max_voices);
+                }
+            });
+            egui::ComboBox::from_label("Voice stealing")
+                .selected_text(self.configs.voice_stealing.to_string())
+                .show_ui(ui, |ui| {
+                    for stealing in [VoiceStealing::Oldest, VoiceStealing::Quietest] {
+                        if ui
+                            .selectable_value(
+                                &mut self.configs.voice_stealing,
+                                stealing,
+                                stealing.to_string(),
+                            )
+                            .changed()
+                        {
+                            self.audio_player.set_voice_stealing(stealing);
+                        }
+                    }
+                });
+            ui.separator();
+            #[cfg(not(target_arch = "wasm32"))]
+            self.ui_output_device(ui);
+            self.ui_metronome(ui);
+            ui.separator();
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                self.ui_monitor(ui);
+                ui.separator();
+                self.ui_loopback_test(ui);
+                ui.separator();
+            }
+            ui.horizontal(|ui| {
+                let mut reproducible = self.configs.synthesis_seed.is_some();
+                if ui
+                    .checkbox(&mut reproducible, "Reproducible export")
+                    .on_hover_text("Use the same pluck noise on every export")
+                    .changed()
+                {
+                    self.configs.synthesis_seed = reproducible.then_some(0);
+                }
+                if let Some(seed) = &mut self.configs.synthesis_seed {
+                    ui.add(egui::DragValue::new(seed).prefix("Seed "));
+                }
+            });
+            ui.horizontal(|ui| {
+                #[cfg(not(target_arch = "wasm32"))]
+                egui::ComboBox::from_label("Audio format")
+                    .selected_text(match self.configs.export_audio_format {
+                        Some(format) => format.to_string(),
+                        None => "WAV".to_string(),
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.configs.export_audio_format, None, "WAV");
+                        for format in AudioFormat::ALL {
+                            ui.selectable_value(
+                                &mut self.configs.export_audio_format,
+                                Some(format),
+                                format.to_string(),
+                            );
+                        }
+                    });
+                #[cfg(not(target_arch = "wasm32"))]
+                let wav = self.configs.export_audio_format.is_none();
+                #[cfg(target_arch = "wasm32")]
+                let wav = true;
+                if wav {
+                    egui::ComboBox::from_label("Bit depth")
+                        .selected_text(self.configs.export_bit_depth.to_string())
+                        .show_ui(ui, |ui| {
+                            for bit_depth in BitDepth::ALL {
+                                ui.selectable_value(
+                                    &mut self.configs.export_bit_depth,
+                                    bit_depth,
+                                    bit_depth.to_string(),
+                                );
+                            }
+                        });
+                }
+            });
+            if ui
+                .add_enabled(self.score.is_some(), egui::Button::new("Export audio…"))
+                .on_hover_text("Render the score with the active guitar's effects to a file")
+                .clicked()
+            {
+                self.export_audio();
+            }
+        });
     }
-}
 
-impl TabApp {
-   
-        fn ui_about(&mut self, ctx: &egui::Context) {
-            egui::Window::new("About")
-                .open(&mut self.show_about)
-                .collapsible(false)
-                .resizable(true) // Allow resizing if the content is extensive
-                .vscroll(true)   // Enable vertical scrolling if content overflows
-                .show(ctx, |ui| {
-                    ui.vertical_centered(|ui| {
-                        ui.heading("Frequently Asked Questions");
-                        ui.separator();
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_loopback_test(&mut self) {
+        let Some(test) = &mut self.loopback_test else {
+            return;
+        };
+        if let Some(report) = test.update(&mut self.audio_player) {
+            self.loopback_test = None;
+            self.loopback_report = Some(report);
+        }
+    }
+
+    /// Practice mode, where playback waits at every division until the listener hears its
+    /// notes.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn ui_practice(&mut self, ui: &mut egui::Ui) {
+        if ui
+            .checkbox(&mut self.wait_for_notes, "Wait for the right notes")
+            .on_hover_text("Playback moves on to the next notes only once you've played these")
+            .changed()
+        {
+            if self.wait_for_notes {
+                if self.listener.is_none() {
+                    self.start_listener();
+                }
+            } else {
+                self.waiting_at = None;
+            }
+        }
+        ui.add_enabled(
+            self.wait_for_notes,
+            egui::Checkbox::new(&mut self.practice_play_notes, "Play the notes too"),
+        )
+        .on_hover_text(
+            "Hear the notes you are expected to play, the listener may hear them as well",
+        );
+        ui.add_enabled(
+            self.wait_for_notes,
+            egui::Checkbox::new(&mut self.hint_missed_notes, "Play missed notes as a hint"),
+        )
+        .on_hover_text(format!(
+            "After {} tries that miss the notes, hear how they should sound",
+            HINT_AFTER_MISSES
+        ));
+        if let Some(errors) = &self.error_map {
+            let mut clear = false;
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "Last run: notes missed in {} of {} measures",
+                    errors.measures_missed(),
+                    errors.measures_played()
+                ));
+                clear = ui.button("Clear").clicked();
+            });
+            ui.checkbox(&mut self.show_error_map, "Show missed measures")
+                .on_hover_text("Tints the measures by how often their notes weren't heard");
+            let skipped = errors.skipped();
+            let mut go_to = None;
+            let mut loop_skipped = None;
+            if !skipped.is_empty() {
+                ui.label("Stopped playing in:")
+                    .on_hover_text("Nothing was heard through these measures, try looping them");
+            }
+            for run in skipped {
+                let (first, last) = (run.start() + 1, run.end() + 1);
+                ui.horizontal(|ui| {
+                    if ui
+                        .small_button("▶")
+                        .on_hover_text("Play from this measure")
+                        .clicked()
+                    {
+                        go_to = Some(first - 1);
+                    }
+                    ui.label(if first == last {
+                        format!("measure {}", first)
+                    } else {
+                        format!("measures {}–{}", first, last)
                     });
-    
-                    ui.add_space(10.0);
-    
-                    // Question 1
-                    ui.label(RichText::new("What is MusicXML?").size(18.0));
-                    ui.label(
-                        "MusicXML is an open, XML-based music notation file format designed for \
-                        the interchange of digital sheet music between applications. It allows for \
-                        the sharing of musical scores between different music notation software. \
-                        For more information, visit the official MusicXML website:",
-                    );
-                    ui.hyperlink("https://www.musicxml.com/");
-    
-                    ui.add_space(10.0);
-    
-                    // Question 2
-                    ui.label(RichText::new("How do I obtain MusicXML files?").size(18.0));
-                    ui.label(
-                        "You can create MusicXML files using music notation software that supports \
-                        exporting to MusicXML format, such as Finale, Sibelius, MuseScore, and others. \
-                        Additionally, you can find MusicXML files from online resources:",
-                    );
-                    ui.hyperlink("https://musescore.com/");
-                    ui.hyperlink("https://www.musicxml.com/music-in-musicxml/");
-    
-                    ui.add_space(10.0);
-    
-                    ui.label(RichText::new("I got a .mxl file instead of .xml file. What do I do?").size(18.0));
-                    ui.label(
-                        "Often when downloading a musicxml file, you get a .mxl file. This is a \
-                        compressed version of musicxml. This is easily converted by renaming the file \
-                        to a .zip file and then extracting it. This gives you a folder your score as .xml \
-                        and another folder that you can ignore. Simply choose the .xml file when uploading \
-                        a file to this application.",
-                    );
+                    if ui
+                        .small_button("Add loop")
+                        .on_hover_text("Keep these measures as a loop in the annotations")
+                        .clicked()
+                    {
+                        loop_skipped = Some((first, last));
+                    }
+                });
+            }
+            if clear {
+                self.error_map = None;
+            }
+            if let Some((start_measure, end_measure)) = loop_skipped {
+                self.add_loop(start_measure, end_measure);
+            }
+            if let Some(measure) = go_to {
+                self.seek_to(measure, 0);
+            }
+        }
+        if !self.wait_for_notes {
+            return;
+        }
+        if let Some(error) = &self.listener_error {
+            ui.colored_label(egui::Color32::RED, error);
+            return;
+        }
+        if !self.is_playing {
+            ui.label("Start playback to begin practicing");
+            return;
+        }
+        if self.waiting_at.is_some() {
+            let notes = self
+                .current_notes
+                .iter()
+                .flatten()
+                .filter_map(|note| Some(format!("string {} fret {}", note.string?, note.fret?)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            ui.label(format!(
+                "Waiting for measure {}: {}",
+                self.current_measure_index + 1,
+                notes
+            ));
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::ProgressBar::new(self.similarity.clamp(0.0, 1.0))
+                        .text(format!("Match {:.0}%", self.similarity * 100.0)),
+                );
+                if ui
+                    .button("Skip")
+                    .on_hover_text("Move on without playing these notes")
+                    .clicked()
+                {
+                    self.waiting_at = None;
+                }
+            });
+        } else {
+            ui.colored_label(egui::Color32::from_rgb(40, 160, 60), "Heard it");
+        }
+    }
 
-                    ui.label(RichText::new("Can I convert other formats to MusicXML?").size(18.0));
-                    ui.label(
-                        "Yes, many music notation programs allow you to import various formats and \
-                        export them as MusicXML. There are also dedicated conversion tools available. \
-                        Check out the following resource for more information:",
-                    );
-                    ui.hyperlink("https://www.musicxml.com/dolet-plugin/");
-    
-                    ui.add_space(10.0);
-    
-                    ui.label(RichText::new("Why use MusicXML in this application?").size(18.0));
-                    ui.label(
-                        "When playing guitar, tablature is a great tool for those not familiar \
-                        with traditional musical notation. It is often easier to find well written \
-                        notation for other instruments. This application tries to understand scores \
-                        written for any instrument and convert it to tablature, and also let you play \
-                        around with playback to let you get a feeling of the expected sound.",
+    /// Plays test tones and reports how loud and how late the input hears them, for sorting
+    /// out audio setups where the listener never matches.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn ui_loopback_test(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let running = self.loopback_test.is_some();
+            if ui
+                .add_enabled(!running, egui::Button::new("Run loopback test"))
+                .on_hover_text("Plays three tones and listens for them with the input device")
+                .clicked()
+            {
+                let device = self.configs.input_device.as_deref();
+                match LoopbackTest::start(device, self.configs.input_filter) {
+                    Ok(test) => {
+                        self.loopback_test = Some(test);
+                        self.loopback_report = None;
+                    }
+                    Err(e) => self.loopback_report = Some(Err(e)),
+                }
+            }
+            if running {
+                ui.spinner();
+                ui.label("Listening…");
+            }
+        });
+        match &self.loopback_report {
+            Some(Ok(report)) => {
+                let text = report.to_string();
+                ui.monospace(&text);
+                if !report.all_heard() {
+                    ui.colored_label(
+                        egui::Color32::RED,
+                        "Not every tone came through, check that the input device can hear the \
+                         output and that neither is muted",
                     );
-    
-                    ui.add_space(10.0);
-    
-                    // Additional Resources
-                    ui.label(RichText::new("**Additional Resources:**").size(18.0));
-                    ui.hyperlink("MusicXML Tutorial - https://www.musicxml.com/tutorial/");
-                    ui.hyperlink("MusicXML GitHub Repository - https://github.com/w3c/musicxml");
-    
-                    ui.add_space(20.0);
-    
-                    ui.separator();
-    
-                    // Credits or other information
-                    ui.vertical_centered(|ui| {
-                        ui.label(RichText::new("Author: Rasmus Hogslätt").size(16.0));
-                    });
-    
-                    ui.add_space(10.0);
+                }
+                if ui.button("Copy").clicked() {
+                    ui.ctx().copy_text(text);
+                }
+            }
+            Some(Err(e)) => {
+                ui.colored_label(egui::Color32::RED, e);
+            }
+            None => {}
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn start_input_monitor(&mut self) {
+        match InputMonitor::start(
+            self.configs.monitor_gain,
+            self.configs.input_device.as_deref(),
+            self.configs.input_filter,
+            &mut self.audio_player,
+        ) {
+            Ok(monitor) => self.input_monitor = Some(monitor),
+            Err(e) => self.monitor_error = Some(e),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn start_input_capture(&mut self) {
+        let device = self.configs.input_device.as_deref();
+        match InputCapture::start(device, self.configs.input_filter) {
+            Ok(capture) => self.input_capture = Some(capture),
+            Err(e) => self.capture_error = Some(e),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn start_listener(&mut self) {
+        let device = self.configs.input_device.as_deref();
+        match Listener::start(device, self.configs.input_filter) {
+            Ok(listener) => {
+                self.listener = Some(listener);
+                self.apply_plot_history();
+            }
+            Err(e) => self.listener_error = Some(e),
+        }
+    }
+
+    /// Moves the monitor, spectrogram and listener that are running to the selected input.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn restart_inputs(&mut self) {
+        self.monitor_error = None;
+        self.capture_error = None;
+        self.listener_error = None;
+        if self.input_monitor.take().is_some() {
+            self.start_input_monitor();
+        }
+        if self.input_capture.take().is_some() {
+            self.spectrogram.clear();
+            self.start_input_capture();
+        }
+        if self.listener.take().is_some() {
+            self.start_listener();
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn ui_input_device(&mut self, ui: &mut egui::Ui) {
+        let current = self.configs.input_device.clone();
+        let mut selected = current.clone();
+        let response = egui::ComboBox::from_label("Input device")
+            .selected_text(current.as_deref().unwrap_or("Default"))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut selected, None, "Default");
+                for name in &self.input_devices {
+                    ui.selectable_value(&mut selected, Some(name.clone()), name);
+                }
+            });
+        // Listed when opened, which also picks up interfaces plugged in since
+        if response.response.clicked() {
+            self.input_devices = input_device_names();
+        }
+        if selected != current {
+            self.configs.input_device = selected;
+            self.restart_inputs();
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn ui_monitor(&mut self, ui: &mut egui::Ui) {
+        self.ui_input_device(ui);
+        let mut monitoring = self.input_monitor.is_some();
+        let mut restart = ui
+            .checkbox(&mut monitoring, "Monitor microphone")
+            .on_hover_text("Use headphones, speakers will feed back into the microphone")
+            .changed();
+        let filter = &mut self.configs.input_filter;
+        restart |= ui
+            .checkbox(&mut filter.high_pass, "Remove DC offset and rumble")
+            .on_hover_text("High-pass filter at 40 Hz")
+            .changed();
+        egui::ComboBox::from_label("Hum filter")
+            .selected_text(filter.hum.to_string())
+            .show_ui(ui, |ui| {
+                for hum in [HumFilter::Off, HumFilter::Hz50, HumFilter::Hz60] {
+                    restart |= ui
+                        .selectable_value(&mut filter.hum, hum, hum.to_string())
+                        .changed();
+                }
+            });
+        if restart {
+            self.input_monitor = None;
+            self.monitor_error = None;
+            if monitoring {
+                self.start_input_monitor();
+            }
+        }
+        if ui
+            .add(egui::Slider::new(&mut self.configs.monitor_gain, 0.0..=4.0).text("Monitor gain"))
+            .changed()
+        {
+            if let Some(monitor) = &self.input_monitor {
+                monitor.set_gain(self.configs.monitor_gain);
+            }
+        }
+        if let Some(error) = &self.monitor_error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+    }
+
+    /// Scrolling spectrogram of the microphone, to check the guitar is picked up.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn ui_spectrogram(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Microphone");
+        let mut capturing = self.input_capture.is_some();
+        if ui.checkbox(&mut capturing, "Show spectrogram").changed() {
+            self.input_capture = None;
+            self.capture_error = None;
+            self.spectrogram.clear();
+            if capturing {
+                self.start_input_capture();
+            }
+        }
+        if let Some(error) = &self.capture_error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+        if let Some(capture) = &self.input_capture {
+            self.spectrogram
+                .push_samples(&capture.take(), capture.sample_rate);
+            self.spectrogram.ui(ui);
+            ui.ctx().request_repaint();
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn ui_output_device(&mut self, ui: &mut egui::Ui) {
+        let current = self.audio_player.device_name().map(str::to_string);
+        let mut selected = current.clone();
+        let response = egui::ComboBox::from_label("Output device")
+            .selected_text(current.as_deref().unwrap_or("Default"))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut selected, None, "Default");
+                for name in &self.output_devices {
+                    ui.selectable_value(&mut selected, Some(name.clone()), name);
+                }
+            });
+        if response.response.clicked() {
+            self.output_devices = output_device_names();
+        }
+        if selected != current {
+            self.output_device_error = self.audio_player.set_output_device(selected).err();
+            // The backing track and the monitor played on the old device
+            if self.is_playing {
+                self.play_backing_track_from(self.current_time);
+            }
+            if self.input_monitor.take().is_some() {
+                self.start_input_monitor();
+            }
+        }
+        if let Some(error) = &self.output_device_error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+    }
+
+    /// The beat through the other senses: a flash around the window and gamepad rumble.
+    fn pulse_beat(&mut self, accent: bool) {
+        if self.configs.beat_flash {
+            self.beat_flashed_at = Some((Instant::now(), accent));
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(rumble) = &mut self.rumble {
+            rumble.pulse(accent);
+        }
+    }
+
+    /// Outlines the window while the latest beat lasts, fading out, brighter on the first beat of
+    /// a measure.
+    fn paint_beat_flash(&mut self, ctx: &egui::Context) {
+        let Some((at, accent)) = self.beat_flashed_at else {
+            return;
+        };
+        let elapsed = at.elapsed();
+        if elapsed >= BEAT_FLASH_DURATION {
+            self.beat_flashed_at = None;
+            return;
+        }
+        let fade = 1.0 - elapsed.as_secs_f32() / BEAT_FLASH_DURATION.as_secs_f32();
+        let color = if accent {
+            egui::Color32::from_rgb(255, 80, 40)
+        } else {
+            egui::Color32::from_rgb(255, 220, 60)
+        };
+        let painter = ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            egui::Id::new("beat_flash"),
+        ));
+        painter.rect_stroke(
+            ctx.screen_rect().shrink(BEAT_FLASH_WIDTH / 2.0),
+            0.0,
+            egui::Stroke::new(BEAT_FLASH_WIDTH, color.gamma_multiply(fade)),
+        );
+        ctx.request_repaint();
+    }
+
+    fn ui_accessibility(&mut self, ui: &mut egui::Ui) {
+        ui.label("Feel or see the beat instead of hearing the click:");
+        ui.checkbox(&mut self.configs.beat_flash, "Flash the window border")
+            .on_hover_text("Lights up on every beat, brighter on the first beat of a measure");
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let mut rumble = self.rumble.is_some();
+            if ui.checkbox(&mut rumble, "Rumble the gamepad").changed() {
+                self.rumble = None;
+                self.rumble_error = None;
+                if rumble {
+                    match Rumble::start() {
+                        Ok(rumble) => self.rumble = Some(rumble),
+                        Err(e) => self.rumble_error = Some(e),
+                    }
+                }
+            }
+            if let Some(rumble) = &mut self.rumble {
+                ui.add(egui::Slider::new(&mut rumble.strength, 0.1..=1.0).text("Strength"));
+                if rumble.gamepads().is_empty() {
+                    ui.label("No gamepad that can rumble is connected");
+                }
+            }
+            if let Some(error) = &self.rumble_error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+        }
+    }
+
+    fn ui_metronome(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.metronome.enabled, "Metronome");
+            ui.checkbox(&mut self.metronome.transport_cues, "Start/stop cues");
+        });
+        ui.add(egui::Slider::new(&mut self.metronome.volume, 0.0..=1.0).text("Click volume"));
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let current = self.metronome.device_name().map(str::to_string);
+            let mut selected = current.clone();
+            let response = egui::ComboBox::from_label("Click output")
+                .selected_text(current.as_deref().unwrap_or("Same as guitar"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut selected, None, "Same as guitar");
+                    for name in &self.output_devices {
+                        ui.selectable_value(&mut selected, Some(name.clone()), name);
+                    }
                 });
+            // Listing devices is slow on some hosts, so only do it when the list is opened
+            if response.response.clicked() {
+                self.output_devices = output_device_names();
+            }
+            if selected != current {
+                self.metronome_error = self.metronome.set_device(selected).err();
+            }
+            if let Some(error) = &self.metronome_error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
         }
-    
-    
+    }
 
-    fn ui_playback_controls(&mut self, ui: &mut egui::Ui, changed_config: &mut bool) {
+    fn ui_render_settings(&mut self, ui: &mut egui::Ui, changed_rendered_score: &mut bool) {
         ui.group(|ui| {
-            ui.heading("Playback Controls");
+            egui::ComboBox::from_label("View")
+                .selected_text(self.configs.score_view.to_string())
+                .show_ui(ui, |ui| {
+                    for view in [ScoreView::Tab, ScoreView::PianoRoll] {
+                        ui.selectable_value(&mut self.configs.score_view, view, view.to_string());
+                    }
+                });
+            if ui
+                .checkbox(&mut self.configs.touch_layout, "Touch layout")
+                .on_hover_text("Large transport buttons, swipe to scroll and pinch to zoom")
+                .changed()
+                && !self.configs.touch_layout
+            {
+                ui.ctx().set_zoom_factor(1.0);
+            }
             ui.horizontal(|ui| {
-                if ui.button("Play").clicked() {
-                    self.start_playback();
-                }
-                if ui.button("Stop").clicked() {
-                    self.stop_playback();
+                ui.label("Dashes per division:");
+                if ui
+                    .add(egui::Slider::new(
+                        &mut self.configs.dashes_per_division,
+                        1..=8,
+                    ))
+                    .changed()
+                {
+                    self.renderer.dashes_per_division = self.configs.dashes_per_division;
+                    *changed_rendered_score = true;
                 }
             });
             ui.horizontal(|ui| {
-                ui.label("Volume:");
-                let active_guitar_config =
-                    &mut self.configs.guitar_configs[self.configs.active_guitar];
+                ui.label("Measures per row:");
                 if ui
-                    .add(
-                        egui::Slider::new(&mut active_guitar_config.volume, 0.0..=1.0)
-                            .step_by(0.01),
-                    )
+                    .add(egui::Slider::new(&mut self.configs.measures_per_row, 1..=8))
                     .changed()
                 {
-                    *changed_config = true;
+                    self.renderer.measures_per_row = self.configs.measures_per_row;
+                    *changed_rendered_score = true;
                 }
             });
+            if ui
+                .checkbox(&mut self.configs.left_handed, "Left-handed")
+                .on_hover_text("Low E string on top and a mirrored fretboard")
+                .changed()
+            {
+                self.renderer.left_handed = self.configs.left_handed;
+                *changed_rendered_score = true;
+            }
+            if ui_tab_style(ui, &mut self.configs.tab_style) {
+                self.renderer.style = self.configs.tab_style;
+                *changed_rendered_score = true;
+            }
+            ui.separator();
             ui.horizontal(|ui| {
-                ui.label("Set custom tempo:");
-                ui.checkbox(&mut self.configs.use_custom_tempo, "");
+                egui::ComboBox::from_label("Image format")
+                    .selected_text(self.configs.export_image_format.to_string())
+                    .show_ui(ui, |ui| {
+                        for format in [ImageFormat::Svg, ImageFormat::Png] {
+                            ui.selectable_value(
+                                &mut self.configs.export_image_format,
+                                format,
+                                format.to_string(),
+                            );
+                        }
+                    });
             });
-            if self.configs.use_custom_tempo {
+            if self.configs.export_image_format == ImageFormat::Png {
+                ui.horizontal(|ui| {
+                    ui.label("Scale:");
+                    for scale in EXPORT_SCALES {
+                        let dpi = scale * BASE_DPI;
+                        if ui
+                            .selectable_label(self.configs.export_dpi == dpi, format!("{}×", scale))
+                            .clicked()
+                        {
+                            self.configs.export_dpi = dpi;
+                        }
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("DPI:");
+                    ui.add(
+                        egui::Slider::new(&mut self.configs.export_dpi, 72.0..=600.0).step_by(1.0),
+                    );
+                });
+                if let Some(score) = &self.score {
+                    // The layout is the same at every scale, only the pixels per unit change
+                    let size = self.renderer.calculate_tab_size(score)
+                        * (self.configs.export_dpi / BASE_DPI);
+                    ui.weak(format!(
+                        "{:.0} × {:.0} pixels",
+                        size.x.ceil(),
+                        size.y.ceil()
+                    ));
+                }
+            }
+            if ui
+                .add_enabled(self.score.is_some(), egui::Button::new("Export image…"))
+                .clicked()
+            {
+                self.export_tab_image();
+            }
+            ui.separator();
+            egui::ComboBox::from_label("Paper size")
+                .selected_text(self.configs.export_paper_size.to_string())
+                .show_ui(ui, |ui| {
+                    for paper_size in [PaperSize::A4, PaperSize::Letter] {
+                        ui.selectable_value(
+                            &mut self.configs.export_paper_size,
+                            paper_size,
+                            paper_size.to_string(),
+                        );
+                    }
+                });
+            ui.horizontal(|ui| {
                 if ui
-                    .add(egui::Slider::new(&mut self.configs.custom_tempo, 1..=240))
-                    .changed()
+                    .add_enabled(self.score.is_some(), egui::Button::new("Export PDF…"))
+                    .clicked()
                 {
-                    *changed_config = true;
+                    self.export_tab_pdf();
                 }
+                if ui
+                    .add_enabled(self.score.is_some(), egui::Button::new("Print view"))
+                    .clicked()
+                {
+                    self.print_mode = true;
+                }
+            });
+            if ui
+                .button("Performance view")
+                .on_hover_text("Only the tab, fullscreen. F11 or Esc to leave")
+                .clicked()
+            {
+                self.set_performance_mode(ui.ctx(), true);
             }
-            ui.label(format!(
-                "Total score time: {:.2} seconds",
-                self.display_metrics.total_score_time
-            ));
-            ui.label("Capo fret:");
-            let active_guitar_config = &mut self.configs.guitar_configs[self.configs.active_guitar];
             if ui
-                .add(egui::Slider::new(&mut active_guitar_config.capo_fret, 0..=24).text("Fret"))
-                .changed()
+                .add_enabled(self.score.is_some(), egui::Button::new("Export MusicXML…"))
+                .on_hover_text("Sections, loops and comments are kept as rehearsal marks and text")
+                .clicked()
             {
-                *changed_config = true;
+                self.export_musicxml();
+            }
+            self.ui_export_excerpt(ui);
+            ui.separator();
+            ui.checkbox(&mut self.configs.show_fretboard, "Show fretboard");
+            ui.checkbox(&mut self.configs.show_chord_timeline, "Show chord timeline");
+            ui.checkbox(&mut self.configs.show_difficulty, "Show difficulty heatmap")
+                .on_hover_text("Tints the measures that need the most work at the playback tempo");
+            ui_scale_overlay(ui, &mut self.configs.fretboard_scale);
+            ui.checkbox(&mut self.diagnostics.visible, "Show diagnostics (F3)");
+        });
+    }
+
+    fn pdf_settings(&self) -> PdfSettings {
+        PdfSettings {
+            paper_size: self.configs.export_paper_size,
+            margin: DEFAULT_MARGIN,
+        }
+    }
+
+    /// Renderer fitting as many measures per row as the paper width allows.
+    fn print_renderer(&self, score: &Score) -> Renderer {
+        let width = self.pdf_settings().content_width();
+        let mut renderer = Renderer::new(1, self.configs.dashes_per_division);
+        renderer.left_handed = self.configs.left_handed;
+        renderer.style = self.configs.tab_style;
+        renderer.capo_fret = self.renderer.capo_fret;
+        renderer.measures_per_row = renderer.measures_per_row_fitting(score, width);
+        renderer
+    }
+
+    /// Black-on-white tab laid out for the chosen paper, with nothing but a small toolbar.
+    fn ui_print_view(&mut self, ctx: &egui::Context) {
+        if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.print_mode = false;
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        let printing = {
+            if self.print_pending_frames > 0 {
+                self.print_pending_frames -= 1;
+                if self.print_pending_frames == 0 {
+                    if let Err(e) = print::print_page() {
+                        eprintln!("Failed to print: {}", e);
+                    }
+                }
+            }
+            self.print_pending_frames > 0
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        let printing = false;
+
+        if !printing {
+            egui::TopBottomPanel::top("print_toolbar").show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_label("Paper size")
+                        .selected_text(self.configs.export_paper_size.to_string())
+                        .show_ui(ui, |ui| {
+                            for paper_size in [PaperSize::A4, PaperSize::Letter] {
+                                ui.selectable_value(
+                                    &mut self.configs.export_paper_size,
+                                    paper_size,
+                                    paper_size.to_string(),
+                                );
+                            }
+                        });
+                    if ui.button("Print…").clicked() {
+                        self.print();
+                    }
+                    if ui.button("Exit print view").clicked() {
+                        self.print_mode = false;
+                    }
+                });
+            });
+        }
+
+        egui::CentralPanel::default()
+            .frame(egui::Frame::none().fill(egui::Color32::WHITE))
+            .show(ctx, |ui| {
+                let Some(score) = &self.score else {
+                    return;
+                };
+                let renderer = self.print_renderer(score);
+                ScrollArea::vertical()
+                    .id_salt("print_scroll_area")
+                    .show(ui, |ui| {
+                        egui::Frame::none()
+                            .inner_margin(Margin::same(TAB_PADDING))
+                            .show(ui, |ui| {
+                                for line in score_info(score).lines() {
+                                    ui.label(
+                                        RichText::new(line).monospace().color(egui::Color32::BLACK),
+                                    );
+                                }
+                            });
+                        let size = renderer.calculate_tab_size(score);
+                        let (rect, _response) = ui.allocate_exact_size(size, egui::Sense::hover());
+                        let origin = rect.min + Vec2::splat(TAB_PADDING);
+                        paint_tab_shapes(
+                            &ui.painter_at(rect),
+                            &renderer.layout(score, origin),
+                            &renderer.style.font.family(),
+                        );
+                    });
+            });
+    }
+
+    fn set_performance_mode(&mut self, ctx: &egui::Context, on: bool) {
+        self.performance_mode = on;
+        ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(on));
+    }
+
+    /// The tab filling the screen with a wide cursor, scrolled along with the playback.
+    fn ui_performance_view(&mut self, ctx: &egui::Context) {
+        if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.set_performance_mode(ctx, false);
+        }
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let Some(score) = &self.score else {
+                ui.centered_and_justified(|ui| ui.label("No score loaded. Press F11 to go back."));
+                return;
+            };
+            let width = ui.available_width() - 2.0 * TAB_MARGIN;
+            self.renderer.measures_per_row = self
+                .renderer
+                .measures_per_row_fitting(score, width)
+                .min(self.configs.measures_per_row);
+            ScrollArea::vertical()
+                .id_salt("performance_scroll_area")
+                .show(ui, |ui| {
+                    egui::Frame::none()
+                        .inner_margin(Margin::same(TAB_MARGIN))
+                        .show(ui, |ui| {
+                            let desired_size = match self.piano_roll(score) {
+                                Some(piano_roll) => piano_roll.size(score),
+                                None => self.renderer.calculate_tab_size(score),
+                            };
+                            let (rect, _response) =
+                                ui.allocate_exact_size(desired_size, egui::Sense::hover());
+                            self.render_tab(&ui.painter_at(rect), rect, PERFORMANCE_CURSOR_WIDTH);
+                            if self.is_playing {
+                                let [top, bottom] = self.playback_indicator(
+                                    score,
+                                    rect.min,
+                                    self.current_measure_index,
+                                    self.current_division_index,
+                                );
+                                let target = egui::Rect::from_two_pos(top, bottom);
+                                ui.scroll_to_rect(target, Some(egui::Align::Center));
+                            }
+                        });
+                });
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn print(&self) {
+        let Some(score) = &self.score else {
+            return;
+        };
+        let data = export_tab_pdf(score, &self.print_renderer(score), &self.pdf_settings());
+        if let Err(e) = print::print_pdf(&data) {
+            eprintln!("Failed to print: {}", e);
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn print(&mut self) {
+        self.print_pending_frames = 2;
+    }
+
+    fn export_tab_pdf(&self) {
+        let Some(score) = &self.score else {
+            return;
+        };
+        let data = export_tab_pdf(score, &self.renderer, &self.pdf_settings());
+
+        let task = rfd::AsyncFileDialog::new()
+            .add_filter("PDF", &["pdf"])
+            .set_file_name("tablature.pdf")
+            .save_file();
+
+        execute(async move {
+            if let Some(file) = task.await {
+                if let Err(e) = file.write(&data).await {
+                    eprintln!("Failed to write PDF: {}", e);
+                }
+            }
+        });
+    }
+
+    fn export_audio(&self) {
+        let Some(score) = self.score.clone() else {
+            return;
+        };
+        let config = self.configs.guitar_configs[self.configs.active_guitar].clone();
+        let tempo = if self.configs.use_custom_tempo {
+            self.configs.custom_tempo
+        } else {
+            score.tempo
+        };
+        let sample_rate = self.audio_player.sample_rate;
+        let seed = self.configs.synthesis_seed;
+        let bit_depth = self.configs.export_bit_depth;
+        #[cfg(not(target_arch = "wasm32"))]
+        let format = self.configs.export_audio_format;
+        #[cfg(not(target_arch = "wasm32"))]
+        let tags = AudioTags {
+            title: score.title.clone(),
+            artist: score.composer.clone(),
+            tempo,
+        };
+        let limiter = self.configs.mixer.limiter;
+        let mut effect_chain = self.audio_player.offline_effect_chain(&config);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let (filter, extension) = match format {
+            Some(format) => (format.to_string(), format.extension()),
+            None => ("WAV".to_string(), "wav"),
+        };
+        #[cfg(target_arch = "wasm32")]
+        let (filter, extension) = ("WAV".to_string(), "wav");
+        let task = rfd::AsyncFileDialog::new()
+            .add_filter(filter, &[extension])
+            .set_file_name(format!("tablature.{}", extension))
+            .save_file();
+
+        execute(async move {
+            if let Some(file) = task.await {
+                let mut frames = render_score_stereo(&score, &config, tempo, sample_rate, seed);
+                // Room for the body and the chorus to ring out after the last note
+                let tail = (EXPORT_TAIL_SECONDS * sample_rate) as usize;
+                frames.resize(frames.len() + tail, kira::Frame::ZERO);
+                effect_chain.render(&mut frames, sample_rate as u32);
+                let mut limiter = Limiter::new(limiter, sample_rate);
+                for frame in &mut frames {
+                    *frame = limiter.process(*frame);
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                let data = match format {
+                    Some(format) => {
+                        match encode_compressed(&frames, sample_rate as u32, format, &tags) {
+                            Ok(data) => data,
+                            Err(e) => {
+                                eprintln!("Failed to encode audio: {}", e);
+                                return;
+                            }
+                        }
+                    }
+                    None => encode_stereo_wav(&frames, sample_rate as u32, bit_depth),
+                };
+                #[cfg(target_arch = "wasm32")]
+                let data = encode_stereo_wav(&frames, sample_rate as u32, bit_depth);
+                if let Err(e) = file.write(&data).await {
+                    eprintln!("Failed to write audio: {}", e);
+                }
+            }
+        });
+    }
+
+    fn export_musicxml(&self) {
+        let Some(score) = &self.score else {
+            return;
+        };
+        let annotations = self
+            .score_arrangement
+            .fingerprint()
+            .and_then(|fingerprint| self.annotation_store.scores.get(fingerprint));
+        let marks = match annotations {
+            Some(annotations) => ExportMarks {
+                loops: &annotations.loops,
+                comments: &annotations.comments,
+            },
+            None => ExportMarks::default(),
+        };
+        let capo_fret = self.configs.guitar_configs[self.configs.active_guitar].capo_fret;
+        let xml = score_to_musicxml(score, capo_fret, &marks);
+        let file_name = format!("{}.musicxml", score.title.as_deref().unwrap_or("score"));
+        self.save_musicxml(xml, file_name);
+    }
+
+    /// Picks the measures to export on their own, like just the solo to share as an exercise.
+    fn ui_export_excerpt(&mut self, ui: &mut egui::Ui) {
+        let measures = self.score.as_ref().map_or(0, |score| score.measures.len());
+        let (first, last) = &mut self.excerpt_measures;
+        let export = ui
+            .add_enabled_ui(measures > 0, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Measures");
+                    ui.add(egui::DragValue::new(first).range(1..=measures.max(1)));
+                    ui.label("to");
+                    ui.add(egui::DragValue::new(last).range(*first..=measures.max(1)));
+                    *last = (*last).max(*first);
+                    ui.button("Export excerpt…")
+                        .on_hover_text(
+                            "Just these measures as MusicXML, at the same tempo and meter",
+                        )
+                        .clicked()
+                })
+                .inner
+            })
+            .inner;
+        if export {
+            self.export_excerpt();
+        }
+    }
+
+    fn export_excerpt(&self) {
+        let Some(score) = &self.score else {
+            return;
+        };
+        let (first, last) = self.excerpt_measures;
+        let mut excerpt = score.excerpt(first - 1..=last - 1);
+        let title = format!(
+            "{} (measures {}-{})",
+            score.title.as_deref().unwrap_or("score"),
+            first,
+            last
+        );
+        // The marks within the excerpt, numbered from its first measure
+        let within = |measure: usize| (first..=last).contains(&measure);
+        let renumber = |measure: usize| measure + 1 - first;
+        let annotations = self
+            .score_arrangement
+            .fingerprint()
+            .and_then(|fingerprint| self.annotation_store.scores.get(fingerprint));
+        let loops: Vec<LoopRegion> = annotations
+            .map(|annotations| &annotations.loops[..])
+            .unwrap_or_default()
+            .iter()
+            .filter(|region| within(region.start_measure) && within(region.end_measure))
+            .map(|region| LoopRegion {
+                name: region.name.clone(),
+                start_measure: renumber(region.start_measure),
+                end_measure: renumber(region.end_measure),
+            })
+            .collect();
+        let comments: Vec<Comment> = annotations
+            .map(|annotations| &annotations.comments[..])
+            .unwrap_or_default()
+            .iter()
+            .filter(|comment| within(comment.measure))
+            .map(|comment| Comment {
+                measure: renumber(comment.measure),
+                text: comment.text.clone(),
+            })
+            .collect();
+        let marks = ExportMarks {
+            loops: &loops,
+            comments: &comments,
+        };
+        let capo_fret = self.configs.guitar_configs[self.configs.active_guitar].capo_fret;
+        let file_name = format!("{}.musicxml", title);
+        excerpt.title = Some(title);
+        let xml = score_to_musicxml(&excerpt, capo_fret, &marks);
+        self.save_musicxml(xml, file_name);
+    }
+
+    fn save_musicxml(&self, xml: String, file_name: String) {
+        let task = rfd::AsyncFileDialog::new()
+            .add_filter("MusicXML", &["musicxml", "xml"])
+            .set_file_name(file_name)
+            .save_file();
+
+        execute(async move {
+            if let Some(file) = task.await {
+                if let Err(e) = file.write(xml.as_bytes()).await {
+                    eprintln!("Failed to write MusicXML: {}", e);
+                }
+            }
+        });
+    }
+
+    fn export_tab_image(&self) {
+        let Some(score) = &self.score else {
+            return;
+        };
+        // Lay out with the same padding as the on-screen tab
+        let shapes = self
+            .renderer
+            .layout(score, egui::pos2(TAB_PADDING, TAB_PADDING));
+        let size = self.renderer.calculate_tab_size(score);
+        let format = self.configs.export_image_format;
+        let dpi = self.configs.export_dpi;
+
+        let task = rfd::AsyncFileDialog::new()
+            .add_filter(format.to_string(), &[format.extension()])
+            .set_file_name(format!("tablature.{}", format.extension()))
+            .save_file();
+
+        execute(async move {
+            if let Some(file) = task.await {
+                match export_tab_image(&shapes, size, format, dpi) {
+                    Ok(data) => {
+                        if let Err(e) = file.write(&data).await {
+                            eprintln!("Failed to write image: {}", e);
+                        }
+                    }
+                    Err(e) => eprintln!("Failed to export image: {}", e),
+                }
+            }
+        });
+    }
+
+    /// One block per measure, darker the more notes it has, for jumping around long scores.
+    fn ui_minimap(&mut self, ui: &mut egui::Ui) {
+        let Some(score) = &self.score else {
+            return;
+        };
+        let measures = score.measures.len();
+        if measures == 0 {
+            return;
+        }
+        let density: Vec<f32> = score
+            .measures
+            .iter()
+            .map(|measure| {
+                let notes: usize = measure.positions.iter().map(Vec::len).sum();
+                notes as f32 / measure.positions.len().max(1) as f32
+            })
+            .collect();
+        let max_density = density
+            .iter()
+            .copied()
+            .fold(0.0, f32::max)
+            .max(f32::EPSILON);
+
+        let (rect, response) =
+            ui.allocate_exact_size(Vec2::new(ui.available_width(), 18.0), egui::Sense::click());
+        let painter = ui.painter_at(rect);
+        let block_width = rect.width() / measures as f32;
+        let block = |measure: usize| {
+            egui::Rect::from_min_size(
+                egui::pos2(rect.min.x + measure as f32 * block_width, rect.min.y + 4.0),
+                Vec2::new(block_width.max(1.0), rect.height() - 4.0),
+            )
+        };
+        for (measure, density) in density.iter().enumerate() {
+            let shade = 235 - (density / max_density * 175.0) as u8;
+            painter.rect_filled(
+                block(measure),
+                0.0,
+                egui::Color32::from_rgb(shade, shade, 255),
+            );
+        }
+        // Section starts get a tick above the strip
+        for section in &score.sections {
+            let x = block(section.measure).min.x;
+            painter.line_segment(
+                [egui::pos2(x, rect.min.y), egui::pos2(x, rect.max.y)],
+                egui::Stroke::new(1.0, egui::Color32::DARK_BLUE),
+            );
+        }
+        if self.is_playing {
+            painter.rect_filled(
+                block(self.current_measure_index.min(measures - 1)),
+                0.0,
+                egui::Color32::from_rgb(255, 140, 0),
+            );
+        }
+
+        let hovered = response
+            .hover_pos()
+            .map(|pos| (((pos.x - rect.min.x) / block_width) as usize).min(measures - 1));
+        if let Some(measure) = hovered {
+            painter.rect_stroke(
+                block(measure),
+                0.0,
+                egui::Stroke::new(1.0, egui::Color32::BLACK),
+            );
+        }
+        let clicked = response.clicked();
+        response.on_hover_text(hovered.map_or(String::new(), |m| format!("Measure {}", m + 1)));
+        if let (true, Some(measure)) = (clicked, hovered) {
+            if self.is_playing {
+                self.seek_to(measure, 0);
+            } else {
+                self.scroll_to_measure = Some(measure);
             }
-            if ui.button("Choose File").clicked() {
-                self.stop_playback();
-                #[cfg(not(target_arch = "wasm32"))]
-                {
-                    let sender = self.score_channel.0.clone();
-                    let task = rfd::AsyncFileDialog::new()
-                        .add_filter("MusicXML", &["xml"])
-                        .pick_file();
-                    let ctx = ui.ctx().clone();
+        }
+    }
 
-                    execute(async move {
-                        if let Some(file) = task.await {
-                            let data = file.read().await;
-                            let xml_string = String::from_utf8_lossy(&data).to_string();
+    fn update_chord_progression(&mut self) {
+        let capo_fret = self.configs.guitar_configs[self.configs.active_guitar].capo_fret;
+        let (progression, key) = match &self.score {
+            Some(score) => (
+                chord_progression(score, capo_fret),
+                score_key(score, capo_fret),
+            ),
+            None => (Vec::new(), None),
+        };
+        self.chord_progression = progression;
+        self.progression_key = key;
+    }
 
-                            if let Ok(new_score) = Score::parse_from_musicxml_str(&xml_string) {
-                                let _ = sender.send(new_score);
+    /// A strip of the chords, as wide as they last, that jumps playback to a chord when clicked.
+    fn ui_chord_timeline(&mut self, ui: &mut egui::Ui) {
+        let Some(score) = &self.score else {
+            return;
+        };
+        let total: usize = self.chord_progression.iter().map(|span| span.length).sum();
+        let current = self.is_playing.then(|| {
+            score.measures[..self.current_measure_index.min(score.measures.len())]
+                .iter()
+                .map(|measure| measure.positions.len())
+                .sum::<usize>()
+                + self.current_division_index
+        });
+        let pixels_per_division = (ui.available_width() / total.max(1) as f32).max(4.0);
+        let mut seek = None;
+
+        ui.horizontal(|ui| {
+            if let Some(key) = self.progression_key {
+                ui.label(key.to_string());
+            }
+            ScrollArea::horizontal()
+                .id_salt("chord_timeline_scroll_area")
+                .show(ui, |ui| {
+                    ui.spacing_mut().item_spacing.x = 1.0;
+                    ui.horizontal(|ui| {
+                        let mut start = 0;
+                        for (idx, span) in self.chord_progression.iter().enumerate() {
+                            let width = (span.length as f32 * pixels_per_division).max(24.0);
+                            let (rect, response) = ui
+                                .allocate_exact_size(Vec2::new(width, 34.0), egui::Sense::click());
+                            let playing =
+                                current.is_some_and(|c| (start..start + span.length).contains(&c));
+                            let fill = if playing {
+                                egui::Color32::from_rgb(255, 200, 120)
+                            } else if response.hovered() {
+                                egui::Color32::from_gray(215)
+                            } else if idx % 2 == 0 {
+                                egui::Color32::from_gray(235)
+                            } else {
+                                egui::Color32::from_gray(225)
+                            };
+                            let painter = ui.painter_at(rect);
+                            painter.rect_filled(rect, 2.0, fill);
+                            let numeral = self
+                                .progression_key
+                                .map(|key| span.chord.roman_numeral(key))
+                                .unwrap_or_default();
+                            painter.text(
+                                rect.center_top() + Vec2::new(0.0, 2.0),
+                                egui::Align2::CENTER_TOP,
+                                numeral,
+                                egui::FontId::proportional(15.0),
+                                egui::Color32::BLACK,
+                            );
+                            painter.text(
+                                rect.center_bottom() - Vec2::new(0.0, 2.0),
+                                egui::Align2::CENTER_BOTTOM,
+                                span.chord.to_string(),
+                                egui::FontId::proportional(10.0),
+                                egui::Color32::DARK_GRAY,
+                            );
+                            if response
+                                .on_hover_text(format!("Measure {}", span.measure + 1))
+                                .clicked()
+                            {
+                                seek = Some((span.measure, span.division));
                             }
+                            start += span.length;
                         }
-                        ctx.request_repaint();
                     });
-                }
+                });
+        });
 
-                #[cfg(target_arch = "wasm32")]
-                {
-                    use wasm_bindgen::prelude::*;
-                    use wasm_bindgen::JsCast;
-                    use web_sys::{Event, HtmlInputElement};
+        if let Some((measure, division)) = seek {
+            self.seek_to(measure, division);
+        }
+    }
 
-                    let document = web_sys::window().unwrap().document().unwrap();
-                    let input = document.create_element("input").unwrap();
-                    input.set_attribute("type", "file").unwrap();
-                    input.set_attribute("accept", ".xml").unwrap();
-                    input.set_attribute("style", "display: none;").unwrap();
-                    let input: HtmlInputElement = input.dyn_into().unwrap();
+    /// Marks the measure being played as a trouble spot, to look at after the run.
+    fn drop_flag(&mut self) {
+        let Some(fingerprint) = self.score_arrangement.fingerprint().cloned() else {
+            return;
+        };
+        let flags = &mut self
+            .annotation_store
+            .scores
+            .entry(fingerprint)
+            .or_default()
+            .flags;
+        flags.push(Flag {
+            measure: self.current_measure_index + 1,
+            division: self.current_division_index,
+            seconds: self.current_time,
+            note: String::new(),
+        });
+        self.flag_note = Some((flags.len() - 1, String::new()));
+        self.save_annotations();
+    }
 
-                    let sender = self.score_channel.0.clone();
-                    let ctx = ui.ctx().clone();
+    /// Keeps measures `start_measure` through `end_measure`, counted from 1, as a loop of the
+    /// score.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn add_loop(&mut self, start_measure: usize, end_measure: usize) {
+        let Some(fingerprint) = self.score_arrangement.fingerprint().cloned() else {
+            return;
+        };
+        let loops = &mut self
+            .annotation_store
+            .scores
+            .entry(fingerprint)
+            .or_default()
+            .loops;
+        loops.push(LoopRegion {
+            name: format!("Skipped {}–{}", start_measure, end_measure),
+            start_measure,
+            end_measure,
+        });
+        self.save_annotations();
+    }
 
-                    let closure = Closure::wrap(Box::new(move |event: Event| {
-                        let input: HtmlInputElement = event.target().unwrap().dyn_into().unwrap();
-                        if let Some(files) = input.files() {
-                            if let Some(file) = files.get(0) {
-                                let file_reader = web_sys::FileReader::new().unwrap();
-                                let fr_c = file_reader.clone();
-                                let sender_clone = sender.clone(); // Clone sender here
-                                let ctx_clone = ctx.clone(); // Clone ctx if needed in inner closure
-                                let onloadend = Closure::wrap(Box::new(move |_event: Event| {
-                                    let result = fr_c.result().unwrap();
-                                    let array = js_sys::Uint8Array::new(&result);
-                                    let data = array.to_vec();
-                                    let xml_string = String::from_utf8_lossy(&data).to_string();
+    /// Offers to add a note to the flag just dropped, without taking the keyboard away from
+    /// playback.
+    fn ui_flag_note(&mut self, ctx: &egui::Context) {
+        let (Some((idx, text)), Some(fingerprint)) =
+            (&mut self.flag_note, self.score_arrangement.fingerprint())
+        else {
+            return;
+        };
+        let Some(flag) = self
+            .annotation_store
+            .scores
+            .get_mut(fingerprint)
+            .and_then(|annotations| annotations.flags.get_mut(*idx))
+        else {
+            self.flag_note = None;
+            return;
+        };
+        let mut open = true;
+        let mut save = false;
+        egui::Window::new("Flag dropped")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-16.0, 48.0))
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "Measure {} at {}",
+                    flag.measure,
+                    format_time(flag.seconds)
+                ));
+                ui.horizontal(|ui| {
+                    let response = ui.add(
+                        egui::TextEdit::singleline(text)
+                            .hint_text("Add a note (optional)")
+                            .desired_width(160.0),
+                    );
+                    let submitted =
+                        response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    save = ui.button("Save").clicked() || submitted;
+                });
+            });
+        if save {
+            flag.note = text.trim().to_string();
+            self.flag_note = None;
+            self.save_annotations();
+        } else if !open {
+            self.flag_note = None;
+        }
+    }
 
-                                    if let Ok(new_score) =
-                                        Score::parse_from_musicxml_str(&xml_string)
-                                    {
-                                        let _ = sender_clone.send(new_score);
-                                    }
-                                    ctx_clone.request_repaint();
-                                })
-                                    as Box<dyn FnMut(_)>);
+    fn ui_go_to_measure(&mut self, ctx: &egui::Context) {
+        let (Some(text), Some(score)) = (&mut self.go_to_measure, &self.score) else {
+            return;
+        };
+        let measure_count = score.measures.len();
+        let mut open = true;
+        let mut target = None;
+        egui::Window::new("Go to measure")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let parsed = text
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|measure| (1..=measure_count).contains(measure));
+                ui.horizontal(|ui| {
+                    ui.label(format!("Measure (1–{}):", measure_count));
+                    let response = ui.text_edit_singleline(text);
+                    response.request_focus();
+                    let submitted =
+                        response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    if ui
+                        .add_enabled(parsed.is_some(), egui::Button::new("Go"))
+                        .clicked()
+                        || submitted
+                    {
+                        target = parsed;
+                    }
+                });
+                if parsed.is_none() && !text.trim().is_empty() {
+                    ui.colored_label(egui::Color32::RED, "No such measure");
+                }
+            });
 
-                                file_reader.set_onloadend(Some(onloadend.as_ref().unchecked_ref()));
-                                file_reader.read_as_array_buffer(&file).unwrap();
-                                onloadend.forget();
-                            }
-                        }
-                    }) as Box<dyn FnMut(_)>);
+        if let Some(measure) = target {
+            self.go_to_measure = None;
+            self.seek_to(measure - 1, 0);
+        } else if !open || ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.go_to_measure = None;
+        }
+    }
 
-                    input.set_onchange(Some(closure.as_ref().unchecked_ref()));
-                    closure.forget();
+    fn ui_setup_prompt(&mut self, ctx: &egui::Context) {
+        let Some(setup) = self.setup_prompt else {
+            return;
+        };
+        let mut open = true;
+        #[cfg(not(target_arch = "wasm32"))]
+        let mut tune = false;
+        // The tuner needs the microphone, which the web version doesn't listen to
+        #[cfg(target_arch = "wasm32")]
+        let tune = false;
+        let mut play = false;
+        egui::Window::new("Before playing")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!("{} — tune now?", setup));
+                ui.horizontal(|ui| {
+                    #[cfg(not(target_arch = "wasm32"))]
+                    {
+                        tune = ui
+                            .button("Open tuner")
+                            .on_hover_text("Tune with the microphone, then press play")
+                            .clicked();
+                    }
+                    play = ui
+                        .button("Play")
+                        .on_hover_text("The guitar is set up already")
+                        .clicked();
+                });
+            });
 
-                    // Add the input to the DOM and trigger the click
-                    document.body().unwrap().append_child(&input).unwrap();
-                    input.click();
-                }
-            }
-        });
+        if tune || play {
+            self.setup_prompt = None;
+            self.apply_setup(setup);
+        } else if !open || ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.setup_prompt = None;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if tune {
+            self.open_tuner();
+        }
+        if play {
+            self.start_playback();
+        }
     }
 
-    fn ui_guitar_settings(&mut self, ui: &mut egui::Ui, changed_config: &mut bool) {
-        ui.group(|ui| {
-            ui.heading("Guitar Profile");
-            egui::ComboBox::from_label("Guitar Type")
-                .selected_text(format!(
-                    "{}",
-                    self.configs.guitar_configs[self.configs.active_guitar].name
-                ))
-                .show_ui(ui, |ui| {
-                    for (index, guitar) in self.configs.guitar_configs.iter().enumerate() {
-                        let checked = index == self.configs.active_guitar;
-                        if ui
-                            .selectable_label(checked, format!("{}", &guitar.name))
-                            .clicked()
-                        {
-                            self.configs.active_guitar = index;
-                            *changed_config = true;
+    fn ui_capo_suggestions(&mut self, ui: &mut egui::Ui, changed_config: &mut bool) {
+        // Moved as written, whichever arrangement of it is shown
+        let Some(score) = self.score_arrangement.written(self.score.as_ref()) else {
+            return;
+        };
+        let current_capo = self.configs.guitar_configs[self.configs.active_guitar].capo_fret;
+        let has_fingerings = self
+            .score_arrangement
+            .fingerprint()
+            .and_then(|fingerprint| self.annotation_store.scores.get(fingerprint))
+            .is_some_and(|annotations| {
+                !annotations.fingerings.is_empty() || !annotations.fingering_variants.is_empty()
+            });
+        let mut apply = None;
+        ui.collapsing("Capo suggestions", |ui| {
+            if let Some(capo_fret) = self.capo_confirmation {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!(
+                        "The fingerings stored for this score only fit the current frets and \
+                         will be removed with the capo at {}.",
+                        capo_fret
+                    ),
+                );
+                ui.horizontal(|ui| {
+                    if ui.button("Apply anyway").clicked() {
+                        apply = Some(capo_fret);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.capo_confirmation = None;
+                    }
+                });
+            }
+            if ui.button("Analyze").clicked() {
+                self.capo_suggestions = Some(capo_suggestions(score, current_capo));
+            }
+            let Some(suggestions) = &self.capo_suggestions else {
+                return;
+            };
+            if suggestions.is_empty() {
+                ui.label("No capo position gives more open chords.");
+            }
+            for suggestion in suggestions {
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "Capo {}: {} open, {} barre chords",
+                        suggestion.capo_fret, suggestion.open_chords, suggestion.barre_chords
+                    ));
+                    if ui.button("Apply").clicked() {
+                        if has_fingerings {
+                            self.capo_confirmation = Some(suggestion.capo_fret);
+                        } else {
+                            apply = Some(suggestion.capo_fret);
                         }
                     }
                 });
-
-            if let GuitarType::Custom = self.configs.guitar_configs[self.configs.active_guitar].name
-            {
-                egui::Grid::new("custom_guitar_config")
-                    .num_columns(2)
+                ScrollArea::horizontal()
+                    .id_salt(("capo_shapes", suggestion.capo_fret))
                     .show(ui, |ui| {
-                        let custom_config =
-                            &mut self.configs.guitar_configs[self.configs.active_guitar];
-
-                        ui.label("Decay:");
-                        if ui
-                            .add(
-                                egui::Slider::new(&mut custom_config.decay, 0.9..=1.0)
-                                    .step_by(0.001),
-                            )
-                            .changed()
-                        {
-                            *changed_config = true;
-                        }
-                        ui.end_row();
-
-                        ui.label("String Damping:");
-                        if ui
-                            .add(
-                                egui::Slider::new(&mut custom_config.string_damping, 0.0..=1.0)
-                                    .step_by(0.001),
-                            )
-                            .changed()
-                        {
-                            *changed_config = true;
-                        }
-                        ui.end_row();
+                        ui.horizontal(|ui| {
+                            for (shape, name) in &suggestion.shapes {
+                                let (rect, _response) = ui.allocate_exact_size(
+                                    chord_diagram_size(),
+                                    egui::Sense::hover(),
+                                );
+                                let shapes = layout_chord_diagram(shape, name.as_deref(), rect.min);
+                                paint_tab_shapes(
+                                    &ui.painter_at(rect),
+                                    &shapes,
+                                    &self.renderer.style.font.family(),
+                                );
+                            }
+                        });
+                    });
+            }
+        });
 
-                        ui.label("Body Damping:");
-                        if ui
-                            .add(
-                                egui::Slider::new(&mut custom_config.body_damping, 0.0..=1.0)
-                                    .step_by(0.001),
-                            )
-                            .changed()
-                        {
-                            *changed_config = true;
+        if let Some(capo_fret) = apply {
+            self.capo_confirmation = None;
+            match apply_capo(score, current_capo, capo_fret) {
+                Some(score) => {
+                    self.configs.guitar_configs[self.configs.active_guitar].capo_fret = capo_fret;
+                    // Same score on other frets, the annotations stay with it but the fingerings
+                    // no longer fit
+                    if has_fingerings {
+                        let fingerprint = self.score_arrangement.fingerprint();
+                        let scores = &mut self.annotation_store.scores;
+                        if let Some(annotations) = fingerprint.and_then(|key| scores.get_mut(key)) {
+                            annotations.fingerings.clear();
+                            annotations.fingering_variants.clear();
                         }
-                        ui.end_row();
+                        self.save_annotations();
+                    }
+                    self.score_arrangement.rewritten();
+                    self.show_score(score);
+                    *changed_config = true;
+                }
+                None => eprintln!("Some notes can't be played with the capo at {}", capo_fret),
+            }
+        }
+    }
 
-                        ui.label("Body Resonance:");
-                        if ui
-                            .add(
-                                egui::Slider::new(&mut custom_config.body_resonance, 0.0..=500.0)
-                                    .step_by(0.1),
-                            )
-                            .changed()
-                        {
-                            *changed_config = true;
-                        }
-                        ui.end_row();
+    fn ui_chord_diagram(&self, ui: &mut egui::Ui) {
+        let Some((shape, name)) = &self.current_chord else {
+            return;
+        };
+        ui.label("Chord:");
+        let (rect, _response) = ui.allocate_exact_size(chord_diagram_size(), egui::Sense::hover());
+        let shapes = layout_chord_diagram(shape, name.as_deref(), rect.min);
+        paint_tab_shapes(
+            &ui.painter_at(rect),
+            &shapes,
+            &self.renderer.style.font.family(),
+        );
+    }
 
-                        ui.label("String Tension:");
-                        if ui
-                            .add(
-                                egui::Slider::new(&mut custom_config.string_tension, 0.0..=1.0)
-                                    .step_by(0.001),
-                            )
-                            .changed()
-                        {
-                            *changed_config = true;
-                        }
-                        ui.end_row();
+    fn ui_fretboard(&self, ui: &mut egui::Ui) {
+        let capo_fret = self.configs.guitar_configs[self.configs.active_guitar].capo_fret;
+        let size = Vec2::new(ui.available_width(), FRETBOARD_HEIGHT);
+        let (rect, _response) = ui.allocate_exact_size(size, egui::Sense::hover());
+        let notes = self.current_notes.as_deref().unwrap_or_default();
+        let shapes = layout_fretboard(
+            notes,
+            capo_fret,
+            rect.min,
+            rect.width(),
+            self.configs.left_handed,
+            self.configs.fretboard_scale.as_ref(),
+        );
+        paint_tab_shapes(
+            &ui.painter_at(rect),
+            &shapes,
+            &self.renderer.style.font.family(),
+        );
+    }
 
-                        ui.label("Scale Length [inch]:");
-                        if ui
-                            .add(
-                                egui::Slider::new(&mut custom_config.scale_length, 10.0..=50.0)
-                                    .step_by(0.1),
-                            )
-                            .changed()
-                        {
-                            *changed_config = true;
-                        }
-                        ui.end_row();
-                    });
+    /// What a screen reader announces for the tab: where playback is and what sounds there.
+    fn tab_description(&self, score: &Score) -> String {
+        let view = self.configs.score_view.to_string();
+        if !self.is_playing {
+            return format!("{}, {} measures, stopped", view, score.measures.len());
+        }
+        let capo_fret = self.configs.guitar_configs[self.configs.active_guitar].capo_fret;
+        let notes: Vec<String> = self
+            .current_notes
+            .iter()
+            .flatten()
+            .filter_map(|note| Some((note.string?, note.fret? + capo_fret)))
+            .map(|(string, fret)| format!("string {} fret {}", string, fret))
+            .collect();
+        format!(
+            "{}, measure {} of {}, {}",
+            view,
+            self.current_measure_index + 1,
+            score.measures.len(),
+            if notes.is_empty() {
+                "rest".to_string()
+            } else {
+                notes.join(", ")
             }
-        });
+        )
     }
 
-    fn ui_render_settings(&mut self, ui: &mut egui::Ui, changed_rendered_score: &mut bool) {
-        ui.group(|ui| {
-            ui.heading("Render Settings");
-            ui.horizontal(|ui| {
-                ui.label("Dashes per division:");
-                if ui
-                    .add(egui::Slider::new(
-                        &mut self.configs.dashes_per_division,
-                        1..=8,
-                    ))
-                    .changed()
-                {
-                    self.renderer.dashes_per_division = self.configs.dashes_per_division;
-                    *changed_rendered_score = true;
-                }
-            });
-            ui.horizontal(|ui| {
-                ui.label("Measures per row:");
-                if ui
-                    .add(egui::Slider::new(&mut self.configs.measures_per_row, 1..=8))
-                    .changed()
-                {
-                    self.renderer.measures_per_row = self.configs.measures_per_row;
-                    *changed_rendered_score = true;
-                }
-            });
+    /// Keyboard control of the focused tab: space plays and stops, the arrows move by a measure.
+    fn handle_tab_keys(&mut self, ctx: &egui::Context) {
+        let (space, left, right) = ctx.input(|i| {
+            (
+                i.key_pressed(egui::Key::Space),
+                i.key_pressed(egui::Key::ArrowLeft),
+                i.key_pressed(egui::Key::ArrowRight),
+            )
         });
+        if space {
+            if self.is_playing {
+                self.stop_playback();
+            } else {
+                self.request_playback();
+            }
+        }
+        let measure = self.current_measure_index;
+        if left {
+            self.seek_to(measure.saturating_sub(1), 0);
+        }
+        if right {
+            self.seek_to(measure + 1, 0);
+        }
     }
 
     fn ui_current_notes(&self, ui: &mut egui::Ui) {
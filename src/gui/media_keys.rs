@@ -0,0 +1,119 @@
+// media_keys.rs
+
+use std::sync::mpsc::{channel, Receiver};
+
+use souvlaki::{MediaControlEvent, MediaControls, MediaMetadata, MediaPlayback, PlatformConfig};
+
+/// What a media key, a headset button or the system's media overlay asks the transport to do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MediaKey {
+    Play,
+    Pause,
+    Toggle,
+    Stop,
+    /// Start of the next section, or the next measure when the score has no sections
+    Next,
+    /// Start of the section being played, or the one before when already at its start
+    Previous,
+}
+
+/// Measure to skip to from `current`, given the measures the sections start at in order. Without
+/// sections every measure is a stop of its own.
+pub fn skip_target(
+    section_starts: &[usize],
+    measure_count: usize,
+    current: usize,
+    key: MediaKey,
+) -> Option<usize> {
+    let stops: Vec<usize> = if section_starts.is_empty() {
+        (0..measure_count).collect()
+    } else {
+        std::iter::once(0)
+            .chain(section_starts.iter().copied())
+            .filter(|&measure| measure < measure_count)
+            .collect()
+    };
+    match key {
+        MediaKey::Next => stops.into_iter().find(|&measure| measure > current),
+        MediaKey::Previous => stops
+            .iter()
+            .rev()
+            .find(|&&measure| measure < current)
+            .or(stops.first())
+            .copied(),
+        _ => None,
+    }
+}
+
+/// Registers with the operating system's media controls, MPRIS on Linux, Now Playing on macOS
+/// and the media transport controls on Windows, so the keyboard's play/pause and skip keys
+/// reach playback while the window isn't focused.
+pub struct MediaKeys {
+    controls: MediaControls,
+    events: Receiver<MediaKey>,
+}
+
+impl MediaKeys {
+    pub fn start() -> Result<Self, String> {
+        let config = PlatformConfig {
+            dbus_name: "cdefgab",
+            display_name: "cdefgab",
+            // Only needed on Windows, where the controls are left unavailable without it
+            hwnd: None,
+        };
+        let mut controls = MediaControls::new(config).map_err(|e| format!("{:?}", e))?;
+        let (sender, events) = channel();
+        controls
+            .attach(move |event| {
+                let key = match event {
+                    MediaControlEvent::Play => MediaKey::Play,
+                    MediaControlEvent::Pause => MediaKey::Pause,
+                    MediaControlEvent::Toggle => MediaKey::Toggle,
+                    MediaControlEvent::Stop => MediaKey::Stop,
+                    MediaControlEvent::Next => MediaKey::Next,
+                    MediaControlEvent::Previous => MediaKey::Previous,
+                    _ => return,
+                };
+                let _ = sender.send(key);
+            })
+            .map_err(|e| format!("{:?}", e))?;
+        Ok(Self { controls, events })
+    }
+
+    /// Keys pressed since the last call.
+    pub fn events(&self) -> Vec<MediaKey> {
+        self.events.try_iter().collect()
+    }
+
+    /// Shows the score and whether it's playing in the system's media overlay.
+    pub fn update(&mut self, title: &str, playing: bool) {
+        let _ = self.controls.set_metadata(MediaMetadata {
+            title: Some(title),
+            ..Default::default()
+        });
+        let playback = if playing {
+            MediaPlayback::Playing { progress: None }
+        } else {
+            MediaPlayback::Stopped
+        };
+        let _ = self.controls.set_playback(playback);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_between_sections_and_back_to_the_start_of_the_current_one() {
+        let sections = [4, 8];
+        assert_eq!(skip_target(&sections, 12, 5, MediaKey::Next), Some(8));
+        assert_eq!(skip_target(&sections, 12, 9, MediaKey::Next), None);
+        assert_eq!(skip_target(&sections, 12, 5, MediaKey::Previous), Some(4));
+        assert_eq!(skip_target(&sections, 12, 4, MediaKey::Previous), Some(0));
+        assert_eq!(skip_target(&sections, 12, 0, MediaKey::Previous), Some(0));
+        // Without sections the keys move a measure at a time
+        assert_eq!(skip_target(&[], 12, 5, MediaKey::Next), Some(6));
+        assert_eq!(skip_target(&[], 12, 5, MediaKey::Previous), Some(4));
+    }
+}
@@ -0,0 +1,77 @@
+// miss_hint.rs
+
+use std::time::Duration;
+
+use instant::Instant;
+
+/// Misses of the same notes after which they are played as a hint.
+pub const HINT_AFTER_MISSES: u32 = 2;
+// A try that rings this long without matching is a miss, even while it keeps ringing
+const MISS_AFTER: Duration = Duration::from_millis(600);
+// Long enough for the end of the hint to have died away and left the listener's smoothing
+const HINT_TAIL: Duration = Duration::from_millis(300);
+
+/// Counts the tries at the notes practice mode is waiting for, so they can be sounded as a
+/// hint once the player has missed them a couple of times. A try starts when the guitar starts
+/// sounding and is a miss if it fades or rings on without the notes being heard.
+#[derive(Default)]
+pub struct MissHint {
+    // When the try being played started, and whether it was counted as a miss already
+    current_try: Option<(Instant, bool)>,
+    misses: u32,
+    // The hint sounds until then, and the listener hearing it doesn't count
+    hinting_until: Option<Instant>,
+}
+
+impl MissHint {
+    /// Starts over for the notes of a new division.
+    pub fn reset(&mut self) {
+        self.current_try = None;
+        self.misses = 0;
+        self.hinting_until = None;
+    }
+
+    /// Whether the hint is still sounding, so what the listener hears is the synthesizer.
+    pub fn is_hinting(&self, now: Instant) -> bool {
+        self.hinting_until.is_some_and(|until| now < until)
+    }
+
+    /// Takes the listener's latest verdict. Returns true when the notes should be played as a
+    /// hint now, after which the tries are counted again from zero.
+    pub fn heard(&mut self, is_match: bool, sounding: bool, now: Instant) -> bool {
+        if self.is_hinting(now) {
+            self.current_try = None;
+            return false;
+        }
+        if is_match {
+            self.current_try = None;
+            self.misses = 0;
+            return false;
+        }
+        match (&mut self.current_try, sounding) {
+            (None, true) => self.current_try = Some((now, false)),
+            (Some((started, counted)), true) => {
+                if !*counted && now.duration_since(*started) >= MISS_AFTER {
+                    *counted = true;
+                    self.misses += 1;
+                }
+            }
+            (Some((_, counted)), false) => {
+                self.misses += !*counted as u32;
+                self.current_try = None;
+            }
+            (None, false) => {}
+        }
+        if self.misses < HINT_AFTER_MISSES {
+            return false;
+        }
+        self.misses = 0;
+        true
+    }
+
+    /// The hint was played and rings for `duration`.
+    pub fn hinted(&mut self, now: Instant, duration: Duration) {
+        self.current_try = None;
+        self.hinting_until = Some(now + duration + HINT_TAIL);
+    }
+}
@@ -1 +1,15 @@
+pub mod arrangement;
+pub mod diagnostics;
+pub mod eq_editor;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod error_map;
 pub mod gui;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod media_keys;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod miss_hint;
+pub mod position_report;
+pub mod setlist;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod spectrogram;
+pub mod tab_cache;
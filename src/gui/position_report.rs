@@ -0,0 +1,202 @@
+// position_report.rs
+
+use std::fmt::Write;
+
+use crate::music_representation::{Note, Score};
+
+pub const APP_TITLE: &str = "Tab App";
+#[cfg(not(target_arch = "wasm32"))]
+pub const DEFAULT_POSITION_PORT: u16 = 7878;
+
+/// `seconds` as m:ss.
+pub fn format_time(seconds: f32) -> String {
+    let seconds = seconds.max(0.0) as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Playback position in a form meant for screen readers, stream overlays and other tools.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PositionReport {
+    pub playing: bool,
+    /// One based, like the numbers printed in sheet music
+    pub measure: usize,
+    pub beat: usize,
+    pub total_measures: usize,
+    /// String and fret of the next notes to be played
+    pub upcoming: Vec<(u8, u8)>,
+}
+
+impl PositionReport {
+    pub fn new(score: &Score, playing: bool, measure_idx: usize, division_idx: usize) -> Self {
+        let divisions_per_beat = (score.divisions_per_quarter as usize * 4
+            / score.time_signature.beat_value.max(1) as usize)
+            .max(1);
+        Self {
+            playing,
+            measure: measure_idx + 1,
+            beat: division_idx / divisions_per_beat + 1,
+            total_measures: score.measures.len(),
+            upcoming: next_notes(score, measure_idx, division_idx)
+                .iter()
+                .filter_map(|note| Some((note.string?, note.fret?)))
+                .collect(),
+        }
+    }
+
+    /// Short sentence like "Measure 17, beat 3. Next: string 2 fret 3".
+    pub fn text(&self) -> String {
+        if !self.playing {
+            return "Stopped".to_string();
+        }
+        let mut text = format!("Measure {}, beat {}", self.measure, self.beat);
+        if !self.upcoming.is_empty() {
+            let notes: Vec<String> = self
+                .upcoming
+                .iter()
+                .map(|(string, fret)| format!("string {} fret {}", string, fret))
+                .collect();
+            let _ = write!(text, ". Next: {}", notes.join(", "));
+        }
+        text
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn json(&self) -> String {
+        let upcoming: Vec<String> = self
+            .upcoming
+            .iter()
+            .map(|(string, fret)| format!(r#"{{"string":{},"fret":{}}}"#, string, fret))
+            .collect();
+        format!(
+            r#"{{"playing":{},"measure":{},"beat":{},"total_measures":{},"upcoming":[{}]}}"#,
+            self.playing,
+            self.measure,
+            self.beat,
+            self.total_measures,
+            upcoming.join(",")
+        )
+    }
+
+    pub fn window_title(&self) -> String {
+        if self.playing {
+            format!(
+                "{} - Measure {}, beat {}",
+                APP_TITLE, self.measure, self.beat
+            )
+        } else {
+            APP_TITLE.to_string()
+        }
+    }
+}
+
+/// The first group of notes after the given position.
+fn next_notes(score: &Score, measure_idx: usize, division_idx: usize) -> &[Note] {
+    score
+        .measures
+        .iter()
+        .enumerate()
+        .skip(measure_idx)
+        .flat_map(|(idx, measure)| {
+            let skip = if idx == measure_idx {
+                division_idx + 1
+            } else {
+                0
+            };
+            measure.positions.iter().skip(skip)
+        })
+        .find(|notes| !notes.is_empty())
+        .map_or(&[], |notes| notes.as_slice())
+}
+
+/// Serves the latest report over HTTP on localhost: plain text at `/` and JSON at `/json`.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct PositionServer {
+    pub port: u16,
+    report: std::sync::Arc<std::sync::Mutex<PositionReport>>,
+    running: std::sync::Arc<std::sync::atomic::AtomicBool>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl PositionServer {
+    pub fn start(port: u16) -> Result<Self, String> {
+        use std::net::TcpListener;
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::{Arc, Mutex};
+
+        let listener = TcpListener::bind(("127.0.0.1", port)).map_err(|e| e.to_string())?;
+        // Polling lets the thread notice when the server is stopped
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+
+        let report = Arc::new(Mutex::new(PositionReport::default()));
+        let running = Arc::new(AtomicBool::new(true));
+        let (thread_report, thread_running) = (report.clone(), running.clone());
+        std::thread::spawn(move || {
+            while thread_running.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let report = thread_report.lock().map(|r| r.clone()).unwrap_or_default();
+                        if let Err(e) = respond(stream, &report) {
+                            eprintln!("Position server: {}", e);
+                        }
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        std::thread::sleep(std::time::Duration::from_millis(20));
+                    }
+                    Err(e) => eprintln!("Position server: {}", e),
+                }
+            }
+        });
+
+        Ok(Self {
+            port,
+            report,
+            running,
+        })
+    }
+
+    pub fn update(&self, report: &PositionReport) {
+        if let Ok(mut shared) = self.report.lock() {
+            *shared = report.clone();
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for PositionServer {
+    fn drop(&mut self) {
+        self.running
+            .store(false, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn respond(mut stream: std::net::TcpStream, report: &PositionReport) -> std::io::Result<()> {
+    use std::io::{BufRead, BufReader, Write};
+
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(std::time::Duration::from_secs(1)))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let target = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let path = target.split('?').next().unwrap_or(target);
+
+    let (status, content_type, body) = match path {
+        "/" => ("200 OK", "text/plain; charset=utf-8", report.text()),
+        "/json" => ("200 OK", "application/json", report.json()),
+        _ => (
+            "404 Not Found",
+            "text/plain; charset=utf-8",
+            "Not found".to_string(),
+        ),
+    };
+    // Overlays in browser sources fetch from another origin
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
@@ -0,0 +1,94 @@
+// setlist.rs
+
+use instant::Instant;
+
+use crate::music_representation::Score;
+
+pub struct SetlistEntry {
+    pub name: String,
+    pub score: Score,
+}
+
+/// Scores played one after another, e.g. for a rehearsal run-through.
+pub struct Setlist {
+    pub entries: Vec<SetlistEntry>,
+    /// Entry currently loaded in the tab view
+    pub current: Option<usize>,
+    pub auto_advance: bool,
+    /// Pause between two scores in seconds
+    pub gap_seconds: f32,
+    // When the next entry should start, while waiting in the gap
+    next_start: Option<Instant>,
+}
+
+impl Setlist {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            current: None,
+            auto_advance: true,
+            gap_seconds: 3.0,
+            next_start: None,
+        }
+    }
+
+    /// Called when the current score has played to the end.
+    pub fn score_finished(&mut self) {
+        let has_next = self.current.is_some_and(|idx| idx + 1 < self.entries.len());
+        if self.auto_advance && has_next {
+            self.next_start =
+                Some(Instant::now() + std::time::Duration::from_secs_f32(self.gap_seconds));
+        }
+    }
+
+    /// Seconds left until the next entry starts, if one is waiting.
+    pub fn time_until_next(&self) -> Option<f32> {
+        self.next_start.map(|start| {
+            start
+                .saturating_duration_since(Instant::now())
+                .as_secs_f32()
+        })
+    }
+
+    /// The index of the next entry once its gap has passed.
+    pub fn take_due(&mut self) -> Option<usize> {
+        let start = self.next_start?;
+        if Instant::now() < start {
+            return None;
+        }
+        self.next_start = None;
+        self.current.map(|idx| idx + 1)
+    }
+
+    pub fn cancel_advance(&mut self) {
+        self.next_start = None;
+    }
+
+    pub fn remove(&mut self, idx: usize) {
+        self.entries.remove(idx);
+        self.current = match self.current {
+            Some(current) if current == idx => None,
+            Some(current) if current > idx => Some(current - 1),
+            current => current,
+        };
+    }
+
+    pub fn swap(&mut self, a: usize, b: usize) {
+        self.entries.swap(a, b);
+        self.current = self.current.map(|current| {
+            if current == a {
+                b
+            } else if current == b {
+                a
+            } else {
+                current
+            }
+        });
+    }
+}
+
+impl Default for Setlist {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,157 @@
+// spectrogram.rs
+
+use std::collections::VecDeque;
+
+use eframe::egui;
+use egui::{pos2, Align2, Color32, ColorImage, FontId, Rect, TextureHandle, TextureOptions, Vec2};
+
+use crate::audio::spectrum::{hann_window, magnitude_spectrum};
+
+const FFT_SIZE: usize = 2048;
+const HOP_SIZE: usize = 512; // samples between columns, about 12 ms at 44.1 kHz
+const COLUMNS: usize = 256;
+const ROWS: usize = 128;
+// Covers the low E string up to the harmonics that make up a guitar's tone
+const MIN_FREQUENCY: f32 = 60.0;
+const MAX_FREQUENCY: f32 = 6000.0;
+const FLOOR_DB: f32 = -80.0;
+const HEIGHT: f32 = 200.0;
+
+// Dark to bright, so quiet bins fade into the background
+const COLORMAP: [Color32; 5] = [
+    Color32::from_rgb(0, 0, 4),
+    Color32::from_rgb(87, 16, 110),
+    Color32::from_rgb(188, 55, 84),
+    Color32::from_rgb(249, 142, 9),
+    Color32::from_rgb(252, 255, 164),
+];
+
+fn colormap(level: f32) -> Color32 {
+    let scaled = level.clamp(0.0, 1.0) * (COLORMAP.len() - 1) as f32;
+    let idx = (scaled as usize).min(COLORMAP.len() - 2);
+    let t = scaled - idx as f32;
+    let (a, b) = (COLORMAP[idx], COLORMAP[idx + 1]);
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t) as u8;
+    Color32::from_rgb(mix(a.r(), b.r()), mix(a.g(), b.g()), mix(a.b(), b.b()))
+}
+
+/// Frequency at the center of a row, with row 0 at the top.
+fn row_frequency(row: usize) -> f32 {
+    let fraction = 1.0 - (row as f32 + 0.5) / ROWS as f32;
+    MIN_FREQUENCY * (MAX_FREQUENCY / MIN_FREQUENCY).powf(fraction)
+}
+
+/// FFT magnitudes over time as a heatmap, newest column on the right, with frequency on a log
+/// scale so every octave gets the same height.
+pub struct Spectrogram {
+    // Levels in 0..=1 for every row, oldest first
+    columns: VecDeque<[f32; ROWS]>,
+    // Input not yet covered by a full FFT frame
+    pending: Vec<f32>,
+    texture: Option<TextureHandle>,
+    dirty: bool,
+}
+
+impl Spectrogram {
+    pub fn new() -> Self {
+        Self {
+            columns: VecDeque::from(vec![[0.0; ROWS]; COLUMNS]),
+            pending: Vec::new(),
+            texture: None,
+            dirty: true,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn push_samples(&mut self, samples: &[f32], sample_rate: u32) {
+        self.pending.extend_from_slice(samples);
+        // Drop what can't be shown anyway if the UI fell behind
+        let excess = self
+            .pending
+            .len()
+            .saturating_sub(FFT_SIZE + COLUMNS * HOP_SIZE);
+        self.pending.drain(..excess);
+
+        while self.pending.len() >= FFT_SIZE {
+            let spectrum = magnitude_spectrum(&hann_window(&self.pending[..FFT_SIZE]));
+            let bin_width = sample_rate as f32 / FFT_SIZE as f32;
+            let mut column = [0.0; ROWS];
+            for (row, level) in column.iter_mut().enumerate() {
+                let bin =
+                    ((row_frequency(row) / bin_width).round() as usize).min(spectrum.len() - 1);
+                // Normalized so a full scale sine reaches 0 dB
+                let magnitude = spectrum[bin] * 4.0 / FFT_SIZE as f32;
+                let db = 20.0 * magnitude.max(1e-9).log10();
+                *level = 1.0 - db / FLOOR_DB;
+            }
+            self.columns.pop_front();
+            self.columns.push_back(column);
+            self.pending.drain(..HOP_SIZE);
+            self.dirty = true;
+        }
+    }
+
+    fn image(&self) -> ColorImage {
+        let mut image = ColorImage::new([COLUMNS, ROWS], Color32::BLACK);
+        for (x, column) in self.columns.iter().enumerate() {
+            for (y, &level) in column.iter().enumerate() {
+                image.pixels[y * COLUMNS + x] = colormap(level);
+            }
+        }
+        image
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        if self.dirty || self.texture.is_none() {
+            let image = self.image();
+            match &mut self.texture {
+                Some(texture) => texture.set(image, TextureOptions::LINEAR),
+                None => {
+                    self.texture = Some(ui.ctx().load_texture(
+                        "spectrogram",
+                        image,
+                        TextureOptions::LINEAR,
+                    ))
+                }
+            }
+            self.dirty = false;
+        }
+        let Some(texture) = &self.texture else {
+            return;
+        };
+
+        let size = Vec2::new(ui.available_width(), HEIGHT);
+        let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        let uv = Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0));
+        painter.image(texture.id(), rect, uv, Color32::WHITE);
+
+        // Frequency labels on the left edge
+        let log_range = (MAX_FREQUENCY / MIN_FREQUENCY).ln();
+        for frequency in [100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0] {
+            let fraction = (frequency / MIN_FREQUENCY).ln() / log_range;
+            let y = rect.bottom() - fraction * rect.height();
+            let text = if frequency >= 1000.0 {
+                format!("{} kHz", frequency / 1000.0)
+            } else {
+                format!("{} Hz", frequency)
+            };
+            painter.text(
+                pos2(rect.left() + 4.0, y),
+                Align2::LEFT_CENTER,
+                text,
+                FontId::proportional(10.0),
+                Color32::from_white_alpha(180),
+            );
+        }
+    }
+}
+
+impl Default for Spectrogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,180 @@
+// tab_cache.rs
+
+use eframe::egui;
+use egui::epaint::{PathStroke, QuadraticBezierShape, Tessellator};
+use egui::{Mesh, Pos2, Shape};
+
+use crate::music_representation::Section;
+use crate::renderer::renderer::{Renderer, TabShape, TabStyle};
+
+/// Everything the laid out tab depends on. The shapes are built again whenever any of it
+/// changes.
+#[derive(Clone, PartialEq)]
+pub struct TabCacheKey {
+    /// Bumped every time another score is loaded
+    pub score_revision: u64,
+    /// Sections are edited in place on the loaded score
+    pub sections: Vec<Section>,
+    pub piano_roll: bool,
+    pub measures_per_row: usize,
+    pub dashes_per_division: usize,
+    pub left_handed: bool,
+    pub style: TabStyle,
+    pub capo_fret: u8,
+    pub pixels_per_point: f32,
+}
+
+impl TabCacheKey {
+    pub fn new(
+        score_revision: u64,
+        sections: &[Section],
+        piano_roll: bool,
+        renderer: &Renderer,
+        capo_fret: u8,
+        pixels_per_point: f32,
+    ) -> Self {
+        Self {
+            score_revision,
+            sections: sections.to_vec(),
+            piano_roll,
+            measures_per_row: renderer.measures_per_row,
+            dashes_per_division: renderer.dashes_per_division,
+            left_handed: renderer.left_handed,
+            style: renderer.style,
+            capo_fret,
+            pixels_per_point,
+        }
+    }
+}
+
+/// One row's lines, curves and dots tessellated into a mesh, and its text laid out into
+/// galleys, relative to the top left corner of the row.
+struct CachedRow {
+    mesh: Mesh,
+    texts: Vec<Shape>,
+}
+
+impl CachedRow {
+    fn new(ctx: &egui::Context, shapes: &[TabShape], family: &egui::FontFamily) -> Self {
+        let options = ctx.tessellation_options(|options| *options);
+        ctx.fonts(|fonts| {
+            let mut tessellator = Tessellator::new(
+                ctx.pixels_per_point(),
+                options,
+                fonts.font_image_size(),
+                fonts.texture_atlas().lock().prepared_discs(),
+            );
+            let mut mesh = Mesh::default();
+            let mut texts = Vec::new();
+            for shape in shapes {
+                let shape = to_shape(fonts, shape, family);
+                if matches!(shape, Shape::Text(_)) {
+                    texts.push(shape);
+                } else {
+                    tessellator.tessellate_shape(shape, &mut mesh);
+                }
+            }
+            Self { mesh, texts }
+        })
+    }
+
+    fn paint(&self, painter: &egui::Painter, origin: Pos2) {
+        let offset = origin.to_vec2();
+        let mut mesh = self.mesh.clone();
+        mesh.translate(offset);
+        painter.add(Shape::mesh(mesh));
+        for text in &self.texts {
+            let mut text = text.clone();
+            text.translate(offset);
+            painter.add(text);
+        }
+    }
+}
+
+/// The tab's rows, each laid out and tessellated the first time it scrolls into view, so a
+/// frame only costs as much as the rows on screen no matter how long the score is.
+pub struct TabCache {
+    pub key: TabCacheKey,
+    rows: Vec<Option<CachedRow>>,
+    row_height: f32,
+}
+
+impl TabCache {
+    pub fn new(key: TabCacheKey, total_rows: usize, row_height: f32) -> Self {
+        Self {
+            key,
+            rows: (0..total_rows).map(|_| None).collect(),
+            row_height,
+        }
+    }
+
+    /// Paints the rows within the painter's clip rect with the top left corner of the tab at
+    /// `origin`. `layout` lays out the given row with its top left corner at `Pos2::ZERO`.
+    /// Returns how many rows had to be laid out.
+    pub fn paint(
+        &mut self,
+        painter: &egui::Painter,
+        origin: Pos2,
+        family: &egui::FontFamily,
+        layout: impl Fn(usize) -> Vec<TabShape>,
+    ) -> usize {
+        let clip = painter.clip_rect();
+        // One more row on each side, as bends and section names reach into their neighbours
+        let first = ((clip.top() - origin.y) / self.row_height).floor() - 1.0;
+        let last = ((clip.bottom() - origin.y) / self.row_height).ceil() + 1.0;
+        let visible = (first.max(0.0) as usize)..(last.max(0.0) as usize).min(self.rows.len());
+
+        let mut laid_out = 0;
+        for row in visible {
+            let cached = self.rows[row].get_or_insert_with(|| {
+                laid_out += 1;
+                CachedRow::new(painter.ctx(), &layout(row), family)
+            });
+            cached.paint(
+                painter,
+                origin + egui::vec2(0.0, row as f32 * self.row_height),
+            );
+        }
+        laid_out
+    }
+}
+
+/// The egui shape drawing `shape`.
+pub fn to_shape(fonts: &egui::epaint::Fonts, shape: &TabShape, family: &egui::FontFamily) -> Shape {
+    match shape {
+        TabShape::Line {
+            points,
+            width,
+            color,
+        } => Shape::line_segment(*points, egui::Stroke::new(*width, *color)),
+        TabShape::Text {
+            pos,
+            anchor,
+            text,
+            size,
+            color,
+        } => Shape::text(
+            fonts,
+            *pos,
+            *anchor,
+            text,
+            egui::FontId::new(*size, family.clone()),
+            *color,
+        ),
+        TabShape::Curve {
+            points,
+            width,
+            color,
+        } => Shape::QuadraticBezier(QuadraticBezierShape {
+            points: *points,
+            closed: false,
+            fill: egui::Color32::TRANSPARENT,
+            stroke: PathStroke::new(*width, *color),
+        }),
+        TabShape::Dot {
+            center,
+            radius,
+            color,
+        } => Shape::circle_filled(*center, *radius, *color),
+    }
+}
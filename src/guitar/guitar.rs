@@ -2,7 +2,13 @@
 
 use std::fmt;
 
-#[derive(Clone, Debug)]
+use crate::audio::chorus::ChorusSettings;
+use crate::audio::convolution::{ACOUSTIC_BODY, CABINET_1X12, JUMBO_BODY};
+use crate::audio::drive::DriveSettings;
+use crate::audio::effect_chain::{EffectKind, DEFAULT_CHAIN};
+use crate::audio::equalizer::{EqPreset, EqSettings};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum GuitarType {
     Custom,
     Acoustic,
@@ -36,9 +42,80 @@ pub struct GuitarConfig {
     pub capo_fret: u8,
     pub name: GuitarType,
     pub volume: f32,
+    /// Level of the scrape of the pick at the start of every note, 0 for none
+    pub pick_noise: f32,
+    /// Sustain from amplifier feedback, 0 lets notes die away naturally
+    pub feedback: f32,
+    pub chorus: ChorusSettings,
+    /// Overdrive in front of the cabinet, for electric guitars
+    pub drive: DriveSettings,
+    /// Name of the body or cabinet impulse response the notes are convolved with
+    pub impulse_response: Option<String>,
+    /// 0 for the plain synth, 1 for the convolved sound only
+    pub impulse_response_mix: f32,
+    pub eq: EqSettings,
+    /// Let the open strings ring along with notes they share harmonics with
+    pub sympathetic_resonance: bool,
+    /// How far apart the strings are panned, low strings to the left and high ones to the
+    /// right, 0 for all of them in the middle
+    pub stereo_width: f32,
+    /// Order the effects run in after the strings, effects left out don't run
+    pub effects: Vec<EffectKind>,
 }
 
 impl GuitarConfig {
+    /// The profile's values as shipped, before any tweaking.
+    pub fn factory(name: &GuitarType) -> Self {
+        match name {
+            GuitarType::Custom => Self::custom(
+                0.996, // decay
+                0.5,   // string_damping
+                100.0, // body_resonance
+                0.5,   // body_damping
+                0.7,   // string_tension
+                25.5,  // scale_length
+                0,     // capo_fret
+                0.5,   // volume
+            ),
+            GuitarType::Acoustic => Self::acoustic(),
+            GuitarType::Classical => Self::classical(),
+            GuitarType::Electric => Self::electric(),
+            GuitarType::Bass => Self::bass_guitar(),
+            GuitarType::TwelveString => Self::twelve_string(),
+        }
+    }
+
+    /// Whether the sound of the profile is still the factory one. Capo and volume are set
+    /// elsewhere and don't count.
+    pub fn is_factory(&self) -> bool {
+        let factory = Self::factory(&self.name);
+        self.decay == factory.decay
+            && self.string_damping == factory.string_damping
+            && self.body_resonance == factory.body_resonance
+            && self.body_damping == factory.body_damping
+            && self.string_tension == factory.string_tension
+            && self.scale_length == factory.scale_length
+            && self.pick_noise == factory.pick_noise
+            && self.feedback == factory.feedback
+            && self.chorus == factory.chorus
+            && self.drive == factory.drive
+            && self.impulse_response == factory.impulse_response
+            && self.impulse_response_mix == factory.impulse_response_mix
+            && self.eq == factory.eq
+            && self.stereo_width == factory.stereo_width
+            && self.effects == factory.effects
+    }
+
+    /// Restores the factory sound, keeping the capo and volume.
+    pub fn reset_to_factory(&mut self) {
+        *self = Self {
+            capo_fret: self.capo_fret,
+            volume: self.volume,
+            sympathetic_resonance: self.sympathetic_resonance,
+            ..Self::factory(&self.name)
+        };
+    }
+
     pub fn acoustic() -> Self {
         Self {
             name: GuitarType::Acoustic,
@@ -50,6 +127,16 @@ impl GuitarConfig {
             scale_length: 25.5,
             capo_fret: 0,
             volume: 0.5,
+            pick_noise: 0.3,
+            feedback: 0.0,
+            chorus: ChorusSettings::off(),
+            drive: DriveSettings::off(),
+            impulse_response: Some(ACOUSTIC_BODY.to_string()),
+            impulse_response_mix: 0.5,
+            eq: EqPreset::LessBoxy.settings(),
+            sympathetic_resonance: false,
+            stereo_width: 0.6,
+            effects: DEFAULT_CHAIN.to_vec(),
         }
     }
 
@@ -64,6 +151,20 @@ impl GuitarConfig {
             scale_length: 25.5,
             capo_fret: 0,
             volume: 0.5,
+            pick_noise: 0.4,
+            feedback: 0.0,
+            chorus: ChorusSettings::chorus(0.6, 2.5, 0.25),
+            drive: DriveSettings {
+                gain: 0.5,
+                tone: 0.6,
+                level: 0.4,
+            },
+            impulse_response: Some(CABINET_1X12.to_string()),
+            impulse_response_mix: 1.0,
+            eq: EqSettings::flat(),
+            sympathetic_resonance: false,
+            stereo_width: 0.3,
+            effects: DEFAULT_CHAIN.to_vec(),
         }
     }
 
@@ -78,6 +179,16 @@ impl GuitarConfig {
             scale_length: 25.6,
             capo_fret: 0,
             volume: 0.5,
+            pick_noise: 0.1,
+            feedback: 0.0,
+            chorus: ChorusSettings::off(),
+            drive: DriveSettings::off(),
+            impulse_response: Some(ACOUSTIC_BODY.to_string()),
+            impulse_response_mix: 0.4,
+            eq: EqSettings::flat(),
+            sympathetic_resonance: false,
+            stereo_width: 0.6,
+            effects: DEFAULT_CHAIN.to_vec(),
         }
     }
 
@@ -92,6 +203,16 @@ impl GuitarConfig {
             scale_length: 34.0,
             capo_fret: 0,
             volume: 0.5,
+            pick_noise: 0.15,
+            feedback: 0.0,
+            chorus: ChorusSettings::off(),
+            drive: DriveSettings::off(),
+            impulse_response: None,
+            impulse_response_mix: 0.5,
+            eq: EqSettings::flat(),
+            sympathetic_resonance: false,
+            stereo_width: 0.0,
+            effects: DEFAULT_CHAIN.to_vec(),
         }
     }
 
@@ -106,6 +227,16 @@ impl GuitarConfig {
             scale_length: 25.5,
             capo_fret: 0,
             volume: 0.5,
+            pick_noise: 0.35,
+            feedback: 0.0,
+            chorus: ChorusSettings::chorus(0.3, 1.5, 0.4),
+            drive: DriveSettings::off(),
+            impulse_response: Some(JUMBO_BODY.to_string()),
+            impulse_response_mix: 0.5,
+            eq: EqSettings::flat(),
+            sympathetic_resonance: false,
+            stereo_width: 0.8,
+            effects: DEFAULT_CHAIN.to_vec(),
         }
     }
 
@@ -131,6 +262,16 @@ impl GuitarConfig {
             capo_fret: validated_capo_fret,
             name: GuitarType::Custom,
             volume,
+            pick_noise: 0.2,
+            feedback: 0.0,
+            chorus: ChorusSettings::off(),
+            drive: DriveSettings::off(),
+            impulse_response: None,
+            impulse_response_mix: 0.5,
+            eq: EqSettings::flat(),
+            sympathetic_resonance: false,
+            stereo_width: 0.5,
+            effects: DEFAULT_CHAIN.to_vec(),
         }
     }
 }
@@ -1,14 +1,28 @@
 // karplus_strong.rs
 
-use rand::random;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::f32::consts::PI;
 
 use crate::guitar::guitar::GuitarConfig;
 
+// Length and decay time of the pick noise transient
+const ATTACK_SECONDS: f32 = 0.02;
+const ATTACK_DECAY_SECONDS: f32 = 0.004;
+// Level a held note settles at with full feedback
+const FEEDBACK_LEVEL: f32 = 0.6;
+// Time a decayed note takes to swell back up to the feedback level
+const FEEDBACK_BLOOM_SECONDS: f32 = 0.3;
+
 pub struct KarplusStrong {
     buffer: Vec<f32>,
     position: usize,
     remaining_samples: usize,
+    // Pick noise added on top of the string's first samples
+    attack: Vec<f32>,
+    rendered_samples: usize,
+    // Loudest sample of the delay line in the current period, tracked for the feedback
+    period_peak: f32,
     config: GuitarConfig,
     sample_rate: f32,
 }
@@ -19,13 +33,37 @@ impl KarplusStrong {
         duration_seconds: f32,
         sample_rate: f32,
         config: &GuitarConfig,
+    ) -> Self {
+        let mut rng = rand::thread_rng();
+        Self::with_rng(frequency, duration_seconds, sample_rate, config, &mut rng)
+    }
+
+    /// Like `new`, but the pluck's noise burst comes from `seed`, so the same seed always
+    /// renders the same samples.
+    pub fn seeded(
+        frequency: f32,
+        duration_seconds: f32,
+        sample_rate: f32,
+        config: &GuitarConfig,
+        seed: u64,
+    ) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        Self::with_rng(frequency, duration_seconds, sample_rate, config, &mut rng)
+    }
+
+    fn with_rng(
+        frequency: f32,
+        duration_seconds: f32,
+        sample_rate: f32,
+        config: &GuitarConfig,
+        rng: &mut impl Rng,
     ) -> Self {
         let buffer_length = (sample_rate / frequency).ceil() as usize;
         let mut buffer = Vec::with_capacity(buffer_length);
 
         let mut prev = 0.0;
         for _ in 0..buffer_length {
-            let white = random::<f32>() * 2.0 - 1.0;
+            let white = rng.gen::<f32>() * 2.0 - 1.0;
 
             let tension_effect = config.string_tension * white;
             let filtered =
@@ -34,48 +72,145 @@ impl KarplusStrong {
             prev = filtered;
         }
 
+        let attack = pick_noise(config.pick_noise, sample_rate, rng);
         let remaining_samples = (duration_seconds * sample_rate) as usize;
         KarplusStrong {
             buffer,
             position: 0,
             remaining_samples,
+            attack,
+            rendered_samples: 0,
+            period_peak: 0.0,
             config: config.clone(),
             sample_rate,
         }
     }
 
-    pub fn next_sample(&mut self) -> Option<f32> {
-        if self.remaining_samples == 0 {
-            return None;
-        }
-
-        let current_value = self.buffer[self.position];
-        let next_index = (self.position + 1) % self.buffer.len();
-        let next_value = self.buffer[next_index];
-
-        let string_sample = self.config.decay
-            * (self.config.string_damping * current_value
-                + (1.0 - self.config.string_damping) * next_value);
+    /// Fills `out` with the next samples and returns how many were written, fewer than
+    /// `out.len()` once the note has ended.
+    pub fn render_block(&mut self, out: &mut [f32]) -> usize {
+        let count = out.len().min(self.remaining_samples);
+        let len = self.buffer.len();
 
+        // Averaging filter, decay and body resonance folded into three constants
+        let current_gain = self.config.decay * self.config.string_damping;
+        let next_gain = self.config.decay * (1.0 - self.config.string_damping);
         let body_freq = 2.0 * PI * self.config.body_resonance / self.sample_rate;
+        let output_gain = 0.7 + 0.3 * body_freq.sin() * (1.0 - self.config.body_damping);
+        let feedback = self.config.feedback > 0.0;
+
+        let mut written = 0;
+        while written < count {
+            if self.position == len - 1 {
+                // The last sample of the delay line reads the already updated first one
+                let string_sample =
+                    current_gain * self.buffer[self.position] + next_gain * self.buffer[0];
+                self.buffer[self.position] = string_sample;
+                out[written] = string_sample * output_gain;
+                self.position = 0;
+                written += 1;
+                if feedback {
+                    self.period_peak = self.period_peak.max(string_sample.abs());
+                    self.regenerate();
+                }
+                continue;
+            }
+
+            // Up to the end of the delay line, every sample only reads values not yet updated
+            let segment = (count - written).min(len - 1 - self.position);
+            let source = &self.buffer[self.position..=self.position + segment];
+            let target = &mut out[written..written + segment];
+            filter_segment(source, target, current_gain, next_gain);
+            self.buffer[self.position..self.position + segment].copy_from_slice(target);
+            if feedback {
+                let peak = target.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+                self.period_peak = self.period_peak.max(peak);
+            }
+            for sample in target.iter_mut() {
+                *sample *= output_gain;
+            }
+
+            self.position += segment;
+            written += segment;
+        }
 
-        let resonated = string_sample * body_freq.sin();
-        let body_sample = resonated * (1.0 - self.config.body_damping);
+        let attack = self.attack.get(self.rendered_samples..).unwrap_or_default();
+        for (sample, noise) in out[..count].iter_mut().zip(attack) {
+            *sample += noise;
+        }
+        self.rendered_samples += count;
+        self.remaining_samples -= count;
+        count
+    }
 
-        self.buffer[self.position] = string_sample;
-        self.position = next_index;
-        self.remaining_samples -= 1;
+    /// Feeds energy back into the delay line once per period while the string is quieter than
+    /// the feedback level, so a held note swells instead of dying away.
+    fn regenerate(&mut self) {
+        let target = FEEDBACK_LEVEL * self.config.feedback;
+        let peak = std::mem::take(&mut self.period_peak);
+        if peak <= f32::EPSILON || peak >= target {
+            return;
+        }
+        let period_seconds = self.buffer.len() as f32 / self.sample_rate;
+        let gain = (target / peak).powf(period_seconds / FEEDBACK_BLOOM_SECONDS);
+        for sample in &mut self.buffer {
+            *sample *= gain;
+        }
+    }
 
-        Some(string_sample * 0.7 + body_sample * 0.3)
+    /// Samples left until the note has ended.
+    pub fn remaining_samples(&self) -> usize {
+        self.remaining_samples
     }
 
     pub fn generate_audio_data(&mut self) -> Vec<f32> {
-        let mut audio_data = Vec::new();
+        let mut audio_data = vec![0.0; self.remaining_samples];
+        self.render_block(&mut audio_data);
+        audio_data
+    }
+}
 
-        while let Some(sample) = self.next_sample() {
-            audio_data.push(sample);
-        }
+/// A short burst of high-passed noise fading out within a few milliseconds, like a pick
+/// scraping over the string before it rings.
+fn pick_noise(level: f32, sample_rate: f32, rng: &mut impl Rng) -> Vec<f32> {
+    if level <= 0.0 {
+        return Vec::new();
+    }
+    let length = (ATTACK_SECONDS * sample_rate) as usize;
+    let decay = (-1.0 / (ATTACK_DECAY_SECONDS * sample_rate)).exp();
+    let mut envelope = 0.3 * level;
+    let (mut previous_in, mut previous_out) = (0.0, 0.0);
+    (0..length)
+        .map(|_| {
+            let white = rng.gen::<f32>() * 2.0 - 1.0;
+            // One-pole high-pass, leaving the bright part a pick adds
+            let filtered = 0.9 * (previous_out + white - previous_in);
+            previous_in = white;
+            previous_out = filtered;
+            let sample = filtered * envelope;
+            envelope *= decay;
+            sample
+        })
+        .collect()
+}
 
-        audio_data
+const LANES: usize = 8;
+
+/// `target[i] = current_gain * source[i] + next_gain * source[i + 1]`, unrolled into fixed size
+/// chunks the compiler turns into SIMD instructions.
+fn filter_segment(source: &[f32], target: &mut [f32], current_gain: f32, next_gain: f32) {
+    let unrolled = target.len() / LANES * LANES;
+    let (chunks, rest) = target.split_at_mut(unrolled);
+    for (chunk_idx, chunk) in chunks.chunks_exact_mut(LANES).enumerate() {
+        let start = chunk_idx * LANES;
+        let current: &[f32; LANES] = source[start..start + LANES].try_into().unwrap();
+        let next: &[f32; LANES] = source[start + 1..start + 1 + LANES].try_into().unwrap();
+        for lane in 0..LANES {
+            chunk[lane] = current_gain * current[lane] + next_gain * next[lane];
+        }
+    }
+    for (idx, sample) in rest.iter_mut().enumerate() {
+        let idx = unrolled + idx;
+        *sample = current_gain * source[idx] + next_gain * source[idx + 1];
     }
 }
@@ -0,0 +1,12 @@
+pub mod annotations;
+pub mod audio;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cli;
+pub mod export;
+pub mod generation;
+pub mod gui;
+pub mod guitar;
+pub mod karplus_strong;
+pub mod library;
+pub mod music_representation;
+pub mod renderer;
@@ -0,0 +1,170 @@
+// library/folder.rs
+
+use std::path::{Path, PathBuf};
+
+use crate::library::store::LibraryEntry;
+use crate::music_representation::Score;
+
+const MUSICXML_EXTENSIONS: [&str; 2] = ["xml", "musicxml"];
+// Listed so they don't seem lost, but there is no reader for them yet
+const GUITAR_PRO_EXTENSIONS: [&str; 5] = ["gp", "gp3", "gp4", "gp5", "gpx"];
+
+enum Metadata {
+    Pending,
+    Parsed(Box<LibraryEntry>),
+    Failed(String),
+}
+
+/// A score file found in the library folder. Its metadata is read the first time it is needed.
+pub struct IndexedFile {
+    pub path: PathBuf,
+    pub file_name: String,
+    metadata: Metadata,
+}
+
+impl IndexedFile {
+    fn new(path: PathBuf) -> Self {
+        let file_name = path
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().to_string());
+        Self {
+            path,
+            file_name,
+            metadata: Metadata::Pending,
+        }
+    }
+
+    fn parse(&mut self) {
+        if !matches!(self.metadata, Metadata::Pending) {
+            return;
+        }
+        self.metadata = match read_entry(&self.path, &self.file_name) {
+            Ok(entry) => Metadata::Parsed(Box::new(entry)),
+            Err(e) => Metadata::Failed(e),
+        };
+    }
+
+    pub fn entry(&self) -> Option<&LibraryEntry> {
+        match &self.metadata {
+            Metadata::Parsed(entry) => Some(entry),
+            _ => None,
+        }
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        match &self.metadata {
+            Metadata::Failed(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// Files not parsed yet can only be found by their name.
+    fn matches(&self, query: &str) -> bool {
+        match self.entry() {
+            Some(entry) => entry.matches(query),
+            None => {
+                let name = self.file_name.to_lowercase();
+                query
+                    .to_lowercase()
+                    .split_whitespace()
+                    .all(|word| name.contains(word))
+            }
+        }
+    }
+
+    pub fn load_score(&self) -> Result<Score, String> {
+        if let Some(e) = self.error() {
+            return Err(e.to_string());
+        }
+        Score::parse_from_musicxml(&self.path)
+    }
+}
+
+fn extension_in(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extensions.contains(&extension.to_lowercase().as_str()))
+}
+
+fn read_entry(path: &Path, file_name: &str) -> Result<LibraryEntry, String> {
+    if extension_in(path, &GUITAR_PRO_EXTENSIONS) {
+        return Err("Guitar Pro files can't be read yet".to_string());
+    }
+    let xml = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let score = Score::parse_from_musicxml_str(&xml)?;
+    Ok(LibraryEntry::new(file_name.to_string(), xml, &score).with_path(path.to_path_buf()))
+}
+
+fn collect_scores(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries = std::fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            // Unreadable subfolders are skipped rather than failing the whole scan
+            if let Err(e) = collect_scores(&path, files) {
+                eprintln!("Skipping {}", e);
+            }
+        } else if extension_in(&path, &MUSICXML_EXTENSIONS)
+            || extension_in(&path, &GUITAR_PRO_EXTENSIONS)
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Every score file in a folder and its subfolders.
+pub struct FolderIndex {
+    pub root: PathBuf,
+    pub files: Vec<IndexedFile>,
+}
+
+impl FolderIndex {
+    pub fn scan(root: &Path) -> Result<Self, String> {
+        let mut paths = Vec::new();
+        collect_scores(root, &mut paths)?;
+        paths.sort();
+        Ok(Self {
+            root: root.to_path_buf(),
+            files: paths.into_iter().map(IndexedFile::new).collect(),
+        })
+    }
+
+    /// Reads the metadata of the file at `idx` if it hasn't been read yet.
+    pub fn parse(&mut self, idx: usize) {
+        if let Some(file) = self.files.get_mut(idx) {
+            file.parse();
+        }
+    }
+
+    /// Reads the metadata of up to `budget` more files. Returns whether any are left.
+    pub fn parse_pending(&mut self, budget: usize) -> bool {
+        let mut pending = self
+            .files
+            .iter_mut()
+            .filter(|file| matches!(file.metadata, Metadata::Pending));
+        for file in pending.by_ref().take(budget) {
+            file.parse();
+        }
+        pending.next().is_some()
+    }
+
+    /// Indices of the files matching the query, in path order.
+    pub fn search(&self, query: &str) -> Vec<usize> {
+        self.files
+            .iter()
+            .enumerate()
+            .filter(|(_, file)| file.matches(query))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// The file's path below the folder, which tells apart files with the same name.
+    pub fn relative_path(&self, idx: usize) -> String {
+        let path = &self.files[idx].path;
+        path.strip_prefix(&self.root)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    }
+}
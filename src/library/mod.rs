@@ -0,0 +1,5 @@
+#[cfg(not(target_arch = "wasm32"))]
+pub mod folder;
+pub mod store;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod watch;
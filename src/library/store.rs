@@ -0,0 +1,311 @@
+// library/store.rs
+
+use std::collections::BTreeSet;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::music_representation::{Note, Score, Technique};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub const LIBRARY_FILE: &str = "library.json";
+
+/// Hardest rating given by `estimate_difficulty`.
+pub const MAX_DIFFICULTY: u8 = 5;
+
+/// Stable FNV-1a hash of a score file, identifying the same score across machines.
+pub fn content_hash(content: &str) -> u64 {
+    content.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Rough 1 to 5 rating from note density, fret span and techniques.
+pub fn estimate_difficulty(score: &Score) -> u8 {
+    let notes: Vec<_> = score
+        .measures
+        .iter()
+        .flat_map(|measure| measure.positions.iter().flatten())
+        .collect();
+    if notes.is_empty() {
+        return 1;
+    }
+
+    let seconds_per_division =
+        60.0 / score.tempo.max(1) as f32 / score.divisions_per_quarter.max(1) as f32;
+    let positions = || {
+        score
+            .measures
+            .iter()
+            .flat_map(|measure| measure.positions.iter())
+    };
+    let seconds = positions().count().max(1) as f32 * seconds_per_division;
+    let onsets_per_second = positions().filter(|notes| !notes.is_empty()).count() as f32 / seconds;
+
+    let highest_fret = notes.iter().filter_map(|note| note.fret).max().unwrap_or(0) as f32;
+    let widest_stretch = positions()
+        .filter_map(|notes| {
+            let fretted = notes.iter().filter_map(|n| n.fret).filter(|&f| f > 0);
+            Some(fretted.clone().max()? - fretted.min()?)
+        })
+        .max()
+        .unwrap_or(0) as f32;
+    let technique_share = notes
+        .iter()
+        .filter(|note| note.technique != Technique::None)
+        .count() as f32
+        / notes.len() as f32;
+
+    // Speed weighs the most, a stretch only counts beyond what one hand position covers
+    let score = (onsets_per_second / 3.0).min(2.0)
+        + (highest_fret / 12.0).min(1.0)
+        + ((widest_stretch - 3.0).max(0.0) / 2.0).min(1.0)
+        + (technique_share * 3.0).min(1.0);
+    (1.0 + score).round().clamp(1.0, MAX_DIFFICULTY as f32) as u8
+}
+
+/// How hard `measure` is to play at `tempo`, from 0 for open strings at a walking pace up to 1.
+/// Fast notes, wide stretches and jumps along the neck all add to it.
+pub fn measure_difficulty(score: &Score, measure: usize, tempo: usize) -> f32 {
+    let Some(positions) = score.measures.get(measure).map(|m| &m.positions) else {
+        return 0.0;
+    };
+    let seconds_per_division =
+        60.0 / tempo.max(1) as f32 / score.divisions_per_quarter.max(1) as f32;
+    let seconds = positions.len().max(1) as f32 * seconds_per_division;
+    let onsets_per_second =
+        positions.iter().filter(|notes| !notes.is_empty()).count() as f32 / seconds;
+
+    let fretted = |notes: &Vec<Note>| -> Vec<u8> {
+        notes
+            .iter()
+            .filter_map(|n| n.fret)
+            .filter(|&f| f > 0)
+            .collect()
+    };
+    let widest_stretch = positions
+        .iter()
+        .filter_map(|notes| {
+            let frets = fretted(notes);
+            Some(frets.iter().max()? - frets.iter().min()?)
+        })
+        .max()
+        .unwrap_or(0) as f32;
+
+    // The hand position is the lowest fretted note, the jump into the measure counts as well
+    let previous = measure.checked_sub(1).and_then(|m| {
+        score.measures[m]
+            .positions
+            .iter()
+            .rev()
+            .find_map(|n| fretted(n).into_iter().min())
+    });
+    let hand_positions: Vec<u8> = previous
+        .into_iter()
+        .chain(
+            positions
+                .iter()
+                .filter_map(|notes| fretted(notes).into_iter().min()),
+        )
+        .collect();
+    let shifts: f32 = hand_positions
+        .windows(2)
+        .map(|pair| (pair[0].abs_diff(pair[1]).saturating_sub(2)) as f32)
+        .sum();
+
+    // Weighed like estimate_difficulty, with speed counting the most
+    let difficulty = (onsets_per_second / 3.0).min(2.0)
+        + ((widest_stretch - 3.0).max(0.0) / 2.0).min(1.0)
+        + (shifts / 8.0).min(1.0);
+    (difficulty / 4.0).clamp(0.0, 1.0)
+}
+
+fn techniques_used(score: &Score) -> Vec<String> {
+    let notes = || {
+        score
+            .measures
+            .iter()
+            .flat_map(|measure| measure.positions.iter().flatten())
+    };
+    let mut techniques = Vec::new();
+    if notes().any(|note| note.technique == Technique::HammerOn) {
+        techniques.push("hammer-on".to_string());
+    }
+    if notes().any(|note| note.technique == Technique::PullOff) {
+        techniques.push("pull-off".to_string());
+    }
+    if score
+        .measures
+        .iter()
+        .flat_map(|measure| measure.positions.iter())
+        .any(|notes| notes.len() >= 3)
+    {
+        techniques.push("chords".to_string());
+    }
+    techniques
+}
+
+/// What the library knows about an imported score, extracted once at import time.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LibraryEntry {
+    pub hash: u64,
+    pub file_name: String,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub path: Option<PathBuf>,
+    pub title: Option<String>,
+    pub composer: Option<String>,
+    pub key: Option<String>,
+    pub techniques: Vec<String>,
+    pub difficulty: u8,
+    pub tempo: usize,
+    pub measures: usize,
+    /// Labels added by the user, like "fingerstyle" or "gig set"
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // Everything above, lower case, so searching needs no parsing
+    search_text: String,
+    // The file itself, kept for this session when there is no path to read it from again
+    #[serde(skip)]
+    xml: Option<String>,
+}
+
+impl LibraryEntry {
+    pub fn new(file_name: String, xml: String, score: &Score) -> Self {
+        let key = score.key.map(|key| key.to_string());
+        let techniques = techniques_used(score);
+        let difficulty = estimate_difficulty(score);
+        let search_text = [
+            file_name.as_str(),
+            score.title.as_deref().unwrap_or_default(),
+            score.composer.as_deref().unwrap_or_default(),
+            key.as_deref().unwrap_or_default(),
+            &techniques.join(" "),
+        ]
+        .join(" ")
+        .to_lowercase();
+        Self {
+            hash: content_hash(&xml),
+            file_name,
+            #[cfg(not(target_arch = "wasm32"))]
+            path: None,
+            title: score.title.clone(),
+            composer: score.composer.clone(),
+            key,
+            techniques,
+            difficulty,
+            tempo: score.tempo,
+            measures: score.measures.len(),
+            tags: Vec::new(),
+            search_text,
+            xml: Some(xml),
+        }
+    }
+
+    /// Files on disk are read again when opened instead of being kept in memory.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_path(mut self, path: PathBuf) -> Self {
+        self.path = Some(path);
+        self.xml = None;
+        self
+    }
+
+    pub fn display_title(&self) -> &str {
+        self.title.as_deref().unwrap_or(&self.file_name)
+    }
+
+    /// Every word of the query must appear in the indexed text or the tags.
+    pub fn matches(&self, query: &str) -> bool {
+        query.to_lowercase().split_whitespace().all(|word| {
+            self.search_text.contains(word)
+                || self
+                    .tags
+                    .iter()
+                    .any(|tag| tag.to_lowercase().contains(word))
+        })
+    }
+
+    /// Replaces the tags with the comma separated ones in `text`.
+    pub fn set_tags(&mut self, text: &str) {
+        let mut tags: Vec<String> = Vec::new();
+        for tag in text.split(',').map(str::trim).filter(|tag| !tag.is_empty()) {
+            if !tags.iter().any(|existing| existing == tag) {
+                tags.push(tag.to_string());
+            }
+        }
+        self.tags = tags;
+    }
+
+    pub fn load_score(&self) -> Result<Score, String> {
+        if let Some(xml) = &self.xml {
+            return Score::parse_from_musicxml_str(xml);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(path) = &self.path {
+            return Score::parse_from_musicxml(path);
+        }
+        Err(format!("{} is no longer available", self.file_name))
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Library {
+    pub entries: Vec<LibraryEntry>,
+    /// Folder of score files browsed alongside the imported scores
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(default)]
+    pub folder: Option<PathBuf>,
+}
+
+impl Library {
+    /// Adds the entry, replacing an earlier import of the same file.
+    /// Adds the entry, replacing an earlier import of the same file but keeping its tags.
+    pub fn add(&mut self, mut entry: LibraryEntry) {
+        if let Some(existing) = self.entries.iter().position(|e| e.hash == entry.hash) {
+            entry.tags = self.entries.remove(existing).tags;
+        }
+        self.entries.push(entry);
+    }
+
+    pub fn all_tags(&self) -> BTreeSet<String> {
+        self.entries
+            .iter()
+            .flat_map(|entry| entry.tags.iter().cloned())
+            .collect()
+    }
+
+    /// Indices of the entries matching the query and difficulty limit that have all of `tags`,
+    /// sorted by title.
+    pub fn search(&self, query: &str, max_difficulty: u8, tags: &BTreeSet<String>) -> Vec<usize> {
+        let mut results: Vec<usize> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| {
+                entry.difficulty <= max_difficulty
+                    && entry.matches(query)
+                    && tags.iter().all(|tag| entry.tags.contains(tag))
+            })
+            .map(|(idx, _)| idx)
+            .collect();
+        results.sort_by_key(|&idx| self.entries[idx].display_title().to_lowercase());
+        results
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &Path) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                eprintln!("Failed to read library: {}", e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,63 @@
+// library/watch.rs
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use instant::Instant;
+
+use crate::music_representation::Score;
+
+// Often enough to feel instant after saving, rare enough to cost nothing
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Notices when a score file is saved, by polling its modification time.
+pub struct ScoreWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    last_poll: Instant,
+}
+
+impl ScoreWatcher {
+    pub fn new(path: PathBuf) -> Self {
+        let modified = modified_time(&path);
+        Self {
+            path,
+            modified,
+            last_poll: Instant::now(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The re-parsed score if the file changed since the last call. Editors often save in
+    /// several steps, so a file that doesn't parse is tried again on the next change.
+    pub fn poll(&mut self) -> Option<Result<Score, String>> {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return None;
+        }
+        self.last_poll = Instant::now();
+        let modified = modified_time(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(Score::parse_from_musicxml(&self.path))
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// The measure and division of `new` at the same place as `measure`/`division` of the score it
+/// replaces, clamped to the end of `new` when that got shorter.
+pub fn equivalent_position(new: &Score, measure: usize, division: usize) -> (usize, usize) {
+    let Some(last) = new.measures.len().checked_sub(1) else {
+        return (0, 0);
+    };
+    let measure = measure.min(last);
+    let divisions = new.measures[measure].positions.len();
+    (measure, division.min(divisions.saturating_sub(1)))
+}
@@ -1,15 +1,40 @@
-use crate::gui::gui::TabApp;
+use cdefgab::gui::gui::TabApp;
+#[cfg(not(target_arch = "wasm32"))]
+use cdefgab::{cli, gui};
 use egui::ViewportBuilder;
 
-mod audio;
-mod gui;
-mod guitar;
-mod karplus_strong;
-mod music_representation;
-mod renderer;
-
 #[cfg(not(target_arch = "wasm32"))]
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("play") {
+        if let Err(e) = cli::play::run(&args[1..]) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.first().map(String::as_str) == Some("video") {
+        if let Err(e) = cli::video::run(&args[1..]) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let [flag, path] = args.as_slice() {
+        if flag == "--dump-json" {
+            if let Err(e) = cli::dump::run(path) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+    }
+    // `cdefgab --watch file.xml` reloads the score whenever it is saved
+    let watch = match args.as_slice() {
+        [flag, path] if flag == "--watch" => Some(std::path::PathBuf::from(path)),
+        _ => None,
+    };
+
     let native_options = eframe::NativeOptions {
         viewport: ViewportBuilder {
             maximized: Some(true),
@@ -19,9 +44,15 @@ fn main() {
         ..Default::default()
     };
     let _ = eframe::run_native(
-        "Tab App",
+        gui::position_report::APP_TITLE,
         native_options,
-        Box::new(|cc| Ok(Box::new(TabApp::new(cc)))),
+        Box::new(|cc| {
+            let app = TabApp::new(cc);
+            Ok(Box::new(match watch {
+                Some(path) => app.watch(path),
+                None => app,
+            }))
+        }),
     );
 }
 
@@ -0,0 +1,57 @@
+// golden_tests.rs
+
+//! Parses every fixture in `tests/fixtures` and compares the result with its JSON snapshot in
+//! `tests/snapshots`. Run with `UPDATE_SNAPSHOTS=1` to accept the parser's current output after
+//! an intended change, then review the snapshot diff.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::music_representation::Score;
+
+fn fixtures() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let mut fixtures: Vec<PathBuf> = fs::read_dir(&dir)
+        .expect("tests/fixtures is missing")
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "xml"))
+        .collect();
+    fixtures.sort();
+    fixtures
+}
+
+fn snapshot_path(fixture: &Path) -> PathBuf {
+    let name = fixture.file_stem().unwrap().to_string_lossy();
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(format!("{}.json", name))
+}
+
+#[test]
+fn parsed_fixtures_match_snapshots() {
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+    let mut mismatches = Vec::new();
+
+    for fixture in fixtures() {
+        let score = Score::parse_from_musicxml(&fixture)
+            .unwrap_or_else(|e| panic!("{} failed to parse: {}", fixture.display(), e));
+        let json = score.to_json().unwrap() + "\n";
+        let snapshot = snapshot_path(&fixture);
+
+        if update {
+            fs::write(&snapshot, &json).unwrap();
+            continue;
+        }
+        match fs::read_to_string(&snapshot) {
+            Ok(expected) if expected == json => {}
+            Ok(_) => mismatches.push(format!("{} differs from its snapshot", fixture.display())),
+            Err(_) => mismatches.push(format!("{} has no snapshot", fixture.display())),
+        }
+    }
+
+    assert!(
+        mismatches.is_empty(),
+        "{}\nRun with UPDATE_SNAPSHOTS=1 to accept the new output",
+        mismatches.join("\n")
+    );
+}
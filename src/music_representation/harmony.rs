@@ -0,0 +1,403 @@
+// harmony.rs
+
+use core::fmt;
+
+use super::{midi_note, KeySignature, Score, PITCH_CLASS_NAMES};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChordQuality {
+    Major,
+    Minor,
+    Diminished,
+    Augmented,
+    Dominant7,
+    Major7,
+    Minor7,
+    Sus2,
+    Sus4,
+    Power,
+}
+
+impl ChordQuality {
+    // Matched against played notes, more specific chords first so that C7 wins over C
+    const DETECTABLE: [ChordQuality; 10] = [
+        ChordQuality::Dominant7,
+        ChordQuality::Major7,
+        ChordQuality::Minor7,
+        ChordQuality::Major,
+        ChordQuality::Minor,
+        ChordQuality::Diminished,
+        ChordQuality::Augmented,
+        ChordQuality::Sus4,
+        ChordQuality::Sus2,
+        ChordQuality::Power,
+    ];
+
+    pub fn intervals(&self) -> &'static [u8] {
+        match self {
+            ChordQuality::Major => &[0, 4, 7],
+            ChordQuality::Minor => &[0, 3, 7],
+            ChordQuality::Diminished => &[0, 3, 6],
+            ChordQuality::Augmented => &[0, 4, 8],
+            ChordQuality::Dominant7 => &[0, 4, 7, 10],
+            ChordQuality::Major7 => &[0, 4, 7, 11],
+            ChordQuality::Minor7 => &[0, 3, 7, 10],
+            ChordQuality::Sus2 => &[0, 2, 7],
+            ChordQuality::Sus4 => &[0, 5, 7],
+            ChordQuality::Power => &[0, 7],
+        }
+    }
+
+    fn suffix(&self) -> &'static str {
+        match self {
+            ChordQuality::Major => "",
+            ChordQuality::Minor => "m",
+            ChordQuality::Diminished => "dim",
+            ChordQuality::Augmented => "aug",
+            ChordQuality::Dominant7 => "7",
+            ChordQuality::Major7 => "maj7",
+            ChordQuality::Minor7 => "m7",
+            ChordQuality::Sus2 => "sus2",
+            ChordQuality::Sus4 => "sus4",
+            ChordQuality::Power => "5",
+        }
+    }
+
+    fn is_minor(&self) -> bool {
+        matches!(
+            self,
+            ChordQuality::Minor | ChordQuality::Minor7 | ChordQuality::Diminished
+        )
+    }
+}
+
+/// What a note is to the chord it is played over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChordFunction {
+    Root,
+    Third,
+    Fifth,
+    Tension,
+}
+
+impl ChordFunction {
+    pub const ALL: [ChordFunction; 4] = [
+        ChordFunction::Root,
+        ChordFunction::Third,
+        ChordFunction::Fifth,
+        ChordFunction::Tension,
+    ];
+}
+
+impl fmt::Display for ChordFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChordFunction::Root => write!(f, "Root"),
+            ChordFunction::Third => write!(f, "3rd"),
+            ChordFunction::Fifth => write!(f, "5th"),
+            ChordFunction::Tension => write!(f, "Tension"),
+        }
+    }
+}
+
+/// A chord by root pitch class (C is 0) and quality.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Chord {
+    pub root: u8,
+    pub quality: ChordQuality,
+}
+
+fn pitch_class_of(step: char) -> Option<u8> {
+    match step.to_ascii_uppercase() {
+        'C' => Some(0),
+        'D' => Some(2),
+        'E' => Some(4),
+        'F' => Some(5),
+        'G' => Some(7),
+        'A' => Some(9),
+        'B' => Some(11),
+        _ => None,
+    }
+}
+
+impl Chord {
+    /// Reads chord symbols like "Am7", "F#", "Bb/D" or "Gsus4". Extensions beyond sevenths
+    /// are ignored, and slash bass notes too.
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        let symbol = symbol.split('/').next()?.trim();
+        let mut chars = symbol.chars();
+        let mut root = pitch_class_of(chars.next()?)?;
+        let mut rest = chars.as_str();
+        if let Some(stripped) = rest.strip_prefix('#').or(rest.strip_prefix('♯')) {
+            root = (root + 1) % 12;
+            rest = stripped;
+        } else if let Some(stripped) = rest.strip_prefix('b').or(rest.strip_prefix('♭')) {
+            root = (root + 11) % 12;
+            rest = stripped;
+        }
+        let quality = match rest {
+            r if r.starts_with("maj7") || r.starts_with("M7") || r.starts_with("Δ") => {
+                ChordQuality::Major7
+            }
+            r if r.starts_with("m7") || r.starts_with("min7") || r.starts_with("-7") => {
+                ChordQuality::Minor7
+            }
+            r if r.starts_with("dim") || r.starts_with('°') => ChordQuality::Diminished,
+            r if r.starts_with("aug") || r.starts_with('+') => ChordQuality::Augmented,
+            r if r.starts_with("sus2") => ChordQuality::Sus2,
+            r if r.starts_with("sus") => ChordQuality::Sus4,
+            r if r.starts_with('m') || r.starts_with('-') => ChordQuality::Minor,
+            r if r.starts_with('5') => ChordQuality::Power,
+            r if r.starts_with('7') || r.starts_with('9') || r.starts_with("13") => {
+                ChordQuality::Dominant7
+            }
+            _ => ChordQuality::Major,
+        };
+        Some(Self { root, quality })
+    }
+
+    /// Best matching chord for notes weighted by how long they sound, indexed by pitch class.
+    /// `bass` is preferred as the root when several chords fit equally well.
+    pub fn detect(weights: &[f32; 12], bass: Option<u8>) -> Option<Self> {
+        let total: f32 = weights.iter().sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut best: Option<(f32, Chord)> = None;
+        for root in 0..12u8 {
+            for quality in ChordQuality::DETECTABLE {
+                let intervals = quality.intervals();
+                let inside: f32 = intervals
+                    .iter()
+                    .map(|i| weights[((root + i) % 12) as usize])
+                    .sum();
+                // Every chord tone has to be there, and most of what sounds must be chord tones
+                let complete = intervals
+                    .iter()
+                    .all(|i| weights[((root + i) % 12) as usize] > 0.0);
+                if !complete || inside / total < 0.75 {
+                    continue;
+                }
+                let mut score = inside / total + intervals.len() as f32 * 0.01;
+                if bass == Some(root) {
+                    score += 0.05;
+                }
+                if best.is_none_or(|(best_score, _)| score > best_score) {
+                    best = Some((score, Chord { root, quality }));
+                }
+            }
+        }
+        best.map(|(_, chord)| chord)
+    }
+
+    /// The function of `pitch_class` in the chord. The suspended note of a sus chord stands in
+    /// for the third, and sevenths count as tensions along with everything outside the triad.
+    pub fn function_of(&self, pitch_class: u8) -> ChordFunction {
+        let interval = (pitch_class + 12 - self.root % 12) % 12;
+        let intervals = self.quality.intervals();
+        match intervals.iter().position(|&i| i == interval) {
+            Some(0) => ChordFunction::Root,
+            Some(_) if matches!(interval, 6..=8) => ChordFunction::Fifth,
+            Some(1) => ChordFunction::Third,
+            _ => ChordFunction::Tension,
+        }
+    }
+
+    /// The chord's function in `key`, like "V7", "ii" or "bVII".
+    pub fn roman_numeral(&self, key: KeySignature) -> String {
+        const NUMERALS: [&str; 7] = ["I", "II", "III", "IV", "V", "VI", "VII"];
+        // Semitones of the scale degrees, counted from the major or minor tonic
+        const MAJOR_DEGREES: [u8; 7] = [0, 2, 4, 5, 7, 9, 11];
+        const MINOR_DEGREES: [u8; 7] = [0, 2, 3, 5, 7, 8, 10];
+        let (tonic, minor) = key_tonic(key);
+        let degrees = if minor { MINOR_DEGREES } else { MAJOR_DEGREES };
+        let interval = (self.root + 12 - tonic) % 12;
+
+        let (degree, accidental) = match degrees.iter().position(|&d| d == interval) {
+            Some(degree) => (degree, ""),
+            // Chromatic chords are named as lowered degrees, like bVII or bIII
+            None => match degrees.iter().position(|&d| d == (interval + 1) % 12) {
+                Some(degree) => (degree, "b"),
+                None => (
+                    degrees.iter().position(|&d| d + 1 == interval).unwrap_or(0),
+                    "#",
+                ),
+            },
+        };
+        let numeral = if self.quality.is_minor() {
+            NUMERALS[degree].to_lowercase()
+        } else {
+            NUMERALS[degree].to_string()
+        };
+        let suffix = match self.quality {
+            ChordQuality::Diminished => "°",
+            ChordQuality::Augmented => "+",
+            ChordQuality::Dominant7 | ChordQuality::Minor7 => "7",
+            ChordQuality::Major7 => "maj7",
+            ChordQuality::Sus2 => "sus2",
+            ChordQuality::Sus4 => "sus4",
+            ChordQuality::Power => "5",
+            ChordQuality::Major | ChordQuality::Minor => "",
+        };
+        format!("{}{}{}", accidental, numeral, suffix)
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            PITCH_CLASS_NAMES[self.root as usize % 12],
+            self.quality.suffix()
+        )
+    }
+}
+
+/// Pitch class of the key's tonic and whether it is minor.
+pub fn key_tonic(key: KeySignature) -> (u8, bool) {
+    // Each fifth moves the major tonic seven semitones
+    let major_tonic = (key.fifths as i32 * 7).rem_euclid(12) as u8;
+    if key.minor {
+        ((major_tonic + 9) % 12, true)
+    } else {
+        (major_tonic, false)
+    }
+}
+
+/// Estimates the key from how long each pitch class sounds, using the Krumhansl-Kessler
+/// key profiles. Used when the score doesn't say.
+pub fn detect_key(weights: &[f32; 12]) -> Option<KeySignature> {
+    const MAJOR_PROFILE: [f32; 12] = [
+        6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+    ];
+    const MINOR_PROFILE: [f32; 12] = [
+        6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+    ];
+    if weights.iter().all(|&w| w <= 0.0) {
+        return None;
+    }
+    let correlation = |profile: &[f32; 12], tonic: usize| {
+        let mean_w = weights.iter().sum::<f32>() / 12.0;
+        let mean_p = profile.iter().sum::<f32>() / 12.0;
+        let (mut num, mut dw, mut dp) = (0.0, 0.0, 0.0);
+        for pc in 0..12 {
+            let w = weights[(pc + tonic) % 12] - mean_w;
+            let p = profile[pc] - mean_p;
+            num += w * p;
+            dw += w * w;
+            dp += p * p;
+        }
+        num / (dw * dp).sqrt().max(f32::EPSILON)
+    };
+    let mut best = (f32::MIN, 0, false);
+    for tonic in 0..12 {
+        for (profile, minor) in [(&MAJOR_PROFILE, false), (&MINOR_PROFILE, true)] {
+            let r = correlation(profile, tonic);
+            if r > best.0 {
+                best = (r, tonic, minor);
+            }
+        }
+    }
+    let (_, tonic, minor) = best;
+    // Key signatures are written for the relative major
+    let major_tonic = if minor { (tonic + 3) % 12 } else { tonic };
+    // Seven fifths per semitone modulo 12, keeping signatures between six flats and six sharps
+    let mut fifths = (major_tonic as i32 * 7).rem_euclid(12) as i8;
+    if fifths > 6 {
+        fifths -= 12;
+    }
+    Some(KeySignature { fifths, minor })
+}
+
+/// A chord of the progression and where it starts.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChordSpan {
+    pub measure: usize,
+    pub division: usize,
+    /// Divisions until the next chord or the end of the score
+    pub length: usize,
+    pub chord: Chord,
+}
+
+/// The chord progression, from the score's chord symbols where it has them and otherwise
+/// detected from the notes of each measure. Repeated chords are merged.
+pub fn chord_progression(score: &Score, capo_fret: u8) -> Vec<ChordSpan> {
+    let mut spans: Vec<ChordSpan> = Vec::new();
+    let mut position = 0;
+    for (measure_idx, measure) in score.measures.iter().enumerate() {
+        let mut changes: Vec<(usize, Chord)> = measure
+            .chord_symbols
+            .iter()
+            .filter_map(|(division, symbol)| Some((*division, Chord::from_symbol(symbol)?)))
+            .collect();
+        if changes.is_empty() {
+            let (weights, bass) =
+                pitch_class_weights(measure.positions.iter().flatten(), capo_fret);
+            changes.extend(Chord::detect(&weights, bass).map(|chord| (0, chord)));
+        }
+        for (division, chord) in changes {
+            if spans.last().is_some_and(|last| last.chord == chord) {
+                continue;
+            }
+            if let Some(last) = spans.last_mut() {
+                last.length = position + division - start_of(score, last);
+            }
+            spans.push(ChordSpan {
+                measure: measure_idx,
+                division,
+                length: 0,
+                chord,
+            });
+        }
+        position += measure.positions.len();
+    }
+    if let Some(last) = spans.last_mut() {
+        last.length = position - start_of(score, last);
+    }
+    spans
+}
+
+/// The chord sounding at `division` of `measure`.
+pub fn chord_at(spans: &[ChordSpan], measure: usize, division: usize) -> Option<Chord> {
+    spans
+        .iter()
+        .take_while(|span| (span.measure, span.division) <= (measure, division))
+        .last()
+        .map(|span| span.chord)
+}
+
+fn start_of(score: &Score, span: &ChordSpan) -> usize {
+    score.measures[..span.measure]
+        .iter()
+        .map(|measure| measure.positions.len())
+        .sum::<usize>()
+        + span.division
+}
+
+/// How long each pitch class sounds in divisions, and the lowest note.
+pub fn pitch_class_weights<'a>(
+    notes: impl Iterator<Item = &'a super::Note>,
+    capo_fret: u8,
+) -> ([f32; 12], Option<u8>) {
+    let mut weights = [0.0; 12];
+    let mut lowest: Option<u8> = None;
+    for note in notes {
+        if let Some(midi) = midi_note(note, capo_fret) {
+            weights[(midi % 12) as usize] += note.duration.max(1) as f32;
+            lowest = Some(lowest.map_or(midi, |low| low.min(midi)));
+        }
+    }
+    (weights, lowest.map(|midi| midi % 12))
+}
+
+/// The score's key, or the most likely key from its notes if it has no key signature.
+pub fn score_key(score: &Score, capo_fret: u8) -> Option<KeySignature> {
+    score.key.or_else(|| {
+        let notes = score
+            .measures
+            .iter()
+            .flat_map(|measure| measure.positions.iter().flatten());
+        detect_key(&pitch_class_weights(notes, capo_fret).0)
+    })
+}
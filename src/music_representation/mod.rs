@@ -1,7 +1,15 @@
 // music_representation/mod.rs
 
+#[cfg(test)]
+mod golden_tests;
+mod harmony;
 mod musical_structures;
 mod musicxml_parser;
+mod scales;
+mod timeline;
 mod utils;
 
+pub use harmony::*;
 pub use musical_structures::*;
+pub use scales::*;
+pub use timeline::*;
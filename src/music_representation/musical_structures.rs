@@ -1,30 +1,131 @@
 // musical_structures.rs
 
 use core::fmt;
+use std::collections::BTreeSet;
+use std::ops::RangeInclusive;
 
-#[derive(Default, Debug, Clone)]
+use serde::Serialize;
+
+use super::PITCH_CLASS_NAMES;
+
+#[derive(Default, Debug, Clone, Serialize)]
 pub struct Score {
+    pub title: Option<String>,
+    pub composer: Option<String>,
+    pub key: Option<KeySignature>,
     pub measures: Vec<Measure>,
+    pub sections: Vec<Section>,
     pub time_signature: TimeSignature,
     pub tempo: usize,
     pub divisions_per_quarter: u8,
     pub divisions_per_measure: u8,
+    /// Fret the score asks for a capo at, when it says
+    pub capo: Option<u8>,
+    /// MIDI note numbers of the open strings the score is written for, high E first, when it
+    /// says
+    pub tuning: Option<[u8; 6]>,
 }
 
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize)]
 pub enum Technique {
     None,
     HammerOn,
     PullOff,
 }
 
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+/// Marks on a note that change how it is played rather than which note it is.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum Articulation {
+    Staccato,
+    Accent,
+    StrongAccent,
+    Tenuto,
+    Harmonic,
+}
+
+/// Whether a note is tied over to the next note on its string, and whether it continues a
+/// note tied over to it. A note can be both in the middle of a chain of ties.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, Serialize)]
+pub struct Tie {
+    pub start: bool,
+    pub stop: bool,
+}
+
+/// A note as notated, with how it sounds worked out separately: a tied note is struck once and
+/// rings on through the notes it is tied to, a staccato note is cut short.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize)]
 pub struct Note {
     pub string: Option<u8>, // The guitar string number (e.g., 1 to 6)
     pub fret: Option<u8>,   // The fret number for the note on the guitar
-    pub duration: u32,      // Duration in divisions
+    /// Division within its measure the note starts at
+    pub onset: usize,
+    pub duration: u32, // Notated duration in divisions
+    /// Divisions the note rings for once struck, 0 for a note that continues a tie
+    pub sounding_duration: u32,
+    pub tie: Tie,
+    pub articulations: BTreeSet<Articulation>,
     pub pitch: Option<Pitch>,
     pub technique: Technique,
+    pub finger: Option<u8>, // Fretting finger from the score, 0 for the thumb
+}
+
+impl Note {
+    /// A plain note on `string` at `fret`, sounding for as long as it is notated.
+    pub fn new(string: u8, fret: u8, onset: usize, duration: u32) -> Self {
+        Self {
+            string: Some(string),
+            fret: Some(fret),
+            onset,
+            duration,
+            sounding_duration: duration,
+            tie: Tie::default(),
+            articulations: BTreeSet::new(),
+            pitch: None,
+            technique: Technique::None,
+            finger: None,
+        }
+    }
+
+    /// Whether the note is struck, rather than ringing on from the note tied to it.
+    pub fn is_struck(&self) -> bool {
+        self.sounding_duration > 0
+    }
+}
+
+// MIDI note numbers of the open strings in standard tuning, high E first
+pub const OPEN_STRING_MIDI: [u8; 6] = [64, 59, 55, 50, 45, 40];
+
+/// Name of a tuning of the open strings, high E first: standard or drop tuned when it is, and
+/// otherwise its notes from the lowest string up.
+pub fn tuning_name(tuning: &[u8; 6]) -> String {
+    let offsets: Vec<i16> = tuning
+        .iter()
+        .zip(OPEN_STRING_MIDI)
+        .map(|(&open, standard)| open as i16 - standard as i16)
+        .collect();
+    let lowest = PITCH_CLASS_NAMES[tuning[5] as usize % 12];
+    if offsets.iter().all(|&offset| offset == 0) {
+        return "Standard tuning".to_string();
+    }
+    if offsets.iter().all(|&offset| offset == offsets[0]) {
+        return format!("{} standard", lowest);
+    }
+    // The low string a whole tone below the others
+    if offsets[..5].iter().all(|&offset| offset == offsets[0]) && offsets[5] == offsets[0] - 2 {
+        return format!("Drop {}", lowest);
+    }
+    tuning
+        .iter()
+        .rev()
+        .map(|&open| PITCH_CLASS_NAMES[open as usize % 12])
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// MIDI note number of a tab note, counting the capo.
+pub fn midi_note(note: &Note, capo_fret: u8) -> Option<u8> {
+    let open = OPEN_STRING_MIDI.get(note.string?.checked_sub(1)? as usize)?;
+    Some(open + note.fret? + capo_fret)
 }
 
 impl fmt::Display for Note {
@@ -38,28 +139,233 @@ impl fmt::Display for Note {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// Key signature as the number of sharps (positive) or flats (negative).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct KeySignature {
+    pub fifths: i8,
+    pub minor: bool,
+}
+
+impl fmt::Display for KeySignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const MAJOR: [&str; 15] = [
+            "Cb", "Gb", "Db", "Ab", "Eb", "Bb", "F", "C", "G", "D", "A", "E", "B", "F#", "C#",
+        ];
+        const MINOR: [&str; 15] = [
+            "Ab", "Eb", "Bb", "F", "C", "G", "D", "A", "E", "B", "F#", "C#", "G#", "D#", "A#",
+        ];
+        let idx = (self.fifths.clamp(-7, 7) + 7) as usize;
+        if self.minor {
+            write!(f, "{} minor", MINOR[idx])
+        } else {
+            write!(f, "{} major", MAJOR[idx])
+        }
+    }
+}
+
+/// A named marker, like a rehearsal mark, at the start of a measure.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Section {
+    pub measure: usize,
+    pub name: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
 pub struct Pitch {
     pub step: char,        // Note step (A, B, C, D, E, F, G)
     pub alter: Option<i8>, // Sharps or flats (-1 for flat, +1 for sharp)
     pub octave: u8,        // Octave number
 }
 
-#[derive(Clone, Copy, Default, Debug)]
+#[derive(Clone, Copy, Default, Debug, Serialize)]
 pub struct TimeSignature {
     pub beats_per_measure: u8,
     pub beat_value: u8,
 }
 
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Default, Debug, Serialize)]
 pub struct Measure {
-    pub positions: Vec<Vec<Note>>, // Notes at each division
+    pub positions: Vec<Vec<Note>>,           // Notes at each division
+    pub chord_symbols: Vec<(usize, String)>, // Harmony symbols and the division they start at
+}
+
+impl Score {
+    /// Sets the onset of every note to the division it is at, and works out how long each note
+    /// sounds from its ties and articulations. Run after notes were added or moved.
+    pub fn resolve_timing(&mut self) {
+        for measure in &mut self.measures {
+            for (division, notes) in measure.positions.iter_mut().enumerate() {
+                for note in notes {
+                    note.onset = division;
+                    note.sounding_duration = if note.articulations.contains(&Articulation::Staccato)
+                    {
+                        (note.duration / 2).max(1)
+                    } else {
+                        note.duration
+                    };
+                }
+            }
+        }
+
+        // The note each string's chain of ties started with, as measure, division and index
+        let mut tied_from: [Option<(usize, usize, usize)>; 6] = [None; 6];
+        for measure_idx in 0..self.measures.len() {
+            for division in 0..self.measures[measure_idx].positions.len() {
+                for note_idx in 0..self.measures[measure_idx].positions[division].len() {
+                    let note = &self.measures[measure_idx].positions[division][note_idx];
+                    let Some(string) = note.string.and_then(|s| s.checked_sub(1)) else {
+                        continue;
+                    };
+                    let Some(slot) = tied_from.get_mut(string as usize) else {
+                        continue;
+                    };
+                    let (fret, duration, tie) = (note.fret, note.duration, note.tie);
+                    let continues = slot.filter(|&(m, d, n)| {
+                        tie.stop && self.measures[m].positions[d][n].fret == fret
+                    });
+                    match continues {
+                        Some((m, d, n)) => {
+                            self.measures[m].positions[d][n].sounding_duration += duration;
+                            self.measures[measure_idx].positions[division][note_idx]
+                                .sounding_duration = 0;
+                            if !tie.start {
+                                *slot = None;
+                            }
+                        }
+                        None => {
+                            *slot = tie.start.then_some((measure_idx, division, note_idx));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// The measures in `measures`, by index, as a score of their own with the tempo, time and key
+    /// signature of this one. Notes tied over from before the excerpt are struck instead, and the
+    /// section the excerpt starts in is marked at its first measure.
+    pub fn excerpt(&self, measures: RangeInclusive<usize>) -> Score {
+        let last = (*measures.end()).min(self.measures.len().saturating_sub(1));
+        let first = (*measures.start()).min(last);
+        let mut excerpt = Score {
+            measures: self.measures.get(first..=last).unwrap_or_default().to_vec(),
+            sections: Vec::new(),
+            ..self.clone()
+        };
+        if let Some(measure) = excerpt.measures.first_mut() {
+            for note in measure.positions.iter_mut().flatten() {
+                note.tie.stop = false;
+            }
+        }
+        excerpt.resolve_timing();
+
+        let current = self.sections.iter().rev().find(|s| s.measure <= first);
+        if let Some(section) = current.filter(|section| section.measure < first) {
+            excerpt.sections.push(Section {
+                measure: 0,
+                name: section.name.clone(),
+            });
+        }
+        excerpt.sections.extend(
+            self.sections
+                .iter()
+                .filter(|section| (first..=last).contains(&section.measure))
+                .map(|section| Section {
+                    measure: section.measure - first,
+                    name: section.name.clone(),
+                }),
+        );
+        excerpt
+    }
+
+    /// Adds the measures of `other` after the last measure of this score, like etudes stitched
+    /// into one practice file. Both are put on the finest division grid they share, and the
+    /// measures of `other` are marked as a section named after it. A score has one tempo, so
+    /// `other` plays at this one's, with its own tempo noted in the section name when different.
+    pub fn append(&mut self, other: &Score) -> Result<(), String> {
+        let ours = self.divisions_per_quarter.max(1) as usize;
+        let theirs = other.divisions_per_quarter.max(1) as usize;
+        let common = lcm(ours, theirs);
+        let divisions_per_quarter = u8::try_from(common).map_err(|_| {
+            format!(
+                "No common grid for {} and {} divisions per quarter",
+                ours, theirs
+            )
+        })?;
+        let mut other = other.clone();
+        self.rescale_divisions(common / ours);
+        other.rescale_divisions(common / theirs);
+        self.divisions_per_quarter = divisions_per_quarter;
+        self.divisions_per_measure =
+            (self.divisions_per_measure as usize * common / ours).min(u8::MAX as usize) as u8;
+
+        // Notes ringing at the end of this score aren't tied into the next one
+        if let Some(measure) = other.measures.first_mut() {
+            for note in measure.positions.iter_mut().flatten() {
+                note.tie.stop = false;
+            }
+        }
+        let offset = self.measures.len();
+        if !other.sections.iter().any(|section| section.measure == 0) {
+            let mut name = other
+                .title
+                .clone()
+                .unwrap_or_else(|| "Appended".to_string());
+            if other.tempo != self.tempo {
+                name = format!("{} (♩ = {})", name, other.tempo);
+            }
+            self.sections.push(Section {
+                measure: offset,
+                name,
+            });
+        }
+        self.sections
+            .extend(other.sections.into_iter().map(|section| Section {
+                measure: section.measure + offset,
+                ..section
+            }));
+        self.measures.extend(other.measures);
+        self.resolve_timing();
+        Ok(())
+    }
+
+    // Spreads every measure over `factor` times as many divisions
+    fn rescale_divisions(&mut self, factor: usize) {
+        if factor == 1 {
+            return;
+        }
+        for measure in &mut self.measures {
+            let mut positions = vec![Vec::new(); measure.positions.len() * factor];
+            for (division, notes) in measure.positions.drain(..).enumerate() {
+                positions[division * factor] = notes
+                    .into_iter()
+                    .map(|note| Note {
+                        duration: note.duration * factor as u32,
+                        ..note
+                    })
+                    .collect();
+            }
+            measure.positions = positions;
+            for (division, _) in &mut measure.chord_symbols {
+                *division *= factor;
+            }
+        }
+        self.resolve_timing();
+    }
+
+    /// The whole parsed structure, for other tools and for checking what the parser made of a
+    /// file.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| e.to_string())
+    }
 }
 
 impl Measure {
     pub fn new(total_divisions: usize) -> Self {
         Measure {
             positions: vec![Vec::new(); total_divisions],
+            chord_symbols: Vec::new(),
         }
     }
 }
@@ -71,6 +377,14 @@ pub struct VoiceState {
     pub first_note: bool,
 }
 
+fn lcm(a: usize, b: usize) -> usize {
+    let (mut x, mut y) = (a, b);
+    while y != 0 {
+        (x, y) = (y, x % y);
+    }
+    a / x * b
+}
+
 pub fn calculate_frequency(note: &Note, scale_length: f32, capo_fret: u8) -> f32 {
     // Define the standard scale length (e.g., 25.5 inches for many guitars)
     const STANDARD_SCALE_LENGTH: f32 = 25.5;
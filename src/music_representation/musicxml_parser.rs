@@ -3,13 +3,18 @@
 use regex::Regex;
 use roxmltree::{Document, Node};
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
 
-use crate::music_representation::utils::{calculate_divisions_per_measure, extract_score_metadata};
-use crate::music_representation::{Measure, Note, Pitch, Score, Technique, VoiceState};
+use crate::music_representation::utils::{
+    calculate_divisions_per_measure, extract_key_signature, extract_score_credits,
+    extract_score_metadata,
+};
+use crate::music_representation::{
+    Articulation, Measure, Note, Pitch, Score, Section, Technique, Tie, VoiceState,
+};
 
 impl Score {
     pub fn parse_from_musicxml_str(xml_content: &str) -> Result<Score, String> {
@@ -23,6 +28,9 @@ impl Score {
 
         // Extract score metadata
         let (divisions_per_quarter, time_signature, tempo) = extract_score_metadata(&root);
+        let (title, composer) = extract_score_credits(&root);
+        let key = extract_key_signature(&root);
+        let (capo, tuning) = parse_staff_details(&root);
 
         // Calculate divisions per measure
         let divisions_per_measure = calculate_divisions_per_measure(
@@ -33,14 +41,23 @@ impl Score {
 
         // Parse measures
         let measures = parse_measures(&root, divisions_per_measure)?;
+        let sections = parse_rehearsal_marks(&root);
 
-        Ok(Score {
+        let mut score = Score {
+            title,
+            composer,
+            key,
             measures,
+            sections,
             time_signature,
             tempo,
             divisions_per_quarter,
             divisions_per_measure: divisions_per_measure as u8,
-        })
+            capo,
+            tuning,
+        };
+        score.resolve_timing();
+        Ok(score)
     }
     pub fn parse_from_musicxml<P: AsRef<Path>>(file_path: P) -> Result<Score, String> {
         let mut file = File::open(&file_path).map_err(|e| e.to_string())?;
@@ -48,34 +65,7 @@ impl Score {
         file.read_to_string(&mut xml_content)
             .map_err(|e| e.to_string())?;
 
-        // Remove the DTD declaration from the XML content
-        let dtd_regex = Regex::new(r"(?s)<!DOCTYPE.*?>").unwrap();
-        let xml_content = dtd_regex.replace(&xml_content, "").to_string();
-
-        // Parse the XML content
-        let doc = Document::parse(&xml_content).map_err(|e| e.to_string())?;
-        let root = doc.root_element();
-
-        // Extract score metadata
-        let (divisions_per_quarter, time_signature, tempo) = extract_score_metadata(&root);
-
-        // Calculate divisions per measure
-        let divisions_per_measure = calculate_divisions_per_measure(
-            time_signature.beats_per_measure,
-            divisions_per_quarter,
-            time_signature.beat_value,
-        );
-
-        // Parse measures
-        let measures = parse_measures(&root, divisions_per_measure)?;
-
-        Ok(Score {
-            measures,
-            time_signature,
-            tempo,
-            divisions_per_quarter,
-            divisions_per_measure: divisions_per_measure as u8,
-        })
+        Self::parse_from_musicxml_str(&xml_content)
     }
 }
 
@@ -92,12 +82,100 @@ fn parse_measures(root: &Node, divisions_per_measure: usize) -> Result<Vec<Measu
     Ok(measures)
 }
 
+fn child_text<'a>(node: Node<'a, '_>, tag: &str) -> Option<&'a str> {
+    node.children()
+        .find(|n| n.has_tag_name(tag))
+        .and_then(|n| n.text())
+        .map(str::trim)
+}
+
+/// The capo and the open strings' MIDI note numbers, high E first, from the details of the tab
+/// staff. The tuning is only taken from a six string staff with every line tuned.
+fn parse_staff_details(root: &Node) -> (Option<u8>, Option<[u8; 6]>) {
+    let Some(details) = root.descendants().find(|n| n.has_tag_name("staff-details")) else {
+        return (None, None);
+    };
+    let capo = child_text(details, "capo").and_then(|capo| capo.parse::<u8>().ok());
+
+    let mut tuning = [None; 6];
+    for staff_tuning in details
+        .children()
+        .filter(|n| n.has_tag_name("staff-tuning"))
+    {
+        // Lines are numbered from the bottom, where the low E is
+        let Some(idx) = staff_tuning
+            .attribute("line")
+            .and_then(|line| line.parse::<usize>().ok())
+            .and_then(|line| tuning.len().checked_sub(line))
+        else {
+            continue;
+        };
+        let step = child_text(staff_tuning, "tuning-step").and_then(|step| step.chars().next());
+        let octave = child_text(staff_tuning, "tuning-octave").and_then(|o| o.parse::<u8>().ok());
+        let (Some(step), Some(octave)) = (step, octave) else {
+            continue;
+        };
+        let pitch = Pitch {
+            step,
+            alter: child_text(staff_tuning, "tuning-alter").and_then(|a| a.parse::<i8>().ok()),
+            octave,
+        };
+        // MIDI counts its octaves from one below the pitch's
+        tuning[idx] = u8::try_from(pitch_to_midi(&pitch) + 12).ok();
+    }
+    let lines =
+        child_text(details, "staff-lines").map_or(Some(6), |lines| lines.parse::<usize>().ok());
+    let tuning = match tuning {
+        [Some(a), Some(b), Some(c), Some(d), Some(e), Some(f)] if lines == Some(6) => {
+            Some([a, b, c, d, e, f])
+        }
+        _ => None,
+    };
+    (capo, tuning)
+}
+
+fn parse_rehearsal_marks(root: &Node) -> Vec<Section> {
+    // Only the first part, the marks are usually repeated in every part
+    let Some(part) = root.children().find(|n| n.has_tag_name("part")) else {
+        return Vec::new();
+    };
+
+    let mut sections = Vec::new();
+    for (measure_idx, measure_node) in part
+        .children()
+        .filter(|n| n.has_tag_name("measure"))
+        .enumerate()
+    {
+        for rehearsal in measure_node
+            .descendants()
+            .filter(|n| n.has_tag_name("rehearsal"))
+        {
+            if let Some(text) = rehearsal.text().map(str::trim).filter(|t| !t.is_empty()) {
+                sections.push(Section {
+                    measure: measure_idx,
+                    name: text.to_string(),
+                });
+            }
+        }
+    }
+    sections
+}
+
 fn parse_measure(measure_node: Node, divisions_per_measure: usize) -> Result<Measure, String> {
     let mut measure = Measure::new(divisions_per_measure);
     let mut voice_states: HashMap<u8, VoiceState> = HashMap::new();
 
-    for note_node in measure_node.children().filter(|n| n.has_tag_name("note")) {
-        parse_note(note_node, &mut voice_states, &mut measure)?;
+    // A harmony symbol applies to the note that follows it
+    let mut pending_harmony = None;
+    for child in measure_node.children() {
+        if child.has_tag_name("harmony") {
+            pending_harmony = extract_harmony(&child);
+        } else if child.has_tag_name("note") {
+            let position = parse_note(child, &mut voice_states, &mut measure)?;
+            if let Some(name) = pending_harmony.take() {
+                measure.chord_symbols.push((position, name));
+            }
+        }
     }
 
     Ok(measure)
@@ -107,7 +185,7 @@ fn parse_note(
     note_node: Node,
     voice_states: &mut HashMap<u8, VoiceState>,
     measure: &mut Measure,
-) -> Result<(), String> {
+) -> Result<usize, String> {
     let voice = note_node
         .children()
         .find(|n| n.has_tag_name("voice"))
@@ -133,13 +211,22 @@ fn parse_note(
     let is_chord = note_node.children().any(|n| n.has_tag_name("chord"));
 
     let technique = extract_technique(&note_node);
+    let finger = extract_fingering(&note_node);
+    let tie = extract_tie(&note_node);
+    let articulations = extract_articulations(&note_node);
 
+    // The onset and how long the note sounds are filled in once the whole score is parsed
     let note = Note {
         string,
         fret,
+        onset: 0,
         duration,
+        sounding_duration: duration,
+        tie,
+        articulations,
         pitch,
         technique,
+        finger,
     };
 
     if !voice_state.first_note {
@@ -162,7 +249,56 @@ fn parse_note(
     voice_state.prev_duration = duration;
     voice_state.prev_is_chord = is_chord;
 
-    Ok(())
+    Ok(voice_state.current_position)
+}
+
+/// Chord name like "F#m7" from a harmony element.
+fn extract_harmony(harmony_node: &Node) -> Option<String> {
+    let root = harmony_node.children().find(|n| n.has_tag_name("root"))?;
+    let step = root
+        .children()
+        .find(|n| n.has_tag_name("root-step"))
+        .and_then(|n| n.text())?
+        .trim();
+    let alter = root
+        .children()
+        .find(|n| n.has_tag_name("root-alter"))
+        .and_then(|n| n.text())
+        .and_then(|t| t.trim().parse::<f32>().ok())
+        .unwrap_or(0.0);
+    let accidental = if alter > 0.0 {
+        "#"
+    } else if alter < 0.0 {
+        "b"
+    } else {
+        ""
+    };
+
+    let kind_node = harmony_node.children().find(|n| n.has_tag_name("kind"));
+    // The text attribute is how the chord is meant to be printed
+    let kind = match kind_node.and_then(|n| n.attribute("text")) {
+        Some(text) => text.to_string(),
+        None => match kind_node.and_then(|n| n.text()).map(str::trim) {
+            Some("major") | None => String::new(),
+            Some("minor") => "m".to_string(),
+            Some("dominant") => "7".to_string(),
+            Some("major-seventh") => "maj7".to_string(),
+            Some("minor-seventh") => "m7".to_string(),
+            Some("diminished") => "dim".to_string(),
+            Some("diminished-seventh") => "dim7".to_string(),
+            Some("augmented") => "aug".to_string(),
+            Some("half-diminished") => "m7b5".to_string(),
+            Some("suspended-second") => "sus2".to_string(),
+            Some("suspended-fourth") => "sus4".to_string(),
+            Some("major-sixth") => "6".to_string(),
+            Some("minor-sixth") => "m6".to_string(),
+            Some("dominant-ninth") => "9".to_string(),
+            Some("power") => "5".to_string(),
+            Some(other) => other.to_string(),
+        },
+    };
+
+    Some(format!("{}{}{}", step, accidental, kind))
 }
 
 fn extract_pitch(note_node: &Node) -> Option<Pitch> {
@@ -241,6 +377,64 @@ fn extract_technique(note_node: &Node) -> Technique {
     Technique::None
 }
 
+/// Tie from `<tie>`, or from `<tied>` in the notations for files that only write that one.
+fn extract_tie(note_node: &Node) -> Tie {
+    let tied = note_node
+        .children()
+        .filter(|n| n.has_tag_name("notations"))
+        .flat_map(|n| n.children().filter(|n| n.has_tag_name("tied")));
+    let mut tie = Tie::default();
+    for node in note_node
+        .children()
+        .filter(|n| n.has_tag_name("tie"))
+        .chain(tied)
+    {
+        match node.attribute("type") {
+            Some("start") => tie.start = true,
+            Some("stop") => tie.stop = true,
+            _ => {}
+        }
+    }
+    tie
+}
+
+fn extract_articulations(note_node: &Node) -> BTreeSet<Articulation> {
+    note_node
+        .children()
+        .filter(|n| n.has_tag_name("notations"))
+        .flat_map(|n| {
+            n.children()
+                .filter(|n| n.has_tag_name("articulations") || n.has_tag_name("technical"))
+        })
+        .flat_map(|n| n.children())
+        .filter_map(|n| match n.tag_name().name() {
+            "staccato" => Some(Articulation::Staccato),
+            "accent" => Some(Articulation::Accent),
+            "strong-accent" => Some(Articulation::StrongAccent),
+            "tenuto" => Some(Articulation::Tenuto),
+            "harmonic" => Some(Articulation::Harmonic),
+            _ => None,
+        })
+        .collect()
+}
+
+fn extract_fingering(note_node: &Node) -> Option<u8> {
+    let fingering = note_node
+        .children()
+        .find(|n| n.has_tag_name("notations"))?
+        .children()
+        .find(|n| n.has_tag_name("technical"))?
+        .children()
+        .find(|n| n.has_tag_name("fingering"))?
+        .text()?
+        .trim()
+        .to_ascii_lowercase();
+    match fingering.as_str() {
+        "t" => Some(0),
+        finger => finger.parse::<u8>().ok().filter(|&finger| finger <= 4),
+    }
+}
+
 fn calculate_string_and_fret(pitch: &Pitch) -> Option<(u8, u8)> {
     // Define standard tuning pitches for each string
     let string_pitches = [
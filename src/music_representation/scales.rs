@@ -0,0 +1,93 @@
+// scales.rs
+
+use core::fmt;
+
+pub const PITCH_CLASS_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+const INTERVAL_NAMES: [&str; 12] = [
+    "R", "b2", "2", "b3", "3", "4", "b5", "5", "b6", "6", "b7", "7",
+];
+
+/// Scales and arpeggios as semitones above the root.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScaleKind {
+    Major,
+    NaturalMinor,
+    MajorPentatonic,
+    MinorPentatonic,
+    Blues,
+    MajorArpeggio,
+    MinorArpeggio,
+    DominantSeventhArpeggio,
+}
+
+impl ScaleKind {
+    pub const ALL: [ScaleKind; 8] = [
+        ScaleKind::Major,
+        ScaleKind::NaturalMinor,
+        ScaleKind::MajorPentatonic,
+        ScaleKind::MinorPentatonic,
+        ScaleKind::Blues,
+        ScaleKind::MajorArpeggio,
+        ScaleKind::MinorArpeggio,
+        ScaleKind::DominantSeventhArpeggio,
+    ];
+
+    pub fn intervals(&self) -> &'static [u8] {
+        match self {
+            ScaleKind::Major => &[0, 2, 4, 5, 7, 9, 11],
+            ScaleKind::NaturalMinor => &[0, 2, 3, 5, 7, 8, 10],
+            ScaleKind::MajorPentatonic => &[0, 2, 4, 7, 9],
+            ScaleKind::MinorPentatonic => &[0, 3, 5, 7, 10],
+            ScaleKind::Blues => &[0, 3, 5, 6, 7, 10],
+            ScaleKind::MajorArpeggio => &[0, 4, 7],
+            ScaleKind::MinorArpeggio => &[0, 3, 7],
+            ScaleKind::DominantSeventhArpeggio => &[0, 4, 7, 10],
+        }
+    }
+}
+
+impl fmt::Display for ScaleKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScaleKind::Major => write!(f, "Major"),
+            ScaleKind::NaturalMinor => write!(f, "Natural minor"),
+            ScaleKind::MajorPentatonic => write!(f, "Major pentatonic"),
+            ScaleKind::MinorPentatonic => write!(f, "Minor pentatonic"),
+            ScaleKind::Blues => write!(f, "Blues"),
+            ScaleKind::MajorArpeggio => write!(f, "Major arpeggio"),
+            ScaleKind::MinorArpeggio => write!(f, "Minor arpeggio"),
+            ScaleKind::DominantSeventhArpeggio => write!(f, "Dominant 7th arpeggio"),
+        }
+    }
+}
+
+/// A scale or arpeggio on a given root, `root` being a pitch class with C as 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Scale {
+    pub root: u8,
+    pub kind: ScaleKind,
+}
+
+impl Scale {
+    /// Name of the interval of `pitch_class` above the root, if it belongs to the scale.
+    pub fn interval_name(&self, pitch_class: u8) -> Option<&'static str> {
+        let interval = (pitch_class + 12 - self.root % 12) % 12;
+        self.kind
+            .intervals()
+            .contains(&interval)
+            .then_some(INTERVAL_NAMES[interval as usize])
+    }
+}
+
+impl fmt::Display for Scale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}",
+            PITCH_CLASS_NAMES[self.root as usize % 12],
+            self.kind
+        )
+    }
+}
@@ -0,0 +1,193 @@
+// timeline.rs
+
+use std::ops::Range;
+
+use crate::music_representation::{Note, Score};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventKind {
+    NoteOn,
+    NoteOff,
+}
+
+/// A note starting or stopping to sound. Ticks are divisions counted from the start of the
+/// score.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimelineEvent {
+    pub tick: usize,
+    pub kind: EventKind,
+    pub measure: usize,
+    /// Division within the measure the note was struck at, also for its note-off
+    pub division: usize,
+    pub note: Note,
+}
+
+/// The score flattened into note-on and note-off events in time order, so that everything
+/// following the score in time works from the same timing instead of walking the measures
+/// and divisions itself.
+#[derive(Clone, Debug, Default)]
+pub struct Timeline {
+    /// Sorted by tick, with note-offs before the note-ons of the same tick
+    pub events: Vec<TimelineEvent>,
+    /// First tick of every measure
+    measure_starts: Vec<usize>,
+    pub total_ticks: usize,
+    pub ticks_per_quarter: u8,
+}
+
+impl Timeline {
+    pub fn from_score(score: &Score) -> Self {
+        let mut events = Vec::new();
+        let mut measure_starts = Vec::with_capacity(score.measures.len());
+        let mut tick = 0;
+        for (measure_idx, measure) in score.measures.iter().enumerate() {
+            measure_starts.push(tick);
+            for (division, notes) in measure.positions.iter().enumerate() {
+                // Notes continuing a tie ring on from the note tied to them
+                for note in notes.iter().filter(|note| note.is_struck()) {
+                    let on = TimelineEvent {
+                        tick: tick + division,
+                        kind: EventKind::NoteOn,
+                        measure: measure_idx,
+                        division,
+                        note: note.clone(),
+                    };
+                    events.push(TimelineEvent {
+                        tick: on.tick + note.sounding_duration as usize,
+                        kind: EventKind::NoteOff,
+                        ..on.clone()
+                    });
+                    events.push(on);
+                }
+            }
+            tick += measure.positions.len();
+        }
+        // Stable, so the notes of a chord stay in the order of the score
+        events.sort_by_key(|event| (event.tick, event.kind == EventKind::NoteOn));
+        Self {
+            events,
+            measure_starts,
+            total_ticks: tick,
+            ticks_per_quarter: score.divisions_per_quarter.max(1),
+        }
+    }
+
+    pub fn seconds_per_tick(&self, tempo: usize) -> f32 {
+        60.0 / tempo.max(1) as f32 / self.ticks_per_quarter as f32
+    }
+
+    /// Tick at which `division` of `measure` starts.
+    pub fn tick_of(&self, measure: usize, division: usize) -> usize {
+        self.measure_starts
+            .get(measure)
+            .map_or(self.total_ticks, |start| start + division)
+    }
+
+    /// Measure and division playing at `tick`, if the score hasn't ended by then.
+    pub fn position_at(&self, tick: usize) -> Option<(usize, usize)> {
+        if tick >= self.total_ticks {
+            return None;
+        }
+        let measure = self.measure_starts.partition_point(|&start| start <= tick) - 1;
+        Some((measure, tick - self.measure_starts[measure]))
+    }
+
+    /// The events with ticks in `ticks`.
+    pub fn events_in(&self, ticks: Range<usize>) -> &[TimelineEvent] {
+        let start = self
+            .events
+            .partition_point(|event| event.tick < ticks.start);
+        let end = self.events.partition_point(|event| event.tick < ticks.end);
+        &self.events[start..end]
+    }
+
+    /// The notes struck at `tick`, with the ticks the first of them rings for. Playback lets all
+    /// of them ring as long as the first one.
+    pub fn struck_at(&self, tick: usize) -> Option<(Vec<Note>, u32)> {
+        let struck: Vec<Note> = self
+            .events_in(tick..tick + 1)
+            .iter()
+            .filter(|event| event.kind == EventKind::NoteOn)
+            .map(|event| event.note.clone())
+            .collect();
+        let ticks = struck.first()?.sounding_duration;
+        Some((struck, ticks))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tied_notes_turn_into_one_note_on_and_off() {
+        let score = Score::parse_from_musicxml(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/ties.xml"
+        ))
+        .unwrap();
+        let timeline = Timeline::from_score(&score);
+        let per_measure = score.measures[0].positions.len();
+        assert_eq!(timeline.total_ticks, 2 * per_measure);
+        assert_eq!(timeline.position_at(per_measure + 2), Some((1, 2)));
+        assert_eq!(timeline.position_at(timeline.total_ticks), None);
+
+        // The note tied across the bar line sounds from the middle of the first measure on
+        let tied: Vec<&TimelineEvent> = timeline
+            .events
+            .iter()
+            .filter(|event| event.note.string == Some(2))
+            .collect();
+        assert_eq!(tied.len(), 2);
+        assert_eq!((tied[0].kind, tied[0].tick), (EventKind::NoteOn, 4));
+        assert_eq!((tied[1].kind, tied[1].tick), (EventKind::NoteOff, 14));
+        assert!(timeline.struck_at(per_measure).is_none());
+    }
+
+    #[test]
+    fn excerpts_strike_the_notes_tied_over_from_before() {
+        let score = Score::parse_from_musicxml(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/ties.xml"
+        ))
+        .unwrap();
+        let excerpt = score.excerpt(1..=1);
+        assert_eq!(excerpt.measures.len(), 1);
+        assert_eq!(excerpt.tempo, score.tempo);
+        let timeline = Timeline::from_score(&excerpt);
+        let (struck, _) = timeline.struck_at(0).unwrap();
+        assert!(struck.iter().any(|note| note.string == Some(2)));
+    }
+
+    #[test]
+    fn appended_scores_share_one_division_grid() {
+        let fixture = |name: &str| {
+            Score::parse_from_musicxml(format!(
+                "{}/tests/fixtures/{}",
+                env!("CARGO_MANIFEST_DIR"),
+                name
+            ))
+            .unwrap()
+        };
+        let mut score = fixture("ties.xml");
+        let tuplets = fixture("tuplets.xml");
+        let measures = score.measures.len();
+        score.append(&tuplets).unwrap();
+        assert_eq!(score.divisions_per_quarter, 6);
+        assert_eq!(score.measures.len(), measures + tuplets.measures.len());
+        assert!(score
+            .sections
+            .iter()
+            .any(|section| section.measure == measures));
+
+        // The tied note keeps its timing at three times the divisions
+        let timeline = Timeline::from_score(&score);
+        let tied: Vec<&TimelineEvent> = timeline
+            .events
+            .iter()
+            .filter(|event| event.measure < measures && event.note.string == Some(2))
+            .collect();
+        assert_eq!((tied[0].kind, tied[0].tick), (EventKind::NoteOn, 12));
+        assert_eq!((tied[1].kind, tied[1].tick), (EventKind::NoteOff, 42));
+    }
+}
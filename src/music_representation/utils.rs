@@ -1,6 +1,6 @@
 use roxmltree::Node;
 
-use super::TimeSignature;
+use super::{KeySignature, TimeSignature};
 
 pub fn extract_score_metadata(root: &Node) -> (u8, TimeSignature, usize) {
     let divisions_per_quarter = root
@@ -46,6 +46,19 @@ pub fn extract_score_metadata(root: &Node) -> (u8, TimeSignature, usize) {
     (divisions_per_quarter, time_signature, tempo)
 }
 
+pub fn extract_key_signature(root: &Node) -> Option<KeySignature> {
+    let key = root.descendants().find(|n| n.has_tag_name("key"))?;
+    let child_text = |tag: &str| {
+        key.children()
+            .find(|n| n.has_tag_name(tag))
+            .and_then(|n| n.text())
+            .map(str::trim)
+    };
+    let fifths = child_text("fifths")?.parse::<i8>().ok()?;
+    let minor = child_text("mode") == Some("minor");
+    Some(KeySignature { fifths, minor })
+}
+
 pub fn calculate_divisions_per_measure(
     beats_per_measure: u8,
     divisions_per_quarter: u8,
@@ -53,3 +66,24 @@ pub fn calculate_divisions_per_measure(
 ) -> usize {
     (beats_per_measure as usize) * (divisions_per_quarter as usize) * 4 / (beat_value as usize)
 }
+
+pub fn extract_score_credits(root: &Node) -> (Option<String>, Option<String>) {
+    let text_of = |tag: &str| {
+        root.descendants()
+            .find(|n| n.has_tag_name(tag))
+            .and_then(|n| n.text())
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+    };
+
+    let title = text_of("work-title").or_else(|| text_of("movement-title"));
+
+    let composer = root
+        .descendants()
+        .find(|n| n.has_tag_name("creator") && n.attribute("type") == Some("composer"))
+        .and_then(|n| n.text())
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty());
+
+    (title, composer)
+}
@@ -0,0 +1,135 @@
+// chord_diagram.rs
+
+use egui::{pos2, vec2, Align2, Color32, Pos2, Vec2};
+
+use crate::music_representation::Note;
+use crate::renderer::renderer::{TabShape, NUM_STRINGS};
+
+// Diagram geometry
+const DIAGRAM_FRETS: u8 = 5;
+const DIAGRAM_STRING_SPACING: f32 = 14.0;
+const DIAGRAM_FRET_SPACING: f32 = 18.0;
+const DIAGRAM_HEADER: f32 = 36.0; // space for the chord name and open/muted markers
+const DIAGRAM_MARGIN: f32 = 32.0; // space for the base fret label
+
+/// The fret played on every string of a chord, `None` for strings that are not played.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChordShape {
+    /// Indexed by string number minus one, so the high E string comes first
+    pub frets: [Option<u8>; NUM_STRINGS],
+}
+
+impl ChordShape {
+    pub fn from_notes(notes: &[Note]) -> Option<Self> {
+        let mut frets = [None; NUM_STRINGS];
+        for note in notes {
+            if let (Some(string), Some(fret)) = (note.string, note.fret) {
+                if (1..=NUM_STRINGS as u8).contains(&string) {
+                    frets[string as usize - 1] = Some(fret);
+                }
+            }
+        }
+        frets.iter().any(Option::is_some).then_some(Self { frets })
+    }
+
+    pub fn played_strings(&self) -> usize {
+        self.frets.iter().filter(|f| f.is_some()).count()
+    }
+
+    /// Lowest fret shown in the diagram. Shapes that fit below the fifth fret start at the nut.
+    fn base_fret(&self) -> u8 {
+        let fretted = self.frets.iter().flatten().filter(|&&f| f > 0);
+        let highest = fretted.clone().max().copied().unwrap_or(0);
+        if highest <= DIAGRAM_FRETS {
+            1
+        } else {
+            fretted.min().copied().unwrap_or(1)
+        }
+    }
+}
+
+pub fn chord_diagram_size() -> Vec2 {
+    vec2(
+        (NUM_STRINGS - 1) as f32 * DIAGRAM_STRING_SPACING + 2.0 * DIAGRAM_MARGIN,
+        DIAGRAM_HEADER + DIAGRAM_FRETS as f32 * DIAGRAM_FRET_SPACING + 8.0,
+    )
+}
+
+/// Lays out a fretboard grid with the low E string on the left, the top-left corner at `origin`.
+pub fn layout_chord_diagram(shape: &ChordShape, name: Option<&str>, origin: Pos2) -> Vec<TabShape> {
+    let mut shapes = Vec::new();
+    let left = origin.x + DIAGRAM_MARGIN;
+    let top = origin.y + DIAGRAM_HEADER;
+    let width = (NUM_STRINGS - 1) as f32 * DIAGRAM_STRING_SPACING;
+    let height = DIAGRAM_FRETS as f32 * DIAGRAM_FRET_SPACING;
+    let string_x =
+        |string_idx: usize| left + (NUM_STRINGS - 1 - string_idx) as f32 * DIAGRAM_STRING_SPACING;
+
+    if let Some(name) = name {
+        shapes.push(TabShape::Text {
+            pos: pos2(left + width / 2.0, origin.y),
+            anchor: Align2::CENTER_TOP,
+            text: name.to_string(),
+            size: 14.0,
+            color: Color32::BLACK,
+        });
+    }
+
+    for string_idx in 0..NUM_STRINGS {
+        let x = string_x(string_idx);
+        shapes.push(TabShape::Line {
+            points: [pos2(x, top), pos2(x, top + height)],
+            width: 1.0,
+            color: Color32::BLACK,
+        });
+    }
+    for fret in 0..=DIAGRAM_FRETS {
+        let y = top + fret as f32 * DIAGRAM_FRET_SPACING;
+        shapes.push(TabShape::Line {
+            points: [pos2(left, y), pos2(left + width, y)],
+            width: 1.0,
+            color: Color32::BLACK,
+        });
+    }
+
+    let base_fret = shape.base_fret();
+    if base_fret == 1 {
+        // A thick nut
+        shapes.push(TabShape::Line {
+            points: [pos2(left, top), pos2(left + width, top)],
+            width: 3.0,
+            color: Color32::BLACK,
+        });
+    } else {
+        shapes.push(TabShape::Text {
+            pos: pos2(left + width + 10.0, top + DIAGRAM_FRET_SPACING / 2.0),
+            anchor: Align2::LEFT_CENTER,
+            text: format!("{}fr", base_fret),
+            size: 11.0,
+            color: Color32::BLACK,
+        });
+    }
+
+    for (string_idx, fret) in shape.frets.iter().enumerate() {
+        let x = string_x(string_idx);
+        match fret {
+            Some(0) | None => shapes.push(TabShape::Text {
+                pos: pos2(x, top - 3.0),
+                anchor: Align2::CENTER_BOTTOM,
+                text: if fret.is_some() { "o" } else { "x" }.to_string(),
+                size: 12.0,
+                color: Color32::BLACK,
+            }),
+            Some(fret) => {
+                let row = fret.saturating_sub(base_fret).min(DIAGRAM_FRETS - 1);
+                shapes.push(TabShape::Dot {
+                    center: pos2(x, top + (row as f32 + 0.5) * DIAGRAM_FRET_SPACING),
+                    radius: 5.0,
+                    color: Color32::BLACK,
+                });
+            }
+        }
+    }
+
+    shapes
+}
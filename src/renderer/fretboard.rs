@@ -0,0 +1,236 @@
+// fretboard.rs
+
+use egui::{pos2, Align, Align2, Color32, Pos2};
+
+use crate::music_representation::{Note, Scale, OPEN_STRING_MIDI};
+use crate::renderer::renderer::{TabShape, NUM_STRINGS};
+
+// Fretboard geometry
+pub const FRETBOARD_HEIGHT: f32 = 130.0;
+const MIN_FRETS: u8 = 15;
+const MAX_FRETS: u8 = 24;
+const STRING_LABEL_WIDTH: f32 = 20.0;
+const OPEN_STRING_WIDTH: f32 = 24.0; // space left of the nut for open strings
+const FRET_LABEL_HEIGHT: f32 = 16.0;
+const EDGE_MARGIN: f32 = 10.0;
+const NOTE_RADIUS: f32 = 7.0;
+
+const STRING_NAMES: [&str; NUM_STRINGS] = ["e", "B", "G", "D", "A", "E"];
+const INLAY_FRETS: [u8; 9] = [3, 5, 7, 9, 12, 15, 17, 19, 21];
+
+const WOOD_COLOR: Color32 = Color32::from_rgb(120, 81, 45);
+const INLAY_COLOR: Color32 = Color32::from_rgb(225, 215, 195);
+const NOTE_COLOR: Color32 = Color32::from_rgb(255, 140, 0);
+const CAPO_COLOR: Color32 = Color32::from_rgb(40, 40, 40);
+const SCALE_COLOR: Color32 = Color32::from_rgb(235, 235, 235);
+const ROOT_COLOR: Color32 = Color32::from_rgb(200, 40, 40);
+
+/// Lays out a horizontal fretboard `width` wide with the high E string at the top, like the tab,
+/// lighting up `notes`. Tab frets are relative to the capo, so notes are drawn `capo_fret` higher.
+/// Left-handed fretboards have the low E on top and the nut on the right. An `overlay` scale marks its
+/// notes everywhere on the neck, under the played notes, labelled with their intervals.
+pub fn layout_fretboard(
+    notes: &[Note],
+    capo_fret: u8,
+    origin: Pos2,
+    width: f32,
+    left_handed: bool,
+    overlay: Option<&Scale>,
+) -> Vec<TabShape> {
+    let mut shapes = Vec::new();
+    let positions: Vec<(u8, u8)> = notes
+        .iter()
+        .filter_map(|note| Some((note.string?, note.fret?.saturating_add(capo_fret))))
+        .filter(|&(string, fret)| (1..=NUM_STRINGS as u8).contains(&string) && fret <= MAX_FRETS)
+        .collect();
+    let highest_fret = positions.iter().map(|&(_, fret)| fret).max().unwrap_or(0);
+    let frets = highest_fret.max(capo_fret).clamp(MIN_FRETS, MAX_FRETS);
+
+    let nut_x = origin.x + STRING_LABEL_WIDTH + OPEN_STRING_WIDTH;
+    let right = origin.x + width - EDGE_MARGIN;
+    let top = origin.y + EDGE_MARGIN;
+    let bottom = origin.y + FRETBOARD_HEIGHT - FRET_LABEL_HEIGHT - EDGE_MARGIN;
+    let string_spacing = (bottom - top) / (NUM_STRINGS - 1) as f32;
+    let string_y = |string: u8| {
+        let line = if left_handed {
+            NUM_STRINGS as u8 - string
+        } else {
+            string - 1
+        };
+        top + line as f32 * string_spacing
+    };
+
+    // Frets get narrower up the neck like on a real guitar, scaled to fill the width
+    let neck_fraction = |fret: u8| 1.0 - 2f32.powf(-(fret as f32) / 12.0);
+    let scale = (right - nut_x) / neck_fraction(frets);
+    let fret_x = |fret: u8| nut_x + neck_fraction(fret) * scale;
+    // Notes sit between the fret wire below them and the one they are pressed against
+    let note_x = |fret: u8| {
+        if fret == 0 {
+            nut_x - OPEN_STRING_WIDTH / 2.0
+        } else {
+            (fret_x(fret - 1) + fret_x(fret.min(frets))) / 2.0
+        }
+    };
+
+    // The wood, drawn as a very wide line
+    let center_y = (top + bottom) / 2.0;
+    shapes.push(TabShape::Line {
+        points: [pos2(nut_x, center_y), pos2(right, center_y)],
+        width: bottom - top + 12.0,
+        color: WOOD_COLOR,
+    });
+
+    for fret in INLAY_FRETS.iter().copied().filter(|&fret| fret <= frets) {
+        let x = note_x(fret);
+        let ys = if fret % 12 == 0 {
+            vec![
+                string_y(2) + string_spacing / 2.0,
+                string_y(4) + string_spacing / 2.0,
+            ]
+        } else {
+            vec![center_y]
+        };
+        for y in ys {
+            shapes.push(TabShape::Dot {
+                center: pos2(x, y),
+                radius: 4.0,
+                color: INLAY_COLOR,
+            });
+        }
+    }
+
+    for fret in 0..=frets {
+        let x = fret_x(fret);
+        shapes.push(TabShape::Line {
+            points: [pos2(x, top - 6.0), pos2(x, bottom + 6.0)],
+            width: if fret == 0 { 4.0 } else { 1.5 },
+            color: if fret == 0 {
+                INLAY_COLOR
+            } else {
+                Color32::from_gray(190)
+            },
+        });
+        if fret > 0 && fret != capo_fret && (INLAY_FRETS.contains(&fret) || fret == 1) {
+            shapes.push(TabShape::Text {
+                pos: pos2(note_x(fret), bottom + 8.0),
+                anchor: Align2::CENTER_TOP,
+                text: fret.to_string(),
+                size: 11.0,
+                color: Color32::GRAY,
+            });
+        }
+    }
+
+    for string in 1..=NUM_STRINGS as u8 {
+        let y = string_y(string);
+        // Lower strings are thicker
+        shapes.push(TabShape::Line {
+            points: [pos2(nut_x - OPEN_STRING_WIDTH, y), pos2(right, y)],
+            width: 0.8 + (string - 1) as f32 * 0.3,
+            color: Color32::from_gray(215),
+        });
+        shapes.push(TabShape::Text {
+            pos: pos2(origin.x + STRING_LABEL_WIDTH / 2.0, y),
+            anchor: Align2::CENTER_CENTER,
+            text: STRING_NAMES[string as usize - 1].to_string(),
+            size: 12.0,
+            color: Color32::GRAY,
+        });
+    }
+
+    // The capo clamps all strings just behind its fret wire
+    let capo_x = || fret_x(capo_fret) - (fret_x(capo_fret) - fret_x(capo_fret - 1)) * 0.3;
+    if capo_fret > 0 {
+        let x = capo_x();
+        shapes.push(TabShape::Line {
+            points: [pos2(x, top - 8.0), pos2(x, bottom + 8.0)],
+            width: 8.0,
+            color: CAPO_COLOR,
+        });
+        shapes.push(TabShape::Text {
+            pos: pos2(x, bottom + 8.0),
+            anchor: Align2::CENTER_TOP,
+            text: "Capo".to_string(),
+            size: 11.0,
+            color: Color32::DARK_GRAY,
+        });
+    }
+
+    if let Some(scale) = overlay {
+        for string in 1..=NUM_STRINGS as u8 {
+            for fret in 0..=frets {
+                let pitch_class = (OPEN_STRING_MIDI[string as usize - 1] + fret) % 12;
+                let Some(interval) = scale.interval_name(pitch_class) else {
+                    continue;
+                };
+                let root = pitch_class == scale.root;
+                let center = pos2(note_x(fret), string_y(string));
+                shapes.push(TabShape::Dot {
+                    center,
+                    radius: NOTE_RADIUS - 1.0,
+                    color: if root { ROOT_COLOR } else { SCALE_COLOR },
+                });
+                shapes.push(TabShape::Text {
+                    pos: center,
+                    anchor: Align2::CENTER_CENTER,
+                    text: interval.to_string(),
+                    size: 9.0,
+                    color: if root {
+                        Color32::WHITE
+                    } else {
+                        Color32::DARK_GRAY
+                    },
+                });
+            }
+        }
+    }
+
+    for (string, fret) in positions {
+        // Open strings ring from the capo
+        let x = if capo_fret > 0 && fret == capo_fret {
+            capo_x()
+        } else {
+            note_x(fret)
+        };
+        let center = pos2(x, string_y(string));
+        shapes.push(TabShape::Dot {
+            center,
+            radius: NOTE_RADIUS,
+            color: NOTE_COLOR,
+        });
+        shapes.push(TabShape::Text {
+            pos: center,
+            anchor: Align2::CENTER_CENTER,
+            text: fret.to_string(),
+            size: 10.0,
+            color: Color32::BLACK,
+        });
+    }
+
+    if left_handed {
+        mirror_horizontally(&mut shapes, origin.x + width / 2.0);
+    }
+    shapes
+}
+
+/// Reflects the shapes around the vertical line at `axis_x`, keeping text readable.
+fn mirror_horizontally(shapes: &mut [TabShape], axis_x: f32) {
+    let mirror = |pos: &mut Pos2| pos.x = 2.0 * axis_x - pos.x;
+    for shape in shapes {
+        match shape {
+            TabShape::Line { points, .. } => points.iter_mut().for_each(mirror),
+            TabShape::Curve { points, .. } => points.iter_mut().for_each(mirror),
+            TabShape::Dot { center, .. } => mirror(center),
+            TabShape::Text { pos, anchor, .. } => {
+                mirror(pos);
+                let x = match anchor.x() {
+                    Align::Min => Align::Max,
+                    Align::Center => Align::Center,
+                    Align::Max => Align::Min,
+                };
+                *anchor = Align2([x, anchor.y()]);
+            }
+        }
+    }
+}
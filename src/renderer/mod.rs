@@ -1 +1,4 @@
+pub mod chord_diagram;
+pub mod fretboard;
+pub mod piano_roll;
 pub mod renderer;
@@ -0,0 +1,180 @@
+// piano_roll.rs
+
+use std::ops::Range;
+
+use egui::{pos2, Align2, Color32, Pos2, Vec2};
+
+use crate::music_representation::{midi_note, Score, OPEN_STRING_MIDI};
+use crate::renderer::renderer::{Renderer, TabShape, NOTE_SPACING, TAB_PADDING};
+
+const KEY_HEIGHT: f32 = 8.0; // pixels per semitone
+const ROW_TOP: f32 = 20.0; // space above the keys for section names
+const PITCH_MARGIN: u8 = 2; // semitones shown above and below the used range
+
+const NOTE_COLOR: Color32 = Color32::from_rgb(70, 130, 200);
+const BLACK_KEY_COLOR: Color32 = Color32::from_gray(232);
+
+fn is_black_key(midi: u8) -> bool {
+    matches!(midi % 12, 1 | 3 | 6 | 8 | 10)
+}
+
+/// Time on the horizontal axis and pitch on the vertical axis, with measures at the same x
+/// positions as in the tab so both views share the playback cursor logic.
+pub struct PianoRoll<'a> {
+    renderer: &'a Renderer,
+    capo_fret: u8,
+    lowest: u8,
+    highest: u8,
+}
+
+impl<'a> PianoRoll<'a> {
+    pub fn new(renderer: &'a Renderer, score: &Score, capo_fret: u8) -> Self {
+        let pitches = score
+            .measures
+            .iter()
+            .flat_map(|measure| measure.positions.iter().flatten())
+            .filter_map(|note| midi_note(note, capo_fret));
+        let (lowest, highest) = pitches.fold((u8::MAX, 0), |(low, high), midi| {
+            (low.min(midi), high.max(midi))
+        });
+        let (lowest, highest) = if lowest > highest {
+            // No pitched notes, show the range of the open strings
+            (OPEN_STRING_MIDI[5], OPEN_STRING_MIDI[0])
+        } else {
+            (lowest, highest)
+        };
+        Self {
+            renderer,
+            capo_fret,
+            lowest: lowest.saturating_sub(PITCH_MARGIN),
+            highest: highest.saturating_add(PITCH_MARGIN),
+        }
+    }
+
+    fn keys_height(&self) -> f32 {
+        (self.highest - self.lowest + 1) as f32 * KEY_HEIGHT
+    }
+
+    pub fn row_height(&self) -> f32 {
+        ROW_TOP + self.keys_height() + self.renderer.style.row_spacing
+    }
+
+    fn key_top(&self, row_y: f32, midi: u8) -> f32 {
+        row_y + ROW_TOP + (self.highest - midi) as f32 * KEY_HEIGHT
+    }
+
+    /// Size of the laid out piano roll including padding on all sides.
+    pub fn size(&self, score: &Score) -> Vec2 {
+        let width = self.renderer.calculate_tab_size(score).x;
+        let rows = self.renderer.total_rows(score);
+        Vec2::new(width, rows as f32 * self.row_height() + 2.0 * TAB_PADDING)
+    }
+
+    pub fn layout(&self, score: &Score, origin: Pos2) -> Vec<TabShape> {
+        self.layout_rows(score, origin, 0..self.renderer.total_rows(score))
+    }
+
+    /// Lays out a range of rows, with the top-left corner of the first row in the range at `origin`.
+    pub fn layout_rows(&self, score: &Score, origin: Pos2, rows: Range<usize>) -> Vec<TabShape> {
+        let mut shapes = Vec::new();
+        let measures_per_row = self.renderer.measures_per_row;
+        let division_width = self.renderer.dashes_per_division as f32 * NOTE_SPACING;
+
+        let chunks = score.measures.chunks(measures_per_row).enumerate();
+        for (row, measures) in chunks.skip(rows.start).take(rows.len()) {
+            let y = origin.y + (row - rows.start) as f32 * self.row_height();
+            let row_width = self
+                .renderer
+                .calculate_row_width(score, row, measures.len());
+            self.layout_keys(&mut shapes, origin.x, y, row_width);
+
+            let mut x_offset = origin.x;
+            for (measure_idx_in_row, measure) in measures.iter().enumerate() {
+                let measure_idx = row * measures_per_row + measure_idx_in_row;
+                let measure_width = self.renderer.measure_width(measure);
+
+                for section in score.sections.iter().filter(|s| s.measure == measure_idx) {
+                    shapes.push(TabShape::Text {
+                        pos: pos2(x_offset, y + ROW_TOP - 2.0),
+                        anchor: Align2::LEFT_BOTTOM,
+                        text: section.name.clone(),
+                        size: 14.0,
+                        color: Color32::DARK_BLUE,
+                    });
+                }
+
+                for (division_idx, notes) in measure.positions.iter().enumerate() {
+                    let x = x_offset + division_idx as f32 * division_width;
+                    for note in notes {
+                        let Some(midi) = midi_note(note, self.capo_fret) else {
+                            continue;
+                        };
+                        // A note is a bar as long as it rings, drawn as a thick line
+                        let length = (note.duration.max(1) as f32 * division_width - 1.0).max(2.0);
+                        let center_y = self.key_top(y, midi) + KEY_HEIGHT / 2.0;
+                        shapes.push(TabShape::Line {
+                            points: [pos2(x, center_y), pos2(x + length, center_y)],
+                            width: KEY_HEIGHT - 1.0,
+                            color: NOTE_COLOR,
+                        });
+                    }
+                }
+
+                for x in [x_offset, x_offset + measure_width] {
+                    shapes.push(TabShape::Line {
+                        points: [
+                            pos2(x, y + ROW_TOP),
+                            pos2(x, y + ROW_TOP + self.keys_height()),
+                        ],
+                        width: 1.0,
+                        color: Color32::GRAY,
+                    });
+                }
+                x_offset += measure_width + self.renderer.style.measure_spacing;
+            }
+        }
+
+        self.renderer.style.apply_contrast(&mut shapes);
+        shapes
+    }
+
+    /// Shaded lanes for the black keys and a label on every C.
+    fn layout_keys(&self, shapes: &mut Vec<TabShape>, x: f32, y: f32, width: f32) {
+        for midi in self.lowest..=self.highest {
+            let center_y = self.key_top(y, midi) + KEY_HEIGHT / 2.0;
+            if is_black_key(midi) {
+                shapes.push(TabShape::Line {
+                    points: [pos2(x, center_y), pos2(x + width, center_y)],
+                    width: KEY_HEIGHT,
+                    color: BLACK_KEY_COLOR,
+                });
+            }
+            if midi % 12 == 0 {
+                shapes.push(TabShape::Text {
+                    pos: pos2(x - 2.0, center_y),
+                    anchor: Align2::RIGHT_CENTER,
+                    text: format!("C{}", midi / 12 - 1),
+                    size: 9.0,
+                    color: Color32::GRAY,
+                });
+            }
+        }
+    }
+
+    /// End points of the playback position indicator, at the same x as in the tab.
+    pub fn playback_indicator(
+        &self,
+        score: &Score,
+        origin: Pos2,
+        current_measure: usize,
+        current_division: usize,
+    ) -> [Pos2; 2] {
+        let [top, _] =
+            self.renderer
+                .playback_indicator(score, origin, current_measure, current_division);
+        let row = current_measure / self.renderer.measures_per_row;
+        let y = origin.y + row as f32 * self.row_height() + ROW_TOP;
+        [pos2(top.x, y), pos2(top.x, y + self.keys_height())]
+    }
+}
+
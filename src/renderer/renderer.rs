@@ -1,11 +1,231 @@
 // renderer.rs
 
-use crate::music_representation::Score;
+use crate::music_representation::{
+    chord_at, chord_progression, midi_note, Chord, Measure, Note, Score, Technique,
+    PITCH_CLASS_NAMES,
+};
+
+use std::ops::Range;
+
+use egui::{pos2, Align2, Color32, FontFamily, Pos2, Rect, Vec2};
+
+// Layout constants shared by the on-screen tab and the exporters
+pub const NUM_STRINGS: usize = 6;
+pub const STRING_SPACING: f32 = 20.0; // default pixels between strings
+pub const NOTE_SPACING: f32 = 10.0; // base pixels between dashes
+pub const MEASURE_SPACING: f32 = 10.0; // default spacing between measures
+pub const ROW_SPACING: f32 = 50.0; // default vertical spacing between rows
+pub const TAB_PADDING: f32 = 20.0; // padding on each side of the tab
+
+// Colors at least this light are left alone by the high contrast mode
+const HIGH_CONTRAST_LIGHT: f32 = 200.0;
+
+/// A single drawing primitive of the tab layout, independent of the output backend.
+#[derive(Clone, Debug)]
+pub enum TabShape {
+    Line {
+        points: [Pos2; 2],
+        width: f32,
+        color: Color32,
+    },
+    Text {
+        pos: Pos2,
+        anchor: Align2,
+        text: String,
+        size: f32,
+        color: Color32,
+    },
+    /// Quadratic bezier through start, control and end point
+    Curve {
+        points: [Pos2; 3],
+        width: f32,
+        color: Color32,
+    },
+    /// Filled circle
+    Dot {
+        center: Pos2,
+        radius: f32,
+        color: Color32,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TabFont {
+    Monospace,
+    Proportional,
+}
+
+impl std::fmt::Display for TabFont {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TabFont::Monospace => write!(f, "Monospace"),
+            TabFont::Proportional => write!(f, "Proportional"),
+        }
+    }
+}
+
+impl TabFont {
+    pub fn family(&self) -> FontFamily {
+        match self {
+            TabFont::Monospace => FontFamily::Monospace,
+            TabFont::Proportional => FontFamily::Proportional,
+        }
+    }
+}
+
+/// What the color of a fret number tells.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoteColoring {
+    None,
+    String,
+    Finger,
+    /// Root, third, fifth or tension of the chord the note is played over
+    Interval,
+}
+
+impl NoteColoring {
+    pub const ALL: [NoteColoring; 4] = [
+        NoteColoring::None,
+        NoteColoring::String,
+        NoteColoring::Finger,
+        NoteColoring::Interval,
+    ];
+}
+
+impl std::fmt::Display for NoteColoring {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NoteColoring::None => write!(f, "None"),
+            NoteColoring::String => write!(f, "String"),
+            NoteColoring::Finger => write!(f, "Finger"),
+            NoteColoring::Interval => write!(f, "Interval to chord"),
+        }
+    }
+}
+
+/// Sizes of the tab's text and lines, adjustable for big screens or small laptops.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TabStyle {
+    pub font: TabFont,
+    pub font_size: f32,
+    pub string_spacing: f32,
+    /// Space between the bottom string of a row and the top string of the next
+    pub row_spacing: f32,
+    /// Horizontal space between consecutive measures of a row
+    pub measure_spacing: f32,
+    pub line_width: f32,
+    pub coloring: NoteColoring,
+    /// Fret number colors from the high E string down
+    pub string_palette: [Color32; NUM_STRINGS],
+    /// Fret number colors for the thumb and fingers 1 to 4
+    pub finger_palette: [Color32; 5],
+    /// Fret number colors for the root, third, fifth and tensions, in `ChordFunction` order
+    pub interval_palette: [Color32; 4],
+    /// Prints pitch names like G#3 instead of fret numbers
+    pub note_names: bool,
+    /// Everything in thick black ink on white, for low vision
+    pub high_contrast: bool,
+}
+
+impl TabStyle {
+    /// Turns the laid out shapes black and their lines thicker in high contrast mode. Light
+    /// colors, like the piano roll's lanes, are backgrounds and stay as they are.
+    pub fn apply_contrast(&self, shapes: &mut [TabShape]) {
+        if !self.high_contrast {
+            return;
+        }
+        for shape in shapes {
+            let (color, width) = match shape {
+                TabShape::Line { color, width, .. } | TabShape::Curve { color, width, .. } => {
+                    (color, Some(width))
+                }
+                TabShape::Text { color, .. } | TabShape::Dot { color, .. } => (color, None),
+            };
+            let [r, g, b, _] = color.to_array();
+            let luminance = 0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32;
+            if luminance < HIGH_CONTRAST_LIGHT {
+                *color = Color32::BLACK;
+                if let Some(width) = width {
+                    *width = (*width * 2.0).max(2.0);
+                }
+            }
+        }
+    }
+
+    fn note_color(&self, note: &Note, chord: Option<Chord>, capo_fret: u8) -> Color32 {
+        let color = match self.coloring {
+            NoteColoring::None => None,
+            NoteColoring::String => note
+                .string
+                .and_then(|string| self.string_palette.get(string.checked_sub(1)? as usize)),
+            NoteColoring::Finger => note
+                .finger
+                .and_then(|finger| self.finger_palette.get(finger as usize)),
+            NoteColoring::Interval => chord
+                .zip(midi_note(note, capo_fret))
+                .map(|(chord, midi)| &self.interval_palette[chord.function_of(midi % 12) as usize]),
+        };
+        color.copied().unwrap_or(Color32::BLACK)
+    }
+
+    fn note_label(&self, note: &Note, fret: u8, capo_fret: u8) -> String {
+        match midi_note(note, capo_fret).filter(|_| self.note_names) {
+            Some(midi) => format!(
+                "{}{}",
+                PITCH_CLASS_NAMES[midi as usize % 12],
+                midi as i32 / 12 - 1
+            ),
+            None => fret.to_string(),
+        }
+    }
+}
+
+impl Default for TabStyle {
+    fn default() -> Self {
+        Self {
+            font: TabFont::Monospace,
+            font_size: 14.0,
+            string_spacing: STRING_SPACING,
+            row_spacing: ROW_SPACING,
+            measure_spacing: MEASURE_SPACING,
+            line_width: 1.0,
+            coloring: NoteColoring::None,
+            string_palette: [
+                Color32::from_rgb(214, 39, 40),
+                Color32::from_rgb(255, 127, 14),
+                Color32::from_rgb(44, 160, 44),
+                Color32::from_rgb(23, 190, 207),
+                Color32::from_rgb(31, 119, 180),
+                Color32::from_rgb(148, 103, 189),
+            ],
+            finger_palette: [
+                Color32::from_rgb(127, 127, 127),
+                Color32::from_rgb(31, 119, 180),
+                Color32::from_rgb(44, 160, 44),
+                Color32::from_rgb(255, 127, 14),
+                Color32::from_rgb(214, 39, 40),
+            ],
+            interval_palette: [
+                Color32::from_rgb(214, 39, 40),
+                Color32::from_rgb(31, 119, 180),
+                Color32::from_rgb(44, 160, 44),
+                Color32::from_rgb(148, 103, 189),
+            ],
+            note_names: false,
+            high_contrast: false,
+        }
+    }
+}
 
 // The Renderer struct encapsulates rendering logic
 pub struct Renderer {
     pub measures_per_row: usize,
     pub dashes_per_division: usize,
+    /// Draws the low E string on top, the way a left-handed player sees their guitar
+    pub left_handed: bool,
+    pub style: TabStyle,
+    /// Only used for note names, fret numbers are relative to the capo
+    pub capo_fret: u8,
 }
 
 impl Renderer {
@@ -13,12 +233,325 @@ impl Renderer {
         Self {
             measures_per_row,
             dashes_per_division,
+            left_handed: false,
+            style: TabStyle::default(),
+            capo_fret: 0,
         }
     }
+
+    /// Line of `string` counted from the top of a row, starting at 0.
+    fn string_line(&self, string: u8) -> usize {
+        let idx = string as usize - 1;
+        if self.left_handed {
+            NUM_STRINGS - 1 - idx
+        } else {
+            idx
+        }
+    }
+
+    /// Lays out the whole score as shapes, with the top-left corner of the first row at `origin`.
+    pub fn layout(&self, score: &Score, origin: Pos2) -> Vec<TabShape> {
+        self.layout_rows(score, origin, 0..self.total_rows(score))
+    }
+
+    /// Lays out a range of rows, with the top-left corner of the first row in the range at `origin`.
+    pub fn layout_rows(&self, score: &Score, origin: Pos2, rows: Range<usize>) -> Vec<TabShape> {
+        let mut shapes = self.layout_rows_in_color(score, origin, rows);
+        self.style.apply_contrast(&mut shapes);
+        shapes
+    }
+
+    fn layout_rows_in_color(
+        &self,
+        score: &Score,
+        origin: Pos2,
+        rows: Range<usize>,
+    ) -> Vec<TabShape> {
+        let mut shapes = Vec::new();
+
+        let total_measures = score.measures.len();
+        let measures_per_row = self.measures_per_row;
+
+        let mut y_offset = origin.y;
+        let chords = match self.style.coloring {
+            NoteColoring::Interval => chord_progression(score, self.capo_fret),
+            _ => Vec::new(),
+        };
+
+        for row in rows.start..rows.end.min(self.total_rows(score)) {
+            let measures_in_row = if (row + 1) * measures_per_row <= total_measures {
+                measures_per_row
+            } else {
+                total_measures % measures_per_row
+            };
+
+            // Calculate the total width of the current row
+            let row_width = self.calculate_row_width(score, row, measures_in_row);
+
+            // Draw strings (horizontal lines) for the current row
+            self.layout_strings(&mut shapes, origin.x, y_offset, row_width);
+
+            let mut x_offset = origin.x;
+            for measure_idx_in_row in 0..measures_in_row {
+                let measure_idx = row * measures_per_row + measure_idx_in_row;
+                let measure = &score.measures[measure_idx];
+
+                // Only the first measure of a row draws its starting vertical line
+                let draw_start_line = measure_idx_in_row == 0;
+                // The chord under every division, looked up once per measure
+                let measure_chords: Vec<Option<Chord>> = (0..measure.positions.len())
+                    .filter(|_| !chords.is_empty())
+                    .map(|division| chord_at(&chords, measure_idx, division))
+                    .collect();
+                self.layout_measure(
+                    &mut shapes,
+                    measure,
+                    &measure_chords,
+                    x_offset,
+                    y_offset,
+                    draw_start_line,
+                );
+
+                for section in score.sections.iter().filter(|s| s.measure == measure_idx) {
+                    shapes.push(TabShape::Text {
+                        pos: pos2(x_offset, y_offset + 4.0),
+                        anchor: Align2::LEFT_BOTTOM,
+                        text: section.name.clone(),
+                        size: 14.0,
+                        color: Color32::DARK_BLUE,
+                    });
+                }
+
+                // Move x_offset to the end of the measure
+                x_offset += self.measure_width(measure) + self.style.measure_spacing;
+            }
+
+            y_offset += self.row_height();
+        }
+
+        shapes
+    }
+
+    pub fn total_rows(&self, score: &Score) -> usize {
+        score.measures.len().div_ceil(self.measures_per_row)
+    }
+
+    /// Vertical distance between the tops of two consecutive rows.
+    pub fn row_height(&self) -> f32 {
+        NUM_STRINGS as f32 * self.style.string_spacing + self.style.row_spacing
+    }
+
+    /// Width of a single measure, excluding the spacing after it.
+    pub fn measure_width(&self, measure: &Measure) -> f32 {
+        let total_dashes = measure.positions.len() * self.dashes_per_division;
+        total_dashes as f32 * NOTE_SPACING
+    }
+
+    /// Calculates the total width of a row of measures.
+    pub fn calculate_row_width(&self, score: &Score, row: usize, measures_in_row: usize) -> f32 {
+        let mut row_width = 0.0;
+
+        for measure_idx_in_row in 0..measures_in_row {
+            let measure_idx = row * self.measures_per_row + measure_idx_in_row;
+            row_width +=
+                self.measure_width(&score.measures[measure_idx]) + self.style.measure_spacing;
+        }
+        // Subtract the extra measure_spacing added after the last measure
+        row_width -= self.style.measure_spacing;
+
+        row_width
+    }
+
+    /// The largest number of measures per row for which no row is wider than `width`, but at
+    /// least one.
+    pub fn measures_per_row_fitting(&self, score: &Score, width: f32) -> usize {
+        let widths: Vec<f32> = score
+            .measures
+            .iter()
+            .map(|measure| self.measure_width(measure) + self.style.measure_spacing)
+            .collect();
+        (2..=widths.len())
+            .take_while(|&measures_per_row| {
+                widths
+                    .chunks(measures_per_row)
+                    .all(|row| row.iter().sum::<f32>() - self.style.measure_spacing <= width)
+            })
+            .last()
+            .unwrap_or(1)
+    }
+
+    /// Size of the laid out tab including padding on all sides.
+    pub fn calculate_tab_size(&self, score: &Score) -> Vec2 {
+        let measures_per_row = self.measures_per_row;
+        let total_rows = self.total_rows(score);
+
+        let total_width = (0..measures_per_row)
+            .map(|measure_idx_in_row| {
+                score
+                    .measures
+                    .get(measure_idx_in_row)
+                    .map_or(0.0, |measure| self.measure_width(measure))
+            })
+            .sum::<f32>()
+            + measures_per_row as f32 * self.style.measure_spacing;
+
+        let total_height = total_rows as f32 * self.row_height();
+
+        let padding = 2.0 * TAB_PADDING;
+        Vec2::new(total_width + padding, total_height + padding)
+    }
+
+    /// End points of the playback position indicator for the given measure and division.
+    pub fn playback_indicator(
+        &self,
+        score: &Score,
+        origin: Pos2,
+        current_measure: usize,
+        current_division: usize,
+    ) -> [Pos2; 2] {
+        let measures_per_row = self.measures_per_row;
+        let row = current_measure / measures_per_row;
+        let measure_idx_in_row = current_measure % measures_per_row;
+
+        // Add the widths of the previous measures in the row
+        let mut x_offset = origin.x;
+        for idx in 0..measure_idx_in_row {
+            let measure = &score.measures[row * measures_per_row + idx];
+            x_offset += self.measure_width(measure) + self.style.measure_spacing;
+        }
+
+        // Add the positions within the current measure
+        let x = x_offset + current_division as f32 * self.dashes_per_division as f32 * NOTE_SPACING;
+
+        let y_offset = origin.y + row as f32 * self.row_height();
+        let y_top = y_offset + self.style.string_spacing;
+        let y_bottom = y_top + self.style.string_spacing * (NUM_STRINGS as f32 - 1.0);
+
+        [pos2(x, y_top), pos2(x, y_bottom)]
+    }
+
+    /// The area of `measure` from half a string above the top string to half a string below the
+    /// bottom one.
+    pub fn measure_rect(&self, score: &Score, origin: Pos2, measure: usize) -> Rect {
+        let [top, bottom] = self.playback_indicator(score, origin, measure, 0);
+        let width = self.measure_width(&score.measures[measure]);
+        let margin = self.style.string_spacing / 2.0;
+        Rect::from_min_max(
+            pos2(top.x, top.y - margin),
+            pos2(top.x + width, bottom.y + margin),
+        )
+    }
+
+    fn layout_strings(&self, shapes: &mut Vec<TabShape>, x_start: f32, y_offset: f32, width: f32) {
+        for string_idx in 0..NUM_STRINGS {
+            let y = y_offset + self.style.string_spacing * (string_idx as f32 + 1.0);
+            shapes.push(TabShape::Line {
+                points: [pos2(x_start, y), pos2(x_start + width, y)],
+                width: self.style.line_width,
+                color: Color32::BLACK,
+            });
+        }
+    }
+
+    fn layout_measure(
+        &self,
+        shapes: &mut Vec<TabShape>,
+        measure: &Measure,
+        chords: &[Option<Chord>],
+        x_offset: f32,
+        y_offset: f32,
+        draw_start_line: bool,
+    ) {
+        let y_top = y_offset + self.style.string_spacing;
+        let y_bottom = y_offset + self.style.string_spacing * NUM_STRINGS as f32;
+
+        // Draw vertical line at the start of the measure if needed
+        if draw_start_line {
+            shapes.push(TabShape::Line {
+                points: [pos2(x_offset, y_top), pos2(x_offset, y_bottom)],
+                width: self.style.line_width,
+                color: Color32::BLACK,
+            });
+        }
+
+        // Store positions of notes for drawing techniques
+        let mut note_positions: Vec<(Pos2, &Note)> = Vec::new();
+
+        for (division_idx, notes) in measure.positions.iter().enumerate() {
+            let position_in_dashes = division_idx * self.dashes_per_division;
+            let x = x_offset + position_in_dashes as f32 * NOTE_SPACING;
+
+            let chord = chords.get(division_idx).copied().flatten();
+            for note in notes {
+                if let (Some(string), Some(fret)) = (note.string, note.fret) {
+                    let y = y_offset
+                        + self.style.string_spacing * (self.string_line(string) as f32 + 1.0);
+
+                    shapes.push(TabShape::Text {
+                        pos: pos2(x, y),
+                        anchor: Align2::LEFT_CENTER,
+                        text: self.style.note_label(note, fret, self.capo_fret),
+                        size: self.style.font_size,
+                        color: self.style.note_color(note, chord, self.capo_fret),
+                    });
+
+                    note_positions.push((pos2(x, y), note));
+                }
+            }
+        }
+
+        // After the notes, draw hammer-on and pull-off arcs
+        for pair in note_positions.windows(2) {
+            let (current_pos, current_note) = pair[0];
+            let (next_pos, next_note) = pair[1];
+
+            // Only draw if the notes are on the same string and the next note has a technique
+            if current_note.string != next_note.string {
+                continue;
+            }
+            let label = match next_note.technique {
+                Technique::HammerOn => "H",
+                Technique::PullOff => "P",
+                Technique::None => continue,
+            };
+
+            let control_point = pos2(
+                (current_pos.x + next_pos.x) / 2.0,
+                current_pos.y - self.style.string_spacing,
+            );
+            shapes.push(TabShape::Curve {
+                points: [current_pos, control_point, next_pos],
+                width: self.style.line_width,
+                color: Color32::BLACK,
+            });
+            shapes.push(TabShape::Text {
+                pos: pos2(control_point.x, control_point.y - 5.0),
+                anchor: Align2::CENTER_BOTTOM,
+                text: label.to_string(),
+                size: self.style.font_size - 2.0,
+                color: Color32::BLACK,
+            });
+        }
+
+        // Draw vertical line at the end of the measure
+        let x_end = x_offset + self.measure_width(measure);
+        shapes.push(TabShape::Line {
+            points: [pos2(x_end, y_top), pos2(x_end, y_bottom)],
+            width: self.style.line_width,
+            color: Color32::BLACK,
+        });
+    }
 }
 
 pub fn score_info(score: &Score) -> String {
-    let info = format!(
+    let mut info = String::new();
+    if let Some(title) = &score.title {
+        info.push_str(&format!("Title: {}\n", title));
+    }
+    if let Some(composer) = &score.composer {
+        info.push_str(&format!("Composer: {}\n", composer));
+    }
+    info.push_str(&format!(
         "Time signature: {}/{}\n\
          Tempo: {}\n\
          Divisions per quarter note: {}\n\
@@ -30,6 +563,6 @@ pub fn score_info(score: &Score) -> String {
         score.divisions_per_quarter,
         score.divisions_per_measure,
         score.measures.len(),
-    );
+    ));
     info
 }